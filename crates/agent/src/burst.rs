@@ -0,0 +1,72 @@
+// crates/agent/src/burst.rs
+use std::time::Duration;
+use tokio::time::Instant;
+
+// When a burst starts and what it consists of
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BurstConfig {
+    /// change_score between consecutive frames that starts a burst
+    pub threshold: f64,
+    /// Extra captures taken once a burst starts
+    pub count: u32,
+    /// Time between burst captures
+    pub interval: Duration,
+}
+
+// Burst state machine. A frame that differs from its predecessor by at
+// least the threshold schedules `count` rapid captures; every capture taken
+// meanwhile uses up one of them. Changes seen mid-burst don't restart it.
+#[derive(Debug, Clone)]
+pub struct Burst {
+    config: BurstConfig,
+    previous: Option<Vec<u8>>,
+    remaining: u32,
+    next: Option<Instant>,
+}
+
+impl Burst {
+    pub fn new(config: BurstConfig) -> Self {
+        Self {
+            config,
+            previous: None,
+            remaining: 0,
+            next: None,
+        }
+    }
+
+    // When the next burst capture is due; None outside a burst
+    pub fn deadline(&self) -> Option<Instant> {
+        self.next
+    }
+
+    // Record the fingerprint of a frame captured at `now`. Returns true when
+    // it starts a new burst.
+    pub fn observe(&mut self, fingerprint: &[u8], now: Instant) -> bool {
+        let changed = self
+            .previous
+            .as_deref()
+            .is_some_and(|prev| capture::change_score(prev, fingerprint) >= self.config.threshold);
+        self.previous = Some(fingerprint.to_vec());
+
+        if self.remaining > 0 {
+            self.remaining -= 1;
+        } else if changed && self.config.count > 0 {
+            self.remaining = self.config.count;
+            self.next = Some(now + self.config.interval);
+            return true;
+        }
+
+        self.next = (self.remaining > 0).then(|| now + self.config.interval);
+        false
+    }
+
+    // A burst capture that was due but never produced a frame (paused,
+    // locked or failed) still uses up its slot, so a stalled burst can't
+    // keep the loop spinning
+    pub fn expire(&mut self, now: Instant) {
+        if self.next.is_some_and(|at| at <= now) {
+            self.remaining = self.remaining.saturating_sub(1);
+            self.next = (self.remaining > 0).then(|| now + self.config.interval);
+        }
+    }
+}
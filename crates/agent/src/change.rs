@@ -0,0 +1,73 @@
+// crates/agent/src/change.rs
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::time::interval;
+use tracing::{debug, info};
+
+// Receiving end of a change notification source. Each message means
+// "something on screen changed"; bursts are coalesced by the agent.
+pub type ChangeEvents = mpsc::Receiver<()>;
+
+// What woke the capture loop
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Trigger {
+    Tick,
+    Change,
+    /// A scheduled capture within a burst
+    Burst,
+}
+
+// Watch the monitor layout and emit an event whenever it changes
+// (resolution, hot-plug, scaling). The platform capture APIs offer no
+// content-change notifications, so those still rely on interval polling.
+pub fn watch_displays(poll: Duration) -> ChangeEvents {
+    let (tx, rx) = mpsc::channel(8);
+
+    tokio::spawn(async move {
+        let mut ticker = interval(poll);
+        let mut last = None;
+
+        loop {
+            ticker.tick().await;
+
+            let Ok(Ok(layout)) = tokio::task::spawn_blocking(capture::monitors).await else {
+                continue;
+            };
+
+            if last.as_ref().is_some_and(|prev| *prev != layout) {
+                info!("Display layout changed ({} monitor(s))", layout.len());
+                // A full channel already has a capture pending
+                if tx.try_send(()).is_err() && tx.is_closed() {
+                    debug!("Display watcher stopped — agent dropped the receiver");
+                    break;
+                }
+            }
+            last = Some(layout);
+        }
+    });
+
+    rx
+}
+
+// Wait for the next change event. Never resolves when there is no source
+// (or it has closed), so the caller simply falls back to interval polling.
+pub async fn next_change(events: &mut Option<ChangeEvents>) {
+    match events {
+        Some(rx) => {
+            if rx.recv().await.is_none() {
+                *events = None;
+                std::future::pending::<()>().await;
+            }
+        }
+        None => std::future::pending::<()>().await,
+    }
+}
+
+// Absorb a burst: wait `window` for follow-up events, then drop them so
+// one capture covers the whole burst.
+pub async fn coalesce(events: &mut Option<ChangeEvents>, window: Duration) {
+    tokio::time::sleep(window).await;
+    if let Some(rx) = events {
+        while rx.try_recv().is_ok() {}
+    }
+}
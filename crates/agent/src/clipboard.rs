@@ -0,0 +1,124 @@
+// crates/agent/src/clipboard.rs
+use anyhow::{Context, Result};
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use sha2::Sha256;
+use std::path::Path;
+
+// Upload field carrying the clipboard hash
+pub const CLIPBOARD_HASH_FIELD: &str = "clipboard_sha256";
+
+// Where the clipboard text comes from; swappable for tests
+pub trait ClipboardSource: Send {
+    // Current clipboard text, or None when it is empty or holds no text
+    fn text(&mut self) -> Result<Option<String>>;
+}
+
+// The OS clipboard
+pub struct SystemClipboard {
+    clipboard: arboard::Clipboard,
+}
+
+impl SystemClipboard {
+    pub fn new() -> Result<Self> {
+        let clipboard = arboard::Clipboard::new()
+            .map_err(|e| anyhow::anyhow!("Failed to open clipboard: {}", e))?;
+        Ok(Self { clipboard })
+    }
+}
+
+impl ClipboardSource for SystemClipboard {
+    fn text(&mut self) -> Result<Option<String>> {
+        match self.clipboard.get_text() {
+            Ok(text) => Ok(Some(text)),
+            // Empty, or holding an image/file list rather than text
+            Err(arboard::Error::ContentNotAvailable) => Ok(None),
+            Err(e) => Err(anyhow::anyhow!("Failed to read clipboard: {}", e)),
+        }
+    }
+}
+
+// Secret the clipboard hash is keyed with. A plain SHA-256 of short
+// clipboard text (a password, an OTP) can be brute-forced by whoever reads
+// the frame metadata; keyed with a secret that never leaves the machine,
+// the hash only shows when the same text was copied twice.
+pub struct HashKey([u8; 32]);
+
+impl HashKey {
+    pub fn generate() -> Self {
+        let mut key = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut key);
+        Self(key)
+    }
+
+    // Read the key stored at `path` as 64 hex characters, creating it with
+    // a fresh random key on first use so hashes stay comparable across
+    // restarts of this install
+    pub fn load_or_create(path: &Path) -> Result<Self> {
+        match std::fs::read_to_string(path) {
+            Ok(stored) => {
+                let bytes = hex::decode(stored.trim())
+                    .with_context(|| format!("Clipboard key in {} is not valid hex", path.display()))?;
+                let key = bytes.try_into().map_err(|bytes: Vec<u8>| {
+                    anyhow::anyhow!("Clipboard key in {} must be 32 bytes, got {}", path.display(), bytes.len())
+                })?;
+                Ok(Self(key))
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                let key = Self::generate();
+                if let Some(dir) = path.parent() {
+                    std::fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+                }
+                write_private(path, hex::encode(key.0).as_bytes())
+                    .with_context(|| format!("Failed to write clipboard key to {}", path.display()))?;
+                Ok(key)
+            }
+            Err(e) => Err(e).with_context(|| format!("Failed to read clipboard key from {}", path.display())),
+        }
+    }
+
+    // Hex HMAC-SHA256 of the clipboard text. Only this digest ever leaves
+    // the machine; the text itself is dropped as soon as it has been hashed.
+    pub fn content_hash(&self, text: &str) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(&self.0).expect("HMAC takes keys of any length");
+        mac.update(text.as_bytes());
+        hex::encode(mac.finalize().into_bytes())
+    }
+}
+
+// Create `path` readable only by the current user where the platform
+// supports it
+fn write_private(path: &Path, data: &[u8]) -> std::io::Result<()> {
+    use std::io::Write;
+
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options.open(path)?.write_all(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hash_key_persists_per_install() {
+        let dir = std::env::temp_dir().join(format!("eye-clipboard-key-{}", std::process::id()));
+        let path = dir.join("clipboard.key");
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let key = HashKey::load_or_create(&path).unwrap();
+        let hash = key.content_hash("hunter2");
+        assert_eq!(HashKey::load_or_create(&path).unwrap().content_hash("hunter2"), hash);
+
+        // Another install hashes the same text differently, and neither
+        // matches the bare SHA-256 of the text
+        assert_ne!(HashKey::generate().content_hash("hunter2"), hash);
+        assert_ne!(hash, "f52fbd32b2b3b86ff88ef6c490628285f482af15ddcb29541f94bcf526a3f6c7");
+
+        std::fs::write(&path, "not hex").unwrap();
+        assert!(HashKey::load_or_create(&path).is_err());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
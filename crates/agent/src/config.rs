@@ -0,0 +1,244 @@
+// crates/agent/src/config.rs
+use crate::burst::BurstConfig;
+use crate::dedup::DedupConfig;
+use anyhow::Result;
+use capture::{
+    Config as CaptureConfig, NotificationMask, PreScale, Provenance, RedactionRule, RegionPreset, Transform,
+};
+use image::ImageFormat;
+use std::sync::Arc;
+use std::time::Duration;
+use transport::FanOutPolicy;
+
+// Shortest capture interval the agent accepts
+pub const MIN_INTERVAL: Duration = Duration::from_millis(50);
+
+// Everything the agent is built from. Distinct from transport::AgentConfig,
+// which carries the settings the server pushes down at runtime.
+#[derive(Debug, Clone)]
+pub struct AgentConfig {
+    pub server_url: String,
+    /// Further servers each frame is also uploaded to, concurrently
+    pub mirror_urls: Vec<String>,
+    /// Whether an upload needs every server or just one to succeed
+    pub fan_out: FanOutPolicy,
+    pub token: String,
+    pub interval: Duration,
+    /// Format, quality, monitor, region and privacy settings for the engine
+    pub capture: CaptureConfig,
+    /// Each interval capture is delayed by a random amount below this,
+    /// so a fleet of agents does not upload in lockstep
+    pub jitter: Duration,
+    /// Stop after this many successful uploads; None runs until stopped
+    pub max_frames: Option<u64>,
+    /// Rapid extra captures after a large scene change; None disables
+    pub burst: Option<BurstConfig>,
+    /// Drop frames that repeat a recent upload; None uploads every frame
+    pub dedup: Option<DedupConfig>,
+}
+
+impl AgentConfig {
+    pub fn validate(&self) -> Result<()> {
+        for url in std::iter::once(&self.server_url).chain(&self.mirror_urls) {
+            if !(url.starts_with("http://") || url.starts_with("https://")) {
+                anyhow::bail!("Server URL '{}' must start with http:// or https://", url);
+            }
+        }
+        if self.interval < MIN_INTERVAL {
+            anyhow::bail!("Interval {:?} is below the minimum of {:?}", self.interval, MIN_INTERVAL);
+        }
+        if !(1..=100).contains(&self.capture.quality) {
+            anyhow::bail!("Quality {} must be between 1 and 100", self.capture.quality);
+        }
+        if self.jitter >= self.interval {
+            anyhow::bail!("Jitter {:?} must be shorter than the interval {:?}", self.jitter, self.interval);
+        }
+        if self.max_frames == Some(0) {
+            anyhow::bail!("max_frames must be at least 1");
+        }
+        if let Some(burst) = &self.burst {
+            if !(burst.threshold > 0.0 && burst.threshold <= 1.0) {
+                anyhow::bail!("Burst threshold {} must be in (0, 1]", burst.threshold);
+            }
+            if burst.count == 0 {
+                anyhow::bail!("Burst count must be at least 1");
+            }
+            if burst.interval < MIN_INTERVAL {
+                anyhow::bail!("Burst interval {:?} is below the minimum of {:?}", burst.interval, MIN_INTERVAL);
+            }
+        }
+        if let Some(dedup) = &self.dedup {
+            if !(0.0..=1.0).contains(&dedup.threshold) {
+                anyhow::bail!("Dedup threshold {} must be in [0, 1]", dedup.threshold);
+            }
+            if dedup.window.is_zero() {
+                anyhow::bail!("Dedup window must be longer than zero");
+            }
+            if dedup.keyframe_interval < self.interval {
+                anyhow::bail!(
+                    "Keyframe interval {:?} is shorter than the capture interval {:?}",
+                    dedup.keyframe_interval,
+                    self.interval
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+// Builds an AgentConfig (and from it an Agent) one setting at a time
+#[derive(Debug, Clone)]
+pub struct AgentBuilder {
+    config: AgentConfig,
+}
+
+impl AgentBuilder {
+    pub fn new(server_url: impl Into<String>) -> Self {
+        Self {
+            config: AgentConfig {
+                server_url: server_url.into(),
+                mirror_urls: Vec::new(),
+                fan_out: FanOutPolicy::default(),
+                token: String::new(),
+                interval: Duration::from_millis(1000),
+                capture: CaptureConfig::default(),
+                jitter: Duration::ZERO,
+                max_frames: None,
+                burst: None,
+                dedup: None,
+            },
+        }
+    }
+
+    // Also upload every frame to these servers
+    pub fn mirrors(mut self, urls: Vec<String>) -> Self {
+        self.config.mirror_urls = urls;
+        self
+    }
+
+    pub fn fan_out_policy(mut self, policy: FanOutPolicy) -> Self {
+        self.config.fan_out = policy;
+        self
+    }
+
+    pub fn token(mut self, token: impl Into<String>) -> Self {
+        self.config.token = token.into();
+        self
+    }
+
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.config.interval = interval;
+        self
+    }
+
+    // Encode frames as `format`
+    pub fn format(mut self, format: ImageFormat) -> Self {
+        self.config.capture.format = format;
+        self.config.capture.raw = false;
+        self
+    }
+
+    // Send uncompressed RGBA frames instead of an encoded format
+    pub fn raw(mut self) -> Self {
+        self.config.capture.raw = true;
+        self
+    }
+
+    // Also encode each capture in these formats and upload them alongside
+    // the frame, e.g. a small JPEG for live view next to a PNG primary
+    pub fn alternate_formats(mut self, formats: Vec<ImageFormat>) -> Self {
+        self.config.capture.alternate_formats = formats;
+        self
+    }
+
+    pub fn quality(mut self, quality: u8) -> Self {
+        self.config.capture.quality = quality;
+        self
+    }
+
+    // Capture the monitor at this index instead of the first one
+    pub fn monitor(mut self, index: usize) -> Self {
+        self.config.capture.monitor = Some(index);
+        self
+    }
+
+    // Capture these monitors in turn, one per capture
+    pub fn monitor_rotation(mut self, indices: Vec<usize>) -> Self {
+        self.config.capture.monitor_rotation = indices;
+        self
+    }
+
+    // Downscale grabs as soon as they are taken, per monitor
+    pub fn pre_scale(mut self, pre_scale: PreScale) -> Self {
+        self.config.capture.pre_scale = pre_scale;
+        self
+    }
+
+    pub fn region(mut self, region: RegionPreset) -> Self {
+        self.config.capture.region = Some(region);
+        self
+    }
+
+    pub fn blocked_windows(mut self, patterns: Vec<String>) -> Self {
+        self.config.capture.blocked_windows = patterns;
+        self
+    }
+
+    pub fn redaction_rules(mut self, rules: Vec<RedactionRule>) -> Self {
+        self.config.capture.redaction_rules = rules;
+        self
+    }
+
+    // Image transforms run in this order on every capture before encoding
+    pub fn transforms(mut self, transforms: Vec<Arc<dyn Transform>>) -> Self {
+        self.config.capture.transforms = transforms;
+        self
+    }
+
+    // Crop every frame to the focused window
+    pub fn follow_focus(mut self) -> Self {
+        self.config.capture.follow_focus = true;
+        self
+    }
+
+    // Black out notification banners detected by `mask`
+    pub fn notification_mask(mut self, mask: NotificationMask) -> Self {
+        self.config.capture.notification_mask = Some(mask);
+        self
+    }
+
+    // Write `provenance` into every encoded PNG/JPEG frame
+    pub fn provenance(mut self, provenance: Provenance) -> Self {
+        self.config.capture.provenance = Some(provenance);
+        self
+    }
+
+    pub fn jitter(mut self, jitter: Duration) -> Self {
+        self.config.jitter = jitter;
+        self
+    }
+
+    pub fn max_frames(mut self, max_frames: u64) -> Self {
+        self.config.max_frames = Some(max_frames);
+        self
+    }
+
+    // Capture a burst of frames after a large scene change
+    pub fn burst(mut self, burst: BurstConfig) -> Self {
+        self.config.burst = Some(burst);
+        self
+    }
+
+    // Skip frames that repeat an upload from the last `window`, with a
+    // keyframe uploaded every `keyframe_interval` regardless
+    pub fn dedup(mut self, dedup: DedupConfig) -> Self {
+        self.config.dedup = Some(dedup);
+        self
+    }
+
+    // Finish building and validate the result
+    pub fn config(self) -> Result<AgentConfig> {
+        self.config.validate()?;
+        Ok(self.config)
+    }
+}
@@ -0,0 +1,76 @@
+// crates/agent/src/dedup.rs
+use std::collections::VecDeque;
+use std::time::Duration;
+use tokio::time::Instant;
+
+// What counts as a duplicate frame, and for how long
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DedupConfig {
+    /// change_score at or below which a frame duplicates an earlier one;
+    /// 0 drops exact repeats only
+    pub threshold: f64,
+    /// Frames are only compared with uploads this recent, so a screen
+    /// that changes slowly still gets uploaded as it drifts
+    pub window: Duration,
+    /// A frame is uploaded at least this often even if nothing changed,
+    /// so the timeline keeps periodic full frames to seek to
+    pub keyframe_interval: Duration,
+}
+
+// What to do with a captured frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    Upload,
+    /// Upload it: a keyframe is due, duplicate or not
+    Keyframe,
+    /// Skip it: it repeats a frame uploaded within the window
+    Drop,
+}
+
+// Time-windowed duplicate suppression. Keeps the fingerprints of frames
+// uploaded in the last `window`; older ones expire, so a duplicate is
+// only dropped while the frame it repeats is recent.
+#[derive(Debug, Clone)]
+pub struct Dedup {
+    config: DedupConfig,
+    recent: VecDeque<(Vec<u8>, Instant)>,
+    last_keyframe: Option<Instant>,
+}
+
+impl Dedup {
+    pub fn new(config: DedupConfig) -> Self {
+        Self {
+            config,
+            recent: VecDeque::new(),
+            last_keyframe: None,
+        }
+    }
+
+    // Decide on a frame captured at `now`. Nothing is remembered until the
+    // frame is reported `uploaded`, so a failed upload doesn't suppress
+    // the retry.
+    pub fn judge(&mut self, fingerprint: &[u8], now: Instant) -> Verdict {
+        self.recent.retain(|(_, at)| now.duration_since(*at) < self.config.window);
+
+        let keyframe_due = self
+            .last_keyframe
+            .is_none_or(|at| now.duration_since(at) >= self.config.keyframe_interval);
+        if keyframe_due {
+            return Verdict::Keyframe;
+        }
+
+        let duplicate = self
+            .recent
+            .iter()
+            .any(|(previous, _)| capture::change_score(previous, fingerprint) <= self.config.threshold);
+        if duplicate { Verdict::Drop } else { Verdict::Upload }
+    }
+
+    // Record a frame that reached the server
+    pub fn uploaded(&mut self, fingerprint: &[u8], verdict: Verdict, now: Instant) {
+        if verdict == Verdict::Keyframe {
+            self.last_keyframe = Some(now);
+        }
+        self.recent.push_back((fingerprint.to_vec(), now));
+    }
+}
@@ -0,0 +1,172 @@
+// crates/agent/src/encoder.rs
+use anyhow::Result;
+use capture::{Config as CaptureConfig, Frame, MonitorInfo};
+use image::DynamicImage;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::{Notify, mpsc};
+use tokio::task::JoinHandle;
+
+// A screen grab waiting to be encoded
+pub struct RawCapture {
+    pub frame_id: i64,
+    pub image: DynamicImage,
+    pub monitor: MonitorInfo,
+    /// Engine settings when the grab was taken, so quality changes apply
+    /// from the next capture on
+    pub config: CaptureConfig,
+}
+
+// Turns a raw capture into an upload-ready frame; runs on a blocking thread
+pub type EncodeFn = Arc<dyn Fn(RawCapture) -> Result<Frame> + Send + Sync>;
+
+struct Queue {
+    raw: Mutex<VecDeque<RawCapture>>,
+    ready: Notify,
+    capacity: usize,
+    dropped: AtomicU64,
+}
+
+// Encodes captures off the capture loop. Raw grabs wait in a small bounded
+// queue; when encoding falls behind the oldest grab is dropped, so pushing
+// never blocks and capture keeps its cadence. Encoded frames come out of
+// `take_output` in completion order.
+pub struct EncodePool {
+    queue: Arc<Queue>,
+    output: Option<mpsc::Receiver<Result<Frame>>>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+impl EncodePool {
+    // Start `workers` encode tasks (at least one) behind a queue of
+    // `capacity` raw captures. Must be called from within a Tokio runtime.
+    pub fn spawn(workers: usize, capacity: usize, encode: EncodeFn) -> Self {
+        let capacity = capacity.max(1);
+        let queue = Arc::new(Queue {
+            raw: Mutex::new(VecDeque::with_capacity(capacity)),
+            ready: Notify::new(),
+            capacity,
+            dropped: AtomicU64::new(0),
+        });
+        // A slow uploader stalls the workers here, which backs up into the
+        // raw queue and drops grabs there instead of holding encoded frames
+        let (tx, rx) = mpsc::channel(capacity);
+
+        let workers = (0..workers.max(1))
+            .map(|_| tokio::spawn(run_worker(queue.clone(), encode.clone(), tx.clone())))
+            .collect();
+
+        Self { queue, output: Some(rx), workers }
+    }
+
+    // Queue a grab for encoding, dropping the oldest one if the queue is full
+    pub fn push(&self, raw: RawCapture) {
+        let mut pending = self.queue.raw.lock().unwrap();
+        while pending.len() >= self.queue.capacity {
+            if let Some(old) = pending.pop_front() {
+                self.queue.dropped.fetch_add(1, Ordering::Relaxed);
+                tracing::warn!("Encoder behind — dropped raw frame #{}", old.frame_id);
+            }
+        }
+        pending.push_back(raw);
+        drop(pending);
+        self.queue.ready.notify_one();
+    }
+
+    // Raw captures dropped because encoding fell behind
+    pub fn dropped(&self) -> u64 {
+        self.queue.dropped.load(Ordering::Relaxed)
+    }
+
+    // Receiver of encoded frames (or encode errors); None once taken
+    pub fn take_output(&mut self) -> Option<mpsc::Receiver<Result<Frame>>> {
+        self.output.take()
+    }
+}
+
+impl Drop for EncodePool {
+    fn drop(&mut self) {
+        for worker in &self.workers {
+            worker.abort();
+        }
+    }
+}
+
+async fn run_worker(queue: Arc<Queue>, encode: EncodeFn, output: mpsc::Sender<Result<Frame>>) {
+    loop {
+        let next = queue.raw.lock().unwrap().pop_front();
+        let Some(raw) = next else {
+            queue.ready.notified().await;
+            continue;
+        };
+
+        let encode = encode.clone();
+        let frame = match tokio::task::spawn_blocking(move || encode(raw)).await {
+            Ok(frame) => frame,
+            Err(e) => Err(anyhow::anyhow!("Encode worker panicked: {}", e)),
+        };
+        if output.send(frame).await.is_err() {
+            // Nobody is listening any more
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use capture::Engine;
+    use std::time::{Duration, Instant};
+
+    fn raw(frame_id: i64) -> RawCapture {
+        RawCapture {
+            frame_id,
+            image: DynamicImage::ImageRgba8(image::RgbaImage::new(32, 18)),
+            monitor: MonitorInfo {
+                id: 1,
+                name: "test".to_string(),
+                x: 0,
+                y: 0,
+                width: 32,
+                height: 18,
+                scale_factor: 1.0,
+                is_primary: true,
+            },
+            config: CaptureConfig {
+                format: image::ImageFormat::Png,
+                ..CaptureConfig::default()
+            },
+        }
+    }
+
+    #[tokio::test]
+    async fn test_slow_encoder_drops_raw_frames_instead_of_stalling() {
+        let slow: EncodeFn = Arc::new(|raw: RawCapture| {
+            std::thread::sleep(Duration::from_millis(200));
+            Engine::new(raw.config).frame_from_capture(raw.frame_id, raw.image, raw.monitor)
+        });
+        let mut pool = EncodePool::spawn(1, 2, slow);
+        let mut output = pool.take_output().unwrap();
+
+        // Ten grabs arrive faster than one encode; none of them waits
+        let started = Instant::now();
+        for id in 0..10 {
+            pool.push(raw(id));
+        }
+        assert!(started.elapsed() < Duration::from_millis(100));
+        assert_eq!(pool.dropped(), 8);
+
+        // Only the newest grabs are encoded
+        let mut ids = Vec::new();
+        for _ in 0..2 {
+            let frame = tokio::time::timeout(Duration::from_secs(5), output.recv())
+                .await
+                .unwrap()
+                .unwrap()
+                .unwrap();
+            ids.push(frame.id);
+        }
+        assert_eq!(ids, vec![8, 9]);
+    }
+}
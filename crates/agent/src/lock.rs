@@ -0,0 +1,64 @@
+// crates/agent/src/lock.rs
+use std::time::Duration;
+use tokio::time::Instant;
+
+// Reports whether the user session is locked. None means the state can't
+// be determined on this platform, in which case captures carry on.
+pub trait LockState: Send {
+    fn is_locked(&mut self) -> Option<bool>;
+}
+
+// How long a lock probe result is reused; probes spawn a process
+const PROBE_TTL: Duration = Duration::from_secs(1);
+
+// Asks the OS: logind's LockedHint on Linux, a running LogonUI.exe on
+// Windows. Unsupported elsewhere.
+#[derive(Default)]
+pub struct SystemLockState {
+    last: Option<(Instant, Option<bool>)>,
+}
+
+impl LockState for SystemLockState {
+    fn is_locked(&mut self) -> Option<bool> {
+        if let Some((at, locked)) = self.last
+            && at.elapsed() < PROBE_TTL
+        {
+            return locked;
+        }
+
+        let locked = probe();
+        self.last = Some((Instant::now(), locked));
+        locked
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "windows"))]
+fn command_output(program: &str, args: &[&str]) -> Option<String> {
+    let output = std::process::Command::new(program).args(args).output().ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(target_os = "linux")]
+fn probe() -> Option<bool> {
+    let session = std::env::var("XDG_SESSION_ID").ok()?;
+    let output = command_output("loginctl", &["show-session", &session, "-p", "LockedHint"])?;
+    match output.trim() {
+        "LockedHint=yes" => Some(true),
+        "LockedHint=no" => Some(false),
+        _ => None,
+    }
+}
+
+#[cfg(target_os = "windows")]
+fn probe() -> Option<bool> {
+    let output = command_output("tasklist", &["/FI", "IMAGENAME eq LogonUI.exe", "/NH"])?;
+    Some(output.to_lowercase().contains("logonui.exe"))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "windows")))]
+fn probe() -> Option<bool> {
+    None
+}
@@ -1,29 +1,157 @@
 // crates/agent/src/main.rs
 use anyhow::{Context, Result};
-use capture::{Config as CaptureConfig, Engine};
+use burst::{Burst, BurstConfig};
+use capture::{Config as CaptureConfig, Engine, Frame};
+use change::{ChangeEvents, Trigger};
+use dedup::{Dedup, DedupConfig, Verdict};
+use clipboard::{ClipboardSource, HashKey};
+use config::{AgentBuilder, AgentConfig};
+use encoder::{EncodePool, RawCapture};
+use lock::LockState;
+use pacer::Pacer;
+use power::{BatteryPolicy, PowerSource};
+use quality::QualityRamp;
+use chrono::{DateTime, Utc};
 use image::ImageFormat;
+use rand::Rng;
+use schedule::{Schedule, ScheduleTz};
+use spool::{Spool, SpoolKey, SpooledFrame};
+use std::collections::HashMap;
 use std::env;
+use std::str::FromStr;
+use std::sync::Arc;
 use std::time::Duration;
 use tokio::signal;
-use tokio::time::interval;
-use tracing::{error, info, warn};
-use transport::Client;
+use tokio::time::{Instant, interval};
+use tracing::{debug, error, info, warn};
+use transport::{Client, Compatibility, FanOut};
+use watchdog::Watchdog;
+
+mod burst;
+mod change;
+mod clipboard;
+mod config;
+mod dedup;
+mod encoder;
+mod lock;
+mod pacer;
+mod power;
+mod quality;
+mod schedule;
+mod spool;
+mod watchdog;
+
+// Storage pressure (0.0..=1.0) at which the agent starts backing off,
+// and below which it speeds back up toward its base interval
+const HIGH_PRESSURE: f64 = 0.9;
+const LOW_PRESSURE: f64 = 0.5;
+
+// How the agent assigns frame ids
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum FrameIdMode {
+    /// 0, 1, 2, ... — restarts from 0 every time the agent starts
+    Sequential,
+    /// Epoch milliseconds, bumped by one if the clock has not advanced.
+    /// Ids keep increasing across agent restarts.
+    Timestamp,
+}
+
+impl FromStr for FrameIdMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "sequential" => Ok(Self::Sequential),
+            "timestamp" => Ok(Self::Timestamp),
+            other => anyhow::bail!("unknown frame id mode '{}'", other),
+        }
+    }
+}
 
 // Agent structure encapsulating capture and upload logic
 struct Agent {
     engine: Engine,
-    client: Client,
+    /// The server, plus any mirrors every frame is also sent to
+    client: FanOut,
     interval: Duration,
+    /// Interval requested via env/server config, before pressure backoff
+    base_interval: Duration,
+    /// Upper bound for pressure backoff; None disables adaptive flow control
+    max_interval: Option<Duration>,
+    /// Lowers encode quality/resolution under storage pressure
+    quality_ramp: Option<QualityRamp>,
+    /// Quality and scale the engine was configured with, restored as
+    /// pressure eases
+    base_quality: u8,
+    base_scale: f32,
+    /// Lowest id the next frame may use
     frame_id: i64,
+    frame_id_mode: FrameIdMode,
     running: bool,
+    /// Optional capture windows; None means capture around the clock
+    schedule: Option<Schedule>,
+    /// Detects stretches with no successful upload and triggers recovery
+    watchdog: Watchdog,
+    /// Optional change notifications that trigger extra captures
+    changes: Option<ChangeEvents>,
+    /// Quiet period used to coalesce bursts of change events
+    change_coalesce: Duration,
+    /// True while the schedule has captures paused
+    paused: bool,
+    /// True while the interval is stretched because the server returned 429
+    rate_limited: bool,
+    /// Upper bound of the random delay before each interval capture
+    jitter: Duration,
+    /// Stop once this many frames have been uploaded
+    max_frames: Option<u64>,
+    /// Successful uploads so far
+    uploaded: u64,
+    /// When set, each frame carries a keyed hash of the clipboard text
+    clipboard: Option<(Box<dyn ClipboardSource>, HashKey)>,
+    /// Session lock detection; captures are skipped while locked
+    lock_state: Option<Box<dyn LockState>>,
+    /// True while captures are skipped because the session is locked
+    locked: bool,
+    /// Power supply probe; None disables battery throttling
+    power_source: Option<Box<dyn PowerSource>>,
+    /// How captures are throttled on battery
+    battery_policy: BatteryPolicy,
+    /// True while the battery policy is in effect
+    on_battery: bool,
+    /// Rapid captures after large scene changes
+    burst: Option<Burst>,
+    /// Drops frames repeating a recent upload
+    dedup: Option<Dedup>,
+    /// Encrypted on-disk queue for frames captured while the server is
+    /// unreachable
+    spool: Option<Spool>,
+    /// Encodes off the capture loop when set; None encodes inline
+    encoder: Option<EncodePool>,
 }
 
 // Implementation of Agent
 impl Agent {
-    fn new(server_url: String, token: String, capture_interval: Duration) -> Self {
+    fn builder(server_url: impl Into<String>) -> AgentBuilder {
+        AgentBuilder::new(server_url)
+    }
+
+    fn from_config(config: AgentConfig) -> Self {
+        let AgentConfig {
+            server_url,
+            mirror_urls,
+            fan_out,
+            token,
+            interval: capture_interval,
+            capture,
+            jitter,
+            max_frames,
+            burst,
+            dedup,
+        } = config;
+        let (base_quality, base_scale) = (capture.quality, capture.scale);
         let engine = Engine::new(CaptureConfig {
             interval: capture_interval,
-            format: ImageFormat::WebP,
+            ..capture
         });
 
         // Set HTTP timeout to 90% of the capture interval (min 2s) so
@@ -31,17 +159,380 @@ impl Agent {
         let http_timeout = Duration::from_secs_f64(
             (capture_interval.as_secs_f64() * 0.9).max(2.0)
         );
-        let client = Client::new_with_timeout(server_url, token, http_timeout);
+        let clients = std::iter::once(server_url)
+            .chain(mirror_urls)
+            .map(|url| {
+                Client::new_with_timeout(url, token.clone(), http_timeout)
+                    .with_agent_version(env!("CARGO_PKG_VERSION"))
+            })
+            .collect();
+        let client = FanOut::new(clients, fan_out);
+
+        // Recover after 30s without a successful upload, or 5 missed
+        // captures at long intervals, whichever is longer.
+        let watchdog = Watchdog::new((capture_interval * 5).max(Duration::from_secs(30)));
 
         Self {
             engine,
             client,
             interval: capture_interval,
+            base_interval: capture_interval,
+            max_interval: None,
+            quality_ramp: None,
+            base_quality,
+            base_scale,
             frame_id: 0,
+            frame_id_mode: FrameIdMode::Sequential,
             running: false,
+            schedule: None,
+            watchdog,
+            changes: None,
+            change_coalesce: Duration::from_millis(250),
+            paused: false,
+            rate_limited: false,
+            jitter,
+            max_frames,
+            uploaded: 0,
+            clipboard: None,
+            lock_state: None,
+            locked: false,
+            power_source: None,
+            battery_policy: BatteryPolicy::default(),
+            on_battery: false,
+            burst: burst.map(Burst::new),
+            dedup: dedup.map(Dedup::new),
+            spool: None,
+            encoder: None,
+        }
+    }
+
+    // Capture immediately whenever `events` fires, on top of the interval
+    fn with_change_events(mut self, events: ChangeEvents) -> Self {
+        self.changes = Some(events);
+        self
+    }
+
+    // Wait for the next reason to capture: the pacer's next deadline, a
+    // pending burst capture or a (coalesced) change notification
+    async fn next_trigger(&mut self, pacer: &Pacer) -> Trigger {
+        let burst_at = self.burst.as_ref().and_then(|b| b.deadline());
+        tokio::select! {
+            _ = tokio::time::sleep_until(pacer.deadline()) => Trigger::Tick,
+            _ = async {
+                match burst_at {
+                    Some(at) => tokio::time::sleep_until(at).await,
+                    None => std::future::pending().await,
+                }
+            } => Trigger::Burst,
+            _ = change::next_change(&mut self.changes) => {
+                change::coalesce(&mut self.changes, self.change_coalesce).await;
+                Trigger::Change
+            }
+        }
+    }
+
+    // Attach a hash of the clipboard text, keyed with `key`, to every frame
+    fn with_clipboard_hash(mut self, source: Box<dyn ClipboardSource>, key: HashKey) -> Self {
+        self.clipboard = Some((source, key));
+        self
+    }
+
+    // Hash of the current clipboard text, if hashing is enabled and the
+    // clipboard holds text. Read failures only cost the metadata, not the frame.
+    fn clipboard_hash(&mut self) -> Option<String> {
+        let (source, key) = self.clipboard.as_mut()?;
+        match source.text() {
+            Ok(text) => text.map(|t| key.content_hash(&t)),
+            Err(e) => {
+                warn!("{}", e);
+                None
+            }
+        }
+    }
+
+    // Skip captures while `lock_state` reports the session as locked
+    fn with_lock_detection(mut self, lock_state: Box<dyn LockState>) -> Self {
+        self.lock_state = Some(lock_state);
+        self
+    }
+
+    // False while the session is locked, logging lock/unlock transitions.
+    // An unknown lock state never blocks captures.
+    fn session_unlocked(&mut self) -> bool {
+        let locked = self
+            .lock_state
+            .as_mut()
+            .and_then(|l| l.is_locked())
+            .unwrap_or(false);
+
+        if locked != self.locked {
+            if locked {
+                info!("Session locked — skipping captures");
+            } else {
+                info!("Session unlocked — resuming captures");
+            }
+            self.locked = locked;
+        }
+        !locked
+    }
+
+    // Throttle captures according to `policy` while `source` reports
+    // battery power
+    fn with_battery_throttle(mut self, source: Box<dyn PowerSource>, policy: BatteryPolicy) -> Self {
+        self.power_source = Some(source);
+        self.battery_policy = policy;
+        self
+    }
+
+    // Interval the agent settles back to once pressure and rate limits
+    // ease: the requested base, stretched while throttled on battery
+    fn floor_interval(&self) -> Duration {
+        if self.on_battery {
+            self.base_interval.mul_f64(self.battery_policy.interval_factor)
+        } else {
+            self.base_interval
+        }
+    }
+
+    // Highest encode quality currently allowed
+    fn quality_ceiling(&self) -> u8 {
+        match self.battery_policy.quality {
+            Some(quality) if self.on_battery => quality.min(self.base_quality),
+            _ => self.base_quality,
+        }
+    }
+
+    // Re-check the power supply and switch between battery and AC settings
+    // when the battery policy starts or stops applying
+    fn apply_power(&mut self) {
+        let Some(source) = self.power_source.as_mut() else {
+            return;
+        };
+
+        let status = source.status();
+        let throttle = self.battery_policy.applies(status);
+        if throttle == self.on_battery {
+            return;
+        }
+        self.on_battery = throttle;
+
+        let interval = if self.rate_limited {
+            self.interval.max(self.floor_interval())
+        } else {
+            self.floor_interval()
+        };
+        let quality = self.quality_ceiling();
+        let config = self.engine.config_mut();
+        if throttle {
+            info!(
+                "On battery ({}) — interval {:?} -> {:?}, quality {} -> {}",
+                status.and_then(|s| s.percent).map_or("charge unknown".to_string(), |p| format!("{}%", p)),
+                self.interval,
+                interval,
+                config.quality,
+                quality.min(config.quality)
+            );
+            config.quality = quality.min(config.quality);
+        } else {
+            info!(
+                "On AC power — interval {:?} -> {:?}, quality {} -> {}",
+                self.interval, interval, config.quality, quality
+            );
+            config.quality = quality;
+        }
+        self.interval = interval;
+    }
+
+    // Feed a captured frame's fingerprint to the burst state machine
+    fn observe_scene(&mut self, fingerprint: &[u8], now: Instant) {
+        if let Some(burst) = self.burst.as_mut()
+            && burst.observe(fingerprint, now)
+        {
+            info!("Scene change detected — starting capture burst");
+        }
+    }
+
+    // Keep frames that can't reach the server in `spool` and upload them
+    // once it is back
+    fn with_spool(mut self, spool: Spool) -> Self {
+        self.spool = Some(spool);
+        self
+    }
+
+    // Upload a batch of spooled frames, oldest first. Called after a
+    // successful upload, so the backlog catches up a little every cycle
+    // without delaying fresh captures for long.
+    async fn drain_spool(&self) {
+        const BATCH: usize = 10;
+
+        let Some(spool) = &self.spool else {
+            return;
+        };
+        let client = &self.client;
+        let result = spool
+            .drain(BATCH, |frame| async move {
+                let fields: Vec<(&str, String)> =
+                    frame.fields.iter().map(|(name, value)| (name.as_str(), value.clone())).collect();
+                match client.upload_frame_to(frame.frame_id, frame.data, &fields, &[], &frame.endpoints).await {
+                    Ok(_) => Ok(()),
+                    // Newer frames already reached the server; this one
+                    // never will, so let it go
+                    Err(e) if e.downcast_ref::<transport::StaleFrame>().is_some() => {
+                        warn!("Dropping spooled frame #{}: {}", frame.frame_id, e);
+                        Ok(())
+                    }
+                    Err(e) => Err(e),
+                }
+            })
+            .await;
+
+        match result {
+            Ok(0) => {}
+            Ok(n) => info!("Uploaded {} spooled frame(s)", n),
+            Err(e) => warn!("Failed to upload spooled frames: {}", e),
+        }
+    }
+
+    // Restrict captures to the given schedule windows
+    fn with_schedule(mut self, schedule: Schedule) -> Self {
+        self.schedule = Some(schedule);
+        self
+    }
+
+    // Allow the interval to stretch up to `max` under server storage pressure
+    fn with_max_interval(mut self, max: Duration) -> Self {
+        self.max_interval = Some(max);
+        self
+    }
+
+    // Adaptive flow control: stretch the interval while the server reports
+    // high storage pressure and relax it back toward the base once it eases
+    fn apply_pressure(&mut self, pressure: f64) {
+        let Some(max) = self.max_interval else {
+            return;
+        };
+
+        let next = if pressure >= HIGH_PRESSURE {
+            self.interval.mul_f64(1.5).min(max.max(self.floor_interval()))
+        } else if pressure < LOW_PRESSURE {
+            self.interval.div_f64(1.5).max(self.floor_interval())
+        } else {
+            return;
+        };
+
+        if next != self.interval {
+            info!(
+                "Storage pressure {:.0}% — interval {:?} -> {:?}",
+                pressure * 100.0,
+                self.interval,
+                next
+            );
+            self.interval = next;
+        }
+    }
+
+    // Shrink frames under storage pressure via lower quality and/or scale
+    fn with_quality_ramp(mut self, ramp: QualityRamp) -> Self {
+        self.quality_ramp = Some(ramp);
+        self
+    }
+
+    // Apply the ramp step for `pressure` to the capture config, or restore
+    // the configured quality and scale once pressure drops below every step.
+    // The ramp only ever lowers settings, never raises them above the base.
+    fn apply_quality_ramp(&mut self, pressure: f64) {
+        let Some(ramp) = &self.quality_ramp else {
+            return;
+        };
+
+        let (quality, scale) = match ramp.step(pressure) {
+            Some(step) => (step.quality.min(self.quality_ceiling()), step.scale.min(self.base_scale)),
+            None => (self.quality_ceiling(), self.base_scale),
+        };
+
+        let config = self.engine.config_mut();
+        if (quality, scale) != (config.quality, config.scale) {
+            info!(
+                "Storage pressure {:.0}% — quality {} -> {}, scale {} -> {}",
+                pressure * 100.0,
+                config.quality,
+                quality,
+                config.scale,
+                scale
+            );
+            config.quality = quality;
+            config.scale = scale;
+        }
+    }
+
+    // Per-format encode quality pushed by the server. The primary format's
+    // entry becomes the base quality, still capped by the battery policy and
+    // quality ramp; the others apply to alternate encodings.
+    fn apply_format_quality(&mut self, qualities: &serde_json::Map<String, serde_json::Value>) {
+        let parsed: HashMap<ImageFormat, u8> = qualities
+            .iter()
+            .filter_map(|(name, quality)| {
+                let format = ImageFormat::from_extension(name.to_lowercase())?;
+                let quality = quality.as_u64().filter(|q| (1..=100).contains(q))? as u8;
+                Some((format, quality))
+            })
+            .collect();
+
+        let primary = self.engine.config().format;
+        let raw = self.engine.config().raw;
+        if !raw
+            && let Some(&quality) = parsed.get(&primary)
+            && quality != self.base_quality
+        {
+            info!("Quality update: {} -> {}", self.base_quality, quality);
+            self.base_quality = quality;
+            self.engine.config_mut().quality = self.quality_ceiling();
+        }
+
+        let config = self.engine.config_mut();
+        config.format_quality = parsed;
+        config.format_quality.remove(&primary);
+    }
+
+    // The server is still rate limiting after the transport waited out one
+    // Retry-After: capture no more often than it asks until an upload succeeds
+    fn apply_rate_limit(&mut self, retry_after: Duration) {
+        let next = self.interval.max(retry_after);
+        if next != self.interval {
+            warn!("Rate limited — interval {:?} -> {:?}", self.interval, next);
+            self.interval = next;
+        }
+        self.rate_limited = true;
+    }
+
+    // First successful upload after rate limiting: back to the base interval
+    fn clear_rate_limit(&mut self) {
+        if self.rate_limited {
+            let interval = self.floor_interval();
+            info!("Rate limit lifted — interval {:?} -> {:?}", self.interval, interval);
+            self.interval = interval;
+            self.rate_limited = false;
+        }
+    }
+
+    fn with_frame_id_mode(mut self, mode: FrameIdMode) -> Self {
+        self.frame_id_mode = mode;
+        self
+    }
+
+    // Id for the next frame captured at `now`
+    fn next_frame_id(&self, now: DateTime<Utc>) -> i64 {
+        match self.frame_id_mode {
+            FrameIdMode::Sequential => self.frame_id,
+            FrameIdMode::Timestamp => now.timestamp_millis().max(self.frame_id),
         }
     }
 
+    // Whether a capture attempt at `now` is allowed by the schedule
+    fn should_capture(&self, now: DateTime<Utc>) -> bool {
+        self.schedule.as_ref().is_none_or(|s| s.is_active(now))
+    }
+
     // Wait for the server to be ready
     async fn wait_for_server(&self, timeout: Duration) -> Result<()> {
         info!("Waiting for server...");
@@ -55,13 +546,37 @@ impl Agent {
                 info!("Server ready! Registering agent...");
 
                 // Claim the server's single connection slot.
-                // Returns an error if another agent is already connected.
-                self.client
+                // Returns an error if another agent is already connected
+                // or the server rejects this agent's version.
+                let compatibility = self.client
                     .connect()
                     .await
                     .context("Failed to register with server")?;
 
+                match compatibility {
+                    Compatibility::Ok => {}
+                    Compatibility::Warn => warn!(
+                        "Agent version {} differs from the server's — consider upgrading",
+                        env!("CARGO_PKG_VERSION")
+                    ),
+                    Compatibility::Reject => anyhow::bail!(
+                        "Server reports agent version {} as incompatible",
+                        env!("CARGO_PKG_VERSION")
+                    ),
+                }
+
                 info!("Agent registered — connection established (1:1)");
+
+                // Older servers have no /version endpoint; not fatal
+                match self.client.server_version().await {
+                    Ok(server) => info!(
+                        "Server version {} ({}), agent version {}",
+                        server.version,
+                        server.git_sha,
+                        env!("CARGO_PKG_VERSION")
+                    ),
+                    Err(e) => warn!("Could not determine server version: {}", e),
+                }
                 return Ok(());
             }
 
@@ -71,32 +586,266 @@ impl Agent {
         }
     }
 
-    // Capture a frame and upload it to the server
+    // Hand encoding to `workers` background tasks behind a queue of
+    // `queue` raw captures, so a slow encode never delays the next capture.
+    // Must be called from within a Tokio runtime.
+    fn with_encode_workers(mut self, workers: usize, queue: usize) -> Self {
+        self.encoder = Some(EncodePool::spawn(
+            workers,
+            queue,
+            Arc::new(|raw: RawCapture| {
+                Engine::new(raw.config).frame_from_capture(raw.frame_id, raw.image, raw.monitor)
+            }),
+        ));
+        self
+    }
+
+    // Capture a frame and upload it to the server. With encode workers the
+    // grab is only queued; the frame is uploaded by upload_encoded.
     async fn capture_and_upload(&mut self) -> Result<()> {
-        let frame = self.engine.capture_frame(self.frame_id)
+        let frame_id = self.next_frame_id(Utc::now());
+
+        if let Some(encoder) = &self.encoder {
+            let (image, monitor) = self.engine.capture_screen()
+                .context("Failed to capture frame")?;
+            encoder.push(RawCapture {
+                frame_id,
+                image,
+                monitor,
+                config: self.engine.config().clone(),
+            });
+            // Later grabs are captured before this one is uploaded
+            self.frame_id = frame_id + 1;
+            return Ok(());
+        }
+
+        let frame = self.engine.capture_frame(frame_id)
             .context("Failed to capture frame")?;
+        self.upload(frame).await
+    }
+
+    // Upload a frame that came out of the encode workers and settle the
+    // outcome like a whole inline cycle
+    async fn upload_encoded(&mut self, frame: Result<Frame>) {
+        let result = match frame {
+            Ok(frame) => self.upload(frame).await,
+            Err(e) => Err(e.context("Failed to encode frame")),
+        };
+        self.settle(result).await;
+    }
+
+    // Upload an encoded frame, spooling it if the server is unreachable
+    async fn upload(&mut self, frame: Frame) -> Result<()> {
+        let frame_id = frame.id;
+        self.observe_scene(&frame.fingerprint, Instant::now());
+
+        let verdict = match self.dedup.as_mut() {
+            Some(dedup) => dedup.judge(&frame.fingerprint, Instant::now()),
+            None => Verdict::Upload,
+        };
+        if verdict == Verdict::Drop {
+            debug!("Frame #{} repeats a recent upload — dropped", frame_id);
+            return Ok(());
+        }
+
+        let mut fields = vec![("format", frame.format.clone())];
+        if frame.is_raw() {
+            fields.push(("width", frame.width.to_string()));
+            fields.push(("height", frame.height.to_string()));
+            fields.push(("stride", frame.stride.to_string()));
+        }
+        if let Some(monitor) = &frame.monitor {
+            fields.extend(monitor.fields());
+        }
+        if let Some(hash) = self.clipboard_hash() {
+            fields.push((clipboard::CLIPBOARD_HASH_FIELD, hash));
+        }
+
+        let alternates: Vec<transport::Alternate> = frame
+            .alternates
+            .into_iter()
+            .map(|a| transport::Alternate { format: a.format, data: a.data })
+            .collect();
 
-        let response = self.client.upload_frame(frame.id, frame.data).await
-            .context("Failed to upload frame")?;
+        // Kept for the spool in case the server turns out to be unreachable.
+        // Spooled frames are replayed without their alternates.
+        let data = self.spool.as_ref().map(|_| frame.data.clone());
+        let response = match self.client
+            .upload_frame_with_alternates(frame.id, frame.data, &fields, &alternates)
+            .await
+        {
+            Ok(response) => response,
+            Err(e) => {
+                if let (Some(spool), Some(data)) = (&self.spool, data)
+                    && transport::is_unreachable(&e)
+                {
+                    let mut fields: Vec<(String, String)> =
+                        fields.iter().map(|(name, value)| (name.to_string(), value.clone())).collect();
+                    // Replayed later, so tell the server when it was captured
+                    fields.push(("timestamp".to_string(), frame.timestamp.to_rfc3339()));
+                    // With several servers, only the ones that missed it
+                    // get the frame again
+                    let endpoints = e
+                        .downcast_ref::<transport::FailedEndpoints>()
+                        .map(|failed| failed.0.clone())
+                        .unwrap_or_default();
+                    let spooled = SpooledFrame { frame_id: frame.id, fields, data, endpoints };
+                    match spool.push(&spooled) {
+                        Ok(()) => {
+                            info!("Server unreachable — spooled frame #{}", frame.id);
+                            // The id is taken now, so the next frame can't reuse it
+                            self.frame_id = self.frame_id.max(frame_id + 1);
+                        }
+                        Err(spool_err) => warn!("Failed to spool frame #{}: {}", frame.id, spool_err),
+                    }
+                }
+                return Err(e.context("Failed to upload frame"));
+            }
+        };
 
         let size_kb = frame.size_bytes as f64 / 1024.0;
-        info!("Frame #{} uploaded ({:.1} KB)", frame.id, size_kb);
+        let request_id = response.get("request_id").and_then(|v| v.as_str()).unwrap_or("-");
+        info!("Frame #{} uploaded ({:.1} KB) rid={}", frame.id, size_kb, request_id);
+        self.clear_rate_limit();
+        self.uploaded += 1;
+        if let Some(dedup) = self.dedup.as_mut() {
+            dedup.uploaded(&frame.fingerprint, verdict, Instant::now());
+        }
 
         // Handle dynamic config updates from server
-        if let Some(config) = response.get("config") {
-            if let Some(interval) = config.get("interval").and_then(|v| v.as_f64()) {
-                let new_interval = Duration::from_secs_f64(interval);
-                if new_interval != self.interval {
-                    info!("Interval update: {:?} -> {:?}", self.interval, new_interval);
-                    self.interval = new_interval;
-                }
+        if let Some(config) = response.get("config")
+            && let Some(interval) = config.get("interval").and_then(|v| v.as_f64())
+        {
+            let new_interval = Duration::from_secs_f64(interval);
+            if new_interval != self.base_interval {
+                info!("Interval update: {:?} -> {:?}", self.base_interval, new_interval);
+                self.base_interval = new_interval;
+                self.interval = self.floor_interval();
             }
         }
 
-        self.frame_id += 1;
+        if let Some(qualities) = response
+            .get("config")
+            .and_then(|c| c.get("qualities"))
+            .and_then(|q| q.as_object())
+        {
+            self.apply_format_quality(qualities);
+        }
+
+        if let Some(pressure) = response
+            .get("storage_pressure")
+            .and_then(|p| p.get("ratio"))
+            .and_then(|v| v.as_f64())
+        {
+            self.apply_pressure(pressure);
+            self.apply_quality_ramp(pressure);
+        }
+
+        self.frame_id = self.frame_id.max(frame_id + 1);
+
+        // The server is reachable again; catch up on anything spooled
+        self.drain_spool().await;
         Ok(())
     }
 
+    // Called by the watchdog when frames have stopped flowing. Re-runs the
+    // capture self-test and, if the server has gone away, waits for it and
+    // re-registers before the loop resumes.
+    async fn recover(&mut self) {
+        warn!(
+            "Watchdog: no successful upload for {:?} — attempting recovery",
+            self.watchdog.threshold()
+        );
+
+        match self.engine.self_test() {
+            Ok(()) => info!("Watchdog: capture self-test passed"),
+            Err(e) => warn!("Watchdog: capture self-test failed: {}", e),
+        }
+
+        if self.client.health_check().await.is_err() {
+            warn!("Watchdog: server unreachable — waiting for it to come back");
+            if let Err(e) = self.wait_for_server(Duration::from_secs(30)).await {
+                error!("Watchdog: server still unavailable: {}", e);
+                return;
+            }
+        }
+
+        info!("Watchdog: recovery complete");
+    }
+
+    // One pass of the capture loop: honour the schedule, capture and upload,
+    // and let the watchdog decide whether recovery is needed
+    async fn run_cycle(&mut self) {
+        // Outside the capture schedule: skip the frame but keep
+        // heartbeating so the server still sees the agent alive.
+        if !self.should_capture(Utc::now()) {
+            if !self.paused {
+                info!("Outside capture schedule — pausing captures");
+                self.paused = true;
+            }
+            if let Err(e) = self.client.health_check().await {
+                warn!("Heartbeat failed: {}", e);
+            }
+            self.watchdog.reset(Instant::now());
+            return;
+        }
+        if self.paused {
+            info!("Capture schedule window opened — resuming captures");
+            self.paused = false;
+        }
+
+        // The lock screen is useless to capture and a privacy risk;
+        // heartbeat like a paused schedule until the session is unlocked
+        if !self.session_unlocked() {
+            if let Err(e) = self.client.health_check().await {
+                warn!("Heartbeat failed: {}", e);
+            }
+            self.watchdog.reset(Instant::now());
+            return;
+        }
+
+        self.apply_power();
+
+        let result = self.capture_and_upload().await;
+        // Queued for encoding; the upload settles the watchdog instead
+        if self.encoder.is_some() && result.is_ok() {
+            return;
+        }
+        self.settle(result).await;
+    }
+
+    // Log a capture/upload outcome and let the watchdog decide whether
+    // recovery is needed
+    async fn settle(&mut self, result: Result<()>) {
+        if let Err(e) = &result
+            && let Some(skipped) = e.downcast_ref::<capture::CaptureSkipped>()
+        {
+            // Deliberate skip, not a failure
+            info!("{}", skipped);
+            self.watchdog.reset(Instant::now());
+            return;
+        }
+        if let Err(e) = &result {
+            error!("Error: {}", e);
+            if let Some(limited) = e.downcast_ref::<transport::RateLimited>() {
+                self.apply_rate_limit(limited.retry_after);
+            }
+        }
+        if self.watchdog.observe(result.is_ok(), Instant::now()) {
+            self.recover().await;
+        }
+    }
+
+    // True once max_frames uploads are done; the loop should stop
+    fn reached_max_frames(&mut self) -> bool {
+        if self.max_frames.is_some_and(|max| self.uploaded >= max) {
+            info!("Uploaded {} frames — stopping", self.uploaded);
+            self.running = false;
+            return true;
+        }
+        false
+    }
+
     // Start the agent's capture and upload loop
     async fn start(&mut self) -> Result<()> {
         self.wait_for_server(Duration::from_secs(30)).await?;
@@ -104,27 +853,59 @@ impl Agent {
         self.running = true;
         info!("Starting capture loop...");
 
-        let mut ticker = interval(self.interval);
-        // Delay missed ticks instead of bursting to catch up.
-        // Without this, if an upload takes longer than the interval,
-        // Tokio fires the next tick immediately causing uneven frame spacing.
-        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        // Fixed cadence: capture time is subtracted from the wait, and
+        // deadlines missed by a slow cycle are skipped rather than burst
+        let mut pacer = Pacer::new(self.interval, Instant::now());
         let ctrl_c = signal::ctrl_c();
         tokio::pin!(ctrl_c);
+        let mut encoded = self.encoder.as_mut().and_then(EncodePool::take_output);
 
         loop {
             tokio::select! {
-                _ = ticker.tick() => {
+                trigger = self.next_trigger(&pacer) => {
+                    match trigger {
+                        Trigger::Change => info!("Change detected — capturing immediately"),
+                        Trigger::Burst => {}
+                        Trigger::Tick if !self.jitter.is_zero() => {
+                            let delay = rand::thread_rng().gen_range(Duration::ZERO..self.jitter);
+                            tokio::time::sleep(delay).await;
+                        }
+                        Trigger::Tick => {}
+                    }
+
                     let prev_interval = self.interval;
-                    if let Err(e) = self.capture_and_upload().await {
-                        error!("Error: {}", e);
+                    self.run_cycle().await;
+                    if let Some(burst) = self.burst.as_mut() {
+                        burst.expire(Instant::now());
                     }
-                    // If the server pushed a new interval, recreate the ticker
-                    // so the change actually takes effect on the next tick.
+                    // If the server pushed a new interval, restart the cadence
+                    // so the change takes effect on the next capture.
                     if self.interval != prev_interval {
-                        info!("Recreating ticker at new interval: {:?}", self.interval);
-                        ticker = interval(self.interval);
-                        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+                        info!("Switching capture cadence to {:?}", self.interval);
+                        pacer.set_period(self.interval, Instant::now());
+                    } else if trigger == Trigger::Tick {
+                        let skipped = pacer.advance(Instant::now());
+                        if skipped > 0 {
+                            warn!(
+                                "Capture cycle overran {:?} — skipped {} tick(s) ({} total)",
+                                self.interval, skipped, pacer.skipped()
+                            );
+                        }
+                    }
+
+                    if self.reached_max_frames() {
+                        break;
+                    }
+                }
+                Some(frame) = async {
+                    match encoded.as_mut() {
+                        Some(rx) => rx.recv().await,
+                        None => std::future::pending().await,
+                    }
+                } => {
+                    self.upload_encoded(frame).await;
+                    if self.reached_max_frames() {
+                        break;
                     }
                 }
                 _ = &mut ctrl_c => {
@@ -135,6 +916,19 @@ impl Agent {
             }
         }
 
+        if let Some(encoder) = &self.encoder
+            && encoder.dropped() > 0
+        {
+            warn!("Dropped {} raw frame(s) while encoding fell behind", encoder.dropped());
+        }
+
+        let servers = self.client.stats();
+        if servers.len() > 1 {
+            for server in servers {
+                info!("{}: {} uploaded, {} failed", server.url, server.succeeded, server.failed);
+            }
+        }
+
         if let Err(e) = self.client.disconnect().await {
             warn!("Failed to disconnect cleanly: {}. \
                 The server slot will remain occupied until the server is restarted.", e);
@@ -146,6 +940,12 @@ impl Agent {
     }
 }
 
+impl AgentBuilder {
+    fn build(self) -> Result<Agent> {
+        Ok(Agent::from_config(self.config()?))
+    }
+}
+
 // Main function to start the agent
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -164,22 +964,418 @@ async fn main() -> Result<()> {
         .init();
 
     // Get configuration from environment
-    let server_url = env::var("EYE_SERVER_URL")
-        .context("EYE_SERVER_URL required")?;
+    // The first URL is the primary; any others are mirrors that receive
+    // every frame too, e.g. EYE_SERVER_URL=http://eye-a:8080,http://eye-b:8080
+    let mut server_urls: Vec<String> = env::var("EYE_SERVER_URL")
+        .context("EYE_SERVER_URL required")?
+        .split(',')
+        .map(|url| url.trim().to_string())
+        .filter(|url| !url.is_empty())
+        .collect();
+    if server_urls.is_empty() {
+        anyhow::bail!("EYE_SERVER_URL required");
+    }
+    let server_url = server_urls.remove(0);
+    let mirror_urls = server_urls;
 
     let token = env::var("EYE_AUTH_TOKEN")
         .unwrap_or_default();
 
-    let interval_ms = env::var("EYE_INTERVAL_MS")
+    // Capture interval as a human string, e.g. EYE_INTERVAL=1.5s or 500ms.
+    // EYE_INTERVAL_MS is still honoured when EYE_INTERVAL is unset.
+    let interval = match env::var("EYE_INTERVAL") {
+        Ok(spec) => clock::parse_interval(&spec, config::MIN_INTERVAL)
+            .context("Invalid EYE_INTERVAL")?,
+        Err(_) => Duration::from_millis(
+            env::var("EYE_INTERVAL_MS")
+                .ok()
+                .and_then(|v| v.parse::<u64>().ok())
+                .unwrap_or(1000),
+        ),
+    };
+
+    // EYE_CAPTURE_ON_CHANGE=1 adds an immediate capture whenever the
+    // display layout changes, on top of the regular interval
+    let capture_on_change = env::var("EYE_CAPTURE_ON_CHANGE")
+        .map(|v| matches!(v.to_lowercase().as_str(), "1" | "true" | "yes"))
+        .unwrap_or(false);
+
+    // Adaptive flow control — back off up to EYE_MAX_INTERVAL_MS when the
+    // server reports high storage pressure. Disabled when unset.
+    let max_interval = env::var("EYE_MAX_INTERVAL_MS")
         .ok()
         .and_then(|v| v.parse::<u64>().ok())
-        .unwrap_or(1000);
-    let interval = Duration::from_millis(interval_ms);
+        .map(Duration::from_millis);
+
+    // EYE_CLIPBOARD_HASH=1 attaches an HMAC-SHA256 of the clipboard text to
+    // each frame. The clipboard content itself is never uploaded. The key is
+    // random per install, kept in EYE_CLIPBOARD_KEY_FILE (default
+    // ~/.eye-agent/clipboard.key).
+    let clipboard_hash = env::var("EYE_CLIPBOARD_HASH")
+        .map(|v| matches!(v.to_lowercase().as_str(), "1" | "true" | "yes"))
+        .unwrap_or(false);
+    let clipboard_key = if clipboard_hash {
+        let path = env::var_os("EYE_CLIPBOARD_KEY_FILE").map(std::path::PathBuf::from).or_else(|| {
+            env::var_os("HOME")
+                .or_else(|| env::var_os("USERPROFILE"))
+                .map(|home| std::path::PathBuf::from(home).join(".eye-agent").join("clipboard.key"))
+        });
+        Some(match path {
+            Some(path) => HashKey::load_or_create(&path)?,
+            None => {
+                warn!("No home directory for the clipboard key; hashes won't match across restarts");
+                HashKey::generate()
+            }
+        })
+    } else {
+        None
+    };
+
+    // Skip captures while the session is locked (on by default where the
+    // platform supports detection); EYE_SKIP_WHEN_LOCKED=0 disables
+    let skip_when_locked = env::var("EYE_SKIP_WHEN_LOCKED")
+        .map(|v| !matches!(v.to_lowercase().as_str(), "0" | "false" | "off" | "no"))
+        .unwrap_or(true);
+
+    // EYE_BATTERY_THROTTLE=1 multiplies the interval by
+    // EYE_BATTERY_INTERVAL_FACTOR (default 2) and caps quality at
+    // EYE_BATTERY_QUALITY while on battery, optionally only once the charge
+    // falls below EYE_BATTERY_BELOW_PERCENT
+    let battery_policy = if env::var("EYE_BATTERY_THROTTLE")
+        .map(|v| matches!(v.to_lowercase().as_str(), "1" | "true" | "yes"))
+        .unwrap_or(false)
+    {
+        let mut policy = BatteryPolicy::default();
+        if let Ok(factor) = env::var("EYE_BATTERY_INTERVAL_FACTOR") {
+            policy.interval_factor = factor.parse().context("Invalid EYE_BATTERY_INTERVAL_FACTOR")?;
+            if !(policy.interval_factor >= 1.0 && policy.interval_factor.is_finite()) {
+                anyhow::bail!("EYE_BATTERY_INTERVAL_FACTOR must be at least 1");
+            }
+        }
+        if let Ok(quality) = env::var("EYE_BATTERY_QUALITY") {
+            policy.quality = Some(quality.parse().context("Invalid EYE_BATTERY_QUALITY")?);
+        }
+        if let Ok(percent) = env::var("EYE_BATTERY_BELOW_PERCENT") {
+            policy.below_percent = Some(percent.parse().context("Invalid EYE_BATTERY_BELOW_PERCENT")?);
+        }
+        Some(policy)
+    } else {
+        None
+    };
+
+    // Smaller frames under storage pressure, e.g. EYE_QUALITY_RAMP="0.8:60,0.9:40:0.5"
+    // (quality 60 from 80% pressure; quality 40 at half resolution from 90%)
+    let quality_ramp = match env::var("EYE_QUALITY_RAMP") {
+        Ok(spec) if !spec.trim().is_empty() => {
+            Some(QualityRamp::parse(&spec).context("Invalid EYE_QUALITY_RAMP")?)
+        }
+        _ => None,
+    };
+
+    // Burst mode: once consecutive frames differ by EYE_BURST_THRESHOLD
+    // (0-1, e.g. 0.3), take EYE_BURST_COUNT (default 5) extra captures
+    // EYE_BURST_INTERVAL apart (default 200ms). Disabled when unset.
+    let burst = match env::var("EYE_BURST_THRESHOLD") {
+        Ok(threshold) if !threshold.trim().is_empty() => Some(BurstConfig {
+            threshold: threshold.parse().context("Invalid EYE_BURST_THRESHOLD")?,
+            count: match env::var("EYE_BURST_COUNT") {
+                Ok(count) => count.parse().context("Invalid EYE_BURST_COUNT")?,
+                Err(_) => 5,
+            },
+            interval: match env::var("EYE_BURST_INTERVAL") {
+                Ok(spec) => clock::parse_interval(&spec, config::MIN_INTERVAL)
+                    .context("Invalid EYE_BURST_INTERVAL")?,
+                Err(_) => Duration::from_millis(200),
+            },
+        }),
+        _ => None,
+    };
+
+    // Dedup: drop frames within EYE_DEDUP_THRESHOLD (change score 0-1,
+    // default 0 = exact repeats) of an upload from the last
+    // EYE_DEDUP_WINDOW, and upload a keyframe every EYE_KEYFRAME_INTERVAL
+    // (default 60s) even if nothing changed. Disabled when unset.
+    let dedup = match env::var("EYE_DEDUP_WINDOW") {
+        Ok(window) if !window.trim().is_empty() => Some(DedupConfig {
+            threshold: match env::var("EYE_DEDUP_THRESHOLD") {
+                Ok(threshold) => threshold.parse().context("Invalid EYE_DEDUP_THRESHOLD")?,
+                Err(_) => 0.0,
+            },
+            window: clock::parse_interval(&window, config::MIN_INTERVAL).context("Invalid EYE_DEDUP_WINDOW")?,
+            keyframe_interval: match env::var("EYE_KEYFRAME_INTERVAL") {
+                Ok(spec) => clock::parse_interval(&spec, config::MIN_INTERVAL)
+                    .context("Invalid EYE_KEYFRAME_INTERVAL")?,
+                Err(_) => Duration::from_secs(60),
+            },
+        }),
+        _ => None,
+    };
+
+    // Optional capture schedule, e.g. EYE_SCHEDULE="mon-fri 09:00-17:00"
+    // with EYE_SCHEDULE_TZ="+02:00" (also accepts "utc" or "local")
+    let schedule = match env::var("EYE_SCHEDULE") {
+        Ok(spec) if !spec.trim().is_empty() => {
+            let tz = ScheduleTz::parse(&env::var("EYE_SCHEDULE_TZ").unwrap_or_default())
+                .context("Invalid EYE_SCHEDULE_TZ")?;
+            Some(Schedule::parse(&spec, tz).context("Invalid EYE_SCHEDULE")?)
+        }
+        _ => None,
+    };
+
+    let mut builder = Agent::builder(server_url.as_str())
+        .mirrors(mirror_urls.clone())
+        .token(token)
+        .interval(interval);
+
+    // With mirrors: EYE_FANOUT_POLICY=all fails an upload unless every
+    // server takes it; the default "any" needs just one
+    if let Ok(policy) = env::var("EYE_FANOUT_POLICY") {
+        builder = builder.fan_out_policy(policy.parse().context("Invalid EYE_FANOUT_POLICY")?);
+    }
+
+    // Output format — EYE_FORMAT=webp|png|jpeg|... or "raw" for lossless
+    // uncompressed RGBA frames
+    if let Ok(f) = env::var("EYE_FORMAT") {
+        builder = if f.eq_ignore_ascii_case(capture::RAW_FORMAT) {
+            builder.raw()
+        } else {
+            builder.format(
+                ImageFormat::from_extension(f.to_lowercase())
+                    .with_context(|| format!("Unsupported EYE_FORMAT '{}'", f))?,
+            )
+        };
+    }
+
+    // Extra encodings of each capture, uploaded with it:
+    // EYE_ALTERNATE_FORMATS=jpeg or jpeg,png
+    if let Ok(list) = env::var("EYE_ALTERNATE_FORMATS") {
+        let formats = list
+            .split(',')
+            .map(str::trim)
+            .filter(|f| !f.is_empty())
+            .map(|f| {
+                ImageFormat::from_extension(f.to_lowercase())
+                    .with_context(|| format!("Unsupported EYE_ALTERNATE_FORMATS entry '{}'", f))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        builder = builder.alternate_formats(formats);
+    }
+
+    // JPEG quality 1-100, e.g. EYE_QUALITY=85
+    if let Ok(quality) = env::var("EYE_QUALITY") {
+        builder = builder.quality(quality.parse().context("Invalid EYE_QUALITY")?);
+    }
+
+    // Monitor index to capture, e.g. EYE_MONITOR=1 for the second screen
+    if let Ok(monitor) = env::var("EYE_MONITOR") {
+        builder = builder.monitor(monitor.parse().context("Invalid EYE_MONITOR")?);
+    }
+
+    // Round-robin across monitors, one per capture: EYE_MONITOR_ROTATION=0,2
+    // or "all". Each frame carries its monitor's fields.
+    if let Ok(list) = env::var("EYE_MONITOR_ROTATION") {
+        let indices: Vec<usize> = if list.trim().eq_ignore_ascii_case("all") {
+            (0..capture::monitors()?.len()).collect()
+        } else {
+            list.split(',')
+                .map(|s| s.trim().parse())
+                .collect::<Result<_, _>>()
+                .context("Invalid EYE_MONITOR_ROTATION")?
+        };
+        info!("Rotating through monitors {:?}", indices);
+        builder = builder.monitor_rotation(indices);
+    }
+
+    // Optional named capture region, e.g. EYE_REGION=center-720p
+    if let Ok(region) = env::var("EYE_REGION") {
+        builder = builder.region(region.parse().context("Invalid EYE_REGION")?);
+    }
+
+    // EYE_FOLLOW_FOCUS=1 crops each frame to the focused window, following
+    // it as it moves; full-screen apps are captured whole
+    if env::var("EYE_FOLLOW_FOCUS")
+        .is_ok_and(|v| matches!(v.to_lowercase().as_str(), "1" | "true" | "on" | "yes"))
+    {
+        builder = builder.follow_focus();
+    }
+
+    // Privacy blocklist, e.g. EYE_BLOCK_WINDOWS="1Password,Signal"
+    if let Ok(list) = env::var("EYE_BLOCK_WINDOWS") {
+        builder = builder.blocked_windows(
+            list.split(',')
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+                .collect(),
+        );
+    }
+
+    // EYE_MASK_NOTIFICATIONS=1 blacks out notification banners from known
+    // notifier processes; EYE_NOTIFICATION_APPS="Growl,..." adds more
+    if env::var("EYE_MASK_NOTIFICATIONS")
+        .is_ok_and(|v| matches!(v.to_lowercase().as_str(), "1" | "true" | "on" | "yes"))
+    {
+        let extra_apps = env::var("EYE_NOTIFICATION_APPS")
+            .map(|list| {
+                list.split(',')
+                    .map(|s| s.trim().to_string())
+                    .filter(|s| !s.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default();
+        builder = builder.notification_mask(capture::NotificationMask { extra_apps });
+    }
+
+    // EYE_PROVENANCE=1 writes hostname, agent id, capture time and monitor
+    // into each PNG/JPEG frame's metadata. Off by default for privacy.
+    // EYE_AGENT_ID names this agent; defaults to the hostname.
+    if env::var("EYE_PROVENANCE")
+        .is_ok_and(|v| matches!(v.to_lowercase().as_str(), "1" | "true" | "on" | "yes"))
+    {
+        let agent_id = env::var("EYE_AGENT_ID").ok().filter(|id| !id.trim().is_empty());
+        builder = builder.provenance(capture::Provenance::local(agent_id));
+    }
+
+    // Per-application redaction rules, e.g.
+    // EYE_REDACTION_RULES="firefox:bank=screen; keepassxc=skip; slack=window"
+    if let Ok(spec) = env::var("EYE_REDACTION_RULES") {
+        builder = builder.redaction_rules(
+            capture::RedactionRule::parse_list(&spec).context("Invalid EYE_REDACTION_RULES")?,
+        );
+    }
+
+    // Shrink huge remote/virtual displays at capture time: EYE_PRE_SCALE=0.5
+    // for every monitor, or per monitor index, e.g. EYE_PRE_SCALE="1=0.25"
+    if let Ok(spec) = env::var("EYE_PRE_SCALE") {
+        builder = builder.pre_scale(spec.parse().context("Invalid EYE_PRE_SCALE")?);
+    }
+
+    // Ordered image transforms, e.g. redact before downscaling:
+    // EYE_TRANSFORMS="redact:0,0,400,80; resize:0.5; grayscale"
+    // A station logo, loaded once here: "logo:/etc/eye/station.png@bottom-right,16,0.8"
+    if let Ok(spec) = env::var("EYE_TRANSFORMS") {
+        builder = builder.transforms(
+            capture::parse_transforms(&spec).context("Invalid EYE_TRANSFORMS")?,
+        );
+    }
+
+    // Random delay before each capture, e.g. EYE_JITTER_MS=200
+    if let Ok(jitter) = env::var("EYE_JITTER_MS") {
+        builder = builder.jitter(Duration::from_millis(
+            jitter.parse().context("Invalid EYE_JITTER_MS")?,
+        ));
+    }
+
+    if let Some(burst) = burst {
+        info!(
+            "Burst mode: {} capture(s) every {:?} after a {:.0}% scene change",
+            burst.count,
+            burst.interval,
+            burst.threshold * 100.0
+        );
+        builder = builder.burst(burst);
+    }
+
+    if let Some(dedup) = dedup {
+        info!(
+            "Dedup: repeats of uploads from the last {:?} dropped, keyframe every {:?}",
+            dedup.window, dedup.keyframe_interval
+        );
+        builder = builder.dedup(dedup);
+    }
+
+    // Stop after N uploaded frames, e.g. EYE_AGENT_MAX_CAPTURES=100. Not
+    // EYE_MAX_FRAMES, which sizes the server's ring buffer.
+    if let Ok(max_frames) = env::var("EYE_AGENT_MAX_CAPTURES") {
+        builder = builder.max_frames(max_frames.parse().context("Invalid EYE_AGENT_MAX_CAPTURES")?);
+    }
+
+    // Offline spool: frames captured while the server is unreachable are
+    // kept in EYE_SPOOL_DIR, AES-256-GCM encrypted with EYE_SPOOL_KEY (64
+    // hex characters), and uploaded once it is back. At most
+    // EYE_SPOOL_MAX_FRAMES (default 1000) are kept; the oldest go first.
+    // Frames the server rejects are moved to EYE_SPOOL_DIR/quarantine.
+    let spool = match env::var("EYE_SPOOL_DIR") {
+        Ok(dir) => {
+            let key = env::var("EYE_SPOOL_KEY")
+                .context("EYE_SPOOL_DIR requires EYE_SPOOL_KEY to encrypt spooled frames")?;
+            let key = SpoolKey::from_hex(&key).context("Invalid EYE_SPOOL_KEY")?;
+            let max_frames = match env::var("EYE_SPOOL_MAX_FRAMES") {
+                Ok(max) => max.parse().context("Invalid EYE_SPOOL_MAX_FRAMES")?,
+                Err(_) => 1000,
+            };
+            let spool = Spool::open(dir, key, max_frames)?;
+            let quarantined = spool.quarantined()?.len();
+            if quarantined > 0 {
+                warn!("{} spooled frame(s) were rejected by the server and are in quarantine", quarantined);
+            }
+            Some(spool)
+        }
+        Err(_) => None,
+    };
+
+    // EYE_ENCODE_WORKERS=N encodes on N background workers so capture keeps
+    // its cadence when an encode is slow; at most EYE_ENCODE_QUEUE (default
+    // 2) raw grabs wait, the oldest dropped beyond that. 0/unset encodes inline.
+    let encode_workers: usize = match env::var("EYE_ENCODE_WORKERS") {
+        Ok(workers) => workers.parse().context("Invalid EYE_ENCODE_WORKERS")?,
+        Err(_) => 0,
+    };
+    let encode_queue: usize = match env::var("EYE_ENCODE_QUEUE") {
+        Ok(queue) => queue.parse().context("Invalid EYE_ENCODE_QUEUE")?,
+        Err(_) => 2,
+    };
+
+    // EYE_FRAME_ID_MODE=timestamp keeps ids increasing across restarts
+    let frame_id_mode = match env::var("EYE_FRAME_ID_MODE") {
+        Ok(mode) => mode.parse().context("Invalid EYE_FRAME_ID_MODE")?,
+        Err(_) => FrameIdMode::Sequential,
+    };
 
     info!("Server: {}", server_url);
+    if !mirror_urls.is_empty() {
+        info!("Mirrors: {}", mirror_urls.join(", "));
+    }
     info!("Interval: {:.1}s", interval.as_secs_f64());
 
-    let mut agent = Agent::new(server_url, token, interval);
+    let mut agent = builder
+        .build()
+        .context("Invalid agent configuration")?
+        .with_frame_id_mode(frame_id_mode);
+    if capture_on_change {
+        info!("Capturing on display changes");
+        agent = agent.with_change_events(change::watch_displays(Duration::from_secs(1)));
+    }
+    if let Some(max_interval) = max_interval {
+        info!("Pressure backoff up to {:.1}s", max_interval.as_secs_f64());
+        agent = agent.with_max_interval(max_interval);
+    }
+    if skip_when_locked {
+        agent = agent.with_lock_detection(Box::new(lock::SystemLockState::default()));
+    }
+    if let Some(policy) = battery_policy {
+        info!("Battery throttling: {:?}", policy);
+        agent = agent.with_battery_throttle(Box::new(power::SystemPowerSource::default()), policy);
+    }
+    if let Some(spool) = spool {
+        info!("Spooling frames to {} while the server is unreachable", env::var("EYE_SPOOL_DIR").unwrap_or_default());
+        agent = agent.with_spool(spool);
+    }
+    if let Some(key) = clipboard_key {
+        info!("Attaching clipboard hashes to frames");
+        agent = agent.with_clipboard_hash(Box::new(clipboard::SystemClipboard::new()?), key);
+    }
+    if let Some(ramp) = quality_ramp {
+        info!("Quality ramp: {}", env::var("EYE_QUALITY_RAMP").unwrap_or_default());
+        agent = agent.with_quality_ramp(ramp);
+    }
+    if let Some(schedule) = schedule {
+        info!("Capture schedule: {}", env::var("EYE_SCHEDULE").unwrap_or_default());
+        agent = agent.with_schedule(schedule);
+    }
+    if encode_workers > 0 {
+        info!("Encoding on {} worker(s), queue of {}", encode_workers, encode_queue);
+        agent = agent.with_encode_workers(encode_workers, encode_queue);
+    }
     agent.start().await?;
 
     Ok(())
@@ -190,13 +1386,480 @@ async fn main() -> Result<()> {
 mod tests {
     use super::*;
 
+    fn test_agent(interval: Duration) -> Agent {
+        Agent::builder("http://localhost:8080")
+            .token("test-token")
+            .interval(interval)
+            .build()
+            .unwrap()
+    }
+
     #[test]
     fn test_agent_creation() {
-        let agent = Agent::new(
-            "http://localhost:8080".to_string(),
-            "test-token".to_string(),
-            Duration::from_secs(1),
-        );
+        let agent = test_agent(Duration::from_secs(1));
         assert_eq!(agent.frame_id, 0);
     }
+
+    #[test]
+    fn test_builder_configures_engine() {
+        let agent = Agent::builder("http://localhost:8080")
+            .interval(Duration::from_millis(500))
+            .format(ImageFormat::Jpeg)
+            .quality(60)
+            .monitor(1)
+            .jitter(Duration::from_millis(100))
+            .max_frames(10)
+            .build()
+            .unwrap();
+
+        let engine = agent.engine.config();
+        assert_eq!(engine.format, ImageFormat::Jpeg);
+        assert!(!engine.raw);
+        assert_eq!(engine.quality, 60);
+        assert_eq!(engine.monitor, Some(1));
+        assert_eq!(engine.interval, Duration::from_millis(500));
+        assert_eq!(agent.max_frames, Some(10));
+
+        // Validation rejects nonsense up front
+        assert!(Agent::builder("localhost:8080").build().is_err());
+        assert!(Agent::builder("http://localhost:8080").quality(0).build().is_err());
+        assert!(Agent::builder("http://localhost:8080")
+            .interval(Duration::from_millis(100))
+            .jitter(Duration::from_millis(100))
+            .build()
+            .is_err());
+    }
+
+    #[test]
+    fn test_schedule_gates_capture() {
+        use chrono::TimeZone;
+
+        let schedule = Schedule::parse("mon-fri 09:00-17:00", ScheduleTz::Utc).unwrap();
+        let agent = test_agent(Duration::from_secs(1))
+            .with_schedule(schedule);
+
+        // Monday 08:00 UTC — outside the window, capture is skipped
+        let outside = Utc.with_ymd_and_hms(2026, 3, 2, 8, 0, 0).unwrap();
+        assert!(!agent.should_capture(outside));
+
+        // Monday 10:00 UTC — inside the window, capture proceeds
+        let inside = Utc.with_ymd_and_hms(2026, 3, 2, 10, 0, 0).unwrap();
+        assert!(agent.should_capture(inside));
+    }
+
+    #[test]
+    fn test_timestamp_frame_ids_survive_restart() {
+        use chrono::TimeZone;
+
+        let new_agent = || {
+            test_agent(Duration::from_secs(1))
+                .with_frame_id_mode(FrameIdMode::Timestamp)
+        };
+        let t0 = Utc.with_ymd_and_hms(2026, 3, 2, 10, 0, 0).unwrap();
+
+        // First session: two uploads within the same millisecond
+        let mut first = new_agent();
+        let a = first.next_frame_id(t0);
+        first.frame_id = a + 1;
+        let b = first.next_frame_id(t0);
+        assert!(b > a);
+
+        // Restarted agent, a second later, continues above the first session
+        let second = new_agent();
+        let c = second.next_frame_id(t0 + chrono::Duration::seconds(1));
+        assert!(c > b);
+    }
+
+    #[test]
+    fn test_pressure_backoff_within_bounds() {
+        let mut agent = test_agent(Duration::from_secs(1))
+            .with_max_interval(Duration::from_secs(4));
+
+        agent.apply_pressure(0.95);
+        assert!(agent.interval > Duration::from_secs(1));
+
+        // Sustained pressure never pushes past the configured maximum
+        for _ in 0..10 {
+            agent.apply_pressure(1.0);
+        }
+        assert_eq!(agent.interval, Duration::from_secs(4));
+
+        // Once pressure eases the interval returns to its base
+        for _ in 0..10 {
+            agent.apply_pressure(0.1);
+        }
+        assert_eq!(agent.interval, Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_quality_ramp_follows_pressure() {
+        let mut agent = Agent::builder("http://localhost:8080")
+            .quality(80)
+            .build()
+            .unwrap()
+            .with_quality_ramp(QualityRamp::parse("0.8:60,0.9:40:0.5").unwrap());
+
+        agent.apply_quality_ramp(0.95);
+        assert_eq!(agent.engine.config().quality, 40);
+        assert_eq!(agent.engine.config().scale, 0.5);
+
+        agent.apply_quality_ramp(0.85);
+        assert_eq!(agent.engine.config().quality, 60);
+        assert_eq!(agent.engine.config().scale, 1.0);
+
+        // Pressure eased: back to the configured settings
+        agent.apply_quality_ramp(0.2);
+        assert_eq!(agent.engine.config().quality, 80);
+        assert_eq!(agent.engine.config().scale, 1.0);
+    }
+
+    struct FakeClipboard(std::sync::Arc<std::sync::Mutex<Option<String>>>);
+
+    impl ClipboardSource for FakeClipboard {
+        fn text(&mut self) -> Result<Option<String>> {
+            Ok(self.0.lock().unwrap().clone())
+        }
+    }
+
+    #[test]
+    fn test_clipboard_hash_tracks_clipboard() {
+        let contents = std::sync::Arc::new(std::sync::Mutex::new(None));
+        let mut agent = test_agent(Duration::from_secs(1))
+            .with_clipboard_hash(Box::new(FakeClipboard(contents.clone())), HashKey::generate());
+
+        // Nothing copied: no metadata
+        assert_eq!(agent.clipboard_hash(), None);
+
+        *contents.lock().unwrap() = Some("hunter2".to_string());
+        let first = agent.clipboard_hash().unwrap();
+        assert_eq!(first.len(), 64);
+        assert!(!first.contains("hunter2"));
+        assert_eq!(agent.clipboard_hash(), Some(first.clone()));
+
+        *contents.lock().unwrap() = Some("something else".to_string());
+        assert_ne!(agent.clipboard_hash().unwrap(), first);
+    }
+
+    struct FakeLockState(std::sync::Arc<std::sync::Mutex<Option<bool>>>);
+
+    impl LockState for FakeLockState {
+        fn is_locked(&mut self) -> Option<bool> {
+            *self.0.lock().unwrap()
+        }
+    }
+
+    #[test]
+    fn test_captures_skipped_while_locked() {
+        let state = std::sync::Arc::new(std::sync::Mutex::new(Some(false)));
+        let mut agent = test_agent(Duration::from_secs(1))
+            .with_lock_detection(Box::new(FakeLockState(state.clone())));
+        assert!(agent.session_unlocked());
+
+        *state.lock().unwrap() = Some(true);
+        assert!(!agent.session_unlocked());
+        assert!(agent.locked);
+
+        *state.lock().unwrap() = Some(false);
+        assert!(agent.session_unlocked());
+        assert!(!agent.locked);
+
+        // Undetectable lock state behaves as if unlocked
+        *state.lock().unwrap() = None;
+        assert!(agent.session_unlocked());
+    }
+
+    struct FakePowerSource(std::sync::Arc<std::sync::Mutex<Option<power::PowerStatus>>>);
+
+    impl PowerSource for FakePowerSource {
+        fn status(&mut self) -> Option<power::PowerStatus> {
+            *self.0.lock().unwrap()
+        }
+    }
+
+    #[test]
+    fn test_battery_throttles_until_ac() {
+        let on = |on_battery, percent| Some(power::PowerStatus { on_battery, percent });
+        let status = std::sync::Arc::new(std::sync::Mutex::new(on(false, Some(90))));
+        let policy = BatteryPolicy { interval_factor: 3.0, quality: Some(50), below_percent: None };
+        let mut agent = Agent::builder("http://localhost:8080")
+            .interval(Duration::from_secs(1))
+            .quality(80)
+            .build()
+            .unwrap()
+            .with_battery_throttle(Box::new(FakePowerSource(status.clone())), policy);
+
+        agent.apply_power();
+        assert_eq!(agent.interval, Duration::from_secs(1));
+
+        *status.lock().unwrap() = on(true, Some(90));
+        agent.apply_power();
+        assert_eq!(agent.interval, Duration::from_secs(3));
+        assert_eq!(agent.engine.config().quality, 50);
+
+        // Pressure relief never undercuts the battery interval
+        agent.max_interval = Some(Duration::from_secs(10));
+        agent.apply_pressure(0.1);
+        assert_eq!(agent.interval, Duration::from_secs(3));
+
+        *status.lock().unwrap() = on(false, Some(91));
+        agent.apply_power();
+        assert_eq!(agent.interval, Duration::from_secs(1));
+        assert_eq!(agent.engine.config().quality, 80);
+
+        // With a charge threshold, a healthy battery is left alone
+        agent.battery_policy.below_percent = Some(20);
+        *status.lock().unwrap() = on(true, Some(60));
+        agent.apply_power();
+        assert_eq!(agent.interval, Duration::from_secs(1));
+        *status.lock().unwrap() = on(true, Some(15));
+        agent.apply_power();
+        assert_eq!(agent.interval, Duration::from_secs(3));
+    }
+
+    #[tokio::test]
+    async fn test_change_event_triggers_extra_capture() {
+        let (tx, rx) = tokio::sync::mpsc::channel(8);
+        let mut agent = test_agent(Duration::from_secs(3600))
+            .with_change_events(rx);
+        agent.change_coalesce = Duration::from_millis(10);
+
+        let mut pacer = Pacer::new(agent.interval, Instant::now());
+        // The first deadline is due immediately
+        assert_eq!(agent.next_trigger(&pacer).await, Trigger::Tick);
+        pacer.advance(Instant::now());
+
+        // A burst of changes yields exactly one extra capture trigger
+        for _ in 0..3 {
+            tx.send(()).await.unwrap();
+        }
+        assert_eq!(agent.next_trigger(&pacer).await, Trigger::Change);
+
+        let next = tokio::time::timeout(
+            Duration::from_millis(50),
+            agent.next_trigger(&pacer),
+        ).await;
+        assert!(next.is_err(), "burst should have been coalesced");
+    }
+
+    #[tokio::test]
+    async fn test_scene_change_triggers_burst() {
+        let mut agent = Agent::builder("http://localhost:8080")
+            .interval(Duration::from_secs(3600))
+            .burst(BurstConfig { threshold: 0.5, count: 3, interval: Duration::from_millis(50) })
+            .build()
+            .unwrap();
+        let dark = vec![0u8; 256];
+        let bright = vec![255u8; 256];
+
+        let mut pacer = Pacer::new(agent.interval, Instant::now());
+        assert_eq!(agent.next_trigger(&pacer).await, Trigger::Tick);
+        pacer.advance(Instant::now());
+        agent.observe_scene(&dark, Instant::now());
+
+        // Small differences never start a burst
+        agent.observe_scene(&[10u8; 256], Instant::now());
+        assert!(agent.burst.as_ref().unwrap().deadline().is_none());
+
+        // A large change is followed by exactly `count` burst captures
+        agent.observe_scene(&bright, Instant::now());
+        for _ in 0..3 {
+            assert_eq!(agent.next_trigger(&pacer).await, Trigger::Burst);
+            agent.observe_scene(&bright, Instant::now());
+        }
+
+        let next = tokio::time::timeout(Duration::from_millis(200), agent.next_trigger(&pacer)).await;
+        assert!(next.is_err(), "burst should be over");
+    }
+
+    #[test]
+    fn test_rate_limit_stretches_interval_until_success() {
+        let mut agent = test_agent(Duration::from_secs(1));
+
+        let err = anyhow::Error::new(transport::RateLimited { retry_after: Duration::from_secs(5) })
+            .context("Failed to upload frame");
+        let limited = err.downcast_ref::<transport::RateLimited>().unwrap();
+        agent.apply_rate_limit(limited.retry_after);
+        assert_eq!(agent.interval, Duration::from_secs(5));
+
+        // A shorter Retry-After never shortens the interval
+        agent.apply_rate_limit(Duration::from_secs(2));
+        assert_eq!(agent.interval, Duration::from_secs(5));
+
+        agent.clear_rate_limit();
+        assert_eq!(agent.interval, Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_watchdog_triggers_on_sustained_failures() {
+        let mut agent = test_agent(Duration::from_secs(1));
+        let start = Instant::now();
+        let threshold = agent.watchdog.threshold();
+
+        // One failed capture per second; recovery must not fire early
+        let mut triggered_at = None;
+        for secs in 1..=threshold.as_secs() * 2 {
+            if agent.watchdog.observe(false, start + Duration::from_secs(secs)) {
+                triggered_at = Some(secs);
+                break;
+            }
+        }
+        assert_eq!(triggered_at, Some(threshold.as_secs()));
+
+        // A successful upload re-arms the watchdog
+        let later = start + threshold * 2;
+        assert!(!agent.watchdog.observe(true, later));
+        assert!(!agent.watchdog.observe(false, later + Duration::from_secs(1)));
+    }
+
+    #[tokio::test]
+    async fn test_spool_encrypts_at_rest_and_drain_decrypts() {
+        let dir = std::env::temp_dir().join(format!("eye-spool-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let key = "11".repeat(32);
+        let spool = Spool::open(&dir, SpoolKey::from_hex(&key).unwrap(), 10).unwrap();
+
+        let secret = b"SECRET-SCREENSHOT-PIXELS".repeat(4);
+        let frame = SpooledFrame {
+            frame_id: 7,
+            fields: vec![("format".to_string(), "png".to_string())],
+            data: secret.clone(),
+            endpoints: vec!["http://backup:8080".to_string()],
+        };
+        spool.push(&frame).unwrap();
+
+        // Nothing readable on disk
+        let files = spool.pending().unwrap();
+        assert_eq!(files.len(), 1);
+        let on_disk = std::fs::read(&files[0]).unwrap();
+        assert!(!on_disk.windows(6).any(|w| w == b"SECRET"));
+
+        // The wrong key can't open it
+        let other = Spool::open(&dir, SpoolKey::from_hex(&"22".repeat(32)).unwrap(), 10).unwrap();
+        assert!(other.read(&files[0]).is_err());
+
+        // A failed upload leaves the frame queued
+        let failed = spool.drain(10, |_| async { anyhow::bail!("offline") }).await;
+        assert_eq!(failed.unwrap(), 0);
+        assert_eq!(spool.pending().unwrap().len(), 1);
+
+        // Drain hands the decrypted frame to the uploader and empties the queue
+        let mut uploaded = Vec::new();
+        let count = spool
+            .drain(10, |f| {
+                uploaded.push(f);
+                async { Ok(()) }
+            })
+            .await
+            .unwrap();
+        assert_eq!(count, 1);
+        assert_eq!(uploaded, vec![frame]);
+        assert!(spool.pending().unwrap().is_empty());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_spool_drain_quarantines_rejects_and_stops_when_unreachable() {
+        let dir = std::env::temp_dir().join(format!("eye-spool-drain-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let spool = Spool::open(&dir, SpoolKey::from_hex(&"44".repeat(32)).unwrap(), 10).unwrap();
+        for id in 0..5 {
+            spool.push(&SpooledFrame { frame_id: id, fields: vec![], data: vec![id as u8], endpoints: vec![] }).unwrap();
+        }
+
+        // Nothing listens on port 1
+        let offline = Client::new("http://127.0.0.1:1".to_string(), String::new());
+        let mut unreachable = Some(offline.upload_frame(0, vec![]).await.unwrap_err());
+        assert!(transport::is_unreachable(unreachable.as_ref().unwrap()));
+
+        let result = spool
+            .drain(10, |frame| {
+                let outcome = match frame.frame_id {
+                    0 => Err(transport::Rejected { status: 400, body: "bad frame".to_string() }.into()),
+                    1 => Err(anyhow::anyhow!("Upload failed: 503")),
+                    2 => Ok(()),
+                    _ => Err(unreachable
+                        .take()
+                        .unwrap()
+                        .context(transport::FailedEndpoints(vec!["http://backup:8080".to_string()]))),
+                };
+                async move { outcome }
+            })
+            .await;
+        assert!(result.is_err());
+
+        // 0 was set aside, 1 stays for a retry, 2 went up, and draining
+        // stopped at 3 so 4 was never tried
+        let ids: Vec<i64> = spool
+            .pending()
+            .unwrap()
+            .iter()
+            .map(|p| spool.read(p).unwrap().frame_id)
+            .collect();
+        assert_eq!(ids, vec![1, 3, 4]);
+        // Only the server that missed frame 3 will get it again
+        let pending = spool.pending().unwrap();
+        assert_eq!(spool.read(&pending[1]).unwrap().endpoints, vec!["http://backup:8080".to_string()]);
+        assert!(spool.read(&pending[2]).unwrap().endpoints.is_empty());
+        let quarantined = spool.quarantined().unwrap();
+        assert_eq!(quarantined.len(), 1);
+        assert_eq!(spool.read(&quarantined[0]).unwrap().frame_id, 0);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_spool_drops_oldest_when_full() {
+        let dir = std::env::temp_dir().join(format!("eye-spool-full-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let spool = Spool::open(&dir, SpoolKey::from_hex(&"33".repeat(32)).unwrap(), 2).unwrap();
+
+        for id in 0..3 {
+            spool.push(&SpooledFrame { frame_id: id, fields: vec![], data: vec![id as u8], endpoints: vec![] }).unwrap();
+        }
+        let ids: Vec<i64> = spool
+            .pending()
+            .unwrap()
+            .iter()
+            .map(|p| spool.read(p).unwrap().frame_id)
+            .collect();
+        assert_eq!(ids, vec![1, 2]);
+
+        assert!(SpoolKey::from_hex("abcd").is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_dedup_drops_repeats_within_window_but_keeps_keyframes() {
+        let mut dedup = Dedup::new(DedupConfig {
+            threshold: 0.0,
+            window: Duration::from_secs(10),
+            keyframe_interval: Duration::from_secs(30),
+        });
+        let screen = vec![7u8; 256];
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+        let upload = |dedup: &mut Dedup, secs| {
+            let verdict = dedup.judge(&screen, at(secs));
+            if verdict != Verdict::Drop {
+                dedup.uploaded(&screen, verdict, at(secs));
+            }
+            verdict
+        };
+
+        // The first frame is a keyframe; unchanged frames inside the
+        // window are dropped
+        assert_eq!(upload(&mut dedup, 0), Verdict::Keyframe);
+        assert_eq!(upload(&mut dedup, 1), Verdict::Drop);
+        assert_eq!(upload(&mut dedup, 9), Verdict::Drop);
+        // Once the last upload ages out of the window, it goes through again
+        assert_eq!(upload(&mut dedup, 10), Verdict::Upload);
+        assert_eq!(upload(&mut dedup, 15), Verdict::Drop);
+        // A changed frame is never a duplicate
+        assert_eq!(dedup.judge(&[200u8; 256], at(16)), Verdict::Upload);
+        assert_eq!(upload(&mut dedup, 19), Verdict::Drop);
+        // Still unchanged, but the keyframe interval is up
+        assert_eq!(upload(&mut dedup, 30), Verdict::Keyframe);
+        assert_eq!(upload(&mut dedup, 31), Verdict::Drop);
+    }
 }
\ No newline at end of file
@@ -0,0 +1,88 @@
+// crates/agent/src/pacer.rs
+use std::time::Duration;
+use tokio::time::Instant;
+
+// Fixed-cadence capture timing. Deadlines sit on a grid of
+// `start + n * period`, so the time spent capturing and uploading is
+// absorbed by a shorter wait rather than added on top of the period.
+// When a cycle overruns, the deadlines it missed are skipped instead of
+// fired back to back, keeping captures on the grid.
+#[derive(Debug, Clone)]
+pub struct Pacer {
+    period: Duration,
+    next: Instant,
+    skipped: u64,
+}
+
+impl Pacer {
+    // First deadline is `start` itself
+    pub fn new(period: Duration, start: Instant) -> Self {
+        Self {
+            period,
+            next: start,
+            skipped: 0,
+        }
+    }
+
+    // When the next capture is due
+    pub fn deadline(&self) -> Instant {
+        self.next
+    }
+
+    // Number of deadlines dropped because a cycle overran
+    pub fn skipped(&self) -> u64 {
+        self.skipped
+    }
+
+    // Move to the first grid deadline after `now`, the time the last cycle
+    // finished. Returns how many deadlines were skipped on the way.
+    pub fn advance(&mut self, now: Instant) -> u64 {
+        self.next += self.period;
+        if self.next > now {
+            return 0;
+        }
+
+        let behind = now.duration_since(self.next);
+        let missed = (behind.as_nanos() / self.period.as_nanos().max(1)) as u32 + 1;
+        self.next += self.period * missed;
+        self.skipped += missed as u64;
+        missed as u64
+    }
+
+    // Switch to a new period; the grid restarts one period after `now`
+    pub fn set_period(&mut self, period: Duration, now: Instant) {
+        self.period = period;
+        self.next = now + period;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fast_cycles_keep_cadence() {
+        let start = Instant::now();
+        let mut pacer = Pacer::new(Duration::from_millis(100), start);
+        assert_eq!(pacer.deadline(), start);
+
+        // 30ms of work leaves a 70ms wait, not a full period
+        assert_eq!(pacer.advance(start + Duration::from_millis(30)), 0);
+        assert_eq!(pacer.deadline(), start + Duration::from_millis(100));
+    }
+
+    #[test]
+    fn test_slow_cycle_skips_instead_of_lagging() {
+        let start = Instant::now();
+        let mut pacer = Pacer::new(Duration::from_millis(100), start);
+
+        // Simulated slow capture: 250ms of work on a 100ms cadence
+        assert_eq!(pacer.advance(start + Duration::from_millis(250)), 2);
+        assert_eq!(pacer.deadline(), start + Duration::from_millis(300));
+
+        // Back on the grid — later cycles carry no accumulated lag
+        assert_eq!(pacer.advance(start + Duration::from_millis(320)), 0);
+        assert_eq!(pacer.deadline(), start + Duration::from_millis(400));
+        assert_eq!(pacer.skipped(), 2);
+    }
+}
@@ -0,0 +1,166 @@
+// crates/agent/src/power.rs
+use std::time::Duration;
+use tokio::time::Instant;
+
+// Snapshot of the machine's power supply
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PowerStatus {
+    pub on_battery: bool,
+    /// Remaining charge 0-100, when the platform reports it
+    pub percent: Option<u8>,
+}
+
+// Reports the current power supply. None means there is no battery or the
+// state can't be determined, in which case captures are never throttled.
+pub trait PowerSource: Send {
+    fn status(&mut self) -> Option<PowerStatus>;
+}
+
+// How captures are throttled while running on battery
+#[derive(Debug, Clone, PartialEq)]
+pub struct BatteryPolicy {
+    /// The base interval is multiplied by this while on battery
+    pub interval_factor: f64,
+    /// Encode quality is capped at this while on battery
+    pub quality: Option<u8>,
+    /// Only throttle once the charge drops below this percentage
+    pub below_percent: Option<u8>,
+}
+
+impl Default for BatteryPolicy {
+    fn default() -> Self {
+        Self {
+            interval_factor: 2.0,
+            quality: None,
+            below_percent: None,
+        }
+    }
+}
+
+impl BatteryPolicy {
+    // Whether captures should be throttled given `status`. A charge level
+    // threshold is ignored when the platform doesn't report the charge.
+    pub fn applies(&self, status: Option<PowerStatus>) -> bool {
+        let Some(status) = status else {
+            return false;
+        };
+        if !status.on_battery {
+            return false;
+        }
+
+        match (self.below_percent, status.percent) {
+            (Some(threshold), Some(percent)) => percent < threshold,
+            _ => true,
+        }
+    }
+}
+
+// How long a power probe result is reused; some probes spawn a process
+const PROBE_TTL: Duration = Duration::from_secs(10);
+
+// Asks the OS: /sys/class/power_supply on Linux, pmset on macOS and
+// Win32_Battery on Windows
+#[derive(Default)]
+pub struct SystemPowerSource {
+    last: Option<(Instant, Option<PowerStatus>)>,
+}
+
+impl PowerSource for SystemPowerSource {
+    fn status(&mut self) -> Option<PowerStatus> {
+        if let Some((at, status)) = self.last
+            && at.elapsed() < PROBE_TTL
+        {
+            return status;
+        }
+
+        let status = probe();
+        self.last = Some((Instant::now(), status));
+        status
+    }
+}
+
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+fn command_output(program: &str, args: &[&str]) -> Option<String> {
+    let output = std::process::Command::new(program).args(args).output().ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(target_os = "linux")]
+fn probe() -> Option<PowerStatus> {
+    let read = |path: &std::path::Path, name: &str| {
+        std::fs::read_to_string(path.join(name))
+            .map(|s| s.trim().to_string())
+            .ok()
+    };
+
+    let mut mains_online = None;
+    let mut battery = None;
+    for entry in std::fs::read_dir("/sys/class/power_supply").ok()?.flatten() {
+        let path = entry.path();
+        match read(&path, "type").as_deref() {
+            Some("Mains") => {
+                let online = read(&path, "online").as_deref() == Some("1");
+                mains_online = Some(mains_online.unwrap_or(false) || online);
+            }
+            Some("Battery") if battery.is_none() => {
+                let discharging = read(&path, "status").as_deref() == Some("Discharging");
+                let percent = read(&path, "capacity").and_then(|c| c.parse().ok());
+                battery = Some((discharging, percent));
+            }
+            _ => {}
+        }
+    }
+
+    // Desktops have no battery at all
+    let (discharging, percent) = battery?;
+    Some(PowerStatus {
+        on_battery: mains_online.map_or(discharging, |online| !online),
+        percent,
+    })
+}
+
+#[cfg(target_os = "macos")]
+fn probe() -> Option<PowerStatus> {
+    // "Now drawing from 'Battery Power'\n -InternalBattery-0 (id=...)\t85%; discharging; ..."
+    let output = command_output("pmset", &["-g", "batt"])?;
+    if !output.contains("InternalBattery") {
+        return None;
+    }
+
+    let percent = output
+        .split(|c: char| c.is_whitespace() || c == ';')
+        .find_map(|word| word.strip_suffix('%')?.parse().ok());
+    Some(PowerStatus {
+        on_battery: output.contains("'Battery Power'"),
+        percent,
+    })
+}
+
+#[cfg(target_os = "windows")]
+fn probe() -> Option<PowerStatus> {
+    // BatteryStatus=1 means discharging; 2 and above are AC states
+    let output = command_output(
+        "wmic",
+        &["path", "Win32_Battery", "get", "BatteryStatus,EstimatedChargeRemaining", "/value"],
+    )?;
+    let value = |key: &str| {
+        output
+            .lines()
+            .find_map(|line| line.trim().strip_prefix(key)?.strip_prefix('='))
+            .and_then(|v| v.trim().parse::<u32>().ok())
+    };
+
+    let status = value("BatteryStatus")?;
+    Some(PowerStatus {
+        on_battery: status == 1,
+        percent: value("EstimatedChargeRemaining").map(|p| p.min(100) as u8),
+    })
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+fn probe() -> Option<PowerStatus> {
+    None
+}
@@ -0,0 +1,100 @@
+// crates/agent/src/quality.rs
+use anyhow::{Context, Result};
+
+// One step of the ramp: from `pressure` upward, encode at no more than
+// `quality` and downscale frames by `scale`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RampStep {
+    pub pressure: f64,
+    pub quality: u8,
+    pub scale: f32,
+}
+
+// Maps server storage pressure to smaller frames. Below the first step the
+// agent's configured quality and resolution apply unchanged.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QualityRamp {
+    /// Sorted by ascending pressure
+    steps: Vec<RampStep>,
+}
+
+impl QualityRamp {
+    // Parse a ','-separated list of "pressure:quality[:scale]" steps, e.g.
+    // "0.8:60,0.9:40:0.5" — quality 60 from 80% pressure, then quality 40
+    // at half resolution from 90%
+    pub fn parse(spec: &str) -> Result<Self> {
+        let mut steps = spec
+            .split(',')
+            .filter(|s| !s.trim().is_empty())
+            .map(parse_step)
+            .collect::<Result<Vec<_>>>()?;
+
+        if steps.is_empty() {
+            anyhow::bail!("Quality ramp '{}' contains no steps", spec);
+        }
+        steps.sort_by(|a, b| a.pressure.total_cmp(&b.pressure));
+
+        Ok(Self { steps })
+    }
+
+    // The step in force at `pressure`, if any
+    pub fn step(&self, pressure: f64) -> Option<&RampStep> {
+        self.steps.iter().rev().find(|s| pressure >= s.pressure)
+    }
+}
+
+fn parse_step(s: &str) -> Result<RampStep> {
+    let s = s.trim();
+    let mut parts = s.split(':');
+    let mut next = |what: &str| {
+        parts
+            .next()
+            .with_context(|| format!("Invalid ramp step '{}': missing {}", s, what))
+    };
+
+    let pressure: f64 = next("pressure")?
+        .trim()
+        .parse()
+        .with_context(|| format!("Invalid pressure in ramp step '{}'", s))?;
+    let quality: u8 = next("quality")?
+        .trim()
+        .parse()
+        .with_context(|| format!("Invalid quality in ramp step '{}'", s))?;
+    let scale: f32 = match parts.next() {
+        Some(scale) => scale
+            .trim()
+            .parse()
+            .with_context(|| format!("Invalid scale in ramp step '{}'", s))?,
+        None => 1.0,
+    };
+
+    if !(0.0..=1.0).contains(&pressure) {
+        anyhow::bail!("Ramp pressure {} out of range 0.0-1.0", pressure);
+    }
+    if !(1..=100).contains(&quality) {
+        anyhow::bail!("Ramp quality {} out of range 1-100", quality);
+    }
+    if !(scale > 0.0 && scale <= 1.0) {
+        anyhow::bail!("Ramp scale {} out of range (0.0, 1.0]", scale);
+    }
+
+    Ok(RampStep { pressure, quality, scale })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_select_step() {
+        let ramp = QualityRamp::parse("0.9:40:0.5, 0.8:60").unwrap();
+        assert_eq!(ramp.step(0.5), None);
+        assert_eq!(ramp.step(0.85).map(|s| s.quality), Some(60));
+        assert_eq!(ramp.step(0.95), Some(&RampStep { pressure: 0.9, quality: 40, scale: 0.5 }));
+
+        assert!(QualityRamp::parse("").is_err());
+        assert!(QualityRamp::parse("0.8").is_err());
+        assert!(QualityRamp::parse("1.5:60").is_err());
+        assert!(QualityRamp::parse("0.8:60:2").is_err());
+    }
+}
@@ -0,0 +1,241 @@
+// crates/agent/src/schedule.rs
+use anyhow::{Context, Result};
+use chrono::{DateTime, Datelike, FixedOffset, Local, Timelike, Utc, Weekday};
+
+const SECONDS_PER_DAY: u32 = 24 * 60 * 60;
+
+// Timezone the schedule windows are expressed in
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ScheduleTz {
+    Utc,
+    Local,
+    Fixed(FixedOffset),
+}
+
+impl ScheduleTz {
+    // Parse "utc", "local" or a fixed offset such as "+02:00" / "-0530"
+    pub fn parse(s: &str) -> Result<Self> {
+        let s = s.trim();
+        match s.to_lowercase().as_str() {
+            "" | "utc" | "z" => return Ok(Self::Utc),
+            "local" => return Ok(Self::Local),
+            _ => {}
+        }
+
+        let (sign, rest) = match s.as_bytes()[0] {
+            b'+' => (1, &s[1..]),
+            b'-' => (-1, &s[1..]),
+            _ => anyhow::bail!("Invalid timezone '{}': expected utc, local or ±HH:MM", s),
+        };
+        let digits: String = rest.chars().filter(|c| *c != ':').collect();
+        if digits.len() != 4 || !digits.chars().all(|c| c.is_ascii_digit()) {
+            anyhow::bail!("Invalid timezone offset '{}': expected ±HH:MM", s);
+        }
+        let hours: i32 = digits[..2].parse()?;
+        let minutes: i32 = digits[2..].parse()?;
+        let offset = FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60))
+            .with_context(|| format!("Timezone offset '{}' out of range", s))?;
+
+        Ok(Self::Fixed(offset))
+    }
+
+    // Weekday and seconds-since-midnight of `now` in this timezone
+    fn local_parts(&self, now: DateTime<Utc>) -> (Weekday, u32) {
+        match self {
+            Self::Utc => (now.weekday(), now.num_seconds_from_midnight()),
+            Self::Local => {
+                let t = now.with_timezone(&Local);
+                (t.weekday(), t.num_seconds_from_midnight())
+            }
+            Self::Fixed(offset) => {
+                let t = now.with_timezone(offset);
+                (t.weekday(), t.num_seconds_from_midnight())
+            }
+        }
+    }
+}
+
+// A single capture window, e.g. "mon-fri 09:00-17:00".
+// When `end` is before `start` the window spans midnight and continues
+// into the following day.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Window {
+    days: [bool; 7],
+    /// Seconds since midnight, inclusive
+    start: u32,
+    /// Seconds since midnight, exclusive (up to 24:00)
+    end: u32,
+}
+
+impl Window {
+    fn parse(s: &str) -> Result<Self> {
+        let s = s.trim();
+        let (days, range) = match s.rsplit_once(char::is_whitespace) {
+            Some((days, range)) => (parse_days(days)?, range),
+            None => ([true; 7], s),
+        };
+
+        let (start, end) = range
+            .split_once('-')
+            .with_context(|| format!("Invalid time range '{}': expected HH:MM-HH:MM", range))?;
+        let start = parse_time(start)?;
+        let end = parse_time(end)?;
+
+        if start == end {
+            anyhow::bail!("Empty time range '{}'", range);
+        }
+        if start == SECONDS_PER_DAY {
+            anyhow::bail!("Time range '{}' cannot start at 24:00", range);
+        }
+
+        Ok(Self { days, start, end })
+    }
+
+    fn contains(&self, weekday: Weekday, secs: u32) -> bool {
+        let today = self.days[weekday.num_days_from_monday() as usize];
+
+        if self.start < self.end {
+            today && secs >= self.start && secs < self.end
+        } else {
+            // Midnight-spanning: the late part belongs to today's window,
+            // the early part to the window that opened yesterday.
+            let yesterday = self.days[weekday.pred().num_days_from_monday() as usize];
+            (today && secs >= self.start) || (yesterday && secs < self.end)
+        }
+    }
+}
+
+// Set of windows during which the agent is allowed to capture.
+// Outside every window the capture loop keeps heartbeating but skips frames.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Schedule {
+    windows: Vec<Window>,
+    tz: ScheduleTz,
+}
+
+impl Schedule {
+    // Parse a ';'-separated list of windows, e.g.
+    // "mon-fri 09:00-17:00; sat 10:00-12:00; sun 22:00-02:00".
+    // A window without a day list applies to every day.
+    pub fn parse(spec: &str, tz: ScheduleTz) -> Result<Self> {
+        let windows = spec
+            .split(';')
+            .filter(|w| !w.trim().is_empty())
+            .map(Window::parse)
+            .collect::<Result<Vec<_>>>()?;
+
+        if windows.is_empty() {
+            anyhow::bail!("Schedule '{}' contains no windows", spec);
+        }
+
+        Ok(Self { windows, tz })
+    }
+
+    // True if `now` falls inside any configured window
+    pub fn is_active(&self, now: DateTime<Utc>) -> bool {
+        let (weekday, secs) = self.tz.local_parts(now);
+        self.windows.iter().any(|w| w.contains(weekday, secs))
+    }
+}
+
+// Parse "mon-fri", "sat,sun", "mon,wed-fri", "daily" or "*"
+fn parse_days(s: &str) -> Result<[bool; 7]> {
+    let s = s.trim().to_lowercase();
+    if s == "*" || s == "daily" {
+        return Ok([true; 7]);
+    }
+
+    let mut days = [false; 7];
+    for part in s.split(',') {
+        match part.split_once('-') {
+            Some((from, to)) => {
+                let from = parse_weekday(from)?;
+                let to = parse_weekday(to)?;
+                let mut day = from;
+                loop {
+                    days[day.num_days_from_monday() as usize] = true;
+                    if day == to {
+                        break;
+                    }
+                    day = day.succ();
+                }
+            }
+            None => days[parse_weekday(part)?.num_days_from_monday() as usize] = true,
+        }
+    }
+
+    Ok(days)
+}
+
+fn parse_weekday(s: &str) -> Result<Weekday> {
+    s.trim()
+        .parse::<Weekday>()
+        .map_err(|_| anyhow::anyhow!("Invalid weekday '{}'", s.trim()))
+}
+
+// Parse "HH:MM" into seconds since midnight. "24:00" is accepted as end of day.
+fn parse_time(s: &str) -> Result<u32> {
+    let s = s.trim();
+    let (h, m) = s
+        .split_once(':')
+        .with_context(|| format!("Invalid time '{}': expected HH:MM", s))?;
+    let h: u32 = h.parse().with_context(|| format!("Invalid hour in '{}'", s))?;
+    let m: u32 = m.parse().with_context(|| format!("Invalid minute in '{}'", s))?;
+
+    if m >= 60 || h > 24 || (h == 24 && m != 0) {
+        anyhow::bail!("Time '{}' out of range", s);
+    }
+
+    Ok(h * 3600 + m * 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    // 2026-03-02 is a Monday
+    fn at(day: u32, h: u32, m: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 3, day, h, m, 0).unwrap()
+    }
+
+    #[test]
+    fn test_weekday_hours() {
+        let schedule = Schedule::parse("mon-fri 09:00-17:00", ScheduleTz::Utc).unwrap();
+        assert!(schedule.is_active(at(2, 9, 0)));
+        assert!(schedule.is_active(at(6, 16, 59)));
+        assert!(!schedule.is_active(at(2, 17, 0)));
+        assert!(!schedule.is_active(at(2, 8, 59)));
+        // Saturday
+        assert!(!schedule.is_active(at(7, 12, 0)));
+    }
+
+    #[test]
+    fn test_midnight_spanning_window() {
+        let schedule = Schedule::parse("fri 22:00-02:00", ScheduleTz::Utc).unwrap();
+        assert!(schedule.is_active(at(6, 23, 30)));
+        // Early Saturday belongs to Friday's window
+        assert!(schedule.is_active(at(7, 1, 30)));
+        assert!(!schedule.is_active(at(7, 2, 0)));
+        // Early Friday would belong to Thursday's window, which is not configured
+        assert!(!schedule.is_active(at(6, 1, 0)));
+    }
+
+    #[test]
+    fn test_fixed_offset_timezone() {
+        let tz = ScheduleTz::parse("+02:00").unwrap();
+        let schedule = Schedule::parse("mon 09:00-10:00", tz).unwrap();
+        // 07:30 UTC is 09:30 at +02:00
+        assert!(schedule.is_active(at(2, 7, 30)));
+        assert!(!schedule.is_active(at(2, 9, 30)));
+    }
+
+    #[test]
+    fn test_invalid_specs() {
+        assert!(Schedule::parse("", ScheduleTz::Utc).is_err());
+        assert!(Schedule::parse("mon 09:00-09:00", ScheduleTz::Utc).is_err());
+        assert!(Schedule::parse("funday 09:00-10:00", ScheduleTz::Utc).is_err());
+        assert!(Schedule::parse("25:00-26:00", ScheduleTz::Utc).is_err());
+        assert!(ScheduleTz::parse("CET").is_err());
+    }
+}
@@ -0,0 +1,267 @@
+// crates/agent/src/spool.rs
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use anyhow::{Context, Result};
+use rand::RngCore;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+
+// AES-GCM nonce length; each spooled file starts with its own random nonce
+const NONCE_LEN: usize = 12;
+
+// Extension of spooled frame files
+const SPOOL_EXT: &str = "frame";
+
+// Subdirectory holding frames the server refused, kept for inspection
+const QUARANTINE_DIR: &str = "quarantine";
+
+// Key used to encrypt spooled frames at rest
+pub struct SpoolKey(Aes256Gcm);
+
+impl SpoolKey {
+    // Parse a 256-bit key given as 64 hex characters
+    pub fn from_hex(s: &str) -> Result<Self> {
+        let bytes = hex::decode(s.trim()).context("Spool key is not valid hex")?;
+        if bytes.len() != 32 {
+            anyhow::bail!("Spool key must be 32 bytes (64 hex characters), got {}", bytes.len());
+        }
+        Ok(Self(Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&bytes))))
+    }
+
+    // nonce || ciphertext (with tag)
+    fn seal(&self, plaintext: &[u8]) -> Result<Vec<u8>> {
+        let mut nonce = [0u8; NONCE_LEN];
+        rand::thread_rng().fill_bytes(&mut nonce);
+        let ciphertext = self
+            .0
+            .encrypt(Nonce::from_slice(&nonce), plaintext)
+            .map_err(|_| anyhow::anyhow!("Failed to encrypt spooled frame"))?;
+
+        let mut sealed = nonce.to_vec();
+        sealed.extend(ciphertext);
+        Ok(sealed)
+    }
+
+    fn open(&self, sealed: &[u8]) -> Result<Vec<u8>> {
+        if sealed.len() < NONCE_LEN {
+            anyhow::bail!("Spooled frame is truncated");
+        }
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+        self.0
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| anyhow::anyhow!("Spooled frame failed to decrypt (wrong key or corrupted)"))
+    }
+}
+
+// A frame waiting in the spool, exactly as it would have been uploaded
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpooledFrame {
+    pub frame_id: i64,
+    /// Extra multipart fields ("format", raw dimensions, ...)
+    pub fields: Vec<(String, String)>,
+    pub data: Vec<u8>,
+    /// Servers still missing the frame when fanning out; empty means all
+    pub endpoints: Vec<String>,
+}
+
+impl SpooledFrame {
+    // frame_id, field count, then each field and the image, all
+    // length-prefixed little-endian. Any endpoints follow as a count and
+    // strings; frames spooled before they existed simply end after the image.
+    fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(self.data.len() + 64);
+        out.extend(self.frame_id.to_le_bytes());
+        out.extend((self.fields.len() as u32).to_le_bytes());
+        for (name, value) in &self.fields {
+            put_bytes(&mut out, name.as_bytes());
+            put_bytes(&mut out, value.as_bytes());
+        }
+        put_bytes(&mut out, &self.data);
+        if !self.endpoints.is_empty() {
+            out.extend((self.endpoints.len() as u32).to_le_bytes());
+            for url in &self.endpoints {
+                put_bytes(&mut out, url.as_bytes());
+            }
+        }
+        out
+    }
+
+    fn decode(mut buf: &[u8]) -> Result<Self> {
+        let frame_id = i64::from_le_bytes(take(&mut buf, 8)?.try_into()?);
+        let count = u32::from_le_bytes(take(&mut buf, 4)?.try_into()?);
+        let mut fields = Vec::new();
+        for _ in 0..count {
+            let name = String::from_utf8(take_bytes(&mut buf)?.to_vec())?;
+            let value = String::from_utf8(take_bytes(&mut buf)?.to_vec())?;
+            fields.push((name, value));
+        }
+        let data = take_bytes(&mut buf)?.to_vec();
+        let mut endpoints = Vec::new();
+        if !buf.is_empty() {
+            let count = u32::from_le_bytes(take(&mut buf, 4)?.try_into()?);
+            for _ in 0..count {
+                endpoints.push(String::from_utf8(take_bytes(&mut buf)?.to_vec())?);
+            }
+        }
+        Ok(Self { frame_id, fields, data, endpoints })
+    }
+}
+
+fn put_bytes(out: &mut Vec<u8>, bytes: &[u8]) {
+    out.extend((bytes.len() as u32).to_le_bytes());
+    out.extend(bytes);
+}
+
+fn take<'a>(buf: &mut &'a [u8], n: usize) -> Result<&'a [u8]> {
+    if buf.len() < n {
+        anyhow::bail!("Spooled frame is truncated");
+    }
+    let (head, rest) = buf.split_at(n);
+    *buf = rest;
+    Ok(head)
+}
+
+fn take_bytes<'a>(buf: &mut &'a [u8]) -> Result<&'a [u8]> {
+    let len = u32::from_le_bytes(take(buf, 4)?.try_into()?) as usize;
+    take(buf, len)
+}
+
+// On-disk queue of frames that could not be uploaded while the server was
+// unreachable. Every file is encrypted with the spool key, so the backlog of
+// screenshots is unreadable without it.
+pub struct Spool {
+    dir: PathBuf,
+    key: SpoolKey,
+    /// Oldest frames are dropped beyond this many
+    max_frames: usize,
+}
+
+impl Spool {
+    pub fn open(dir: impl Into<PathBuf>, key: SpoolKey, max_frames: usize) -> Result<Self> {
+        let dir = dir.into();
+        std::fs::create_dir_all(&dir)
+            .with_context(|| format!("Failed to create spool directory {}", dir.display()))?;
+        Ok(Self { dir, key, max_frames: max_frames.max(1) })
+    }
+
+    // Spooled files, oldest first
+    pub fn pending(&self) -> Result<Vec<PathBuf>> {
+        let mut paths: Vec<PathBuf> = std::fs::read_dir(&self.dir)
+            .with_context(|| format!("Failed to read spool directory {}", self.dir.display()))?
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == SPOOL_EXT))
+            .collect();
+        // Names are zero-padded sequence numbers, so they sort by age
+        paths.sort();
+        Ok(paths)
+    }
+
+    // Encrypt `frame` and add it to the queue, dropping the oldest frames
+    // once the spool is full
+    pub fn push(&self, frame: &SpooledFrame) -> Result<()> {
+        let pending = self.pending()?;
+        let next = pending
+            .last()
+            .and_then(|p| p.file_stem()?.to_str()?.parse::<u64>().ok())
+            .map_or(0, |n| n + 1);
+
+        self.write(&self.dir.join(format!("{:020}.{}", next, SPOOL_EXT)), frame)?;
+
+        let excess = (pending.len() + 1).saturating_sub(self.max_frames);
+        for old in &pending[..excess] {
+            let _ = std::fs::remove_file(old);
+        }
+        Ok(())
+    }
+
+    // Encrypt `frame` to `path`. Written then renamed, so a crash never
+    // leaves a half-written frame.
+    fn write(&self, path: &Path, frame: &SpooledFrame) -> Result<()> {
+        let sealed = self.key.seal(&frame.encode())?;
+        let tmp = path.with_extension("tmp");
+        std::fs::write(&tmp, sealed)
+            .with_context(|| format!("Failed to write {}", tmp.display()))?;
+        std::fs::rename(&tmp, path)
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    // Decrypt one spooled file
+    pub fn read(&self, path: &Path) -> Result<SpooledFrame> {
+        let sealed = std::fs::read(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        SpooledFrame::decode(&self.key.open(&sealed)?)
+    }
+
+    // Hand up to `limit` frames, oldest first and decrypted, to `upload`,
+    // deleting each one it accepts. Stops once the server is unreachable so
+    // the rest are retried later; if the error names the servers still
+    // missing the frame (FailedEndpoints), the frame is narrowed to those. Frames the server rejects with a 4xx are
+    // moved to the quarantine subdirectory; other failures stay queued for
+    // the next drain. Frames that can't be decrypted are discarded.
+    // Returns how many frames were uploaded.
+    pub async fn drain<F, Fut>(&self, limit: usize, mut upload: F) -> Result<usize>
+    where
+        F: FnMut(SpooledFrame) -> Fut,
+        Fut: Future<Output = Result<()>>,
+    {
+        let mut uploaded = 0;
+        for path in self.pending()?.into_iter().take(limit) {
+            let frame = match self.read(&path) {
+                Ok(frame) => frame,
+                Err(e) => {
+                    tracing::warn!("Discarding spooled frame {}: {}", path.display(), e);
+                    let _ = std::fs::remove_file(&path);
+                    continue;
+                }
+            };
+            let retry = frame.clone();
+            let frame_id = frame.frame_id;
+            match upload(frame).await {
+                Ok(()) => {
+                    std::fs::remove_file(&path)
+                        .with_context(|| format!("Failed to remove {}", path.display()))?;
+                    uploaded += 1;
+                }
+                Err(e) if transport::is_unreachable(&e) => {
+                    if let Some(failed) = e.downcast_ref::<transport::FailedEndpoints>()
+                        && failed.0 != retry.endpoints
+                    {
+                        self.write(&path, &SpooledFrame { endpoints: failed.0.clone(), ..retry })?;
+                    }
+                    return Err(e);
+                }
+                Err(e) if e.downcast_ref::<transport::Rejected>().is_some() => {
+                    tracing::warn!("Quarantining spooled frame #{}: {}", frame_id, e);
+                    self.quarantine(&path)?;
+                }
+                Err(e) => tracing::warn!("Keeping spooled frame #{} for later: {}", frame_id, e),
+            }
+        }
+        Ok(uploaded)
+    }
+
+    // Frames moved aside after the server rejected them
+    pub fn quarantined(&self) -> Result<Vec<PathBuf>> {
+        let dir = self.dir.join(QUARANTINE_DIR);
+        if !dir.exists() {
+            return Ok(Vec::new());
+        }
+        let mut paths: Vec<PathBuf> = std::fs::read_dir(&dir)
+            .with_context(|| format!("Failed to read {}", dir.display()))?
+            .flatten()
+            .map(|entry| entry.path())
+            .collect();
+        paths.sort();
+        Ok(paths)
+    }
+
+    fn quarantine(&self, path: &Path) -> Result<()> {
+        let dir = self.dir.join(QUARANTINE_DIR);
+        std::fs::create_dir_all(&dir)
+            .with_context(|| format!("Failed to create {}", dir.display()))?;
+        let name = path.file_name().context("Spooled frame has no file name")?;
+        std::fs::rename(path, dir.join(name))
+            .with_context(|| format!("Failed to quarantine {}", path.display()))
+    }
+}
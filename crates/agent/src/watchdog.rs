@@ -0,0 +1,62 @@
+// crates/agent/src/watchdog.rs
+use std::time::Duration;
+use tokio::time::Instant;
+
+// Tracks time since the last successful upload and signals when the
+// capture loop has stalled for longer than `threshold`.
+#[derive(Debug, Clone)]
+pub struct Watchdog {
+    threshold: Duration,
+    last_success: Instant,
+}
+
+impl Watchdog {
+    pub fn new(threshold: Duration) -> Self {
+        Self {
+            threshold,
+            last_success: Instant::now(),
+        }
+    }
+
+    pub fn threshold(&self) -> Duration {
+        self.threshold
+    }
+
+    // Record the outcome of one capture/upload attempt at `now`.
+    // Returns true when no attempt has succeeded for `threshold`; the timer
+    // is then re-armed so recovery runs at most once per threshold period.
+    pub fn observe(&mut self, success: bool, now: Instant) -> bool {
+        if success {
+            self.last_success = now;
+            return false;
+        }
+
+        if now.duration_since(self.last_success) >= self.threshold {
+            self.last_success = now;
+            return true;
+        }
+
+        false
+    }
+
+    // Re-arm the timer without recording an attempt, e.g. while captures
+    // are intentionally paused by the schedule
+    pub fn reset(&mut self, now: Instant) {
+        self.last_success = now;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_success_keeps_watchdog_quiet() {
+        let mut watchdog = Watchdog::new(Duration::from_secs(10));
+        let start = Instant::now();
+
+        for i in 0..30 {
+            assert!(!watchdog.observe(true, start + Duration::from_secs(i)));
+        }
+    }
+}
@@ -1,38 +1,140 @@
 // crates/auth/src/lib.rs
 use anyhow::Result;
 use axum::{
-    extract::Request,
-    http::StatusCode,
+    extract::{ConnectInfo, Request},
+    http::{StatusCode, header},
     middleware::Next,
-    response::Response,
+    response::{IntoResponse, Response},
 };
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Duration, Utc};
+use clock::SharedClock;
 use oauth2::{
-    basic::BasicClient, AuthUrl, ClientId, ClientSecret, RedirectUrl, TokenUrl,
+    basic::{BasicClient, BasicTokenResponse}, AuthUrl, ClientId, ClientSecret, RedirectUrl, TokenUrl,
     AuthorizationCode, TokenResponse as OAuth2TokenResponse, CsrfToken,
+    PkceCodeChallenge, PkceCodeVerifier, RefreshToken,
 };
 use rand::Rng;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::net::SocketAddr;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
 // Token Authentication
 #[derive(Clone)]
 pub struct TokenAuth {
-    token: String,
+    /// Accepted token values and who holds each
+    tokens: HashMap<String, Credential>,
+    clock: SharedClock,
+}
+
+#[derive(Clone)]
+struct Credential {
+    /// Who the token belongs to, e.g. an agent's name; empty if unlabeled
+    label: String,
+    /// When the token stops being accepted; None never expires
+    expires_at: Option<DateTime<Utc>>,
+    /// What the token may do, checked by RequireScope
+    scopes: HashSet<String>,
+}
+
+// Label of the token a request authenticated with, added to the request
+// extensions for labeled tokens
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TokenLabel(pub String);
+
+// Scopes granted to the token a request authenticated with, added to the
+// request extensions by TokenAuth and read by RequireScope
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Scopes(pub HashSet<String>);
+
+impl Scopes {
+    pub fn contains(&self, scope: &str) -> bool {
+        self.0.contains(scope)
+    }
 }
 
 // Middleware for token authentication
 impl TokenAuth {
+    // A single unlabeled token that never expires
     pub fn new(token: String) -> Self {
-        Self { token }
+        Self::with_tokens(HashMap::from([(token, String::new())]))
+    }
+
+    // Several tokens, mapping each value to a label, e.g. one per agent or
+    // an old and a new token during rotation
+    pub fn with_tokens(tokens: HashMap<String, String>) -> Self {
+        let tokens = tokens
+            .into_iter()
+            .map(|(value, label)| {
+                let credential = Credential { label, expires_at: None, scopes: HashSet::new() };
+                (value, credential)
+            })
+            .collect();
+        Self {
+            tokens,
+            clock: clock::system(),
+        }
+    }
+
+    // A single unlabeled token rejected from its `expires_at` on
+    pub fn with_expiry(token: Token) -> Self {
+        let credential = Credential {
+            label: String::new(),
+            expires_at: Some(token.expires_at),
+            scopes: HashSet::new(),
+        };
+        Self {
+            tokens: HashMap::from([(token.value, credential)]),
+            clock: clock::system(),
+        }
+    }
+
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    // Grant scopes to one of the configured tokens; tokens start with none
+    pub fn with_scopes(mut self, token: &str, scopes: &[&str]) -> Self {
+        if let Some(credential) = self.tokens.get_mut(token) {
+            credential.scopes.extend(scopes.iter().map(|scope| scope.to_string()));
+        }
+        self
+    }
+
+    // Check an Authorization header: a bearer token matching one of ours
+    // that hasn't expired. Returns its label.
+    pub fn authorize(&self, auth_header: Option<&str>) -> Result<&str, StatusCode> {
+        self.authenticate(auth_header).map(|credential| credential.label.as_str())
+    }
+
+    // The credential an Authorization header presents. Every token is
+    // compared, in constant time, so timing doesn't reveal which one
+    // nearly matched.
+    fn authenticate(&self, auth_header: Option<&str>) -> Result<&Credential, StatusCode> {
+        let token = auth_header
+            .and_then(|header| header.strip_prefix("Bearer "))
+            .ok_or(StatusCode::UNAUTHORIZED)?;
+
+        let mut matched = None;
+        for (value, credential) in &self.tokens {
+            if constant_time_eq(token.as_bytes(), value.as_bytes()) {
+                matched = Some(credential);
+            }
+        }
+
+        let credential = matched.ok_or(StatusCode::UNAUTHORIZED)?;
+        if credential.expires_at.is_some_and(|expires_at| self.clock.now() >= expires_at) {
+            return Err(StatusCode::UNAUTHORIZED);
+        }
+        Ok(credential)
     }
 
     // Middleware function
     pub async fn middleware(
         &self,
-        req: Request,
+        mut req: Request,
         next: Next,
     ) -> Result<Response, StatusCode> {
         // Skip auth for health endpoint
@@ -45,20 +147,216 @@ impl TokenAuth {
             .get("Authorization")
             .and_then(|v| v.to_str().ok());
 
-        match auth_header {
-            Some(header) if header.starts_with("Bearer ") => {
-                let token = header.trim_start_matches("Bearer ");
-                if token == self.token {
-                    Ok(next.run(req).await)
-                } else {
-                    Err(StatusCode::UNAUTHORIZED)
+        let credential = self.authenticate(auth_header)?;
+        if !credential.label.is_empty() {
+            req.extensions_mut().insert(TokenLabel(credential.label.clone()));
+        }
+        req.extensions_mut().insert(Scopes(credential.scopes.clone()));
+        Ok(next.run(req).await)
+    }
+}
+
+// Scope enforcement. Reads the Scopes that TokenAuth (or another auth
+// layer) put in the request extensions, so it must run after it. With
+// axum, later layers wrap earlier ones: add RequireScope with
+// `route_layer` on the routes it guards, then TokenAuth with `layer`.
+//
+//     let admin = RequireScope::new("admin");
+//     Router::new()
+//         .route("/admin/config", post(update_config))
+//         .route_layer(from_fn(move |req, next| {
+//             let admin = admin.clone();
+//             async move { admin.middleware(req, next).await }
+//         }))
+//         .layer(/* TokenAuth::middleware, the same way */)
+//
+// Requests without Scopes, i.e. that no auth layer has seen, are
+// forbidden too.
+#[derive(Clone)]
+pub struct RequireScope {
+    scope: String,
+}
+
+impl RequireScope {
+    pub fn new(scope: &str) -> Self {
+        Self { scope: scope.to_string() }
+    }
+
+    // 403 unless `scopes` grants the required scope
+    pub fn check(&self, scopes: Option<&Scopes>) -> Result<(), StatusCode> {
+        if scopes.is_some_and(|scopes| scopes.contains(&self.scope)) {
+            Ok(())
+        } else {
+            Err(StatusCode::FORBIDDEN)
+        }
+    }
+
+    // Middleware function
+    pub async fn middleware(
+        &self,
+        req: Request,
+        next: Next,
+    ) -> Result<Response, StatusCode> {
+        self.check(req.extensions().get::<Scopes>())?;
+        Ok(next.run(req).await)
+    }
+}
+
+// Clients tracked at once by default; beyond this the least recently
+// seen is forgotten, and starts over with a full bucket if it returns
+const MAX_TRACKED_CLIENTS: usize = 10_000;
+
+// Requests a client may still make, refilled lazily from the time passed
+// since it was last updated
+struct TokenBucket {
+    tokens: f64,
+    updated: DateTime<Utc>,
+}
+
+// Buckets by client, plus the clients ordered by last use so the least
+// recently seen can be evicted without a scan
+#[derive(Default)]
+struct Buckets {
+    by_client: HashMap<String, TokenBucket>,
+    by_use: BTreeSet<(DateTime<Utc>, String)>,
+}
+
+// Token-bucket rate limiting per client: each may make `burst` requests
+// at once, refilled at `requests_per_sec`. Clients are told apart by the
+// TokenLabel of their token when TokenAuth ran first, otherwise by IP,
+// which needs the server run with `into_make_service_with_connect_info`.
+// A request with neither is answered with 500 rather than lumping every
+// such client into one shared bucket. At most `max_clients` are tracked.
+#[derive(Clone)]
+pub struct RateLimit {
+    requests_per_sec: f64,
+    burst: f64,
+    max_clients: usize,
+    buckets: Arc<RwLock<Buckets>>,
+    clock: SharedClock,
+}
+
+impl RateLimit {
+    pub fn new(requests_per_sec: f64, burst: u32) -> Self {
+        Self {
+            requests_per_sec,
+            burst: f64::from(burst.max(1)),
+            max_clients: MAX_TRACKED_CLIENTS,
+            buckets: Arc::new(RwLock::new(Buckets::default())),
+            clock: clock::system(),
+        }
+    }
+
+    pub fn with_max_clients(mut self, max_clients: usize) -> Self {
+        self.max_clients = max_clients.max(1);
+        self
+    }
+
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    // Take a request from `client`'s bucket, or return how long until one
+    // is available
+    pub async fn check(&self, client: &str) -> Result<(), std::time::Duration> {
+        let now = self.clock.now();
+        let mut guard = self.buckets.write().await;
+        let Buckets { by_client, by_use } = &mut *guard;
+
+        match by_client.get(client) {
+            Some(bucket) => {
+                by_use.remove(&(bucket.updated, client.to_string()));
+            }
+            None => {
+                while by_client.len() >= self.max_clients
+                    && let Some((_, oldest)) = by_use.pop_first()
+                {
+                    by_client.remove(&oldest);
                 }
             }
-            _ => Err(StatusCode::UNAUTHORIZED),
         }
+        let bucket = by_client
+            .entry(client.to_string())
+            .or_insert(TokenBucket { tokens: self.burst, updated: now });
+        bucket.tokens = self.refilled(bucket, now);
+        bucket.updated = now;
+        by_use.insert((now, client.to_string()));
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            return Ok(());
+        }
+        let wait = if self.requests_per_sec > 0.0 {
+            (1.0 - bucket.tokens) / self.requests_per_sec
+        } else {
+            f64::MAX
+        };
+        Err(std::time::Duration::try_from_secs_f64(wait).unwrap_or(std::time::Duration::MAX))
+    }
+
+    // Tokens in a bucket once refilled up to `now`
+    fn refilled(&self, bucket: &TokenBucket, now: DateTime<Utc>) -> f64 {
+        let elapsed = (now - bucket.updated).to_std().unwrap_or_default().as_secs_f64();
+        (bucket.tokens + elapsed * self.requests_per_sec).min(self.burst)
+    }
+
+    // Middleware function
+    pub async fn middleware(&self, req: Request, next: Next) -> Response {
+        // Skip rate limiting for health endpoint
+        if req.uri().path() == "/health" {
+            return next.run(req).await;
+        }
+
+        let label = req.extensions().get::<TokenLabel>();
+        let peer = req.extensions().get::<ConnectInfo<SocketAddr>>();
+        let Some(client) = client_key(label, peer.map(|ConnectInfo(addr)| addr)) else {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Rate limiting cannot identify the client: run TokenAuth first or serve with \
+                 into_make_service_with_connect_info",
+            )
+                .into_response();
+        };
+
+        match self.check(&client).await {
+            Ok(()) => next.run(req).await,
+            Err(wait) => {
+                // Whole seconds, rounded up so a retry isn't early
+                let retry_after = wait.as_secs_f64().ceil().max(1.0) as u64;
+                (
+                    StatusCode::TOO_MANY_REQUESTS,
+                    [(header::RETRY_AFTER, retry_after.to_string())],
+                    "Too many requests",
+                )
+                    .into_response()
+            }
+        }
+    }
+}
+
+// Rate-limit bucket for a request: its token's label, else its IP. None
+// when there is neither, since one bucket shared by every such client
+// would let any of them lock out the rest.
+fn client_key(label: Option<&TokenLabel>, peer: Option<&SocketAddr>) -> Option<String> {
+    match (label, peer) {
+        (Some(TokenLabel(label)), _) => Some(format!("token:{}", label)),
+        (None, Some(addr)) => Some(format!("ip:{}", addr.ip())),
+        (None, None) => None,
     }
 }
 
+// Compare a presented secret with the expected one in time that depends
+// only on the expected length, so a matching prefix doesn't leak through
+// timing. A length mismatch is folded into the result, not returned early.
+pub fn constant_time_eq(presented: &[u8], expected: &[u8]) -> bool {
+    let mut diff = (presented.len() ^ expected.len()) as u64;
+    for (i, &byte) in expected.iter().enumerate() {
+        diff |= u64::from(presented.get(i).copied().unwrap_or(0) ^ byte);
+    }
+    std::hint::black_box(diff) == 0
+}
+
 // Token structure
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Token {
@@ -66,16 +364,42 @@ pub struct Token {
     pub expires_at: DateTime<Utc>,
 }
 
+// Random bytes in a generated token
+const TOKEN_BYTES: usize = 32;
+
+// Why a string isn't a token generate_token could have produced
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+pub enum TokenError {
+    #[error("token is empty")]
+    Empty,
+    #[error("token is not valid base64")]
+    NotBase64,
+    #[error("token decodes to {0} bytes, expected {TOKEN_BYTES}")]
+    WrongLength(usize),
+}
+
 // Token generation and validation
 pub fn generate_token() -> Result<String> {
     let mut rng = rand::thread_rng();
-    let bytes: [u8; 32] = rng.r#gen();
+    let bytes: [u8; TOKEN_BYTES] = rng.r#gen();
     Ok(base64::encode(&bytes))
 }
 
-// Simple token validation
+// Check a token has the shape generate_token gives: standard base64 of
+// exactly TOKEN_BYTES bytes
+pub fn validate_token_detailed(token: &str) -> Result<(), TokenError> {
+    if token.is_empty() {
+        return Err(TokenError::Empty);
+    }
+    let bytes = base64::decode(token).map_err(|_| TokenError::NotBase64)?;
+    if bytes.len() != TOKEN_BYTES {
+        return Err(TokenError::WrongLength(bytes.len()));
+    }
+    Ok(())
+}
+
 pub fn validate_token(token: &str) -> bool {
-    !token.is_empty()
+    validate_token_detailed(token).is_ok()
 }
 
 // OAuth Provider
@@ -89,10 +413,31 @@ pub struct OAuthConfig {
     pub scopes: Vec<String>,
 }
 
+// How long an authorization state stays valid for the callback
+const STATE_TTL_MINUTES: i64 = 10;
+
+// Tokens granted by the provider
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OAuthTokens {
+    pub access_token: String,
+    /// For `OAuthProvider::refresh`, if the provider issued one
+    pub refresh_token: Option<String>,
+    /// When the access token stops working, if the provider said
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+// An authorization in flight, keyed by its state
+struct PendingAuth {
+    issued: DateTime<Utc>,
+    /// PKCE verifier whose challenge went out in the auth URL
+    verifier: PkceCodeVerifier,
+}
+
 // OAuth Provider structure
 pub struct OAuthProvider {
     client: BasicClient,
-    states: Arc<RwLock<HashMap<String, DateTime<Utc>>>>,
+    states: Arc<RwLock<HashMap<String, PendingAuth>>>,
+    clock: SharedClock,
 }
 
 // OAuth Provider implementation
@@ -109,39 +454,79 @@ impl OAuthProvider {
         Ok(Self {
             client,
             states: Arc::new(RwLock::new(HashMap::new())),
+            clock: clock::system(),
         })
     }
 
-    // Generate authorization URL
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    // Generate authorization URL, with a PKCE (S256) code challenge
     pub async fn get_auth_url(&self) -> Result<(String, String)> {
         let state = generate_state()?;
-        
+        let (challenge, verifier) = PkceCodeChallenge::new_random_sha256();
+
         // OAuth2 expects CsrfToken, not String
         let (auth_url, _csrf_token) = self.client
             .authorize_url(|| CsrfToken::new(state.clone()))
+            .set_pkce_challenge(challenge)
             .url();
 
+        let now = self.clock.now();
         let mut states = self.states.write().await;
-        states.insert(state.clone(), Utc::now());
+        // Drop states whose callbacks never arrived
+        states.retain(|_, pending| now - pending.issued < Duration::minutes(STATE_TTL_MINUTES));
+        states.insert(state.clone(), PendingAuth { issued: now, verifier });
 
         Ok((auth_url.to_string(), state))
     }
 
-    // Exchange code for access token
-    pub async fn exchange(&self, code: String, state: String) -> Result<String> {
+    // Exchange code for tokens
+    pub async fn exchange(&self, code: String, state: String) -> Result<OAuthTokens> {
         let mut states = self.states.write().await;
-        
-        if !states.contains_key(&state) {
+
+        let Some(pending) = states.remove(&state) else {
             anyhow::bail!("Invalid state");
+        };
+        if self.clock.now() - pending.issued >= Duration::minutes(STATE_TTL_MINUTES) {
+            anyhow::bail!("Expired state");
         }
-        states.remove(&state);
 
         let token = self.client
             .exchange_code(AuthorizationCode::new(code))
+            .set_pkce_verifier(pending.verifier)
+            .request_async(oauth2::reqwest::async_http_client)
+            .await?;
+
+        Ok(self.tokens(&token, None))
+    }
+
+    // Trade a refresh token for a new access token. Providers that don't
+    // rotate refresh tokens leave the old one valid, so it is kept.
+    pub async fn refresh(&self, refresh_token: &str) -> Result<OAuthTokens> {
+        let token = self.client
+            .exchange_refresh_token(&RefreshToken::new(refresh_token.to_string()))
             .request_async(oauth2::reqwest::async_http_client)
             .await?;
 
-        Ok(token.access_token().secret().clone())
+        Ok(self.tokens(&token, Some(refresh_token)))
+    }
+
+    // Tokens from a token response, with the expiry made absolute
+    fn tokens(&self, token: &BasicTokenResponse, previous_refresh: Option<&str>) -> OAuthTokens {
+        OAuthTokens {
+            access_token: token.access_token().secret().clone(),
+            refresh_token: token
+                .refresh_token()
+                .map(|t| t.secret().clone())
+                .or_else(|| previous_refresh.map(str::to_string)),
+            expires_at: token
+                .expires_in()
+                .and_then(|lifetime| Duration::from_std(lifetime).ok())
+                .map(|lifetime| self.clock.now() + lifetime),
+        }
     }
 }
 
@@ -159,6 +544,10 @@ mod base64 {
     pub fn encode(data: &[u8]) -> String {
         general_purpose::STANDARD.encode(data)
     }
+
+    pub fn decode(data: &str) -> Result<Vec<u8>, base64::DecodeError> {
+        general_purpose::STANDARD.decode(data)
+    }
 }
 
 // Unit tests
@@ -173,9 +562,269 @@ mod tests {
         assert!(validate_token(&token));
     }
 
+    #[test]
+    fn test_token_validation_rejects_malformed_tokens() {
+        assert_eq!(validate_token_detailed(""), Err(TokenError::Empty));
+        assert_eq!(validate_token_detailed("not base64!"), Err(TokenError::NotBase64));
+        assert_eq!(validate_token_detailed(&base64::encode(b"short")), Err(TokenError::WrongLength(5)));
+        assert!(!validate_token("secret"));
+        assert!(validate_token(&base64::encode(&[7; TOKEN_BYTES])));
+    }
+
+    #[tokio::test]
+    async fn test_oauth_state_expires() {
+        let clock = clock::FakeClock::default();
+        let provider = OAuthProvider::new(OAuthConfig {
+            client_id: "eye".to_string(),
+            client_secret: "secret".to_string(),
+            redirect_url: "http://localhost:8080/callback".to_string(),
+            auth_url: "https://auth.example.com/authorize".to_string(),
+            token_url: "https://auth.example.com/token".to_string(),
+            scopes: Vec::new(),
+        })
+        .unwrap()
+        .with_clock(Arc::new(clock.clone()));
+
+        let (_, state) = provider.get_auth_url().await.unwrap();
+        clock.advance(Duration::minutes(STATE_TTL_MINUTES));
+
+        let err = provider.exchange("code".to_string(), state.clone()).await.unwrap_err();
+        assert_eq!(err.to_string(), "Expired state");
+        // Expired states are consumed, not left for retries
+        let err = provider.exchange("code".to_string(), state).await.unwrap_err();
+        assert_eq!(err.to_string(), "Invalid state");
+    }
+
+    #[tokio::test]
+    async fn test_auth_url_carries_pkce_challenge() {
+        let provider = OAuthProvider::new(OAuthConfig {
+            client_id: "eye".to_string(),
+            client_secret: "secret".to_string(),
+            redirect_url: "http://localhost:8080/callback".to_string(),
+            auth_url: "https://auth.example.com/authorize".to_string(),
+            token_url: "https://auth.example.com/token".to_string(),
+            scopes: Vec::new(),
+        })
+        .unwrap();
+
+        let (url, state) = provider.get_auth_url().await.unwrap();
+        let url = oauth2::url::Url::parse(&url).unwrap();
+        let param = |name: &str| url.query_pairs().find(|(key, _)| key == name).map(|(_, value)| value.into_owned());
+        assert_eq!(param("code_challenge_method").as_deref(), Some("S256"));
+
+        // The challenge sent is the one for the verifier kept for exchange
+        let states = provider.states.read().await;
+        let expected = PkceCodeChallenge::from_code_verifier_sha256(&states[&state].verifier);
+        assert_eq!(param("code_challenge").as_deref(), Some(expected.as_str()));
+    }
+
+    #[test]
+    fn test_oauth_tokens_keep_refresh_token_and_expiry() {
+        use oauth2::{AccessToken, EmptyExtraTokenFields, basic::BasicTokenType};
+
+        let now = Utc::now();
+        let provider = OAuthProvider::new(OAuthConfig {
+            client_id: "eye".to_string(),
+            client_secret: "secret".to_string(),
+            redirect_url: "http://localhost:8080/callback".to_string(),
+            auth_url: "https://auth.example.com/authorize".to_string(),
+            token_url: "https://auth.example.com/token".to_string(),
+            scopes: Vec::new(),
+        })
+        .unwrap()
+        .with_clock(Arc::new(clock::FakeClock::new(now)));
+
+        let mut response = BasicTokenResponse::new(
+            AccessToken::new("access".to_string()),
+            BasicTokenType::Bearer,
+            EmptyExtraTokenFields {},
+        );
+        response.set_expires_in(Some(&std::time::Duration::from_secs(3600)));
+        let tokens = provider.tokens(&response, Some("old-refresh"));
+        assert_eq!(tokens.access_token, "access");
+        assert_eq!(tokens.refresh_token.as_deref(), Some("old-refresh"));
+        assert_eq!(tokens.expires_at, Some(now + Duration::hours(1)));
+
+        response.set_refresh_token(Some(RefreshToken::new("new-refresh".to_string())));
+        response.set_expires_in(None);
+        let tokens = provider.tokens(&response, Some("old-refresh"));
+        assert_eq!(tokens.refresh_token.as_deref(), Some("new-refresh"));
+        assert_eq!(tokens.expires_at, None);
+    }
+
+    #[test]
+    fn test_require_scope() {
+        let auth = TokenAuth::with_tokens(HashMap::from([
+            ("admin-token".to_string(), "ops".to_string()),
+            ("agent-token".to_string(), "agent-a".to_string()),
+        ]))
+        .with_scopes("admin-token", &["admin", "upload"])
+        .with_scopes("agent-token", &["upload"]);
+        let scopes = |header| Scopes(auth.authenticate(Some(header)).unwrap().scopes.clone());
+
+        let require = RequireScope::new("admin");
+        assert_eq!(require.check(Some(&scopes("Bearer admin-token"))), Ok(()));
+        assert_eq!(require.check(Some(&scopes("Bearer agent-token"))), Err(StatusCode::FORBIDDEN));
+        assert_eq!(require.check(None), Err(StatusCode::FORBIDDEN));
+        assert_eq!(RequireScope::new("upload").check(Some(&scopes("Bearer agent-token"))), Ok(()));
+    }
+
+    #[tokio::test]
+    async fn test_require_scope_behind_token_auth() {
+        use axum::{Router, body::Body, middleware::from_fn, routing::post};
+        use tower::ServiceExt;
+
+        let auth = TokenAuth::with_tokens(HashMap::from([
+            ("admin-token".to_string(), "ops".to_string()),
+            ("agent-token".to_string(), "agent-a".to_string()),
+        ]))
+        .with_scopes("admin-token", &["admin"])
+        .with_scopes("agent-token", &["upload"]);
+        let admin = RequireScope::new("admin");
+        // Layered as documented on RequireScope
+        let app = Router::new()
+            .route("/admin/config", post(|| async { "updated" }))
+            .route_layer(from_fn(move |req, next| {
+                let admin = admin.clone();
+                async move { admin.middleware(req, next).await }
+            }))
+            .layer(from_fn(move |req, next| {
+                let auth = auth.clone();
+                async move { auth.middleware(req, next).await }
+            }));
+        let status = |token: Option<&str>| {
+            let mut builder = Request::builder().method("POST").uri("/admin/config");
+            if let Some(token) = token {
+                builder = builder.header(header::AUTHORIZATION, format!("Bearer {}", token));
+            }
+            let app = app.clone();
+            async move { app.oneshot(builder.body(Body::empty()).unwrap()).await.unwrap().status() }
+        };
+
+        assert_eq!(status(Some("admin-token")).await, StatusCode::OK);
+        assert_eq!(status(Some("agent-token")).await, StatusCode::FORBIDDEN);
+        assert_eq!(status(None).await, StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_rejects_past_burst() {
+        let clock = clock::FakeClock::default();
+        let limit = RateLimit::new(2.0, 3).with_clock(Arc::new(clock.clone()));
+
+        for _ in 0..3 {
+            assert_eq!(limit.check("ip:10.0.0.1").await, Ok(()));
+        }
+        let wait = limit.check("ip:10.0.0.1").await.unwrap_err();
+        assert_eq!(wait, std::time::Duration::from_millis(500));
+        // Other clients have buckets of their own
+        assert_eq!(limit.check("ip:10.0.0.2").await, Ok(()));
+
+        clock.advance(Duration::milliseconds(500));
+        assert_eq!(limit.check("ip:10.0.0.1").await, Ok(()));
+        assert!(limit.check("ip:10.0.0.1").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_forgets_least_recently_seen_client() {
+        let clock = clock::FakeClock::default();
+        let limit = RateLimit::new(1.0, 1).with_clock(Arc::new(clock.clone())).with_max_clients(2);
+
+        assert_eq!(limit.check("a").await, Ok(()));
+        clock.advance(Duration::milliseconds(10));
+        assert_eq!(limit.check("b").await, Ok(()));
+        clock.advance(Duration::milliseconds(10));
+        // "a" is seen again, so "b" is now the least recent
+        assert!(limit.check("a").await.is_err());
+        clock.advance(Duration::milliseconds(10));
+
+        // A new client never grows the map past the cap
+        assert_eq!(limit.check("c").await, Ok(()));
+        let buckets = limit.buckets.read().await;
+        assert_eq!(buckets.by_client.len(), 2);
+        assert_eq!(buckets.by_use.len(), 2);
+        assert!(!buckets.by_client.contains_key("b"));
+        drop(buckets);
+        // "a" kept its drained bucket
+        assert!(limit.check("a").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_middleware_sets_retry_after() {
+        use axum::{Router, body::Body, middleware::from_fn, routing::get};
+        use tower::ServiceExt;
+
+        let clock = clock::FakeClock::default();
+        let limit = RateLimit::new(0.5, 2).with_clock(Arc::new(clock));
+        let app = Router::new().route("/frames", get(|| async { "ok" })).layer(from_fn(move |req, next| {
+            let limit = limit.clone();
+            async move { limit.middleware(req, next).await }
+        }));
+        let request = || {
+            let addr: SocketAddr = "10.0.0.1:4000".parse().unwrap();
+            Request::builder().uri("/frames").extension(ConnectInfo(addr)).body(Body::empty()).unwrap()
+        };
+
+        for _ in 0..2 {
+            assert_eq!(app.clone().oneshot(request()).await.unwrap().status(), StatusCode::OK);
+        }
+        let response = app.clone().oneshot(request()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers()[header::RETRY_AFTER], "2");
+    }
+
+    #[test]
+    fn test_rate_limit_needs_a_client_identity() {
+        let addr: SocketAddr = "10.0.0.1:4000".parse().unwrap();
+        let label = TokenLabel("agent-a".to_string());
+        assert_eq!(client_key(Some(&label), Some(&addr)), Some("token:agent-a".to_string()));
+        assert_eq!(client_key(None, Some(&addr)), Some("ip:10.0.0.1".to_string()));
+        assert_eq!(client_key(None, None), None);
+    }
+
     #[test]
     fn test_token_auth() {
         let auth = TokenAuth::new("test-token".to_string());
-        assert_eq!(auth.token, "test-token");
+        assert_eq!(auth.authorize(Some("Bearer test-token")), Ok(""));
+        assert_eq!(auth.authorize(Some("test-token")), Err(StatusCode::UNAUTHORIZED));
+        assert_eq!(auth.authorize(None), Err(StatusCode::UNAUTHORIZED));
+    }
+
+    #[test]
+    fn test_token_auth_accepts_any_labeled_token() {
+        let auth = TokenAuth::with_tokens(HashMap::from([
+            ("token-a".to_string(), "agent-a".to_string()),
+            ("token-b".to_string(), "agent-b".to_string()),
+        ]));
+        assert_eq!(auth.authorize(Some("Bearer token-a")), Ok("agent-a"));
+        assert_eq!(auth.authorize(Some("Bearer token-b")), Ok("agent-b"));
+        assert_eq!(auth.authorize(Some("Bearer token-c")), Err(StatusCode::UNAUTHORIZED));
+    }
+
+    #[test]
+    fn test_constant_time_eq() {
+        assert!(constant_time_eq(b"test-token", b"test-token"));
+        assert!(!constant_time_eq(b"test-tokeX", b"test-token"));
+        assert!(!constant_time_eq(b"test", b"test-token"));
+        assert!(!constant_time_eq(b"test-token\0", b"test-token"));
+        assert!(!constant_time_eq(b"", b"test-token"));
+        assert!(constant_time_eq(b"", b""));
+    }
+
+    #[test]
+    fn test_expired_token_is_rejected() {
+        let start = Utc::now();
+        let clock = clock::FakeClock::new(start);
+        let token = |expires_at| Token { value: "test-token".to_string(), expires_at };
+
+        let expired = TokenAuth::with_expiry(token(start - Duration::seconds(1))).with_clock(Arc::new(clock.clone()));
+        assert_eq!(expired.authorize(Some("Bearer test-token")), Err(StatusCode::UNAUTHORIZED));
+
+        let auth = TokenAuth::with_expiry(token(start + Duration::minutes(5))).with_clock(Arc::new(clock.clone()));
+
+        assert_eq!(auth.authorize(Some("Bearer test-token")), Ok(""));
+        assert_eq!(auth.authorize(Some("Bearer other-token")), Err(StatusCode::UNAUTHORIZED));
+
+        clock.advance(Duration::minutes(5));
+        assert_eq!(auth.authorize(Some("Bearer test-token")), Err(StatusCode::UNAUTHORIZED));
     }
 }
\ No newline at end of file
@@ -1,28 +1,246 @@
 // crates/capture/src/lib.rs
 use anyhow::{Context, Result};
 use chrono::{DateTime, Utc};
-use image::{DynamicImage, ImageFormat, GenericImageView, ImageEncoder};
+use image::{DynamicImage, ImageFormat, GenericImageView, ImageEncoder, Rgba, RgbaImage};
 use xcap::Monitor;
+use std::collections::HashMap;
 use std::io::Cursor;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::time::Duration;
+use tracing::warn;
+
+mod notifications;
+mod pipeline;
+mod provenance;
+mod rules;
+
+pub use notifications::NotificationMask;
+pub use pipeline::{parse_transforms, Corner, Grayscale, Hook, HookFn, Logo, Overlay, Quantize, Redact, Resize, Transform};
+pub use provenance::Provenance;
+pub use rules::{
+    evaluate, ActiveWindowProvider, CaptureSkipped, RedactionRule, RuleAction, SystemActiveWindow,
+};
 
 // Configuration for the capture engine
 #[derive(Debug, Clone)]
 pub struct Config {
     pub interval: Duration,
     pub format: ImageFormat,
+    /// Skip encoding and emit the raw RGBA8 pixels (lossless, uncompressed).
+    /// `format` is ignored when set.
+    pub raw: bool,
+    /// Extra encodings produced from the same screen grab, e.g. a small
+    /// JPEG for live view alongside a lossless PNG primary
+    pub alternate_formats: Vec<ImageFormat>,
+    /// Capture only this part of the monitor; resolved against the
+    /// monitor's size at capture time
+    pub region: Option<RegionPreset>,
+    /// Windows whose title or app name contains any of these strings
+    /// (case-insensitive) are blacked out before encoding
+    pub blocked_windows: Vec<String>,
+    /// Encoder quality 1-100 for lossy formats (JPEG)
+    pub quality: u8,
+    /// Per-format overrides of `quality`, e.g. for a JPEG alternate
+    /// alongside a WebP primary
+    pub format_quality: HashMap<ImageFormat, u8>,
+    /// Downscale of each grab, per monitor, applied before `transforms`;
+    /// for displays too large to handle at full resolution
+    pub pre_scale: PreScale,
+    /// Image transforms applied in order to every grab before encoding
+    pub transforms: Vec<Arc<dyn Transform>>,
+    /// Downscale factor applied after `transforms`; 1.0 keeps native resolution
+    pub scale: f32,
+    /// Index into the monitor list to capture; None captures the first
+    pub monitor: Option<usize>,
+    /// Round-robin: each capture takes the next monitor index in this list,
+    /// wrapping around. Overrides `monitor` when non-empty.
+    pub monitor_rotation: Vec<usize>,
+    /// Redact or skip frames depending on the active application; the
+    /// first matching rule wins
+    pub redaction_rules: Vec<RedactionRule>,
+    /// Crop each frame to the focused window's bounds
+    pub follow_focus: bool,
+    /// Black out notification banners; None leaves them in the frame
+    pub notification_mask: Option<NotificationMask>,
+    /// Write who/when/where the frame was captured into the encoded PNG or
+    /// JPEG; None leaves the image metadata empty
+    pub provenance: Option<Provenance>,
 }
 
+// JPEG quality used unless configured otherwise
+pub const DEFAULT_QUALITY: u8 = 75;
+
 // Default configuration
 impl Default for Config {
     fn default() -> Self {
         Self {
             interval: Duration::from_millis(1000),
             format: ImageFormat::WebP,
+            raw: false,
+            alternate_formats: Vec::new(),
+            region: None,
+            blocked_windows: Vec::new(),
+            quality: DEFAULT_QUALITY,
+            format_quality: HashMap::new(),
+            pre_scale: PreScale::default(),
+            transforms: Vec::new(),
+            scale: 1.0,
+            monitor: None,
+            monitor_rotation: Vec::new(),
+            redaction_rules: Vec::new(),
+            follow_focus: false,
+            notification_mask: None,
+            provenance: None,
+        }
+    }
+}
+
+impl Config {
+    // Encoder quality for `format`: its override if set, else `quality`
+    pub fn quality_for(&self, format: ImageFormat) -> u8 {
+        self.format_quality.get(&format).copied().unwrap_or(self.quality)
+    }
+}
+
+// A concrete capture rectangle in monitor pixels
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+// Named capture regions, resolved against the monitor size at capture time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegionPreset {
+    Full,
+    LeftHalf,
+    RightHalf,
+    TopHalf,
+    BottomHalf,
+    TopLeftQuarter,
+    TopRightQuarter,
+    BottomLeftQuarter,
+    BottomRightQuarter,
+    Center480p,
+    Center720p,
+    Center1080p,
+}
+
+impl RegionPreset {
+    // Resolve the preset to a rectangle on a monitor of the given size.
+    // Centered presets are clamped to the monitor if it is smaller.
+    pub fn resolve(&self, monitor_width: u32, monitor_height: u32) -> Rect {
+        let (w, h) = (monitor_width, monitor_height);
+        let (half_w, half_h) = (w / 2, h / 2);
+
+        let rect = |x, y, width, height| Rect { x, y, width, height };
+        let centered = |width: u32, height: u32| {
+            let (width, height) = (width.min(w), height.min(h));
+            rect((w - width) / 2, (h - height) / 2, width, height)
+        };
+
+        match self {
+            Self::Full => rect(0, 0, w, h),
+            Self::LeftHalf => rect(0, 0, half_w, h),
+            Self::RightHalf => rect(half_w, 0, w - half_w, h),
+            Self::TopHalf => rect(0, 0, w, half_h),
+            Self::BottomHalf => rect(0, half_h, w, h - half_h),
+            Self::TopLeftQuarter => rect(0, 0, half_w, half_h),
+            Self::TopRightQuarter => rect(half_w, 0, w - half_w, half_h),
+            Self::BottomLeftQuarter => rect(0, half_h, half_w, h - half_h),
+            Self::BottomRightQuarter => rect(half_w, half_h, w - half_w, h - half_h),
+            Self::Center480p => centered(854, 480),
+            Self::Center720p => centered(1280, 720),
+            Self::Center1080p => centered(1920, 1080),
+        }
+    }
+}
+
+impl FromStr for RegionPreset {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(match s.trim().to_lowercase().as_str() {
+            "full" => Self::Full,
+            "left-half" => Self::LeftHalf,
+            "right-half" => Self::RightHalf,
+            "top-half" => Self::TopHalf,
+            "bottom-half" => Self::BottomHalf,
+            "top-left-quarter" => Self::TopLeftQuarter,
+            "top-right-quarter" => Self::TopRightQuarter,
+            "bottom-left-quarter" => Self::BottomLeftQuarter,
+            "bottom-right-quarter" => Self::BottomRightQuarter,
+            "center-480p" => Self::Center480p,
+            "center-720p" => Self::Center720p,
+            "center-1080p" => Self::Center1080p,
+            other => anyhow::bail!("unknown region preset '{}'", other),
+        })
+    }
+}
+
+// Fixed downscale factors applied as soon as a monitor is captured,
+// regardless of content: a remote desktop reporting 7680x4320 with a 0.25
+// factor is handled as 1920x1080 from then on. `Config::scale` by contrast
+// applies to the finished image after every transform.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PreScale {
+    /// Factor for monitors without their own entry
+    pub default: f32,
+    /// Factors by monitor index
+    pub monitors: HashMap<usize, f32>,
+}
+
+impl Default for PreScale {
+    fn default() -> Self {
+        Self { default: 1.0, monitors: HashMap::new() }
+    }
+}
+
+impl PreScale {
+    // Factor for the monitor at `index`, in (0, 1]
+    pub fn factor_for(&self, index: usize) -> f32 {
+        self.monitors.get(&index).copied().unwrap_or(self.default)
+    }
+}
+
+// "0.5" for every monitor, "1=0.5,2=0.25" per monitor index, or both,
+// e.g. "0.5,0=1" to scale everything except the first monitor
+impl FromStr for PreScale {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let factor = |v: &str| -> Result<f32> {
+            let factor: f32 = v.trim().parse().with_context(|| format!("Invalid scale factor '{}'", v.trim()))?;
+            if !(factor > 0.0 && factor <= 1.0) {
+                anyhow::bail!("Scale factor must be in (0, 1], got {}", factor);
+            }
+            Ok(factor)
+        };
+
+        let mut pre_scale = Self::default();
+        for entry in s.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            match entry.split_once('=') {
+                Some((index, value)) => {
+                    let index = index
+                        .trim()
+                        .parse()
+                        .with_context(|| format!("Invalid monitor index '{}'", index.trim()))?;
+                    pre_scale.monitors.insert(index, factor(value)?);
+                }
+                None => pre_scale.default = factor(entry)?,
+            }
         }
+        Ok(pre_scale)
     }
 }
 
+// Format string reported for frames captured with `Config::raw`
+pub use rawframe::RAW_FORMAT;
+
 // Representation of a captured frame
 #[derive(Debug, Clone)]
 pub struct Frame {
@@ -33,92 +251,572 @@ pub struct Frame {
     pub height: u32,
     pub format: String,
     pub size_bytes: i64,
+    /// Bytes per row for raw frames; 0 for encoded frames
+    pub stride: u32,
+    /// Additional encodings of the same image, one per
+    /// `Config::alternate_formats` entry
+    pub alternates: Vec<Encoding>,
+    /// Monitor the image was captured from, when known
+    pub monitor: Option<MonitorInfo>,
+    /// Tiny grayscale copy of the image for cheap change detection; see
+    /// `change_score`
+    pub fingerprint: Vec<u8>,
+}
+
+// Edge length of a frame fingerprint in pixels
+pub const FINGERPRINT_SIZE: u32 = 16;
+
+// Downscale to a FINGERPRINT_SIZE square of luma values
+fn fingerprint(image: &DynamicImage) -> Vec<u8> {
+    image
+        .resize_exact(FINGERPRINT_SIZE, FINGERPRINT_SIZE, image::imageops::FilterType::Triangle)
+        .into_luma8()
+        .into_raw()
+}
+
+// How much two frames differ, 0.0 (identical) to 1.0, as the mean absolute
+// difference of their fingerprints. Fingerprints of different sizes count
+// as a complete change.
+pub fn change_score(a: &[u8], b: &[u8]) -> f64 {
+    if a.len() != b.len() || a.is_empty() {
+        return 1.0;
+    }
+
+    let total: u64 = a.iter().zip(b).map(|(x, y)| x.abs_diff(*y) as u64).sum();
+    total as f64 / (a.len() as f64 * 255.0)
+}
+
+// An additional encoding of a captured image
+#[derive(Debug, Clone)]
+pub struct Encoding {
+    pub format: String,
+    pub data: Vec<u8>,
+}
+
+// Implementation of Frame helpers
+impl Frame {
+    pub fn is_raw(&self) -> bool {
+        self.format == RAW_FORMAT
+    }
+
+    // Decode the frame back into an image. Raw frames are rebuilt from
+    // their RGBA8 rows without any loss; encoded frames are decoded.
+    pub fn to_image(&self) -> Result<DynamicImage> {
+        if !self.is_raw() {
+            return image::load_from_memory(&self.data).context("Failed to decode frame");
+        }
+
+        let img = rawframe::to_image(&self.data, self.width, self.height, self.stride as usize)?;
+        Ok(DynamicImage::ImageRgba8(img))
+    }
+
+    // Produce a downscaled preview of this frame encoded as `format`.
+    // The frame only holds encoded bytes, so this decodes, resizes so the
+    // longest edge equals `max_dim` (aspect ratio preserved) and re-encodes.
+    // Frames already within `max_dim` are re-encoded without upscaling.
+    pub fn thumbnail(&self, max_dim: u32, format: ImageFormat) -> Result<Vec<u8>> {
+        if max_dim == 0 {
+            anyhow::bail!("Thumbnail size must be greater than zero");
+        }
+
+        let img = self.to_image()?;
+
+        let thumb = if img.width().max(img.height()) > max_dim {
+            img.thumbnail(max_dim, max_dim)
+        } else {
+            img
+        };
+
+        encode_as(&thumb, format, DEFAULT_QUALITY).context("Failed to encode thumbnail")
+    }
+
+    // Find the alternate encoding in `format` ("png", "jpeg", ...)
+    pub fn alternate(&self, format: &str) -> Option<&Encoding> {
+        self.alternates.iter().find(|a| a.format == format)
+    }
+}
+
+// Description of a connected monitor
+#[derive(Debug, Clone, PartialEq)]
+pub struct MonitorInfo {
+    pub id: u32,
+    pub name: String,
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    pub scale_factor: f32,
+    pub is_primary: bool,
+}
+
+impl MonitorInfo {
+    fn from_monitor(monitor: &Monitor) -> Result<Self> {
+        let err = |e: xcap::XCapError| anyhow::anyhow!(e);
+
+        Ok(Self {
+            id: monitor.id().map_err(err)?,
+            name: monitor.name().map_err(err)?,
+            x: monitor.x().map_err(err)?,
+            y: monitor.y().map_err(err)?,
+            width: monitor.width().map_err(err)?,
+            height: monitor.height().map_err(err)?,
+            scale_factor: monitor.scale_factor().map_err(err)?,
+            is_primary: monitor.is_primary().map_err(err)?,
+        })
+    }
+}
+
+impl MonitorInfo {
+    // Flat key/value form used for upload fields and frame metadata
+    pub fn fields(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("monitor_id", self.id.to_string()),
+            ("monitor_name", self.name.clone()),
+            ("monitor_x", self.x.to_string()),
+            ("monitor_y", self.y.to_string()),
+            ("monitor_width", self.width.to_string()),
+            ("monitor_height", self.height.to_string()),
+            ("monitor_scale_factor", self.scale_factor.to_string()),
+            ("monitor_primary", self.is_primary.to_string()),
+        ]
+    }
+}
+
+// Enumerate the connected monitors. Comparing successive results is a
+// cheap way to detect display changes (resolution, hot-plug, scaling).
+pub fn monitors() -> Result<Vec<MonitorInfo>> {
+    Monitor::all()
+        .context("Failed to enumerate monitors")?
+        .iter()
+        .map(MonitorInfo::from_monitor)
+        .collect()
+}
+
+// Title and global geometry of an on-screen window
+#[derive(Debug, Clone, PartialEq)]
+pub struct WindowInfo {
+    pub title: String,
+    pub app_name: String,
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    pub is_minimized: bool,
+}
+
+impl WindowInfo {
+    // Case-insensitive substring match against title or app name
+    fn matches(&self, patterns: &[String]) -> bool {
+        let title = self.title.to_lowercase();
+        let app = self.app_name.to_lowercase();
+        patterns.iter().any(|p| {
+            let p = p.to_lowercase();
+            !p.is_empty() && (title.contains(&p) || app.contains(&p))
+        })
+    }
+}
+
+// Enumerate the open windows
+pub fn windows() -> Result<Vec<WindowInfo>> {
+    xcap::Window::all()
+        .context("Failed to enumerate windows")?
+        .iter()
+        .map(window_info)
+        .collect()
+}
+
+fn window_info(w: &xcap::Window) -> Result<WindowInfo> {
+    let err = |e: xcap::XCapError| anyhow::anyhow!(e);
+
+    Ok(WindowInfo {
+        title: w.title().map_err(err)?,
+        app_name: w.app_name().map_err(err)?,
+        x: w.x().map_err(err)?,
+        y: w.y().map_err(err)?,
+        width: w.width().map_err(err)?,
+        height: w.height().map_err(err)?,
+        is_minimized: w.is_minimized().map_err(err)?,
+    })
+}
+
+// Window bounds translated into the coordinates of an image whose top-left
+// corner sits at `origin` on the desktop, clipped to `size`. Window and
+// monitor positions are in desktop points; `scale` is the monitor's pixels
+// per point (2.0 on a Retina display). Edges are rounded outwards.
+fn window_rect(w: &WindowInfo, origin: (i32, i32), scale: f32, size: (u32, u32)) -> Option<Rect> {
+    let scale = f64::from(scale);
+    let to_pixels = |points: i64| points as f64 * scale;
+    let left = to_pixels(w.x as i64 - origin.0 as i64).floor().max(0.0) as i64;
+    let top = to_pixels(w.y as i64 - origin.1 as i64).floor().max(0.0) as i64;
+    let right = (to_pixels(w.x as i64 + w.width as i64 - origin.0 as i64).ceil() as i64).min(size.0 as i64);
+    let bottom = (to_pixels(w.y as i64 + w.height as i64 - origin.1 as i64).ceil() as i64).min(size.1 as i64);
+
+    (right > left && bottom > top).then(|| Rect {
+        x: left as u32,
+        y: top as u32,
+        width: (right - left) as u32,
+        height: (bottom - top) as u32,
+    })
+}
+
+// Bounding boxes of blocklisted windows, translated into the coordinates
+// of an image whose top-left corner sits at `origin` on the global desktop,
+// scaled by the monitor's `scale` and clipped to `size`. Occlusion is
+// ignored on purpose: a blocked window partly covered by another is still
+// blacked out in full.
+fn blocked_rects(
+    windows: &[WindowInfo],
+    patterns: &[String],
+    origin: (i32, i32),
+    scale: f32,
+    size: (u32, u32),
+) -> Vec<Rect> {
+    windows
+        .iter()
+        .filter(|w| !w.is_minimized && w.matches(patterns))
+        .filter_map(|w| window_rect(w, origin, scale, size))
+        .collect()
+}
+
+// Black out `rects` in place
+fn redact(image: &mut RgbaImage, rects: &[Rect]) {
+    for rect in rects {
+        for y in rect.y..rect.y + rect.height {
+            for x in rect.x..rect.x + rect.width {
+                image.put_pixel(x, y, Rgba([0, 0, 0, 255]));
+            }
+        }
+    }
 }
 
 // Capture engine
 pub struct Engine {
     config: Config,
+    active_window: Arc<dyn ActiveWindowProvider>,
+    /// Captures taken so far in round-robin mode
+    rotation: AtomicUsize,
 }
 
 // Implementation of the capture engine
 impl Engine {
     pub fn new(config: Config) -> Self {
-        Self { config }
+        Self {
+            config,
+            active_window: Arc::new(SystemActiveWindow),
+            rotation: AtomicUsize::new(0),
+        }
+    }
+
+    // Replace the source of active-window info consulted by redaction rules
+    // and the focus crop
+    pub fn with_active_window(mut self, provider: Arc<dyn ActiveWindowProvider>) -> Self {
+        self.active_window = provider;
+        self
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    // Adjust settings between captures, e.g. quality under storage pressure
+    pub fn config_mut(&mut self) -> &mut Config {
+        &mut self.config
     }
 
     // Capture a frame
     pub fn capture_frame(&self, frame_id: i64) -> Result<Frame> {
-        let image = self.capture_screen()?;
+        let (image, monitor) = self.capture_screen()?;
+        self.frame_from_capture(frame_id, image, monitor)
+    }
+
+    // Build a frame from an image grabbed from `monitor`, recording the
+    // monitor's details alongside it
+    pub fn frame_from_capture(
+        &self,
+        frame_id: i64,
+        image: DynamicImage,
+        monitor: MonitorInfo,
+    ) -> Result<Frame> {
+        self.build_frame(frame_id, image, Some(monitor))
+    }
+
+    // Build a frame from an already-captured image using the configured
+    // output format
+    pub fn frame_from_image(&self, frame_id: i64, image: DynamicImage) -> Result<Frame> {
+        self.build_frame(frame_id, image, None)
+    }
+
+    fn build_frame(
+        &self,
+        frame_id: i64,
+        image: DynamicImage,
+        monitor: Option<MonitorInfo>,
+    ) -> Result<Frame> {
+        let timestamp = Utc::now();
+        let image = pipeline::apply_all(&self.config.transforms, image)?;
+        let image = pipeline::downscale(image, self.config.scale);
         let (width, height) = image.dimensions();
-        
-        let data = self.encode_image(&image)?;
+        let fingerprint = fingerprint(&image);
+
+        // Alternates are encoded from the same grab before the primary may
+        // consume the image
+        let alternates = self
+            .config
+            .alternate_formats
+            .iter()
+            .map(|&format| {
+                Ok(Encoding {
+                    format: format_name(format),
+                    data: encode_as(&image, format, self.config.quality_for(format))?,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let (data, format, stride, width, height) = if self.config.raw {
+            (image.into_rgba8().into_raw(), RAW_FORMAT.to_string(), width * 4, width, height)
+        } else {
+            let quality = self.config.quality_for(self.config.format);
+            let (data, width, height) = encode_with_fallback(&image, self.config.format, |img, format| {
+                encode_as(img, format, quality)
+            })?;
+            (data, format_name(self.config.format), 0, width, height)
+        };
+
+        let (data, alternates) = match &self.config.provenance {
+            Some(provenance) if !self.config.raw => {
+                let fields = provenance.fields(timestamp, monitor.as_ref());
+                let stamp = |data, format| provenance::embed(data, format, &fields, timestamp);
+                let alternates = alternates
+                    .into_iter()
+                    .zip(&self.config.alternate_formats)
+                    .map(|(alt, &format)| Encoding { data: stamp(alt.data, format), ..alt })
+                    .collect();
+                (stamp(data, self.config.format), alternates)
+            }
+            _ => (data, alternates),
+        };
         let size_bytes = data.len() as i64;
 
         Ok(Frame {
             id: frame_id,
-            timestamp: Utc::now(),
+            timestamp,
             data,
             width,
             height,
-            format: format!("{:?}", self.config.format).to_lowercase(),
+            format,
             size_bytes,
+            stride,
+            alternates,
+            monitor,
+            fingerprint,
         })
     }
 
-    // Capture the screen and return as DynamicImage
-    fn capture_screen(&self) -> Result<DynamicImage> {
+    // Verify the screen can still be captured, e.g. after display sleep.
+    // The captured image is discarded.
+    pub fn self_test(&self) -> Result<()> {
+        self.capture_screen().map(|_| ())
+    }
+
+    // Monitor index for the next capture: the next entry of the rotation,
+    // else the configured monitor (None for the first)
+    pub fn next_monitor_index(&self) -> Option<usize> {
+        let rotation = &self.config.monitor_rotation;
+        if rotation.is_empty() {
+            return self.config.monitor;
+        }
+        let turn = self.rotation.fetch_add(1, Ordering::Relaxed);
+        Some(rotation[turn % rotation.len()])
+    }
+
+    // Capture the screen and return it with the monitor it came from,
+    // without encoding; see frame_from_capture
+    pub fn capture_screen(&self) -> Result<(DynamicImage, MonitorInfo)> {
         let monitors = Monitor::all()
             .context("Failed to enumerate monitors")?;
         
-        let monitor = monitors
-            .first()
-            .context("No screens available")?;
-        
-        let screenshot = monitor
-            .capture_image()
-            .map_err(|e| anyhow::anyhow!(e))
-            .context("Failed to capture screen")?;
+        let index = self.next_monitor_index();
+        let monitor = match index {
+            Some(index) => monitors.get(index).with_context(|| {
+                format!("Monitor {} not found ({} available)", index, monitors.len())
+            })?,
+            None => monitors.first().context("No screens available")?,
+        };
         
-        let image = DynamicImage::ImageRgba8(screenshot);
-        
-        Ok(image)
-    }
-
-    // Encode the image to the specified format
-    fn encode_image(&self, img: &DynamicImage) -> Result<Vec<u8>> {
-        let mut buffer = Cursor::new(Vec::new());
-        
-        match self.config.format {
-            ImageFormat::Png => {
-                // CompressionType::Fast (zlib level 1) cuts encode time from
-                // ~150ms to ~25ms at the cost of ~15% larger files.
-                // The default (level 6) is too slow for 1s capture intervals.
-                let encoder = image::codecs::png::PngEncoder::new_with_quality(
-                    &mut buffer,
-                    image::codecs::png::CompressionType::Fast,
-                    image::codecs::png::FilterType::Sub,
-                );
-                encoder.write_image(
-                    img.as_bytes(),
-                    img.width(),
-                    img.height(),
-                    img.color().into(),
-                ).context("Failed to encode PNG")?;
+        let (mut screenshot, offset) = match self.config.region {
+            Some(preset) => {
+                let width = monitor.width().map_err(|e| anyhow::anyhow!(e))?;
+                let height = monitor.height().map_err(|e| anyhow::anyhow!(e))?;
+                let rect = preset.resolve(width, height);
+                let image = monitor
+                    .capture_region(rect.x, rect.y, rect.width, rect.height)
+                    .map_err(|e| anyhow::anyhow!(e))
+                    .context("Failed to capture screen region")?;
+                (image, (rect.x as i32, rect.y as i32))
             }
-            ImageFormat::Jpeg => {
-                img.write_to(&mut buffer, ImageFormat::Jpeg)
-                    .context("Failed to encode JPEG")?;
+            None => {
+                let image = monitor
+                    .capture_image()
+                    .map_err(|e| anyhow::anyhow!(e))
+                    .context("Failed to capture screen")?;
+                (image, (0, 0))
             }
-            _ => {
-                img.write_to(&mut buffer, self.config.format)
-                    .context("Failed to encode image")?;
+        };
+
+        // A failure to list windows fails the capture rather than risk
+        // uploading an unredacted frame
+        let origin = (
+            monitor.x().map_err(|e| anyhow::anyhow!(e))? + offset.0,
+            monitor.y().map_err(|e| anyhow::anyhow!(e))? + offset.1,
+        );
+        let scale = monitor.scale_factor().map_err(|e| anyhow::anyhow!(e))?;
+        if !self.config.blocked_windows.is_empty() || self.config.notification_mask.is_some() {
+            let windows = windows()?;
+            let size = screenshot.dimensions();
+            let mut rects = blocked_rects(&windows, &self.config.blocked_windows, origin, scale, size);
+            if let Some(mask) = &self.config.notification_mask {
+                rects.extend(mask.rects(&windows, origin, scale, size));
             }
+            redact(&mut screenshot, &rects);
+        }
+        self.apply_rules(&mut screenshot, origin, scale)?;
+
+        if self.config.follow_focus
+            && let Some(rect) = self.focus_crop(origin, scale, screenshot.dimensions())?
+        {
+            screenshot = image::imageops::crop_imm(&screenshot, rect.x, rect.y, rect.width, rect.height)
+                .to_image();
         }
         
-        Ok(buffer.into_inner())
+        // Redaction and the focus crop above work in screen coordinates, so
+        // the pre-scale follows them but precedes every configured transform
+        let image = self.pre_scale(DynamicImage::ImageRgba8(screenshot), index.unwrap_or(0));
+
+        Ok((image, MonitorInfo::from_monitor(monitor)?))
+    }
+
+    // Apply the configured pre-scale for the monitor at `index` to a grab
+    pub fn pre_scale(&self, image: DynamicImage, index: usize) -> DynamicImage {
+        pipeline::downscale(image, self.config.pre_scale.factor_for(index))
+    }
+
+    // Part of an image captured with its top-left corner at `origin` that
+    // the focused window covers, re-read every frame so the crop follows the
+    // window as it moves, resizes or loses focus. None keeps the whole image:
+    // nothing is focused, the focused window is minimised or on another
+    // monitor, or it fills the image anyway (full-screen apps).
+    pub fn focus_crop(&self, origin: (i32, i32), scale: f32, size: (u32, u32)) -> Result<Option<Rect>> {
+        let Some(active) = self.active_window.active_window()? else {
+            return Ok(None);
+        };
+        if active.is_minimized {
+            return Ok(None);
+        }
+        let full = Rect { x: 0, y: 0, width: size.0, height: size.1 };
+        Ok(window_rect(&active, origin, scale, size).filter(|rect| *rect != full))
+    }
+
+    // Consult the active window and apply the first matching redaction
+    // rule to `image`, captured with its top-left corner at `origin` on a
+    // monitor with `scale` pixels per point. A Skip rule fails with
+    // `CaptureSkipped`.
+    fn apply_rules(&self, image: &mut RgbaImage, origin: (i32, i32), scale: f32) -> Result<()> {
+        if self.config.redaction_rules.is_empty() {
+            return Ok(());
+        }
+        let Some(active) = self.active_window.active_window()? else {
+            return Ok(());
+        };
+        let Some(rule) = evaluate(&self.config.redaction_rules, &active) else {
+            return Ok(());
+        };
+
+        if rule.action == RuleAction::Skip {
+            return Err(CaptureSkipped { app: active.app_name }.into());
+        }
+        let rects = rules::action_rects(rule.action, &active, origin, scale, image.dimensions());
+        redact(image, &rects);
+        Ok(())
     }
 }
 
+// Lowercase format name used in frame metadata, e.g. "webp"
+fn format_name(format: ImageFormat) -> String {
+    format!("{:?}", format).to_lowercase()
+}
+
+// Encode with `encode`, retrying once at half resolution if the full-size
+// encode fails (e.g. a transient allocation failure on a huge screen) so the
+// frame is degraded instead of lost. Returns the data and its dimensions.
+fn encode_with_fallback<F>(
+    img: &DynamicImage,
+    format: ImageFormat,
+    encode: F,
+) -> Result<(Vec<u8>, u32, u32)>
+where
+    F: Fn(&DynamicImage, ImageFormat) -> Result<Vec<u8>>,
+{
+    let err = match encode(img, format) {
+        Ok(data) => return Ok((data, img.width(), img.height())),
+        Err(e) => e,
+    };
+
+    let (width, height) = ((img.width() / 2).max(1), (img.height() / 2).max(1));
+    warn!(
+        "Encode failed ({:#}); degrading frame from {}x{} to {}x{}",
+        err,
+        img.width(),
+        img.height(),
+        width,
+        height
+    );
+
+    let smaller = img.resize_exact(width, height, image::imageops::FilterType::Triangle);
+    let data = encode(&smaller, format).context("Fallback encode failed")?;
+
+    Ok((data, width, height))
+}
+
+// Encode the image to the specified format. `quality` applies to JPEG only.
+fn encode_as(img: &DynamicImage, format: ImageFormat, quality: u8) -> Result<Vec<u8>> {
+    let mut buffer = Cursor::new(Vec::new());
+
+    match format {
+        ImageFormat::Png => {
+            // CompressionType::Fast (zlib level 1) cuts encode time from
+            // ~150ms to ~25ms at the cost of ~15% larger files.
+            // The default (level 6) is too slow for 1s capture intervals.
+            let encoder = image::codecs::png::PngEncoder::new_with_quality(
+                &mut buffer,
+                image::codecs::png::CompressionType::Fast,
+                image::codecs::png::FilterType::Sub,
+            );
+            encoder.write_image(
+                img.as_bytes(),
+                img.width(),
+                img.height(),
+                img.color().into(),
+            ).context("Failed to encode PNG")?;
+        }
+        ImageFormat::Jpeg => {
+            // JPEG has no alpha channel — drop it rather than fail the encode
+            let encoder = image::codecs::jpeg::JpegEncoder::new_with_quality(
+                &mut buffer,
+                quality.clamp(1, 100),
+            );
+            DynamicImage::ImageRgb8(img.to_rgb8())
+                .write_with_encoder(encoder)
+                .context("Failed to encode JPEG")?;
+        }
+        _ => {
+            img.write_to(&mut buffer, format)
+                .context("Failed to encode image")?;
+        }
+    }
+
+    Ok(buffer.into_inner())
+}
+
 // Function to compress PNG images
 pub fn compress_png(img: &DynamicImage) -> Result<Vec<u8>> {
     let mut buffer = Cursor::new(Vec::new());
@@ -151,4 +849,580 @@ mod tests {
         let engine = Engine::new(config);
         assert!(std::mem::size_of_val(&engine) > 0);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_change_score_tracks_scene_changes() {
+        let engine = Engine::new(Config::default());
+        let frame = |shade: u8| {
+            let img = image::RgbaImage::from_pixel(64, 48, image::Rgba([shade, shade, shade, 255]));
+            engine.frame_from_image(1, DynamicImage::ImageRgba8(img)).unwrap()
+        };
+
+        let dark = frame(0);
+        assert_eq!(dark.fingerprint.len(), (FINGERPRINT_SIZE * FINGERPRINT_SIZE) as usize);
+        assert_eq!(change_score(&dark.fingerprint, &frame(0).fingerprint), 0.0);
+        assert!(change_score(&dark.fingerprint, &frame(10).fingerprint) < 0.1);
+        assert!(change_score(&dark.fingerprint, &frame(255).fingerprint) > 0.9);
+    }
+
+    #[test]
+    fn test_frame_thumbnail() {
+        let img = DynamicImage::ImageRgba8(image::RgbaImage::new(320, 180));
+        let mut buffer = Cursor::new(Vec::new());
+        img.write_to(&mut buffer, ImageFormat::Png).unwrap();
+        let data = buffer.into_inner();
+
+        let frame = Frame {
+            id: 1,
+            timestamp: Utc::now(),
+            size_bytes: data.len() as i64,
+            data,
+            width: 320,
+            height: 180,
+            format: "png".to_string(),
+            stride: 0,
+            alternates: Vec::new(),
+            monitor: None,
+            fingerprint: Vec::new(),
+        };
+
+        let thumb = frame.thumbnail(64, ImageFormat::Jpeg).unwrap();
+        let decoded = image::load_from_memory(&thumb).unwrap();
+        assert_eq!(decoded.width().max(decoded.height()), 64);
+        assert_eq!(decoded.height(), 36);
+    }
+
+    #[test]
+    fn test_raw_frame_round_trip() {
+        let mut pixels = image::RgbaImage::new(7, 5);
+        for (x, y, p) in pixels.enumerate_pixels_mut() {
+            *p = image::Rgba([x as u8 * 30, y as u8 * 40, 200, 128]);
+        }
+        let original = DynamicImage::ImageRgba8(pixels);
+
+        let engine = Engine::new(Config { raw: true, ..Config::default() });
+        let frame = engine.frame_from_image(1, original.clone()).unwrap();
+
+        assert!(frame.is_raw());
+        assert_eq!(frame.stride, 7 * 4);
+        assert_eq!(frame.data.len(), 7 * 4 * 5);
+        assert_eq!(frame.to_image().unwrap(), original);
+    }
+
+    #[test]
+    fn test_alternate_formats_from_one_capture() {
+        let original = DynamicImage::ImageRgba8(image::RgbaImage::new(64, 48));
+
+        let engine = Engine::new(Config {
+            format: ImageFormat::Png,
+            alternate_formats: vec![ImageFormat::Jpeg],
+            ..Config::default()
+        });
+        let frame = engine.frame_from_image(1, original).unwrap();
+
+        let png = image::load_from_memory_with_format(&frame.data, ImageFormat::Png).unwrap();
+        let jpeg = frame.alternate("jpeg").unwrap();
+        let jpeg = image::load_from_memory_with_format(&jpeg.data, ImageFormat::Jpeg).unwrap();
+
+        assert_eq!(frame.format, "png");
+        assert_eq!(png.dimensions(), (64, 48));
+        assert_eq!(jpeg.dimensions(), png.dimensions());
+    }
+
+    #[test]
+    fn test_format_quality_overrides_scalar() {
+        // Noisy enough that JPEG quality makes a visible size difference
+        let original = DynamicImage::ImageRgb8(image::RgbImage::from_fn(128, 128, |x, y| {
+            image::Rgb([(x * 7 + y * 13) as u8, (x * y) as u8, (x ^ y) as u8])
+        }));
+        let encode = |jpeg_quality: u8| {
+            let engine = Engine::new(Config {
+                format: ImageFormat::Jpeg,
+                quality: 75,
+                format_quality: HashMap::from([(ImageFormat::Jpeg, jpeg_quality)]),
+                ..Config::default()
+            });
+            engine.frame_from_image(1, original.clone()).unwrap().data.len()
+        };
+
+        assert!(encode(50) < encode(90));
+        assert_eq!(Config::default().quality_for(ImageFormat::Jpeg), DEFAULT_QUALITY);
+    }
+
+    #[test]
+    fn test_center_720p_region() {
+        let preset: RegionPreset = "center-720p".parse().unwrap();
+        let rect = preset.resolve(1920, 1080);
+        assert_eq!(rect, Rect { x: 320, y: 180, width: 1280, height: 720 });
+
+        // Clamped on a monitor smaller than the preset
+        let rect = RegionPreset::Center1080p.resolve(1280, 720);
+        assert_eq!(rect, Rect { x: 0, y: 0, width: 1280, height: 720 });
+    }
+
+    #[test]
+    fn test_encode_fallback_after_failure() {
+        use std::cell::Cell;
+
+        let img = DynamicImage::ImageRgba8(image::RgbaImage::new(200, 100));
+        let attempts = Cell::new(0);
+        let flaky = |img: &DynamicImage, format: ImageFormat| {
+            attempts.set(attempts.get() + 1);
+            if attempts.get() == 1 {
+                anyhow::bail!("simulated allocation failure");
+            }
+            encode_as(img, format, DEFAULT_QUALITY)
+        };
+
+        let (data, width, height) = encode_with_fallback(&img, ImageFormat::Png, flaky).unwrap();
+        assert_eq!(attempts.get(), 2);
+        assert_eq!((width, height), (100, 50));
+
+        let decoded = image::load_from_memory(&data).unwrap();
+        assert_eq!(decoded.dimensions(), (100, 50));
+    }
+
+    #[test]
+    fn test_blocked_window_is_redacted() {
+        let window = |title: &str, x, y| WindowInfo {
+            title: title.to_string(),
+            app_name: String::new(),
+            x,
+            y,
+            width: 20,
+            height: 10,
+            is_minimized: false,
+        };
+        let windows = vec![
+            window("1Password — Vault", 110, 105),
+            window("Terminal", 130, 120),
+            // Hangs off the top-left edge of the monitor
+            window("Signal", 90, 95),
+        ];
+        let patterns = vec!["1password".to_string(), "SIGNAL".to_string()];
+
+        // Monitor positioned at (100, 100) on the desktop
+        let rects = blocked_rects(&windows, &patterns, (100, 100), 1.0, (64, 64));
+        assert_eq!(rects, vec![
+            Rect { x: 10, y: 5, width: 20, height: 10 },
+            Rect { x: 0, y: 0, width: 10, height: 5 },
+        ]);
+
+        let mut image = RgbaImage::from_pixel(64, 64, Rgba([255, 255, 255, 255]));
+        redact(&mut image, &rects);
+        assert_eq!(image.get_pixel(15, 10), &Rgba([0, 0, 0, 255]));
+        assert_eq!(image.get_pixel(40, 25), &Rgba([255, 255, 255, 255]));
+
+        // On a 2x display the image has two pixels per desktop point, so
+        // the same windows cover twice the pixels, from twice the offset
+        let rects = blocked_rects(&windows, &patterns, (100, 100), 2.0, (128, 128));
+        assert_eq!(rects, vec![
+            Rect { x: 20, y: 10, width: 40, height: 20 },
+            Rect { x: 0, y: 0, width: 20, height: 10 },
+        ]);
+    }
+
+    #[test]
+    fn test_notification_windows_are_redacted() {
+        let window = |app: &str, title: &str, x, y, width, height| WindowInfo {
+            title: title.to_string(),
+            app_name: app.to_string(),
+            x,
+            y,
+            width,
+            height,
+            is_minimized: false,
+        };
+        let windows = vec![
+            // macOS banner in the top-right corner
+            window("NotificationCenter", "", 40, 0, 24, 10),
+            // GNOME-style banner owned by the shell, recognised by its title
+            window("gnome-shell", "Notification", 0, 50, 30, 8),
+            window("Slack", "general", 0, 0, 40, 40),
+            // Large window that merely mentions notifications
+            window("Settings", "Notifications", 0, 0, 1024, 768),
+        ];
+        let mask = NotificationMask::default();
+
+        assert!(mask.is_notification(&windows[0]));
+        assert!(mask.is_notification(&windows[1]));
+        assert!(!mask.is_notification(&windows[2]));
+        assert!(!mask.is_notification(&windows[3]));
+
+        let rects = mask.rects(&windows, (0, 0), 1.0, (64, 64));
+        assert_eq!(rects, vec![
+            Rect { x: 40, y: 0, width: 24, height: 10 },
+            Rect { x: 0, y: 50, width: 30, height: 8 },
+        ]);
+        // A 2x display: twice the pixels, clipped to the image
+        assert_eq!(mask.rects(&windows, (0, 0), 2.0, (128, 128)), vec![
+            Rect { x: 80, y: 0, width: 48, height: 20 },
+            Rect { x: 0, y: 100, width: 60, height: 16 },
+        ]);
+        let mut image = RgbaImage::from_pixel(64, 64, Rgba([255, 255, 255, 255]));
+        redact(&mut image, &rects);
+        assert_eq!(image.get_pixel(50, 5), &Rgba([0, 0, 0, 255]));
+        assert_eq!(image.get_pixel(20, 20), &Rgba([255, 255, 255, 255]));
+
+        // Windows toasts share a host process with the Start menu and
+        // Action Center; only the toast-sized windows are masked
+        assert!(mask.is_notification(&window("ShellExperienceHost", "New notification", 0, 0, 364, 100)));
+        assert!(mask.is_notification(&window("ShellExperienceHost", "", 0, 0, 364, 100)));
+        assert!(!mask.is_notification(&window("ShellExperienceHost", "Start", 0, 0, 640, 720)));
+        assert!(!mask.is_notification(&window("ShellExperienceHost", "Action center", 1500, 0, 400, 1040)));
+
+        // Extra notifier names are configurable
+        let custom = NotificationMask { extra_apps: vec!["Growl".to_string()] };
+        assert!(custom.is_notification(&window("growl", "", 0, 0, 10, 10)));
+        assert!(!mask.is_notification(&window("growl", "", 0, 0, 10, 10)));
+    }
+
+    struct FakeActiveWindow(WindowInfo);
+
+    impl ActiveWindowProvider for FakeActiveWindow {
+        fn active_window(&self) -> Result<Option<WindowInfo>> {
+            Ok(Some(self.0.clone()))
+        }
+    }
+
+    #[test]
+    fn test_redaction_rule_follows_active_app() {
+        let active = |app: &str, title: &str| {
+            Arc::new(FakeActiveWindow(WindowInfo {
+                title: title.to_string(),
+                app_name: app.to_string(),
+                x: 0,
+                y: 0,
+                width: 32,
+                height: 32,
+                is_minimized: false,
+            }))
+        };
+        let config = Config {
+            redaction_rules: RedactionRule::parse_list("firefox:bank=screen; keepassxc=skip").unwrap(),
+            ..Config::default()
+        };
+        let white = RgbaImage::from_pixel(64, 64, Rgba([255, 255, 255, 255]));
+
+        // Browser on a banking site: the whole frame is blacked out
+        let engine = Engine::new(config.clone()).with_active_window(active("Firefox", "My Bank — Login"));
+        let mut image = white.clone();
+        engine.apply_rules(&mut image, (0, 0), 1.0).unwrap();
+        assert!(image.pixels().all(|p| *p == Rgba([0, 0, 0, 255])));
+
+        // Same browser elsewhere: untouched
+        let engine = Engine::new(config.clone()).with_active_window(active("Firefox", "Rust docs"));
+        let mut image = white.clone();
+        engine.apply_rules(&mut image, (0, 0), 1.0).unwrap();
+        assert_eq!(image, white);
+
+        // Skip rules surface as a distinct error
+        let engine = Engine::new(config).with_active_window(active("KeePassXC", "Vault"));
+        let err = engine.apply_rules(&mut white.clone(), (0, 0), 1.0).unwrap_err();
+        assert!(err.downcast_ref::<CaptureSkipped>().is_some());
+    }
+
+    // Focused window that can move between frames
+    struct MovingWindow(std::sync::Mutex<Option<WindowInfo>>);
+
+    impl ActiveWindowProvider for MovingWindow {
+        fn active_window(&self) -> Result<Option<WindowInfo>> {
+            Ok(self.0.lock().unwrap().clone())
+        }
+    }
+
+    #[test]
+    fn test_focus_crop_follows_focused_window() {
+        let window = |x: i32, y: i32, width: u32, height: u32| WindowInfo {
+            title: "Editor".to_string(),
+            app_name: "code".to_string(),
+            x,
+            y,
+            width,
+            height,
+            is_minimized: false,
+        };
+        let provider = Arc::new(MovingWindow(std::sync::Mutex::new(Some(window(2020, 50, 800, 600)))));
+        let engine = Engine::new(Config { follow_focus: true, ..Config::default() })
+            .with_active_window(provider.clone());
+        // Second monitor at x=1920
+        let (origin, size) = ((1920, 0), (1920, 1080));
+        let set = |w: Option<WindowInfo>| *provider.0.lock().unwrap() = w;
+
+        assert_eq!(
+            engine.focus_crop(origin, 1.0, size).unwrap(),
+            Some(Rect { x: 100, y: 50, width: 800, height: 600 })
+        );
+
+        // Moved and resized: the crop tracks it, clipped to the monitor
+        set(Some(window(3440, 900, 800, 600)));
+        assert_eq!(
+            engine.focus_crop(origin, 1.0, size).unwrap(),
+            Some(Rect { x: 1520, y: 900, width: 400, height: 180 })
+        );
+
+        // Full-screen app, nothing focused, minimised or on another monitor:
+        // keep the whole frame
+        set(Some(window(1920, 0, 1920, 1080)));
+        assert_eq!(engine.focus_crop(origin, 1.0, size).unwrap(), None);
+        set(None);
+        assert_eq!(engine.focus_crop(origin, 1.0, size).unwrap(), None);
+        set(Some(WindowInfo { is_minimized: true, ..window(2020, 50, 800, 600) }));
+        assert_eq!(engine.focus_crop(origin, 1.0, size).unwrap(), None);
+        set(Some(window(0, 0, 800, 600)));
+        assert_eq!(engine.focus_crop(origin, 1.0, size).unwrap(), None);
+    }
+
+    #[test]
+    fn test_frame_records_monitor_metadata() {
+        let monitor = MonitorInfo {
+            id: 7,
+            name: "DP-1".to_string(),
+            x: 1920,
+            y: 0,
+            width: 2560,
+            height: 1440,
+            scale_factor: 1.5,
+            is_primary: false,
+        };
+        let engine = Engine::new(Config { format: ImageFormat::Png, ..Config::default() });
+        let image = DynamicImage::ImageRgba8(RgbaImage::new(64, 36));
+
+        let frame = engine.frame_from_capture(1, image, monitor.clone()).unwrap();
+        assert_eq!(frame.monitor.as_ref(), Some(&monitor));
+
+        let fields = frame.monitor.unwrap().fields();
+        let field = |key: &str| fields.iter().find(|(k, _)| *k == key).map(|(_, v)| v.as_str());
+        assert_eq!(field("monitor_id"), Some("7"));
+        assert_eq!(field("monitor_name"), Some("DP-1"));
+        assert_eq!(field("monitor_width"), Some("2560"));
+        assert_eq!(field("monitor_x"), Some("1920"));
+        assert_eq!(field("monitor_scale_factor"), Some("1.5"));
+    }
+
+    #[test]
+    fn test_provenance_written_to_png_text_chunks() {
+        let monitor = MonitorInfo {
+            id: 2,
+            name: "HDMI-1".to_string(),
+            x: 0,
+            y: 0,
+            width: 1920,
+            height: 1080,
+            scale_factor: 1.0,
+            is_primary: true,
+        };
+        let provenance = Provenance {
+            hostname: "build-box".to_string(),
+            agent_id: "desk-3".to_string(),
+        };
+        let engine = Engine::new(Config {
+            format: ImageFormat::Png,
+            alternate_formats: vec![ImageFormat::Jpeg],
+            provenance: Some(provenance),
+            ..Config::default()
+        });
+        let image = DynamicImage::ImageRgba8(RgbaImage::new(64, 36));
+        let frame = engine.frame_from_capture(1, image, monitor).unwrap();
+
+        // Walk the chunks after the signature, collecting tEXt entries
+        let mut text = HashMap::new();
+        let mut rest = &frame.data[8..];
+        while rest.len() >= 12 {
+            let len = u32::from_be_bytes(rest[..4].try_into().unwrap()) as usize;
+            if &rest[4..8] == b"tEXt" {
+                let body = std::str::from_utf8(&rest[8..8 + len]).unwrap();
+                let (key, value) = body.split_once('\0').unwrap();
+                text.insert(key.to_string(), value.to_string());
+            }
+            rest = &rest[12 + len..];
+        }
+        assert_eq!(text.get("hostname").map(String::as_str), Some("build-box"));
+        assert_eq!(text.get("agent_id").map(String::as_str), Some("desk-3"));
+        assert_eq!(text.get("capture_time"), Some(&frame.timestamp.to_rfc3339()));
+        assert!(text["monitor"].starts_with("HDMI-1"));
+
+        // Both encodings still decode, checksums included
+        image::load_from_memory_with_format(&frame.data, ImageFormat::Png).unwrap();
+        let jpeg = &frame.alternates[0].data;
+        assert!(jpeg.windows(6).any(|w| w == b"Exif\0\0"));
+        image::load_from_memory_with_format(jpeg, ImageFormat::Jpeg).unwrap();
+
+        // Opt-in: nothing is written by default
+        let plain = Engine::new(Config { format: ImageFormat::Png, ..Config::default() })
+            .frame_from_image(1, DynamicImage::ImageRgba8(RgbaImage::new(64, 36)))
+            .unwrap();
+        assert!(!plain.data.windows(4).any(|w| w == b"tEXt"));
+    }
+
+    #[test]
+    fn test_monitor_rotation_cycles_in_order() {
+        let engine = Engine::new(Config {
+            monitor: Some(5),
+            monitor_rotation: vec![2, 0, 1],
+            ..Config::default()
+        });
+        let order: Vec<_> = (0..7).map(|_| engine.next_monitor_index()).collect();
+        assert_eq!(order, [2, 0, 1, 2, 0, 1, 2].map(Some));
+
+        // Without a rotation every capture uses the configured monitor
+        let engine = Engine::new(Config { monitor: Some(1), ..Config::default() });
+        assert_eq!(engine.next_monitor_index(), Some(1));
+        assert_eq!(engine.next_monitor_index(), Some(1));
+    }
+
+    #[test]
+    fn test_transform_order_changes_result() {
+        let white = DynamicImage::ImageRgba8(RgbaImage::from_pixel(8, 8, Rgba([255, 255, 255, 255])));
+        let frame_with = |transforms: Vec<Arc<dyn Transform>>| {
+            let engine = Engine::new(Config { raw: true, transforms, ..Config::default() });
+            let frame = engine.frame_from_image(1, white.clone()).unwrap();
+            (frame.width, frame.height, frame.to_image().unwrap().into_rgba8())
+        };
+        let left_half: Arc<dyn Transform> = Arc::new(Redact {
+            rects: vec![Rect { x: 0, y: 0, width: 4, height: 8 }],
+        });
+        let halve: Arc<dyn Transform> = Arc::new(Resize { scale: 0.5 });
+
+        // Redacting first blacks out the left half of the source
+        let (width, height, image) = frame_with(vec![left_half.clone(), halve.clone()]);
+        assert_eq!((width, height), (4, 4));
+        assert_eq!(image.get_pixel(0, 0).0, [0, 0, 0, 255]);
+        assert_eq!(image.get_pixel(3, 0).0, [255, 255, 255, 255]);
+
+        // Resizing first leaves a 4x4 image the same rect covers entirely
+        let (_, _, image) = frame_with(vec![halve, left_half]);
+        assert!(image.pixels().all(|p| p.0 == [0, 0, 0, 255]));
+
+        // An empty pipeline passes the grab through untouched
+        let (width, height, image) = frame_with(Vec::new());
+        assert_eq!((width, height), (8, 8));
+        assert_eq!(image, white.to_rgba8());
+    }
+
+    #[test]
+    fn test_parse_transforms() {
+        let transforms = parse_transforms("redact:0,0,10,10/20,20,5,5; resize:0.5; grayscale; quantize:16").unwrap();
+        let names: Vec<_> = transforms.iter().map(|t| t.name()).collect();
+        assert_eq!(names, ["redact", "resize", "grayscale", "quantize"]);
+
+        assert!(parse_transforms("resize:2").is_err());
+        assert!(parse_transforms("quantize:1").is_err());
+        assert!(parse_transforms("sharpen").is_err());
+    }
+
+    #[test]
+    fn test_pre_scale_halves_grab_per_monitor() {
+        let engine = Engine::new(Config {
+            pre_scale: "0.5,2=0.25,3=1".parse().unwrap(),
+            format: ImageFormat::Png,
+            ..Config::default()
+        });
+        let grab = || DynamicImage::ImageRgba8(RgbaImage::new(384, 216));
+
+        let frame = engine.frame_from_image(1, engine.pre_scale(grab(), 0)).unwrap();
+        assert_eq!((frame.width, frame.height), (192, 108));
+        assert_eq!(engine.pre_scale(grab(), 2).dimensions(), (96, 54));
+        assert_eq!(engine.pre_scale(grab(), 3).dimensions(), (384, 216));
+
+        assert!("1.5".parse::<PreScale>().is_err());
+        assert!("x=0.5".parse::<PreScale>().is_err());
+    }
+
+    #[test]
+    fn test_logo_is_composited_in_corner() {
+        let grey = Rgba([40, 40, 40, 255]);
+        let red = Rgba([255, 0, 0, 255]);
+        let logo: Arc<dyn Transform> = Arc::new(Logo::new(RgbaImage::from_pixel(4, 3, red), Corner::BottomRight, 2, 1.0));
+        let engine = Engine::new(Config { raw: true, transforms: vec![logo], ..Config::default() });
+        let frame = engine
+            .frame_from_image(1, DynamicImage::ImageRgba8(RgbaImage::from_pixel(32, 24, grey)))
+            .unwrap();
+        let image = frame.to_image().unwrap().into_rgba8();
+
+        // 2px in from the bottom-right edges: x 26..30, y 19..22
+        for (x, y, pixel) in image.enumerate_pixels() {
+            let in_logo = (26..30).contains(&x) && (19..22).contains(&y);
+            assert_eq!(*pixel, if in_logo { red } else { grey }, "pixel ({}, {})", x, y);
+        }
+
+        // Opacity blends the logo with what is underneath
+        let faded = Logo::new(RgbaImage::from_pixel(4, 3, red), Corner::TopLeft, 0, 0.5);
+        let image = faded
+            .apply(DynamicImage::ImageRgba8(RgbaImage::from_pixel(8, 8, grey)))
+            .unwrap()
+            .into_rgba8();
+        let blended = image.get_pixel(0, 0).0;
+        assert!(blended[0] > 100 && blended[0] < 200 && blended[1] < 40, "{:?}", blended);
+        assert_eq!(*image.get_pixel(4, 0), grey);
+    }
+
+    #[test]
+    fn test_hook_rewrites_frame_before_encoding() {
+        let invert: Arc<dyn Transform> = Arc::new(Hook::new("invert", Duration::from_secs(5), |mut image| {
+            image.pixels_mut().for_each(|p| {
+                let [r, g, b, a] = p.0;
+                p.0 = [255 - r, 255 - g, 255 - b, a];
+            });
+            Ok(image)
+        }));
+        let engine = Engine::new(Config { raw: true, transforms: vec![invert], ..Config::default() });
+        let frame = engine
+            .frame_from_image(1, DynamicImage::ImageRgba8(RgbaImage::from_pixel(8, 8, Rgba([10, 200, 0, 255]))))
+            .unwrap();
+        let image = frame.to_image().unwrap().into_rgba8();
+        assert!(image.pixels().all(|p| p.0 == [245, 55, 255, 255]));
+
+        // A failing hook leaves the frame as it was
+        let failing = Hook::new("broken", Duration::from_secs(5), |_| anyhow::bail!("model not loaded"));
+        let grey = RgbaImage::from_pixel(4, 4, Rgba([40, 40, 40, 255]));
+        let image = failing.apply(DynamicImage::ImageRgba8(grey.clone())).unwrap();
+        assert_eq!(image.into_rgba8(), grey);
+    }
+
+    #[test]
+    fn test_hook_over_budget_falls_back_to_original() {
+        let slow = Hook::new("slow", Duration::from_millis(20), |image| {
+            std::thread::sleep(Duration::from_millis(500));
+            Ok(RgbaImage::new(image.width(), image.height()))
+        });
+        let grey = RgbaImage::from_pixel(4, 4, Rgba([40, 40, 40, 255]));
+
+        let started = std::time::Instant::now();
+        let image = slow.apply(DynamicImage::ImageRgba8(grey.clone())).unwrap();
+        assert!(started.elapsed() < Duration::from_millis(400));
+        assert_eq!(image.into_rgba8(), grey);
+    }
+
+    #[test]
+    fn test_hook_reuses_one_thread_and_skips_while_busy() {
+        let calls = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let seen = calls.clone();
+        let hook = Hook::new("tracked", Duration::from_millis(50), move |image| {
+            seen.lock().unwrap().push(std::thread::current().id());
+            if image.get_pixel(0, 0).0[0] == 0 {
+                std::thread::sleep(Duration::from_millis(300));
+            }
+            Ok(image)
+        });
+        let frame = |value| DynamicImage::ImageRgba8(RgbaImage::from_pixel(2, 2, Rgba([value, 0, 0, 255])));
+
+        for _ in 0..3 {
+            hook.apply(frame(1)).unwrap();
+        }
+        let threads = calls.lock().unwrap().clone();
+        assert_eq!(threads.len(), 3);
+        assert!(threads.iter().all(|id| *id == threads[0] && *id != std::thread::current().id()));
+
+        // The slow call overruns; the next frame skips the hook instead
+        // of queueing behind it
+        hook.apply(frame(0)).unwrap();
+        hook.apply(frame(1)).unwrap();
+        assert_eq!(calls.lock().unwrap().len(), 4);
+
+        // Once it finishes, frames go through the hook again
+        std::thread::sleep(Duration::from_millis(400));
+        hook.apply(frame(1)).unwrap();
+        assert_eq!(calls.lock().unwrap().len(), 5);
+    }
+}
@@ -0,0 +1,75 @@
+// crates/capture/src/notifications.rs
+use crate::{Rect, WindowInfo};
+
+// Processes that only draw notification banners, compared against the
+// whole app name (case-insensitive). Deliberately narrow: shells that also
+// own ordinary windows (gnome-shell, plasmashell, explorer) are left to the
+// title heuristic instead.
+const KNOWN_NOTIFIERS: &[&str] = &[
+    // macOS
+    "notificationcenter",
+    "notification center",
+    "notification centre",
+    "usernotificationcenter",
+    // Linux notification daemons
+    "dunst",
+    "mako",
+    "notify-osd",
+    "xfce4-notifyd",
+    "swaync",
+    "notification-daemon",
+    "mate-notification-daemon",
+    "lxqt-notificationd",
+];
+
+// Processes that draw notification toasts but also other shell surfaces,
+// so only their banner-sized windows count. On Windows the Start menu and
+// Action Center belong to the same host as the toasts.
+const SHARED_NOTIFIERS: &[&str] = &["shellexperiencehost", "windows shell experience host"];
+
+// Banners are small. A bigger window that merely mentions "notification"
+// in its title (a settings page, a mail thread) is left alone.
+const MAX_BANNER_WIDTH: u32 = 800;
+const MAX_BANNER_HEIGHT: u32 = 300;
+
+// Decides which windows are notification banners to black out. Detection
+// is heuristic, so it errs towards leaving windows alone; `extra_apps`
+// covers notifiers the built-in list misses.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NotificationMask {
+    /// Further app names treated as notifiers, matched like the built-ins
+    pub extra_apps: Vec<String>,
+}
+
+impl NotificationMask {
+    // A known notifier process, or a small window titled as a notification
+    // (GNOME Shell and KDE banners belong to the shell process itself)
+    pub fn is_notification(&self, window: &WindowInfo) -> bool {
+        if window.is_minimized {
+            return false;
+        }
+
+        let app = window.app_name.trim().to_lowercase();
+        let known = !app.is_empty()
+            && (KNOWN_NOTIFIERS.contains(&app.as_str())
+                || self.extra_apps.iter().any(|a| a.trim().to_lowercase() == app));
+        if known {
+            return true;
+        }
+
+        let banner_sized = window.width <= MAX_BANNER_WIDTH && window.height <= MAX_BANNER_HEIGHT;
+        banner_sized
+            && (SHARED_NOTIFIERS.contains(&app.as_str()) || window.title.to_lowercase().contains("notification"))
+    }
+
+    // Bounds of the notification windows among `windows`, in the
+    // coordinates of an image whose top-left corner sits at `origin` on a
+    // desktop with `scale` pixels per point, clipped to `size`
+    pub(crate) fn rects(&self, windows: &[WindowInfo], origin: (i32, i32), scale: f32, size: (u32, u32)) -> Vec<Rect> {
+        windows
+            .iter()
+            .filter(|w| self.is_notification(w))
+            .filter_map(|w| crate::window_rect(w, origin, scale, size))
+            .collect()
+    }
+}
@@ -0,0 +1,447 @@
+// crates/capture/src/pipeline.rs
+use crate::{Rect, redact};
+use anyhow::{Context, Result};
+use image::{DynamicImage, GenericImageView, RgbaImage};
+use std::fmt::Debug;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, mpsc};
+use std::time::Duration;
+use tracing::warn;
+
+// One step of the capture pipeline. Transforms run in configured order on
+// the grabbed image, before any encoding, so e.g. redacting before resizing
+// blacks out source pixels while resizing first redacts scaled ones.
+pub trait Transform: Debug + Send + Sync {
+    // Short name used in logs and errors
+    fn name(&self) -> &str;
+
+    fn apply(&self, image: DynamicImage) -> Result<DynamicImage>;
+}
+
+// Run `image` through `transforms` in order
+pub(crate) fn apply_all(transforms: &[Arc<dyn Transform>], image: DynamicImage) -> Result<DynamicImage> {
+    transforms.iter().try_fold(image, |image, transform| {
+        transform
+            .apply(image)
+            .with_context(|| format!("Transform '{}' failed", transform.name()))
+    })
+}
+
+// Black out fixed rectangles, in the coordinates of the image as it
+// reaches this step; parts outside the image are ignored
+#[derive(Debug, Clone, PartialEq)]
+pub struct Redact {
+    pub rects: Vec<Rect>,
+}
+
+impl Transform for Redact {
+    fn name(&self) -> &str {
+        "redact"
+    }
+
+    fn apply(&self, image: DynamicImage) -> Result<DynamicImage> {
+        let mut image = image.into_rgba8();
+        let (width, height) = image.dimensions();
+        let clipped: Vec<Rect> = self
+            .rects
+            .iter()
+            .filter(|r| r.x < width && r.y < height)
+            .map(|r| Rect {
+                x: r.x,
+                y: r.y,
+                width: r.width.min(width - r.x),
+                height: r.height.min(height - r.y),
+            })
+            .collect();
+        redact(&mut image, &clipped);
+        Ok(DynamicImage::ImageRgba8(image))
+    }
+}
+
+// Downscale by a factor in (0, 1]; 1.0 leaves the image untouched
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Resize {
+    pub scale: f32,
+}
+
+impl Transform for Resize {
+    fn name(&self) -> &str {
+        "resize"
+    }
+
+    fn apply(&self, image: DynamicImage) -> Result<DynamicImage> {
+        Ok(downscale(image, self.scale))
+    }
+}
+
+// Shrink by `scale`; factors outside (0, 1) return the image as is
+pub(crate) fn downscale(image: DynamicImage, scale: f32) -> DynamicImage {
+    if !(scale > 0.0 && scale < 1.0) {
+        return image;
+    }
+    let (width, height) = image.dimensions();
+    image.resize_exact(
+        ((width as f32 * scale).round() as u32).max(1),
+        ((height as f32 * scale).round() as u32).max(1),
+        image::imageops::FilterType::Triangle,
+    )
+}
+
+// Drop colour, keeping alpha
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Grayscale;
+
+impl Transform for Grayscale {
+    fn name(&self) -> &str {
+        "grayscale"
+    }
+
+    fn apply(&self, image: DynamicImage) -> Result<DynamicImage> {
+        // Back to RGBA so every output format can encode it
+        Ok(DynamicImage::ImageRgba8(image.grayscale().into_rgba8()))
+    }
+}
+
+// Reduce each colour channel to `levels` evenly spaced values, which
+// shrinks PNG output considerably for screen content
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Quantize {
+    pub levels: u8,
+}
+
+impl Transform for Quantize {
+    fn name(&self) -> &str {
+        "quantize"
+    }
+
+    fn apply(&self, image: DynamicImage) -> Result<DynamicImage> {
+        if self.levels < 2 {
+            anyhow::bail!("quantize needs at least 2 levels, got {}", self.levels);
+        }
+        let steps = (self.levels - 1) as f32;
+        let mut image = image.into_rgba8();
+        for pixel in image.pixels_mut() {
+            for channel in &mut pixel.0[..3] {
+                *channel = ((*channel as f32 * steps / 255.0).round() * 255.0 / steps).round() as u8;
+            }
+        }
+        Ok(DynamicImage::ImageRgba8(image))
+    }
+}
+
+// Draw an image, e.g. a logo or watermark, over the frame with its
+// top-left corner at (x, y)
+#[derive(Debug, Clone, PartialEq)]
+pub struct Overlay {
+    pub image: RgbaImage,
+    pub x: i64,
+    pub y: i64,
+}
+
+impl Overlay {
+    pub fn load(path: impl Into<PathBuf>, x: i64, y: i64) -> Result<Self> {
+        let path = path.into();
+        let image = image::open(&path)
+            .with_context(|| format!("Failed to load overlay image {}", path.display()))?
+            .into_rgba8();
+        Ok(Self { image, x, y })
+    }
+}
+
+impl Transform for Overlay {
+    fn name(&self) -> &str {
+        "overlay"
+    }
+
+    fn apply(&self, image: DynamicImage) -> Result<DynamicImage> {
+        let mut image = image.into_rgba8();
+        image::imageops::overlay(&mut image, &self.image, self.x, self.y);
+        Ok(DynamicImage::ImageRgba8(image))
+    }
+}
+
+// Corner of the frame a logo is anchored to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Corner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    #[default]
+    BottomRight,
+}
+
+impl FromStr for Corner {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "top-left" | "tl" => Ok(Self::TopLeft),
+            "top-right" | "tr" => Ok(Self::TopRight),
+            "bottom-left" | "bl" => Ok(Self::BottomLeft),
+            "bottom-right" | "br" => Ok(Self::BottomRight),
+            other => anyhow::bail!(
+                "Unknown corner '{}': expected top-left, top-right, bottom-left or bottom-right",
+                other
+            ),
+        }
+    }
+}
+
+// Station label or logo burned into a corner of every frame, e.g. for
+// shared monitoring walls. The image keeps its own alpha, scaled by
+// `opacity` once up front, and sits `padding` pixels in from the edges.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Logo {
+    image: RgbaImage,
+    corner: Corner,
+    padding: u32,
+}
+
+impl Logo {
+    // `opacity` is clamped to [0, 1]
+    pub fn new(mut image: RgbaImage, corner: Corner, padding: u32, opacity: f32) -> Self {
+        let opacity = opacity.clamp(0.0, 1.0);
+        if opacity < 1.0 {
+            for pixel in image.pixels_mut() {
+                pixel.0[3] = (pixel.0[3] as f32 * opacity).round() as u8;
+            }
+        }
+        Self { image, corner, padding }
+    }
+
+    pub fn load(path: impl Into<PathBuf>, corner: Corner, padding: u32, opacity: f32) -> Result<Self> {
+        let path = path.into();
+        let image = image::open(&path)
+            .with_context(|| format!("Failed to load logo image {}", path.display()))?
+            .into_rgba8();
+        Ok(Self::new(image, corner, padding, opacity))
+    }
+}
+
+impl Transform for Logo {
+    fn name(&self) -> &str {
+        "logo"
+    }
+
+    fn apply(&self, image: DynamicImage) -> Result<DynamicImage> {
+        let mut image = image.into_rgba8();
+        let (width, height) = (image.width() as i64, image.height() as i64);
+        let (logo_width, logo_height) = (self.image.width() as i64, self.image.height() as i64);
+        let padding = self.padding as i64;
+
+        let x = match self.corner {
+            Corner::TopLeft | Corner::BottomLeft => padding,
+            Corner::TopRight | Corner::BottomRight => width - logo_width - padding,
+        };
+        let y = match self.corner {
+            Corner::TopLeft | Corner::TopRight => padding,
+            Corner::BottomLeft | Corner::BottomRight => height - logo_height - padding,
+        };
+        image::imageops::overlay(&mut image, &self.image, x, y);
+        Ok(DynamicImage::ImageRgba8(image))
+    }
+}
+
+// Per-frame callback run by a Hook: takes the RGBA buffer and returns the
+// buffer to encode in its place
+pub type HookFn = dyn Fn(RgbaImage) -> Result<RgbaImage> + Send + Sync;
+
+// A frame handed to the hook thread, and where to send the result
+type HookJob = (RgbaImage, mpsc::SyncSender<Result<RgbaImage>>);
+
+// User-supplied processing (custom redaction, ML blurring...) plugged into
+// the pipeline. The callback runs on one long-lived thread under a time
+// budget; if it fails, panics, overruns the budget or changes the frame's
+// dimensions, the frame continues unmodified. An overrunning call is left
+// to finish in the background, and frames arriving meanwhile skip the hook
+// rather than queue behind it. Clones share the thread.
+#[derive(Clone)]
+pub struct Hook {
+    name: String,
+    budget: Duration,
+    callback: Arc<HookFn>,
+    /// Started on the first frame; None again if the thread died
+    jobs: Arc<Mutex<Option<mpsc::SyncSender<HookJob>>>>,
+    /// Set from handing a frame over until the callback returns
+    busy: Arc<AtomicBool>,
+}
+
+impl Debug for Hook {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Hook")
+            .field("name", &self.name)
+            .field("budget", &self.budget)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Hook {
+    pub fn new(
+        name: impl Into<String>,
+        budget: Duration,
+        callback: impl Fn(RgbaImage) -> Result<RgbaImage> + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            budget,
+            callback: Arc::new(callback),
+            jobs: Arc::new(Mutex::new(None)),
+            busy: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    // The hook thread: runs jobs until every Hook sharing it is dropped
+    fn spawn(&self) -> Result<mpsc::SyncSender<HookJob>> {
+        let (tx, rx) = mpsc::sync_channel::<HookJob>(1);
+        let callback = self.callback.clone();
+        let busy = self.busy.clone();
+        std::thread::Builder::new()
+            .name(format!("hook-{}", self.name))
+            .spawn(move || {
+                for (image, reply) in rx {
+                    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| callback(image)));
+                    busy.store(false, Ordering::Release);
+                    // A panic drops `reply`, which apply reports as such
+                    if let Ok(result) = result {
+                        let _ = reply.send(result);
+                    }
+                }
+            })
+            .context("Failed to start hook thread")?;
+        Ok(tx)
+    }
+
+    fn submit(&self, job: HookJob) -> Result<()> {
+        let mut jobs = self.jobs.lock().unwrap_or_else(|e| e.into_inner());
+        let tx = match jobs.as_ref() {
+            Some(tx) => tx,
+            None => jobs.insert(self.spawn()?),
+        };
+        if tx.send(job).is_err() {
+            *jobs = None;
+            anyhow::bail!("hook thread stopped");
+        }
+        Ok(())
+    }
+}
+
+impl Transform for Hook {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn apply(&self, image: DynamicImage) -> Result<DynamicImage> {
+        let original = image.into_rgba8();
+        let dimensions = original.dimensions();
+
+        if self.busy.swap(true, Ordering::AcqRel) {
+            warn!("Hook '{}' is still running on an earlier frame; skipping it", self.name);
+            return Ok(DynamicImage::ImageRgba8(original));
+        }
+        let (tx, rx) = mpsc::sync_channel(1);
+        if let Err(e) = self.submit((original.clone(), tx)) {
+            self.busy.store(false, Ordering::Release);
+            return Err(e);
+        }
+
+        let reason = match rx.recv_timeout(self.budget) {
+            Ok(Ok(image)) if image.dimensions() == dimensions => return Ok(DynamicImage::ImageRgba8(image)),
+            Ok(Ok(image)) => format!("returned a {:?} frame for a {:?} one", image.dimensions(), dimensions),
+            Ok(Err(e)) => format!("{:#}", e),
+            Err(mpsc::RecvTimeoutError::Timeout) => format!("exceeded its {:?} budget", self.budget),
+            Err(mpsc::RecvTimeoutError::Disconnected) => "panicked".to_string(),
+        };
+        warn!("Hook '{}' {}; using the unmodified frame", self.name, reason);
+        Ok(DynamicImage::ImageRgba8(original))
+    }
+}
+
+// Parse a ';'-separated pipeline, applied left to right, e.g.
+// "redact:0,0,400,80; resize:0.5; grayscale; quantize:16; overlay:/etc/eye/logo.png@10,10"
+// A logo takes a corner, padding and opacity: "logo:/etc/eye/station.png@bottom-right,16,0.8"
+pub fn parse_transforms(spec: &str) -> Result<Vec<Arc<dyn Transform>>> {
+    spec.split(';')
+        .map(str::trim)
+        .filter(|t| !t.is_empty())
+        .map(parse_one)
+        .collect()
+}
+
+fn parse_one(spec: &str) -> Result<Arc<dyn Transform>> {
+    let (name, args) = match spec.split_once(':') {
+        Some((name, args)) => (name.trim(), args.trim()),
+        None => (spec, ""),
+    };
+    let numbers = |args: &str| -> Result<Vec<i64>> {
+        args.split(',')
+            .map(|n| n.trim().parse().with_context(|| format!("Invalid number '{}' in '{}'", n.trim(), spec)))
+            .collect()
+    };
+
+    let transform: Arc<dyn Transform> = match name.to_lowercase().as_str() {
+        "redact" => {
+            let rects = args
+                .split('/')
+                .map(|rect| match numbers(rect)?[..] {
+                    [x, y, width, height] if x >= 0 && y >= 0 && width > 0 && height > 0 => Ok(Rect {
+                        x: x as u32,
+                        y: y as u32,
+                        width: width as u32,
+                        height: height as u32,
+                    }),
+                    _ => anyhow::bail!("Invalid rect '{}': expected x,y,width,height", rect.trim()),
+                })
+                .collect::<Result<_>>()?;
+            Arc::new(Redact { rects })
+        }
+        "resize" | "scale" => {
+            let scale: f32 = args.parse().with_context(|| format!("Invalid scale in '{}'", spec))?;
+            if !(scale > 0.0 && scale <= 1.0) {
+                anyhow::bail!("Scale must be in (0, 1], got {}", scale);
+            }
+            Arc::new(Resize { scale })
+        }
+        "grayscale" | "greyscale" => Arc::new(Grayscale),
+        "quantize" => {
+            let levels: u8 = args.parse().with_context(|| format!("Invalid level count in '{}'", spec))?;
+            if levels < 2 {
+                anyhow::bail!("quantize needs at least 2 levels, got {}", levels);
+            }
+            Arc::new(Quantize { levels })
+        }
+        "overlay" => {
+            let (path, position) = args.rsplit_once('@').unwrap_or((args, "0,0"));
+            let [x, y] = numbers(position)?[..] else {
+                anyhow::bail!("Invalid overlay position '{}': expected x,y", position);
+            };
+            Arc::new(Overlay::load(path.trim(), x, y)?)
+        }
+        "logo" => {
+            let (path, placement) = args.rsplit_once('@').unwrap_or((args, ""));
+            let mut parts = placement.split(',').map(str::trim);
+            let corner = match parts.next() {
+                Some(corner) if !corner.is_empty() => corner.parse()?,
+                _ => Corner::default(),
+            };
+            let padding: u32 = match parts.next() {
+                Some(padding) => padding.parse().with_context(|| format!("Invalid logo padding in '{}'", spec))?,
+                None => 0,
+            };
+            let opacity: f32 = match parts.next() {
+                Some(opacity) => opacity.parse().with_context(|| format!("Invalid logo opacity in '{}'", spec))?,
+                None => 1.0,
+            };
+            if !(0.0..=1.0).contains(&opacity) {
+                anyhow::bail!("Logo opacity must be in [0, 1], got {}", opacity);
+            }
+            Arc::new(Logo::load(path.trim(), corner, padding, opacity)?)
+        }
+        other => anyhow::bail!(
+            "Unknown transform '{}': expected redact, resize, grayscale, quantize, overlay or logo",
+            other
+        ),
+    };
+    Ok(transform)
+}
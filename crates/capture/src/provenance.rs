@@ -0,0 +1,202 @@
+// crates/capture/src/provenance.rs
+use crate::MonitorInfo;
+use chrono::{DateTime, Utc};
+use image::ImageFormat;
+
+// Who captured a frame. When configured, it is written into the encoded
+// image so a downloaded screenshot documents itself. Off by default:
+// hostnames and monitor names travel with every copy of the file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Provenance {
+    pub hostname: String,
+    pub agent_id: String,
+}
+
+impl Provenance {
+    // Provenance for this machine. The agent is identified by `agent_id`,
+    // or by the hostname when none is given.
+    pub fn local(agent_id: Option<String>) -> Self {
+        let hostname = hostname().unwrap_or_else(|| "unknown".to_string());
+        Self {
+            agent_id: agent_id.unwrap_or_else(|| hostname.clone()),
+            hostname,
+        }
+    }
+
+    // Key/value pairs embedded in one frame
+    pub fn fields(
+        &self,
+        captured_at: DateTime<Utc>,
+        monitor: Option<&MonitorInfo>,
+    ) -> Vec<(&'static str, String)> {
+        let mut fields = vec![
+            ("hostname", self.hostname.clone()),
+            ("agent_id", self.agent_id.clone()),
+            ("capture_time", captured_at.to_rfc3339()),
+        ];
+        if let Some(m) = monitor {
+            fields.push((
+                "monitor",
+                format!("{} (id {}, {}x{} at {},{})", m.name, m.id, m.width, m.height, m.x, m.y),
+            ));
+        }
+        fields
+    }
+}
+
+#[cfg(unix)]
+fn hostname() -> Option<String> {
+    let mut buf = [0u8; 256];
+    // SAFETY: the length passed is the buffer's own
+    if unsafe { libc::gethostname(buf.as_mut_ptr().cast(), buf.len()) } != 0 {
+        return None;
+    }
+    let len = buf.iter().position(|&b| b == 0).unwrap_or(buf.len());
+    Some(String::from_utf8_lossy(&buf[..len]).into_owned()).filter(|h| !h.is_empty())
+}
+
+#[cfg(not(unix))]
+fn hostname() -> Option<String> {
+    std::env::var("COMPUTERNAME").ok().filter(|h| !h.is_empty())
+}
+
+// Write `fields` into already-encoded image data: one tEXt chunk per field
+// for PNG (iTXt when the value isn't ASCII), an EXIF segment for JPEG.
+// Other formats, and data that doesn't parse as the given format, are
+// returned unchanged.
+pub(crate) fn embed(
+    data: Vec<u8>,
+    format: ImageFormat,
+    fields: &[(&str, String)],
+    captured_at: DateTime<Utc>,
+) -> Vec<u8> {
+    match format {
+        ImageFormat::Png => embed_png(data, fields),
+        ImageFormat::Jpeg => embed_jpeg(data, fields, captured_at),
+        _ => data,
+    }
+}
+
+// PNG signature plus the IHDR chunk, which must stay first
+const PNG_HEADER_LEN: usize = 8 + 4 + 4 + 13 + 4;
+
+fn embed_png(data: Vec<u8>, fields: &[(&str, String)]) -> Vec<u8> {
+    if data.len() < PNG_HEADER_LEN || !data.starts_with(b"\x89PNG\r\n\x1a\n") || &data[12..16] != b"IHDR" {
+        return data;
+    }
+
+    let mut out = Vec::with_capacity(data.len() + 256);
+    out.extend_from_slice(&data[..PNG_HEADER_LEN]);
+    for (key, value) in fields {
+        let mut body = key.as_bytes().to_vec();
+        body.push(0);
+        if value.is_ascii() {
+            body.extend_from_slice(value.as_bytes());
+            push_png_chunk(&mut out, b"tEXt", &body);
+        } else {
+            // Uncompressed, no language tag or translated keyword
+            body.extend_from_slice(&[0, 0, 0, 0]);
+            body.extend_from_slice(value.as_bytes());
+            push_png_chunk(&mut out, b"iTXt", &body);
+        }
+    }
+    out.extend_from_slice(&data[PNG_HEADER_LEN..]);
+    out
+}
+
+fn push_png_chunk(out: &mut Vec<u8>, kind: &[u8; 4], body: &[u8]) {
+    out.extend((body.len() as u32).to_be_bytes());
+    let start = out.len();
+    out.extend_from_slice(kind);
+    out.extend_from_slice(body);
+    let crc = crc32fast::hash(&out[start..]);
+    out.extend(crc.to_be_bytes());
+}
+
+// EXIF tags written for JPEG, in the ascending order TIFF requires
+const TAG_IMAGE_DESCRIPTION: u16 = 0x010E;
+const TAG_DATE_TIME: u16 = 0x0132;
+const TAG_HOST_COMPUTER: u16 = 0x013C;
+
+// TIFF field type for NUL-terminated 7-bit text
+const TIFF_ASCII: u16 = 2;
+
+fn embed_jpeg(data: Vec<u8>, fields: &[(&str, String)], captured_at: DateTime<Utc>) -> Vec<u8> {
+    if !data.starts_with(&[0xFF, 0xD8]) {
+        return data;
+    }
+
+    // Every field goes in the description as key=value pairs; the
+    // hostname and time also get their standard tags
+    let description = fields
+        .iter()
+        .map(|(key, value)| format!("{}={}", key, value))
+        .collect::<Vec<_>>()
+        .join("; ");
+    let mut tags = vec![
+        (TAG_IMAGE_DESCRIPTION, description),
+        (TAG_DATE_TIME, captured_at.format("%Y:%m:%d %H:%M:%S").to_string()),
+    ];
+    if let Some((_, host)) = fields.iter().find(|(key, _)| *key == "hostname") {
+        tags.push((TAG_HOST_COMPUTER, host.clone()));
+    }
+
+    let tiff = tiff_ascii_ifd(&tags);
+    // APP1 length covers itself, the "Exif\0\0" marker and the TIFF body
+    let Ok(len) = u16::try_from(2 + 6 + tiff.len()) else {
+        return data;
+    };
+
+    // Keep a JFIF APP0 segment first, as its spec requires
+    let insert_at = if data.len() >= 6 && data[2..4] == [0xFF, 0xE0] {
+        4 + u16::from_be_bytes([data[4], data[5]]) as usize
+    } else {
+        2
+    };
+    if insert_at > data.len() {
+        return data;
+    }
+
+    let mut out = Vec::with_capacity(data.len() + len as usize + 2);
+    out.extend_from_slice(&data[..insert_at]);
+    out.extend_from_slice(&[0xFF, 0xE1]);
+    out.extend(len.to_be_bytes());
+    out.extend_from_slice(b"Exif\0\0");
+    out.extend(tiff);
+    out.extend_from_slice(&data[insert_at..]);
+    out
+}
+
+// Little-endian TIFF header and a single IFD of ASCII tags. Non-ASCII
+// characters are replaced, since EXIF text is 7-bit.
+fn tiff_ascii_ifd(tags: &[(u16, String)]) -> Vec<u8> {
+    let ifd_len = 2 + tags.len() * 12 + 4;
+    let mut values_at = (8 + ifd_len) as u32;
+
+    let mut out = b"II*\0".to_vec();
+    out.extend(8u32.to_le_bytes());
+    out.extend((tags.len() as u16).to_le_bytes());
+
+    let mut values = Vec::new();
+    for (tag, text) in tags {
+        let mut bytes: Vec<u8> = text.chars().map(|c| if c.is_ascii() { c as u8 } else { b'?' }).collect();
+        bytes.push(0);
+
+        out.extend(tag.to_le_bytes());
+        out.extend(TIFF_ASCII.to_le_bytes());
+        out.extend((bytes.len() as u32).to_le_bytes());
+        if bytes.len() <= 4 {
+            // Short values live in the offset field itself
+            bytes.resize(4, 0);
+            out.extend(bytes);
+        } else {
+            out.extend(values_at.to_le_bytes());
+            values_at += bytes.len() as u32;
+            values.extend(bytes);
+        }
+    }
+    // No further IFDs
+    out.extend(0u32.to_le_bytes());
+    out.extend(values);
+    out
+}
@@ -0,0 +1,166 @@
+// crates/capture/src/rules.rs
+use crate::{Rect, WindowInfo};
+use anyhow::{Context, Result};
+use std::str::FromStr;
+
+// What to do with a frame while a rule's application is in the foreground
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RuleAction {
+    /// Black out the whole captured image
+    RedactScreen,
+    /// Black out only the active window
+    RedactWindow,
+    /// Drop the frame entirely
+    Skip,
+}
+
+impl FromStr for RuleAction {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "screen" | "redact-screen" => Ok(Self::RedactScreen),
+            "window" | "redact-window" => Ok(Self::RedactWindow),
+            "skip" => Ok(Self::Skip),
+            other => anyhow::bail!("Unknown rule action '{}': expected screen, window or skip", other),
+        }
+    }
+}
+
+// A redaction rule keyed by the active application, optionally narrowed
+// by a title substring, e.g. a browser whose title mentions a bank.
+// Both matches are case-insensitive substrings.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RedactionRule {
+    pub app: String,
+    pub title: Option<String>,
+    pub action: RuleAction,
+}
+
+impl RedactionRule {
+    fn matches(&self, window: &WindowInfo) -> bool {
+        let app = window.app_name.to_lowercase();
+        if !app.contains(&self.app.to_lowercase()) {
+            return false;
+        }
+        match &self.title {
+            Some(title) => window.title.to_lowercase().contains(&title.to_lowercase()),
+            None => true,
+        }
+    }
+
+    // Parse a ';'-separated list of rules, e.g.
+    // "firefox:bank=screen; keepassxc=skip; slack=window"
+    pub fn parse_list(spec: &str) -> Result<Vec<Self>> {
+        spec.split(';')
+            .filter(|r| !r.trim().is_empty())
+            .map(str::parse)
+            .collect()
+    }
+}
+
+// "<app>[:<title>]=<action>"
+impl FromStr for RedactionRule {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (pattern, action) = s
+            .trim()
+            .rsplit_once('=')
+            .with_context(|| format!("Invalid rule '{}': expected app[:title]=action", s.trim()))?;
+        let (app, title) = match pattern.split_once(':') {
+            Some((app, title)) => (app.trim(), Some(title.trim().to_string())),
+            None => (pattern.trim(), None),
+        };
+        if app.is_empty() {
+            anyhow::bail!("Invalid rule '{}': missing application", s.trim());
+        }
+
+        Ok(Self {
+            app: app.to_string(),
+            title: title.filter(|t| !t.is_empty()),
+            action: action.parse()?,
+        })
+    }
+}
+
+// Source of the currently focused window. Swappable so rule evaluation
+// can be exercised without a real desktop.
+pub trait ActiveWindowProvider: Send + Sync {
+    fn active_window(&self) -> Result<Option<WindowInfo>>;
+}
+
+// Asks the OS which window has focus
+pub struct SystemActiveWindow;
+
+impl ActiveWindowProvider for SystemActiveWindow {
+    fn active_window(&self) -> Result<Option<WindowInfo>> {
+        for window in xcap::Window::all().context("Failed to enumerate windows")? {
+            if window.is_focused().map_err(|e| anyhow::anyhow!(e))? {
+                return crate::window_info(&window).map(Some);
+            }
+        }
+        Ok(None)
+    }
+}
+
+// Returned from a capture when a rule drops the frame, so callers can tell
+// a deliberate skip from a failure
+#[derive(Debug)]
+pub struct CaptureSkipped {
+    pub app: String,
+}
+
+impl std::fmt::Display for CaptureSkipped {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Capture skipped by redaction rule for '{}'", self.app)
+    }
+}
+
+impl std::error::Error for CaptureSkipped {}
+
+// First rule matching the active window, if any
+pub fn evaluate<'a>(rules: &'a [RedactionRule], active: &WindowInfo) -> Option<&'a RedactionRule> {
+    rules.iter().find(|rule| rule.matches(active))
+}
+
+// Region to black out for `action`, in the coordinates of an image whose
+// top-left corner sits at `origin` on a desktop with `scale` pixels per point
+pub(crate) fn action_rects(
+    action: RuleAction,
+    active: &WindowInfo,
+    origin: (i32, i32),
+    scale: f32,
+    size: (u32, u32),
+) -> Vec<Rect> {
+    match action {
+        RuleAction::RedactScreen => vec![Rect { x: 0, y: 0, width: size.0, height: size.1 }],
+        RuleAction::RedactWindow => crate::window_rect(active, origin, scale, size).into_iter().collect(),
+        RuleAction::Skip => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_rules() {
+        let rules = RedactionRule::parse_list("Firefox:bank=screen; keepassxc=skip;").unwrap();
+        assert_eq!(rules, vec![
+            RedactionRule {
+                app: "Firefox".to_string(),
+                title: Some("bank".to_string()),
+                action: RuleAction::RedactScreen,
+            },
+            RedactionRule {
+                app: "keepassxc".to_string(),
+                title: None,
+                action: RuleAction::Skip,
+            },
+        ]);
+        assert!(RedactionRule::parse_list("firefox").is_err());
+        assert!(RedactionRule::parse_list("firefox=blur").is_err());
+        assert!(RedactionRule::parse_list(":bank=screen").is_err());
+    }
+}
@@ -0,0 +1,152 @@
+// crates/clock/src/lib.rs
+use chrono::{DateTime, Duration, Utc};
+use std::sync::{Arc, Mutex};
+
+// Source of wall-clock time. Time-based logic (expiry, retention,
+// staleness) reads the time through this so tests can control it.
+pub trait Clock: Send + Sync {
+    fn now(&self) -> DateTime<Utc>;
+}
+
+// Shared handle used by the stores, auth and server state
+pub type SharedClock = Arc<dyn Clock>;
+
+// The real system clock
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+// Default clock for production code
+pub fn system() -> SharedClock {
+    Arc::new(SystemClock)
+}
+
+// Manually driven clock for tests. Clones share the same time, so a test
+// can keep one handle and advance the clock seen by the code under test.
+#[derive(Debug, Clone)]
+pub struct FakeClock {
+    now: Arc<Mutex<DateTime<Utc>>>,
+}
+
+impl FakeClock {
+    pub fn new(start: DateTime<Utc>) -> Self {
+        Self {
+            now: Arc::new(Mutex::new(start)),
+        }
+    }
+
+    pub fn set(&self, now: DateTime<Utc>) {
+        *self.now.lock().unwrap() = now;
+    }
+
+    pub fn advance(&self, by: Duration) {
+        *self.now.lock().unwrap() += by;
+    }
+}
+
+impl Default for FakeClock {
+    fn default() -> Self {
+        Self::new(Utc::now())
+    }
+}
+
+impl Clock for FakeClock {
+    fn now(&self) -> DateTime<Utc> {
+        *self.now.lock().unwrap()
+    }
+}
+
+// Gap put between a clamped timestamp and the one before it
+pub const MONOTONIC_EPSILON: Duration = Duration::milliseconds(1);
+
+// Hands out timestamps that never go backwards, even when the wall clock
+// is stepped back (e.g. by an NTP correction). A reading earlier than the
+// last one handed out is clamped to just after it.
+#[derive(Debug, Default)]
+pub struct MonotonicStamps {
+    last: Mutex<Option<DateTime<Utc>>>,
+}
+
+impl MonotonicStamps {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // The timestamp to use for a wall-clock reading of `wall`; differs from
+    // `wall` only when the clock went backwards
+    pub fn stamp(&self, wall: DateTime<Utc>) -> DateTime<Utc> {
+        let mut last = self.last.lock().unwrap();
+        let stamp = match *last {
+            Some(prev) if wall < prev => prev + MONOTONIC_EPSILON,
+            _ => wall,
+        };
+        *last = Some(stamp);
+        stamp
+    }
+}
+
+// Parse a human-readable interval such as "1.5s", "500ms", "2m" or
+// "1m 30s". Anything shorter than `min` is rejected.
+pub fn parse_interval(s: &str, min: std::time::Duration) -> anyhow::Result<std::time::Duration> {
+    let interval = humantime::parse_duration(s.trim())
+        .map_err(|e| anyhow::anyhow!("Invalid interval '{}': {}", s.trim(), e))?;
+
+    if interval < min || interval.is_zero() {
+        anyhow::bail!("Interval '{}' is below the minimum of {:?}", s.trim(), min);
+    }
+
+    Ok(interval)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fake_clock_shared_between_clones() {
+        let clock = FakeClock::default();
+        let shared: SharedClock = Arc::new(clock.clone());
+        let start = shared.now();
+
+        clock.advance(Duration::minutes(5));
+        assert_eq!(shared.now() - start, Duration::minutes(5));
+    }
+
+    #[test]
+    fn test_monotonic_stamps_clamp_backward_jumps() {
+        let clock = FakeClock::default();
+        let stamps = MonotonicStamps::new();
+        let first = stamps.stamp(clock.now());
+        assert_eq!(first, clock.now());
+
+        clock.advance(Duration::seconds(-30));
+        let second = stamps.stamp(clock.now());
+        assert_eq!(second, first + MONOTONIC_EPSILON);
+
+        // Once the clock catches up, its readings are used as they are
+        clock.advance(Duration::minutes(1));
+        assert_eq!(stamps.stamp(clock.now()), clock.now());
+    }
+
+    #[test]
+    fn test_parse_interval() {
+        use std::time::Duration as StdDuration;
+        let min = StdDuration::from_millis(50);
+
+        assert_eq!(parse_interval("1.5s", min).unwrap(), StdDuration::from_millis(1500));
+        assert_eq!(parse_interval("500ms", min).unwrap(), StdDuration::from_millis(500));
+        assert_eq!(parse_interval("2m", min).unwrap(), StdDuration::from_secs(120));
+        assert_eq!(parse_interval(" 1m 30s ", min).unwrap(), StdDuration::from_secs(90));
+
+        assert!(parse_interval("10ms", min).is_err());
+        assert!(parse_interval("0s", StdDuration::ZERO).is_err());
+        assert!(parse_interval("1500", min).is_err());
+        assert!(parse_interval("fast", min).is_err());
+        assert!(parse_interval("", min).is_err());
+    }
+}
\ No newline at end of file
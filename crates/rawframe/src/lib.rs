@@ -0,0 +1,58 @@
+// crates/rawframe/src/lib.rs
+use anyhow::{Context, Result};
+use image::RgbaImage;
+
+// Format name agents use for uncompressed RGBA8 frames
+pub const RAW_FORMAT: &str = "raw";
+
+// Rebuild an image from RGBA8 rows `stride` bytes apart. Row padding
+// beyond width * 4 bytes is dropped. Shared by the capture crate and the
+// server, so both read raw frames the same way.
+pub fn to_image(data: &[u8], width: u32, height: u32, stride: usize) -> Result<RgbaImage> {
+    if width == 0 || height == 0 {
+        anyhow::bail!("Raw frame has no pixels ({}x{})", width, height);
+    }
+    let row_len = width as usize * 4;
+    if stride < row_len {
+        anyhow::bail!("Raw frame stride {} is shorter than a row of {} bytes", stride, row_len);
+    }
+    let needed = stride
+        .checked_mul(height as usize)
+        .context("Raw frame dimensions are too large")?;
+    if data.len() < needed {
+        anyhow::bail!("Raw frame data does not match its dimensions");
+    }
+
+    let pixels: Vec<u8> = data
+        .chunks(stride)
+        .take(height as usize)
+        .flat_map(|row| &row[..row_len])
+        .copied()
+        .collect();
+
+    RgbaImage::from_raw(width, height, pixels).context("Raw frame data does not match its dimensions")
+}
+
+// Unit tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rows_are_unpadded() {
+        // 1x2 image with 4 bytes of padding per row
+        let data = [1, 2, 3, 4, 0, 0, 0, 0, 5, 6, 7, 8, 0, 0, 0, 0];
+        let img = to_image(&data, 1, 2, 8).unwrap();
+        assert_eq!(img.into_raw(), vec![1, 2, 3, 4, 5, 6, 7, 8]);
+    }
+
+    #[test]
+    fn test_degenerate_dimensions_are_errors() {
+        let data = [0u8; 16];
+        assert!(to_image(&data, 0, 2, 8).is_err());
+        assert!(to_image(&data, 1, 0, 8).is_err());
+        assert!(to_image(&data, 1, 2, 0).is_err());
+        assert!(to_image(&data, 2, 2, 4).is_err());
+        assert!(to_image(&data, 1, 5, 4).is_err());
+    }
+}
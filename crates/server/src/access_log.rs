@@ -0,0 +1,160 @@
+// crates/server/src/access_log.rs
+use anyhow::{Context, Result};
+use axum::http::{HeaderMap, header};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::VecDeque;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use std::sync::Mutex;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::mpsc;
+
+// Entries kept in memory for GET /admin/access-log; the file, when
+// configured, keeps everything
+pub const ACCESS_LOG_CAPACITY: usize = 10_000;
+
+// Response extension naming the frames a handler served. The access-log
+// middleware records one entry per frame for successful responses.
+#[derive(Debug, Clone)]
+pub struct FramesServed(pub Vec<i64>);
+
+// One frame handed to one caller
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AccessEntry {
+    /// Position in the log, increasing from 1; pages continue after it
+    pub seq: u64,
+    pub at: DateTime<Utc>,
+    /// "bearer:<fingerprint>" for callers presenting a token, else "anonymous"
+    pub identity: String,
+    /// Request path the frame was fetched through, e.g. /snapshot.png
+    pub path: String,
+    pub frame_id: i64,
+}
+
+struct Entries {
+    recent: VecDeque<AccessEntry>,
+    next_seq: u64,
+}
+
+// Append-only record of frame retrievals, kept apart from the request log:
+// a bounded in-memory window plus, optionally, a JSON-lines file that is
+// only ever appended to
+pub struct AccessLog {
+    entries: Mutex<Entries>,
+    capacity: usize,
+    /// Lines for the background task appending to the file
+    file: Option<mpsc::UnboundedSender<String>>,
+}
+
+impl Default for AccessLog {
+    fn default() -> Self {
+        Self::new(ACCESS_LOG_CAPACITY)
+    }
+}
+
+impl AccessLog {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: Mutex::new(Entries { recent: VecDeque::new(), next_seq: 1 }),
+            capacity: capacity.max(1),
+            file: None,
+        }
+    }
+
+    // Also append every entry to `path`, from a background task so requests
+    // never wait on the disk. Numbering carries on from the entries already
+    // in the file. Must be called within a Tokio runtime.
+    pub fn with_file(mut self, path: &Path) -> Result<Self> {
+        let existing = match File::open(path) {
+            Ok(file) => BufReader::new(file).lines().count() as u64,
+            Err(_) => 0,
+        };
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("Failed to open access log {}", path.display()))?;
+
+        let (tx, rx) = mpsc::unbounded_channel();
+        tokio::spawn(append_lines(tokio::fs::File::from_std(file), rx));
+
+        self.entries.get_mut().unwrap().next_seq = existing + 1;
+        self.file = Some(tx);
+        Ok(self)
+    }
+
+    pub fn record(&self, at: DateTime<Utc>, identity: &str, path: &str, frame_ids: &[i64]) {
+        let mut entries = self.entries.lock().unwrap();
+        for &frame_id in frame_ids {
+            let entry = AccessEntry {
+                seq: entries.next_seq,
+                at,
+                identity: identity.to_string(),
+                path: path.to_string(),
+                frame_id,
+            };
+            entries.next_seq += 1;
+
+            if let Some(file) = &self.file {
+                let line = serde_json::to_string(&entry).unwrap_or_default();
+                let _ = file.send(line);
+            }
+            entries.recent.push_back(entry);
+            if entries.recent.len() > self.capacity {
+                entries.recent.pop_front();
+            }
+        }
+    }
+
+    // Up to `limit` entries with seq greater than `after`, oldest first
+    pub fn page(&self, after: u64, limit: usize) -> Vec<AccessEntry> {
+        let entries = self.entries.lock().unwrap();
+        entries
+            .recent
+            .iter()
+            .filter(|e| e.seq > after)
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+}
+
+// Write queued lines to the access log file until the log is dropped,
+// flushing after each burst
+async fn append_lines(mut file: tokio::fs::File, mut rx: mpsc::UnboundedReceiver<String>) {
+    while let Some(line) = rx.recv().await {
+        let mut buf = line.into_bytes();
+        buf.push(b'\n');
+        while let Ok(line) = rx.try_recv() {
+            buf.extend_from_slice(line.as_bytes());
+            buf.push(b'\n');
+        }
+        if let Err(e) = async {
+            file.write_all(&buf).await?;
+            file.flush().await
+        }
+        .await
+        {
+            tracing::warn!("Failed to append to access log: {}", e);
+        }
+    }
+}
+
+// Who a request came from. Tokens are fingerprinted rather than logged;
+// callers pass headers only once the token has been checked.
+pub fn identity(headers: &HeaderMap) -> String {
+    let token = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .map(str::trim)
+        .filter(|t| !t.is_empty());
+
+    match token {
+        Some(token) => format!("bearer:{}", &hex::encode(Sha256::digest(token.as_bytes()))[..12]),
+        None => "anonymous".to_string(),
+    }
+}
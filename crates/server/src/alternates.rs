@@ -0,0 +1,58 @@
+// crates/server/src/alternates.rs
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+
+// Multipart field prefix the agent sends alternate encodings under, e.g.
+// "alternate_jpeg" (EYE_ALTERNATE_FORMATS on the agent)
+pub const ALTERNATE_FIELD_PREFIX: &str = "alternate_";
+
+// Frames whose alternates are held; older frames are transcoded on request
+pub const ALTERNATES_CAPACITY: usize = 64;
+
+// Encodings of one frame, by format name ("jpeg", "png", ...)
+pub type Encodings = HashMap<String, Vec<u8>>;
+
+// Other encodings of the newest frames, made by the agent from the same
+// capture and uploaded with the frame. A ?convert= request for one of
+// them is served these bytes instead of a server-side transcode. Kept in
+// memory only.
+pub struct Alternates {
+    capacity: usize,
+    frames: Mutex<VecDeque<(i64, Encodings)>>,
+}
+
+impl Default for Alternates {
+    fn default() -> Self {
+        Self::new(ALTERNATES_CAPACITY)
+    }
+}
+
+impl Alternates {
+    pub fn new(capacity: usize) -> Self {
+        Self { capacity, frames: Mutex::new(VecDeque::new()) }
+    }
+
+    // Hold `encodings` for frame `id`. Anything held for an earlier frame
+    // with the same id is dropped, even when `encodings` is empty, so a
+    // reused id never serves stale bytes.
+    pub fn insert(&self, id: i64, encodings: Encodings) {
+        let mut frames = self.frames.lock().unwrap();
+        frames.retain(|(held, _)| *held != id);
+        if encodings.is_empty() || self.capacity == 0 {
+            return;
+        }
+        frames.push_back((id, encodings));
+        while frames.len() > self.capacity {
+            frames.pop_front();
+        }
+    }
+
+    // Frame `id` encoded as `format`, if the agent uploaded it
+    pub fn get(&self, id: i64, format: &str) -> Option<Vec<u8>> {
+        let frames = self.frames.lock().unwrap();
+        frames
+            .iter()
+            .find(|(held, _)| *held == id)
+            .and_then(|(_, encodings)| encodings.get(format).cloned())
+    }
+}
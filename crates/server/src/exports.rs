@@ -0,0 +1,160 @@
+// crates/server/src/exports.rs
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::sync::{Mutex, OwnedSemaphorePermit, Semaphore};
+
+// How long a prepared export stays downloadable
+pub const EXPORT_TTL_MINUTES: i64 = 30;
+
+// Exports held at once, building or prepared (EYE_MAX_EXPORTS)
+pub const DEFAULT_MAX_EXPORTS: usize = 4;
+
+// Bytes of prepared archives held at once (EYE_MAX_EXPORT_MB)
+pub const DEFAULT_MAX_EXPORT_BYTES: u64 = 1024 * 1024 * 1024;
+
+// Why an export could not be prepared
+#[derive(Debug)]
+pub enum ExportError {
+    /// Every export slot is taken
+    Busy,
+    /// The archive would push held exports past the byte cap
+    TooLarge,
+    Io(anyhow::Error),
+}
+
+impl std::fmt::Display for ExportError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExportError::Busy => write!(f, "Too many exports in progress; retry once one expires"),
+            ExportError::TooLarge => write!(f, "Export exceeds the space reserved for prepared exports"),
+            ExportError::Io(e) => write!(f, "Failed to write export: {:#}", e),
+        }
+    }
+}
+
+// An archive built by POST /export, served in ranges by GET /export/:token.
+// Its file is deleted, and its slot freed, when it is dropped.
+pub struct PreparedExport {
+    pub path: PathBuf,
+    pub size_bytes: u64,
+    /// Frames in the archive, for the access log
+    pub frame_ids: Vec<i64>,
+    pub filename: String,
+    pub expires_at: DateTime<Utc>,
+    _slot: OwnedSemaphorePermit,
+}
+
+impl Drop for PreparedExport {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+// Prepared exports on disk under `dir`, keyed by download token. At most
+// `max_exports` are held at a time — including ones still being built —
+// and together they take at most `max_bytes`.
+pub struct Exports {
+    dir: PathBuf,
+    max_bytes: u64,
+    slots: Arc<Semaphore>,
+    prepared: Mutex<HashMap<String, PreparedExport>>,
+}
+
+impl Default for Exports {
+    fn default() -> Self {
+        Self::new(std::env::temp_dir().join("eye-exports"), DEFAULT_MAX_EXPORTS, DEFAULT_MAX_EXPORT_BYTES)
+    }
+}
+
+impl Exports {
+    pub fn new(dir: PathBuf, max_exports: usize, max_bytes: u64) -> Self {
+        Self {
+            dir,
+            max_bytes,
+            slots: Arc::new(Semaphore::new(max_exports)),
+            prepared: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn dir(&self) -> &std::path::Path {
+        &self.dir
+    }
+
+    // Remove archives left behind by an earlier run; their tokens died
+    // with it
+    pub fn clear_stale(&self) -> Result<()> {
+        let entries = match std::fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {}", self.dir.display())),
+        };
+        for entry in entries.flatten() {
+            if entry.path().extension().is_some_and(|ext| ext == "zip") {
+                let _ = std::fs::remove_file(entry.path());
+            }
+        }
+        Ok(())
+    }
+
+    // Claim a slot before building an archive; fails once `max_exports`
+    // are held. Expired exports are dropped first so their slots count
+    // as free.
+    pub async fn reserve(&self, now: DateTime<Utc>) -> Result<OwnedSemaphorePermit, ExportError> {
+        self.expire(now).await;
+        self.slots.clone().try_acquire_owned().map_err(|_| ExportError::Busy)
+    }
+
+    // Write `data` to disk and hand back its token and expiry
+    pub async fn insert(
+        &self,
+        now: DateTime<Utc>,
+        slot: OwnedSemaphorePermit,
+        data: Vec<u8>,
+        frame_ids: Vec<i64>,
+        filename: String,
+    ) -> Result<(String, DateTime<Utc>), ExportError> {
+        let mut prepared = self.prepared.lock().await;
+        prepared.retain(|_, export| export.expires_at > now);
+
+        let held: u64 = prepared.values().map(|export| export.size_bytes).sum();
+        let size_bytes = data.len() as u64;
+        if held + size_bytes > self.max_bytes {
+            return Err(ExportError::TooLarge);
+        }
+
+        let token = uuid::Uuid::new_v4().simple().to_string();
+        let path = self.dir.join(format!("{}.zip", token));
+        tokio::fs::create_dir_all(&self.dir)
+            .await
+            .with_context(|| format!("Failed to create {}", self.dir.display()))
+            .map_err(ExportError::Io)?;
+        tokio::fs::write(&path, data)
+            .await
+            .with_context(|| format!("Failed to write {}", path.display()))
+            .map_err(ExportError::Io)?;
+
+        let expires_at = now + chrono::Duration::minutes(EXPORT_TTL_MINUTES);
+        prepared.insert(
+            token.clone(),
+            PreparedExport { path, size_bytes, frame_ids, filename, expires_at, _slot: slot },
+        );
+        Ok((token, expires_at))
+    }
+
+    // File, size, frame ids and download name of a live export
+    pub async fn get(&self, now: DateTime<Utc>, token: &str) -> Option<(PathBuf, u64, Vec<i64>, String)> {
+        let mut prepared = self.prepared.lock().await;
+        prepared.retain(|_, export| export.expires_at > now);
+        prepared
+            .get(token)
+            .map(|export| (export.path.clone(), export.size_bytes, export.frame_ids.clone(), export.filename.clone()))
+    }
+
+    // Drop exports past their expiry, deleting their files
+    pub async fn expire(&self, now: DateTime<Utc>) {
+        self.prepared.lock().await.retain(|_, export| export.expires_at > now);
+    }
+}
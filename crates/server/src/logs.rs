@@ -0,0 +1,76 @@
+// crates/server/src/logs.rs
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::fmt::Write;
+use tokio::sync::broadcast;
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::layer::{Context, Layer};
+
+// Log lines buffered per subscriber before a slow client starts missing them
+pub const LOG_CHANNEL_CAPACITY: usize = 256;
+
+// One tracing event as forwarded to /admin/logs subscribers
+#[derive(Debug, Clone, Serialize)]
+pub struct LogLine {
+    pub timestamp: DateTime<Utc>,
+    #[serde(serialize_with = "serialize_level")]
+    pub level: Level,
+    pub target: String,
+    pub message: String,
+}
+
+fn serialize_level<S: serde::Serializer>(level: &Level, s: S) -> Result<S::Ok, S::Error> {
+    s.serialize_str(level.as_str())
+}
+
+// Tracing layer that publishes every event to a broadcast channel.
+// Sending never blocks; with no subscribers the line is simply dropped.
+pub struct BroadcastLayer {
+    tx: broadcast::Sender<LogLine>,
+}
+
+impl BroadcastLayer {
+    pub fn new(tx: broadcast::Sender<LogLine>) -> Self {
+        Self { tx }
+    }
+}
+
+impl<S: Subscriber> Layer<S> for BroadcastLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        if self.tx.receiver_count() == 0 {
+            return;
+        }
+
+        let mut visitor = MessageVisitor::default();
+        event.record(&mut visitor);
+
+        let _ = self.tx.send(LogLine {
+            timestamp: Utc::now(),
+            level: *event.metadata().level(),
+            target: event.metadata().target().to_string(),
+            message: visitor.message,
+        });
+    }
+}
+
+// Renders the `message` field followed by any other fields as key=value
+#[derive(Default)]
+struct MessageVisitor {
+    message: String,
+    fields: String,
+}
+
+impl Visit for MessageVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "message" {
+            let _ = write!(self.message, "{:?}", value);
+            self.message.push_str(&self.fields);
+            self.fields.clear();
+        } else if self.message.is_empty() {
+            let _ = write!(self.fields, " {}={:?}", field.name(), value);
+        } else {
+            let _ = write!(self.message, " {}={:?}", field.name(), value);
+        }
+    }
+}
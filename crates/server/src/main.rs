@@ -1,31 +1,71 @@
 // crates/server/src/main.rs
+mod access_log;
+mod alternates;
+mod exports;
+mod logs;
+mod namespaces;
+mod placeholder;
+mod presign;
+mod similarity;
+mod tenants;
+mod timelapse;
+mod timings;
+mod transcode;
+
 use anyhow::{Context, Result};
 use axum::{
     extract::{DefaultBodyLimit, Multipart, Path, Query, Request, State},
-    http::{StatusCode, header},
+    http::{HeaderMap, StatusCode, header},
     middleware::{self, Next},
-    response::{IntoResponse, Json, Response},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Json, Response,
+    },
     routing::{get, post},
     Router,
 };
+use access_log::{AccessLog, FramesServed};
+use alternates::{Alternates, Encodings, ALTERNATE_FIELD_PREFIX};
 use chrono::{DateTime, Utc};
+use clock::SharedClock;
+use exports::{ExportError, Exports};
+use logs::{BroadcastLayer, LogLine, LOG_CHANNEL_CAPACITY};
+use namespaces::{IdNamespaces, AGENT_FRAME_ID_FIELD};
+use placeholder::Placeholder;
+use presign::Presigner;
+use tenants::Tenants;
+use timings::{Stage, Timings};
+use transcode::TranscodePool;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::env;
 use std::io::Write;
+use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::Instant;
-use storage::{Frame, MemoryStore};
+use storage::{Frame, FrameMeta, Manager, StaleLatest, StorageHealth, StorageMode, WriteBatching};
 use tokio::sync::RwLock;
-use tracing::info;
+use tower_http::compression::{
+    predicate::{Predicate, SizeAbove},
+    CompressionLayer,
+};
+use tracing::{info, warn};
 
 // Configuration structure for the agent
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct AgentConfig {
+    /// Seconds between captures. Accepts a number of seconds or a human
+    /// string such as "1.5s" or "500ms"; always serialized as seconds.
+    #[serde(deserialize_with = "deserialize_interval")]
     interval: f64,
     format: String,
+    /// Quality of the active format; kept for older clients. Mirrors the
+    /// active format's entry in `qualities`.
     quality: i32,
+    /// Encode quality per lossy format, e.g. {"jpeg": 80, "webp": 75}
+    #[serde(default)]
+    qualities: BTreeMap<String, i32>,
 }
 
 // Default configuration values
@@ -35,28 +75,339 @@ impl Default for AgentConfig {
             interval: 1.0,
             format: "webp".to_string(),
             quality: 95,
+            qualities: BTreeMap::from([("webp".to_string(), 95)]),
+        }
+    }
+}
+
+// Formats whose encoders take a quality setting
+const LOSSY_FORMATS: &[&str] = &["jpeg", "webp"];
+
+// Shortest interval the server hands out to agents
+const MIN_INTERVAL: std::time::Duration = std::time::Duration::from_millis(100);
+
+// Interval as seconds from either a JSON number or a human string
+fn deserialize_interval<'de, D>(deserializer: D) -> Result<f64, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Interval {
+        Seconds(f64),
+        Human(String),
+    }
+
+    match Interval::deserialize(deserializer)? {
+        Interval::Seconds(secs) => Ok(secs),
+        Interval::Human(s) => clock::parse_interval(&s, MIN_INTERVAL)
+            .map(|d| d.as_secs_f64())
+            .map_err(serde::de::Error::custom),
+    }
+}
+
+impl AgentConfig {
+    // Clamp out-of-range values and normalise the format name. A `qualities`
+    // entry that can't apply is an error naming its key, rather than being
+    // dropped behind the caller's back.
+    fn validated(mut self) -> Result<Self, String> {
+        if self.interval < MIN_INTERVAL.as_secs_f64() {
+            self.interval = MIN_INTERVAL.as_secs_f64();
+        }
+        if self.quality < 1 || self.quality > 100 {
+            self.quality = 75;
+        }
+        self.format = self.format.to_lowercase();
+        const SUPPORTED_FORMATS: &[&str] = &["png", "jpeg", "jpg", "webp", "bmp", "tiff"];
+        if !SUPPORTED_FORMATS.contains(&self.format.as_str()) {
+            info!("Invalid format '{}', defaulting to PNG", self.format);
+            self.format = "png".to_string();
+        }
+        if self.format == "jpg" {
+            self.format = "jpeg".to_string();
         }
+
+        // Only formats with a quality knob, and only values in range
+        self.qualities = std::mem::take(&mut self.qualities)
+            .into_iter()
+            .map(|(key, quality)| {
+                let format = match key.to_lowercase().as_str() {
+                    "jpg" => "jpeg".to_string(),
+                    other => other.to_string(),
+                };
+                if !LOSSY_FORMATS.contains(&format.as_str()) {
+                    return Err(format!(
+                        "qualities.{}: format has no quality setting (expected one of: {})",
+                        key,
+                        LOSSY_FORMATS.join(", ")
+                    ));
+                }
+                if !(1..=100).contains(&quality) {
+                    return Err(format!("qualities.{}: quality {} is outside 1-100", key, quality));
+                }
+                Ok((format, quality))
+            })
+            .collect::<Result<_, _>>()?;
+        // A config with only the scalar applies it to the active format
+        if LOSSY_FORMATS.contains(&self.format.as_str()) {
+            self.quality = *self.qualities.entry(self.format.clone()).or_insert(self.quality);
+        }
+        Ok(self)
     }
 }
 
 // Application state shared across handlers
 #[derive(Clone)]
 struct AppState {
-    store: Arc<MemoryStore>,
+    store: Arc<Manager>,
     start_time: Instant,
     config: Arc<RwLock<AgentConfig>>,
     /// True when an agent has claimed the connection slot.
     /// Only one agent may be connected at a time (1:1 model).
     agent_connected: Arc<RwLock<bool>>,
+    /// Bearer token from EYE_AUTH_TOKEN. Requests presenting it may see
+    /// details that are withheld from anonymous callers (e.g. disk layout).
+    auth_token: Option<String>,
+    /// Gzip or brotli JSON and text responses for clients that accept it
+    /// (EYE_COMPRESSION)
+    compression: bool,
+    /// Caps requests in flight (EYE_MAX_CONCURRENT); None means unlimited
+    concurrency: Option<Arc<tokio::sync::Semaphore>>,
+    /// Wall clock used to timestamp frames; replaced by a fake in tests
+    clock: SharedClock,
+    /// Keeps server-assigned frame timestamps from going backwards when
+    /// the clock is stepped back
+    stamps: Arc<clock::MonotonicStamps>,
+    /// Prepared export archives on disk, keyed by download token
+    exports: Arc<Exports>,
+    /// Agent-made encodings of recent frames, served for ?convert=
+    alternates: Arc<Alternates>,
+    /// JSON file the agent config is persisted to and reloaded from on
+    /// SIGHUP (EYE_CONFIG_FILE)
+    config_path: Option<PathBuf>,
+    /// Id of the most recently uploaded frame; long-poll requests wait on it
+    latest_frame: Arc<tokio::sync::watch::Sender<Option<i64>>>,
+    /// Transcode every upload to this format before storing
+    /// (EYE_CANONICAL_FORMAT); None stores frames as sent
+    canonical_format: Option<image::ImageFormat>,
+    /// Record a perceptual hash with every upload, for GET
+    /// /frames/:id/similar (EYE_PERCEPTUAL_HASH)
+    perceptual_hash: bool,
+    /// Tracing events fanned out to GET /admin/logs subscribers
+    logs: tokio::sync::broadcast::Sender<LogLine>,
+    /// Multipart field names accepted for the image and frame id
+    upload_fields: Arc<UploadFields>,
+    /// Image /snapshot.png serves instead of a 404 while no frames exist
+    placeholder: Arc<Placeholder>,
+    /// Largest accepted request body (EYE_MAX_UPLOAD_MB)
+    max_upload_bytes: usize,
+    /// Bounded workers for image transcoding (EYE_TRANSCODE_WORKERS)
+    transcoder: Arc<TranscodePool>,
+    /// Uploads timestamped further than this behind the latest frame, or
+    /// ahead of the server clock, are rejected (EYE_MAX_FRAME_SKEW); None
+    /// accepts any time
+    max_frame_skew: Option<chrono::Duration>,
+    /// Default freshness limit for /snapshot.png and /frames/latest
+    /// (EYE_MAX_FRAME_AGE); ?max_age= overrides it per request
+    max_frame_age: Option<chrono::Duration>,
+    /// Who fetched which frame, for GET /admin/access-log
+    access_log: Arc<AccessLog>,
+    /// Upload stage latencies, for GET /admin/timings
+    timings: Arc<Timings>,
+    /// Server-wide allocator of frame id namespaces (EYE_ID_NAMESPACES);
+    /// None stores agent frame ids as sent
+    id_namespaces: Option<Arc<IdNamespaces>>,
+    /// Namespace assigned to the connected agent
+    agent_namespace: Arc<RwLock<Option<i64>>>,
+    /// Signs and checks presigned URLs; keyed off the auth token when set
+    presigner: Arc<Presigner>,
+    /// Last storage health check and when it ran, reused by /health until
+    /// `health_check_interval` has passed
+    storage_health: Arc<tokio::sync::Mutex<Option<CheckedHealth>>>,
+    health_check_interval: std::time::Duration,
 }
 
+// How long /health reuses a storage check, so frequent unauthenticated
+// polling doesn't probe and scan the disk store on every call
+// (EYE_HEALTH_CHECK_SECS)
+const HEALTH_CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(10);
+
+// A storage health check and when it ran
+type CheckedHealth = (Instant, Vec<StorageHealth>);
+
+// Default request body limit; comfortably above a raw 4K RGBA frame
+const DEFAULT_MAX_UPLOAD_BYTES: usize = 50 * 1024 * 1024;
+
 impl AppState {
-    fn new(max_frames: usize) -> Self {
+    fn new(store: Manager) -> Self {
         Self {
-            store: Arc::new(MemoryStore::new(max_frames)),
+            store: Arc::new(store),
             start_time: Instant::now(),
             config: Arc::new(RwLock::new(AgentConfig::default())),
             agent_connected: Arc::new(RwLock::new(false)),
+            auth_token: None,
+            compression: true,
+            concurrency: None,
+            clock: clock::system(),
+            stamps: Arc::new(clock::MonotonicStamps::new()),
+            exports: Arc::new(Exports::default()),
+            alternates: Arc::new(Alternates::default()),
+            config_path: None,
+            latest_frame: Arc::new(tokio::sync::watch::Sender::new(None)),
+            canonical_format: None,
+            perceptual_hash: false,
+            logs: tokio::sync::broadcast::Sender::new(LOG_CHANNEL_CAPACITY),
+            upload_fields: Arc::new(UploadFields::default()),
+            placeholder: Arc::new(Placeholder::default()),
+            max_upload_bytes: DEFAULT_MAX_UPLOAD_BYTES,
+            transcoder: Arc::new(TranscodePool::new(TranscodePool::default_workers())),
+            max_frame_skew: None,
+            max_frame_age: None,
+            access_log: Arc::new(AccessLog::default()),
+            timings: Arc::new(Timings::default()),
+            id_namespaces: None,
+            agent_namespace: Arc::new(RwLock::new(None)),
+            presigner: Arc::new(Presigner::random()),
+            storage_health: Arc::new(tokio::sync::Mutex::new(None)),
+            health_check_interval: HEALTH_CHECK_INTERVAL,
+        }
+    }
+
+    fn with_access_log(mut self, access_log: AccessLog) -> Self {
+        self.access_log = Arc::new(access_log);
+        self
+    }
+
+    fn with_max_frame_age(mut self, age: Option<chrono::Duration>) -> Self {
+        self.max_frame_age = age;
+        self
+    }
+
+    fn with_max_frame_skew(mut self, skew: Option<chrono::Duration>) -> Self {
+        self.max_frame_skew = skew;
+        self
+    }
+
+    // Namespace agent frame ids; share one allocator between states so
+    // namespaces are unique across tenants too
+    fn with_id_namespaces(mut self, namespaces: Option<Arc<IdNamespaces>>) -> Self {
+        self.id_namespaces = namespaces;
+        self
+    }
+
+    // Share one transcode pool between states, e.g. across tenants
+    fn with_transcoder(mut self, transcoder: Arc<TranscodePool>) -> Self {
+        self.transcoder = transcoder;
+        self
+    }
+
+    fn with_exports(mut self, exports: Exports) -> Self {
+        self.exports = Arc::new(exports);
+        self
+    }
+
+    fn with_max_upload_bytes(mut self, bytes: usize) -> Self {
+        self.max_upload_bytes = bytes;
+        self
+    }
+
+    fn with_health_check_interval(mut self, interval: std::time::Duration) -> Self {
+        self.health_check_interval = interval;
+        self
+    }
+
+    // Storage health, checked at most once per `health_check_interval`
+    async fn storage_health(&self) -> Vec<StorageHealth> {
+        // Held while checking, so concurrent calls share one check
+        let mut cached = self.storage_health.lock().await;
+        if let Some((checked_at, health)) = cached.as_ref()
+            && checked_at.elapsed() < self.health_check_interval
+        {
+            return health.clone();
+        }
+        let health = self.store.storage_health().await;
+        *cached = Some((Instant::now(), health.clone()));
+        health
+    }
+
+    fn with_placeholder(mut self, placeholder: Placeholder) -> Self {
+        self.placeholder = Arc::new(placeholder);
+        self
+    }
+
+    fn with_upload_fields(mut self, fields: UploadFields) -> Self {
+        self.upload_fields = Arc::new(fields);
+        self
+    }
+
+    fn with_log_channel(mut self, logs: tokio::sync::broadcast::Sender<LogLine>) -> Self {
+        self.logs = logs;
+        self
+    }
+
+    fn with_canonical_format(mut self, format: Option<image::ImageFormat>) -> Self {
+        self.canonical_format = format;
+        self
+    }
+
+    fn with_perceptual_hash(mut self, enabled: bool) -> Self {
+        self.perceptual_hash = enabled;
+        self
+    }
+
+    fn with_config_path(mut self, path: Option<PathBuf>) -> Self {
+        self.config_path = path;
+        self
+    }
+
+    #[cfg(test)]
+    fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    fn with_max_concurrent(mut self, limit: Option<usize>) -> Self {
+        self.concurrency = limit
+            .filter(|&n| n > 0)
+            .map(|n| Arc::new(tokio::sync::Semaphore::new(n)));
+        self
+    }
+
+    fn with_compression(mut self, enabled: bool) -> Self {
+        self.compression = enabled;
+        self
+    }
+
+    fn with_auth_token(mut self, token: Option<String>) -> Self {
+        self.auth_token = token.filter(|t| !t.is_empty());
+        // Presigned URLs survive restarts and are revoked with the token
+        if let Some(token) = &self.auth_token {
+            self.presigner = Arc::new(Presigner::new(token));
+        }
+        self
+    }
+
+    // True when the request carries the configured bearer token.
+    // Always false if the server runs without a token.
+    fn is_authorized(&self, headers: &HeaderMap) -> bool {
+        let Some(expected) = &self.auth_token else {
+            return false;
+        };
+
+        headers
+            .get(header::AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "))
+            .is_some_and(|token| auth::constant_time_eq(token.as_bytes(), expected.as_bytes()))
+    }
+
+    // Guard for privileged endpoints: when the server has a token, the
+    // request must present it. Servers without a token stay open.
+    fn require_auth(&self, headers: &HeaderMap) -> Result<(), (StatusCode, String)> {
+        if self.auth_token.is_none() || self.is_authorized(headers) {
+            Ok(())
+        } else {
+            Err((StatusCode::UNAUTHORIZED, "Missing or invalid bearer token".to_string()))
         }
     }
 }
@@ -70,6 +421,93 @@ struct RangeQuery {
     to: i64,
 }
 
+// Query parameters accepted by GET /frames/:id and /snapshot.png
+#[derive(Debug, Default, Deserialize)]
+struct FrameQuery {
+    /// Re-encode the frame to this format (e.g. "png") before serving.
+    /// Needed to view raw RGBA frames in ordinary image tools.
+    /// "original" converts a canonicalised frame back to the upload format.
+    convert: Option<String>,
+    /// /snapshot.png only: serve a placeholder image instead of a 404 while
+    /// the store is empty. Defaults to the server's EYE_PLACEHOLDER setting.
+    placeholder: Option<bool>,
+    /// /snapshot.png only: latest frame from the monitor with this
+    /// monitor_id, e.g. for an agent rotating through several displays
+    monitor: Option<String>,
+    /// /snapshot.png only: answer 409 instead of serving a latest frame
+    /// captured more than this many seconds ago
+    max_age: Option<u64>,
+}
+
+// Query parameters accepted by GET /frames/latest
+#[derive(Debug, Default, Deserialize)]
+struct LatestQuery {
+    /// "1"/"true"/"yes" inlines the image as a base64 data URL instead of
+    /// linking to /snapshot.png
+    embed: Option<String>,
+    /// Answer 409 if the latest frame is older than this many seconds
+    max_age: Option<u64>,
+}
+
+// The freshness limit for a latest-frame request: ?max_age= if given,
+// else the server default
+fn max_frame_age(state: &AppState, max_age: Option<u64>) -> Option<chrono::Duration> {
+    max_age
+        .map(|secs| chrono::Duration::seconds(secs.min(i64::MAX as u64) as i64))
+        .or(state.max_frame_age)
+}
+
+// 409 for a latest frame that is too old, so pollers can tell "agent has
+// stopped uploading" apart from "no frames yet" (404)
+fn stale_frame_response(stale: &StaleLatest) -> Response {
+    (
+        StatusCode::CONFLICT,
+        [
+            (header::HeaderName::from_static("x-frame-stale"), "true".to_string()),
+            (header::HeaderName::from_static("x-frame-id"), stale.frame_id.to_string()),
+            (header::HeaderName::from_static("x-frame-timestamp"), stale.timestamp.to_rfc3339()),
+            (header::HeaderName::from_static("x-frame-age"), stale.age.num_seconds().to_string()),
+        ],
+        stale.to_string(),
+    )
+        .into_response()
+}
+
+// Query parameters accepted by GET /view
+#[derive(Debug, Default, Deserialize)]
+struct ViewQuery {
+    /// Seconds between page reloads; defaults to the agent's capture
+    /// interval
+    refresh: Option<u64>,
+}
+
+// Query parameters accepted by GET /animation.gif
+#[derive(Debug, Deserialize)]
+struct AnimationQuery {
+    /// Start of the window as a Unix timestamp (seconds, inclusive)
+    from: i64,
+    /// End of the window as a Unix timestamp (seconds, inclusive)
+    to: i64,
+    /// Playback speed in frames per second (default 2)
+    fps: Option<f64>,
+    /// Output width in pixels; height follows the aspect ratio (default 480)
+    width: Option<u32>,
+    /// "uniform" (default) shows every frame for 1/fps; "adaptive" drops
+    /// near-duplicates and shows each frame longer the more it changed
+    speed: Option<String>,
+    /// Return the frame list and per-frame delays as JSON instead of a GIF
+    manifest: Option<bool>,
+}
+
+// Query parameters accepted by GET /snapshot/wait
+#[derive(Debug, Deserialize)]
+struct WaitQuery {
+    /// Id of the frame the client already has; omit to accept any frame
+    since: Option<i64>,
+    /// How long to wait in milliseconds (default 30000, max 60000)
+    timeout: Option<u64>,
+}
+
 // Query parameters accepted by GET /frames/closest
 #[derive(Debug, Deserialize)]
 struct ClosestQuery {
@@ -77,17 +515,87 @@ struct ClosestQuery {
     timestamp: i64,
 }
 
+// Query parameters accepted by GET /admin/access-log
+#[derive(Debug, Deserialize)]
+struct AccessLogQuery {
+    /// Return entries after this sequence number
+    after: Option<u64>,
+    limit: Option<usize>,
+}
+
+// Body of POST /admin/presign
+#[derive(Debug, Default, Deserialize)]
+struct PresignRequest {
+    /// Sign /frames/:id for this frame instead of /snapshot.png
+    frame_id: Option<i64>,
+    /// Seconds the URL stays valid (default 900, max 7 days)
+    ttl: Option<u64>,
+}
+
+const DEFAULT_PRESIGN_TTL_SECS: u64 = 15 * 60;
+const MAX_PRESIGN_TTL_SECS: u64 = 7 * 24 * 60 * 60;
+
+const DEFAULT_ACCESS_LOG_PAGE: usize = 100;
+const MAX_ACCESS_LOG_PAGE: usize = 1000;
+
+// Query parameters accepted by GET /frames/:id/similar
+#[derive(Debug, Deserialize)]
+struct SimilarQuery {
+    /// Largest Hamming distance between perceptual hashes still counted as
+    /// similar, 0-64
+    threshold: Option<u32>,
+}
+
+// Hamming distance used when ?threshold= is omitted
+const DEFAULT_SIMILARITY_THRESHOLD: u32 = 10;
+
 // Logging middleware
 
+// Correlation header: propagated from the client when present, otherwise
+// generated per request, and always echoed in the response
+const REQUEST_ID_HEADER: &str = "x-request-id";
+
+// Longest client-supplied request ID we accept; longer values are replaced
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+// Use the client's X-Request-Id if it is printable and reasonably short,
+// otherwise mint a fresh UUID
+fn request_id_from(headers: &HeaderMap) -> String {
+    headers
+        .get(REQUEST_ID_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .map(str::trim)
+        .filter(|id| !id.is_empty() && id.len() <= MAX_REQUEST_ID_LEN)
+        .map(str::to_string)
+        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string())
+}
+
 async fn logging_middleware(req: Request, next: Next) -> Response {
     let method = req.method().clone();
     let uri = req.uri().clone();
     let start = Instant::now();
 
-    let response = next.run(req).await;
+    let request_id = request_id_from(req.headers());
+
+    let mut response = next.run(req).await;
+
+    if let Ok(value) = header::HeaderValue::from_str(&request_id) {
+        response.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+
+    println!("{}", log_line(&method, &uri, response.status(), start.elapsed(), &request_id));
+
+    response
+}
 
-    let latency = start.elapsed();
-    let status = response.status();
+// Colorized access log line, tagged with the request ID
+fn log_line(
+    method: &axum::http::Method,
+    uri: &axum::http::Uri,
+    status: StatusCode,
+    latency: std::time::Duration,
+    request_id: &str,
+) -> String {
     let status_code = status.as_u16();
 
     let status_str = match status_code {
@@ -109,36 +617,362 @@ async fn logging_middleware(req: Request, next: Next) -> Response {
     let latency_str = format!("{:?}", latency);
     let client_ip = "::1";
 
-    println!(
-        "[Eye] {} |{}| {:>13} |{:>15} | {} \"{}\"",
-        timestamp, status_str, latency_str, client_ip, method_str, uri
-    );
+    format!(
+        "[Eye] {} |{}| {:>13} |{:>15} | {} \"{}\" rid={}",
+        timestamp, status_str, latency_str, client_ip, method_str, uri, request_id
+    )
+}
+
+// Concurrency limit
+
+// Record frames served by successful responses (see FramesServed) in the
+// access log, under the identity of the caller. A bearer token is only
+// logged once it checks out; anything else is anonymous.
+async fn access_log_middleware(State(state): State<AppState>, req: Request, next: Next) -> Response {
+    let identity = if presign::is_signed(req.uri().query()) {
+        "presigned".to_string()
+    } else if state.is_authorized(req.headers()) {
+        access_log::identity(req.headers())
+    } else {
+        "anonymous".to_string()
+    };
+    let path = req.uri().path().to_string();
+    let response = next.run(req).await;
 
+    if response.status().is_success()
+        && let Some(FramesServed(ids)) = response.extensions().get::<FramesServed>()
+    {
+        state.access_log.record(state.clock.now(), &identity, &path, ids);
+    }
     response
 }
 
+// Requests carrying a presigned URL signature must match it: right path,
+// unexpired and untampered, else 403. Unsigned requests pass untouched.
+async fn presign_middleware(State(state): State<AppState>, req: Request, next: Next) -> Response {
+    let query = req.uri().query();
+    if !presign::is_signed(query) {
+        return next.run(req).await;
+    }
+    if req.method() != axum::http::Method::GET {
+        return (StatusCode::FORBIDDEN, "Presigned URLs are read-only").into_response();
+    }
+    match state.presigner.verify(req.uri().path(), query.unwrap_or_default(), state.clock.now()) {
+        Ok(()) => next.run(req).await,
+        Err(rejection) => (StatusCode::FORBIDDEN, rejection.to_string()).into_response(),
+    }
+}
+
+// Reject requests with 503 once the configured number are in flight.
+// /health stays exempt so liveness probes keep working under load.
+async fn concurrency_middleware(State(state): State<AppState>, req: Request, next: Next) -> Response {
+    let Some(semaphore) = state.concurrency.clone() else {
+        return next.run(req).await;
+    };
+    if req.uri().path() == "/health" {
+        return next.run(req).await;
+    }
+
+    match semaphore.try_acquire_owned() {
+        Ok(_permit) => next.run(req).await,
+        Err(_) => (
+            StatusCode::SERVICE_UNAVAILABLE,
+            [(header::RETRY_AFTER, "1")],
+            "Server busy — too many concurrent requests",
+        )
+            .into_response(),
+    }
+}
+
+// Response compression
+
+// Bodies smaller than this are not worth the compression framing overhead
+const COMPRESSION_MIN_BYTES: u16 = 1024;
+
+// Only JSON and text are compressed — image bytes are already compressed
+// (or, for raw frames, served to agents that expect them untouched)
+fn is_compressible(headers: &HeaderMap) -> bool {
+    headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        // Event streams are sent as they happen, not held back for a codec
+        .is_some_and(|ct| {
+            ct.starts_with("application/json")
+                || (ct.starts_with("text/") && !ct.starts_with("text/event-stream"))
+        })
+}
+
+// Gzip or brotli for JSON/text responses, streamed, when enabled and the
+// client's Accept-Encoding allows it
+fn compression_layer(enabled: bool) -> CompressionLayer<impl Predicate> {
+    let compressible = move |_: StatusCode, _: axum::http::Version, headers: &HeaderMap, _: &axum::http::Extensions| {
+        enabled && is_compressible(headers)
+    };
+    CompressionLayer::new()
+        .no_deflate()
+        .no_zstd()
+        .compress_when(SizeAbove::new(COMPRESSION_MIN_BYTES).and(compressible))
+}
+
+// Raw frames
+
+use rawframe::RAW_FORMAT;
+
+// Rebuild a raw RGBA8 frame from the width/height/stride metadata recorded
+// at upload time
+fn raw_frame_to_image(frame: &Frame) -> Result<image::RgbaImage> {
+    let dim = |key: &str| -> Result<u32> {
+        frame
+            .metadata
+            .get(key)
+            .with_context(|| format!("raw frame is missing '{}'", key))?
+            .parse()
+            .with_context(|| format!("raw frame has invalid '{}'", key))
+    };
+    rawframe::to_image(&frame.data, dim("width")?, dim("height")?, dim("stride")? as usize)
+}
+
+// Decode any stored frame, raw or encoded
+fn decode_frame(frame: &Frame) -> Result<image::DynamicImage> {
+    if frame.metadata.get("format").map(String::as_str) == Some(RAW_FORMAT) {
+        Ok(image::DynamicImage::ImageRgba8(raw_frame_to_image(frame)?))
+    } else {
+        image::load_from_memory(&frame.data).context("failed to decode frame")
+    }
+}
+
+// Decode any stored frame (raw or encoded) and re-encode it as `target`
+fn convert_frame(frame: &Frame, target: image::ImageFormat) -> Result<Vec<u8>> {
+    encode_image(&decode_frame(frame)?, target)
+}
+
+fn encode_image(img: &image::DynamicImage, target: image::ImageFormat) -> Result<Vec<u8>> {
+    let mut buffer = std::io::Cursor::new(Vec::new());
+    match target {
+        // JPEG has no alpha channel
+        image::ImageFormat::Jpeg => image::DynamicImage::ImageRgb8(img.to_rgb8()).write_to(&mut buffer, target),
+        _ => img.write_to(&mut buffer, target),
+    }
+    .context("failed to encode frame")?;
+    Ok(buffer.into_inner())
+}
+
+// Lowercase name of an image format as recorded in frame metadata, e.g. "jpeg"
+fn format_name(format: image::ImageFormat) -> String {
+    format!("{:?}", format).to_lowercase()
+}
+
+// Metadata key recording what the agent sent before canonical transcoding
+const ORIGINAL_FORMAT_KEY: &str = "original_format";
+
+// Re-encode `frame` as `target` for a ?convert= request, resolving
+// "original" to the format recorded at upload. Returns the bytes, their
+// content type and the format name used for the file extension.
+fn convert_for_request(
+    frame: &Frame,
+    target: &str,
+) -> Result<(Vec<u8>, String, String), (StatusCode, String)> {
+    let mut target = target.to_lowercase();
+    if target == "original" {
+        target = frame
+            .metadata
+            .get(ORIGINAL_FORMAT_KEY)
+            .or_else(|| frame.metadata.get("format"))
+            .cloned()
+            .unwrap_or_else(|| "png".to_string());
+    }
+
+    let image_format = image::ImageFormat::from_extension(&target).ok_or((
+        StatusCode::BAD_REQUEST,
+        format!("Unsupported conversion format '{}'", target),
+    ))?;
+    let data = convert_frame(frame, image_format)
+        .map_err(|e| (StatusCode::UNPROCESSABLE_ENTITY, e.to_string()))?;
+
+    Ok((data, image_format.to_mime_type().to_string(), target))
+}
+
+// convert_for_request on the transcode pool, unless the agent uploaded
+// the frame in that format too
+async fn convert_on_pool(
+    state: &AppState,
+    frame: Frame,
+    target: String,
+) -> Result<(Vec<u8>, String, String), (StatusCode, String)> {
+    if let Some(format) = image::ImageFormat::from_extension(target.to_lowercase())
+        && let Some(data) = state.alternates.get(frame.id, &format_name(format))
+    {
+        return Ok((data, format.to_mime_type().to_string(), format_name(format)));
+    }
+
+    let started = Instant::now();
+    let converted = state
+        .transcoder
+        .run(move || convert_for_request(&frame, &target))
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("{:#}", e)))?;
+    state.timings.record(Stage::Transcode, started.elapsed());
+    converted
+}
+
+// Transcode a freshly uploaded frame to the canonical format in place,
+// keeping the agent's format under `original_format`. Frames already in
+// the canonical format are left alone. Returns the decoded image, when
+// transcoding needed one, so later steps needn't decode again.
+fn canonicalize_frame(frame: &mut Frame, canonical: image::ImageFormat) -> Result<Option<image::DynamicImage>> {
+    let canonical_name = format_name(canonical);
+    let original = frame.metadata.get("format").cloned().unwrap_or_else(|| "png".to_string());
+    if image::ImageFormat::from_extension(&original) == Some(canonical) {
+        return Ok(None);
+    }
+
+    let img = decode_frame(frame)?;
+    frame.data = encode_image(&img, canonical)
+        .with_context(|| format!("failed to transcode {} frame to {}", original, canonical_name))?;
+
+    // Raw row stride no longer describes the stored bytes
+    frame.metadata.remove("stride");
+    frame.metadata.insert("content-type".to_string(), canonical.to_mime_type().to_string());
+    frame.metadata.insert("format".to_string(), canonical_name);
+    frame.metadata.insert(ORIGINAL_FORMAT_KEY.to_string(), original);
+    Ok(Some(img))
+}
+
 // Health
 
-async fn health_handler(State(state): State<AppState>) -> Json<serde_json::Value> {
+async fn health_handler(State(state): State<AppState>, headers: HeaderMap) -> Json<serde_json::Value> {
     let uptime = state.start_time.elapsed().as_secs_f64();
-    let frames = state.store.list().await;
+    let frame_count = state.store.usage().await.frames;
     let agent_connected = *state.agent_connected.read().await;
+    // A backend that can't take writes leaves the server up but degraded
+    let storage = state.storage_health().await;
+    let status = if storage.iter().all(|s| s.healthy) { "healthy" } else { "degraded" };
+    // Errors name paths on the server; anonymous callers only learn which
+    // backends are up
+    let storage = if state.require_auth(&headers).is_ok() {
+        json!(storage)
+    } else {
+        storage
+            .iter()
+            .map(|s| json!({ "backend": s.backend, "healthy": s.healthy, "degraded": s.degraded }))
+            .collect()
+    };
 
     Json(json!({
-        "status": "healthy",
+        "status": status,
         "uptime": format!("{:.2}s", uptime),
-        "frame_count": frames.len(),
+        "frame_count": frame_count,
         "agent_connected": agent_connected,
+        "storage": storage,
     }))
 }
 
+// Version
+
+// Crate version and build details baked in by build.rs
+fn version_info() -> serde_json::Value {
+    let built_at = env!("EYE_BUILD_TIMESTAMP")
+        .parse::<i64>()
+        .ok()
+        .and_then(|secs| DateTime::from_timestamp(secs, 0))
+        .map(|t| t.to_rfc3339());
+    let features: Vec<&str> = env!("EYE_BUILD_FEATURES")
+        .split(',')
+        .filter(|f| !f.is_empty())
+        .collect();
+
+    json!({
+        "name": env!("CARGO_PKG_NAME"),
+        "version": env!("CARGO_PKG_VERSION"),
+        "git_sha": env!("EYE_GIT_SHA"),
+        "build_timestamp": built_at,
+        "features": features,
+    })
+}
+
+async fn version_handler() -> Json<serde_json::Value> {
+    Json(version_info())
+}
+
+// Version negotiation
+
+// Agents report their crate version in this header on /connect
+const AGENT_VERSION_HEADER: &str = "x-agent-version";
+
+// Echoed on /connect responses so agents can log what they talk to
+const SERVER_VERSION_HEADER: &str = "x-server-version";
+
+// Verdict returned to a connecting agent
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Compatibility {
+    Ok,
+    Warn,
+    Reject,
+}
+
+// "1.2.3", "v1.2.3" or "1.2.3-beta" → (1, 2, 3)
+fn parse_version(v: &str) -> Option<(u64, u64, u64)> {
+    let v = v.trim().trim_start_matches('v');
+    let core = v.split(['-', '+']).next()?;
+    let mut parts = core.split('.').map(|p| p.parse::<u64>().ok());
+    let version = (parts.next()??, parts.next()??, parts.next()??);
+    parts.next().is_none().then_some(version)
+}
+
+// Semver policy: a different major version — or minor, while still on
+// 0.x where minor bumps are breaking — is rejected. Any other difference
+// apart from the patch level is allowed with a warning. Agents that send
+// no (or an unreadable) version predate negotiation and get a warning too.
+fn compatibility(agent: Option<&str>, server: &str) -> Compatibility {
+    let (Some(agent), Some(server)) = (agent.and_then(parse_version), parse_version(server))
+    else {
+        return Compatibility::Warn;
+    };
+
+    let breaking = |(major, minor, _): (u64, u64, u64)| {
+        if major == 0 { (0, minor) } else { (major, 0) }
+    };
+    if breaking(agent) != breaking(server) {
+        Compatibility::Reject
+    } else if (agent.0, agent.1) != (server.0, server.1) {
+        Compatibility::Warn
+    } else {
+        Compatibility::Ok
+    }
+}
+
 // 1:1 Connection
 
 // An agent calls this to claim the sole connection slot.
-// Returns 409 Conflict if a different agent is already connected.
+// Returns 409 Conflict if a different agent is already connected and
+// 426 Upgrade Required if its X-Agent-Version is incompatible.
 async fn connect_handler(
     State(state): State<AppState>,
-) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    headers: HeaderMap,
+) -> Result<Response, (StatusCode, String)> {
+    let server_version = env!("CARGO_PKG_VERSION");
+    let agent_version = headers.get(AGENT_VERSION_HEADER).and_then(|v| v.to_str().ok());
+    let verdict = compatibility(agent_version, server_version);
+
+    if verdict == Compatibility::Reject {
+        return Err((
+            StatusCode::UPGRADE_REQUIRED,
+            format!(
+                "Agent version {} is incompatible with server version {}",
+                agent_version.unwrap_or("unknown"),
+                server_version
+            ),
+        ));
+    }
+    if verdict == Compatibility::Warn {
+        warn!(
+            "Agent version {} differs from server version {}",
+            agent_version.unwrap_or("unknown"),
+            server_version
+        );
+    }
+
     let mut connected = state.agent_connected.write().await;
 
     if *connected {
@@ -153,13 +987,29 @@ async fn connect_handler(
     *connected = true;
     info!("Agent connected");
 
-    Ok(Json(json!({ "status": "connected" })))
+    let mut body = json!({
+        "status": "connected",
+        "server_version": server_version,
+        "compatibility": verdict,
+    });
+    if let Some(namespaces) = &state.id_namespaces {
+        // Above every stored frame's namespace, so ids keep rising
+        let floor = state.store.get_latest().await.map_or(1, |f| namespaces::namespace_of(f.id) + 1);
+        let namespace = namespaces.allocate(floor);
+        *state.agent_namespace.write().await = Some(namespace);
+        info!("Agent frame ids namespaced under {}", namespace);
+        body["id_namespace"] = json!(namespace);
+        body["id_offset"] = json!(namespaces::global_id(namespace, 0));
+    }
+    let body = Json(body);
+    Ok(([(SERVER_VERSION_HEADER, server_version)], body).into_response())
 }
 
 // The agent calls this on clean shutdown to free the slot.
 async fn disconnect_handler(State(state): State<AppState>) -> Json<serde_json::Value> {
     let mut connected = state.agent_connected.write().await;
     *connected = false;
+    *state.agent_namespace.write().await = None;
     info!("Agent disconnected");
     Json(json!({ "status": "disconnected" }))
 }
@@ -168,95 +1018,396 @@ async fn disconnect_handler(State(state): State<AppState>) -> Json<serde_json::V
 
 async fn admin_config_handler(
     State(state): State<AppState>,
+    headers: HeaderMap,
     Json(new_config): Json<AgentConfig>,
-) -> Json<serde_json::Value> {
-    let mut config = state.config.write().await;
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    state.require_auth(&headers)?;
 
-    let mut validated = new_config;
-    if validated.interval < 0.1 {
-        validated.interval = 0.1;
-    }
-    if validated.quality < 1 || validated.quality > 100 {
-        validated.quality = 75;
-    }
-    validated.format = validated.format.to_lowercase();
-    const SUPPORTED_FORMATS: &[&str] = &["png", "jpeg", "jpg", "webp", "bmp", "tiff"];
-    if !SUPPORTED_FORMATS.contains(&validated.format.as_str()) {
-        info!("Invalid format '{}', defaulting to PNG", validated.format);
-        validated.format = "png".to_string();
-    }
-    if validated.format == "jpg" {
-        validated.format = "jpeg".to_string();
-    }
+    let new_config = new_config.validated().map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    let mut config = state.config.write().await;
 
-    *config = validated.clone();
+    *config = new_config;
     info!("Config updated: {:?}", config);
 
-    Json(json!({
+    if let Some(path) = &state.config_path
+        && let Err(e) = save_config(path, &config).await
+    {
+        warn!("Failed to persist config to {}: {:#}", path.display(), e);
+    }
+
+    Ok(Json(json!({
         "message": "Configuration updated",
         "config": config.clone(),
-    }))
+    })))
 }
 
-// Upload
-
-// Receives frames from the connected agent.
-// Requires a prior POST /connect — rejects with 403 otherwise.
-// Reads the "format" multipart field to store the real content-type instead
-// of blindly assuming PNG.
-async fn upload_handler(
+// Restores the default agent configuration. Agents pick it up on their
+// next upload, the same way as a POST /admin/config change.
+async fn admin_reset_handler(
     State(state): State<AppState>,
-    mut multipart: Multipart,
+    headers: HeaderMap,
 ) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
-    // Enforce 1:1 — reject uploads from agents that haven't registered
+    state.require_auth(&headers)?;
+
+    let mut config = state.config.write().await;
+    *config = AgentConfig::default();
+    info!("Config reset to defaults: {:?}", config);
+
+    // Otherwise a restart or SIGHUP would bring the old config back
+    if let Some(path) = &state.config_path
+        && let Err(e) = save_config(path, &config).await
     {
-        let connected = state.agent_connected.read().await;
-        if !*connected {
-            return Err((
-                StatusCode::FORBIDDEN,
-                "No agent is registered with this server. \
-                 Call POST /connect before uploading frames."
-                    .to_string(),
-            ));
-        }
+        warn!("Failed to persist config to {}: {:#}", path.display(), e);
     }
 
-    let mut data: Option<Vec<u8>> = None;
-    let mut frame_id = 0i64;
-    // Default to png; overwritten if the agent sends a "format" field
-    let mut format = "png".to_string();
+    Ok(Json(json!({
+        "message": "Configuration reset to defaults",
+        "config": config.clone(),
+    })))
+}
 
-    while let Some(field) = multipart
-        .next_field()
+// Verifies the frame hash chain and lists the frames where it breaks
+async fn admin_chain_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    state.require_auth(&headers)?;
+
+    let breaks = state
+        .store
+        .verify_chain()
         .await
-        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?
-    {
-        let name = field.name().unwrap_or("").to_string();
+        .map_err(|e| (StatusCode::CONFLICT, e.to_string()))?;
 
-        match name.as_str() {
-            "image" => {
-                data = Some(
+    Ok(Json(json!({
+        "intact": breaks.is_empty(),
+        "breaks": breaks,
+    })))
+}
+
+// Page through the frame access log, oldest first: ?after=<seq> continues
+// from the last entry of the previous page
+async fn admin_access_log_handler(
+    State(state): State<AppState>,
+    Query(params): Query<AccessLogQuery>,
+    headers: HeaderMap,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    state.require_auth(&headers)?;
+
+    let limit = params.limit.unwrap_or(DEFAULT_ACCESS_LOG_PAGE).clamp(1, MAX_ACCESS_LOG_PAGE);
+    let entries = state.access_log.page(params.after.unwrap_or(0), limit);
+    // A full page may have more behind it
+    let next_after = (entries.len() == limit).then(|| entries.last().map(|e| e.seq)).flatten();
+
+    Ok(Json(json!({
+        "count":      entries.len(),
+        "entries":    entries,
+        "next_after": next_after,
+    })))
+}
+
+// Latency histograms for the decode, transcode and store stages of frame
+// handling since startup, to tell whether slowness is storage or encoding
+async fn admin_timings_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    state.require_auth(&headers)?;
+
+    Ok(Json(json!({
+        "uptime_sec": state.start_time.elapsed().as_secs_f64(),
+        "stages":     state.timings.snapshot(),
+    })))
+}
+
+// Sign a time-limited URL for /snapshot.png, or /frames/:id with a
+// frame_id, that works without the bearer token, e.g. to embed a snapshot
+// in a dashboard
+async fn admin_presign_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    body: Option<Json<PresignRequest>>,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    state.require_auth(&headers)?;
+
+    let Json(request) = body.unwrap_or_default();
+    let ttl = request.ttl.unwrap_or(DEFAULT_PRESIGN_TTL_SECS);
+    if ttl == 0 || ttl > MAX_PRESIGN_TTL_SECS {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("ttl must be between 1 and {} seconds", MAX_PRESIGN_TTL_SECS),
+        ));
+    }
+    let path = match request.frame_id {
+        Some(id) => format!("/frames/{}", id),
+        None => "/snapshot.png".to_string(),
+    };
+    let expires_at = state.clock.now() + chrono::Duration::seconds(ttl as i64);
+
+    Ok(Json(json!({
+        "url":        state.presigner.url(&path, expires_at),
+        "expires_at": expires_at.to_rfc3339(),
+    })))
+}
+
+// The "make everything consistent now" call for scripts: waits for
+// batched disk writes to land, runs a compaction pass over the disk store
+// and reports the resulting state of the store
+async fn admin_flush_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    state.require_auth(&headers)?;
+
+    let internal = |e: anyhow::Error| (StatusCode::INTERNAL_SERVER_ERROR, format!("{:#}", e));
+    state.store.flush().await.map_err(internal)?;
+    let compaction = state.store.compact().await.map_err(internal)?;
+    let disk = state.store.disk_usage().await.map_err(internal)?;
+
+    // Disk stores count what is on disk, not just the frames in memory
+    let stats = state.store.stats().await;
+    let frames = stats.disk_frame_count.unwrap_or(stats.frame_count);
+    let bytes = disk.map_or(stats.total_bytes as u64, |disk| disk.bytes);
+    info!("Admin flush: {} frame(s), disk {:?}", frames, disk);
+
+    Ok(Json(json!({
+        "message":    "Storage flushed",
+        "backend":    state.store.mode().as_str(),
+        "frames":     frames,
+        "bytes":      bytes,
+        "oldest":     stats.oldest.map(|t| t.to_rfc3339()),
+        "newest":     stats.newest.map(|t| t.to_rfc3339()),
+        "disk":       disk,
+        "compaction": compaction,
+    })))
+}
+
+// Query parameters accepted by GET /admin/logs
+#[derive(Debug, Default, Deserialize)]
+struct LogsQuery {
+    /// Most verbose level to forward ("error", "warn", "info", "debug",
+    /// "trace"); defaults to info
+    level: Option<String>,
+}
+
+// Streams server log lines as Server-Sent Events, one JSON object per
+// event. Clients that fall behind skip the lines they missed.
+async fn admin_logs_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<LogsQuery>,
+) -> Result<Response, (StatusCode, String)> {
+    state.require_auth(&headers)?;
+
+    let max_level: tracing::Level = match query.level.as_deref() {
+        Some(level) => level
+            .parse()
+            .map_err(|_| (StatusCode::BAD_REQUEST, format!("Invalid log level '{}'", level)))?,
+        None => tracing::Level::INFO,
+    };
+
+    let rx = state.logs.subscribe();
+    let stream = futures::stream::unfold(rx, move |mut rx| async move {
+        loop {
+            match rx.recv().await {
+                // Level ordering runs from ERROR (least) to TRACE (most verbose)
+                Ok(line) if line.level <= max_level => {
+                    let event = Event::default()
+                        .event(line.level.as_str())
+                        .json_data(&line)
+                        .unwrap_or_else(|_| Event::default().data(line.message.clone()));
+                    return Some((Ok::<_, std::convert::Infallible>(event), rx));
+                }
+                Ok(_) => continue,
+                Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(tokio::sync::broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    });
+
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()).into_response())
+}
+
+// Upload
+
+// Multipart fields with this prefix describe the source monitor and are
+// kept verbatim in frame metadata
+const MONITOR_FIELD_PREFIX: &str = "monitor_";
+
+// Longest accepted value for a monitor_* field
+const MAX_MONITOR_FIELD_LEN: usize = 256;
+
+// Optional hex HMAC-SHA256 of the agent's clipboard text at capture time,
+// keyed per agent install
+const CLIPBOARD_HASH_FIELD: &str = "clipboard_sha256";
+
+// Metadata key recording the actual clock reading (RFC 3339) of a frame
+// whose timestamp was clamped because the clock went backwards
+const WALL_TIME_FIELD: &str = "wall_time";
+
+// Multipart field names the upload handler reads the image and frame id
+// from. Defaults to the agent's names plus aliases common in other upload
+// clients (EYE_UPLOAD_IMAGE_FIELDS / EYE_UPLOAD_ID_FIELDS).
+#[derive(Debug, Clone)]
+struct UploadFields {
+    image: Vec<String>,
+    frame_id: Vec<String>,
+}
+
+impl Default for UploadFields {
+    fn default() -> Self {
+        Self {
+            image: vec!["image".to_string(), "file".to_string()],
+            frame_id: vec!["frame_id".to_string(), "id".to_string()],
+        }
+    }
+}
+
+impl UploadFields {
+    // Parse a comma-separated list of field names
+    fn parse_list(list: &str) -> Vec<String> {
+        list.split(',')
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+            .collect()
+    }
+
+    fn is_image(&self, name: &str) -> bool {
+        self.image.iter().any(|n| n == name)
+    }
+
+    fn is_frame_id(&self, name: &str) -> bool {
+        self.frame_id.iter().any(|n| n == name)
+    }
+}
+
+// Machine-readable upload failure, serialized as
+// {"error": code, "message": ..., "detail": ...}. Codes are stable so
+// clients can react to them; messages are for humans and may change.
+#[derive(Debug)]
+struct UploadError {
+    status: StatusCode,
+    code: &'static str,
+    message: String,
+    detail: Option<String>,
+}
+
+impl UploadError {
+    fn new(status: StatusCode, code: &'static str, message: impl Into<String>) -> Self {
+        Self { status, code, message: message.into(), detail: None }
+    }
+
+    fn with_detail(mut self, detail: impl ToString) -> Self {
+        self.detail = Some(detail.to_string());
+        self
+    }
+
+    // A multipart body that couldn't be read; hitting the body size limit
+    // is reported as too_large
+    fn multipart(e: axum::extract::multipart::MultipartError) -> Self {
+        if e.status() == StatusCode::PAYLOAD_TOO_LARGE {
+            Self::new(StatusCode::PAYLOAD_TOO_LARGE, "too_large", "Upload exceeds the size limit")
+                .with_detail(e.body_text())
+        } else {
+            Self::new(StatusCode::BAD_REQUEST, "invalid_multipart", "Malformed multipart body")
+                .with_detail(e.body_text())
+        }
+    }
+
+    fn invalid_field(name: &str, message: impl Into<String>) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, "invalid_field", message).with_detail(name)
+    }
+}
+
+impl IntoResponse for UploadError {
+    fn into_response(self) -> Response {
+        let body = json!({
+            "error": self.code,
+            "message": self.message,
+            "detail": self.detail,
+        });
+        (self.status, Json(body)).into_response()
+    }
+}
+
+// Receives frames from the connected agent.
+// Requires a prior POST /connect — rejects with 403 otherwise.
+// Reads the "format" multipart field to store the real content-type instead
+// of blindly assuming PNG.
+// The response reports the storage backend; the on-disk relative path is
+// only disclosed to callers presenting the server's auth token.
+// Every rejection is an UploadError with a stable code.
+async fn upload_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    multipart: Result<Multipart, axum::extract::multipart::MultipartRejection>,
+) -> Result<Json<serde_json::Value>, UploadError> {
+    // Enforce 1:1 — reject uploads from agents that haven't registered
+    {
+        let connected = state.agent_connected.read().await;
+        if !*connected {
+            return Err(UploadError::new(
+                StatusCode::FORBIDDEN,
+                "not_connected",
+                "No agent is registered with this server. \
+                 Call POST /connect before uploading frames.",
+            ));
+        }
+    }
+
+    let mut multipart = multipart.map_err(|e| {
+        UploadError::new(StatusCode::BAD_REQUEST, "invalid_multipart", "Expected a multipart/form-data body")
+            .with_detail(e.body_text())
+    })?;
+    let decode_started = Instant::now();
+
+    let mut data: Option<Vec<u8>> = None;
+    let mut frame_id = 0i64;
+    // Default to png; overwritten if the agent sends a "format" field
+    let mut format = "png".to_string();
+    let mut dimensions: HashMap<String, String> = HashMap::new();
+    let mut monitor: HashMap<String, String> = HashMap::new();
+    let mut clipboard_hash: Option<String> = None;
+    // Other encodings of the same capture, by format name
+    let mut alternates = Encodings::new();
+    // Agents set "pin" to keep a frame out of eviction until unpinned
+    let mut pin = false;
+    // Capture time sent by the agent, e.g. for replayed frames; defaults
+    // to the time of upload
+    let mut captured_at: Option<DateTime<Utc>> = None;
+
+    while let Some(field) = multipart
+        .next_field()
+        .await
+        .map_err(UploadError::multipart)?
+    {
+        let name = field.name().unwrap_or("").to_string();
+
+        match name.as_str() {
+            _ if state.upload_fields.is_image(&name) => {
+                data = Some(
                     field
                         .bytes()
                         .await
-                        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?
+                        .map_err(UploadError::multipart)?
                         .to_vec(),
                 );
             }
-            "frame_id" => {
+            _ if state.upload_fields.is_frame_id(&name) => {
                 let text = field
                     .text()
                     .await
-                    .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
-                frame_id = text
-                    .parse()
-                    .map_err(|_| (StatusCode::BAD_REQUEST, "Invalid frame_id".to_string()))?;
+                    .map_err(UploadError::multipart)?;
+                frame_id = text.trim().parse().map_err(|_| {
+                    UploadError::new(StatusCode::BAD_REQUEST, "invalid_frame_id", "Frame id must be an integer")
+                        .with_detail(&text)
+                })?;
             }
             "format" => {
                 let text = field
                     .text()
                     .await
-                    .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+                    .map_err(UploadError::multipart)?;
                 // Normalise "jpg" → "jpeg" so content-type strings are consistent
                 format = if text.to_lowercase() == "jpg" {
                     "jpeg".to_string()
@@ -264,40 +1415,321 @@ async fn upload_handler(
                     text.to_lowercase()
                 };
             }
+            "pin" => {
+                let text = field
+                    .text()
+                    .await
+                    .map_err(UploadError::multipart)?;
+                pin = matches!(text.trim().to_lowercase().as_str(), "true" | "1" | "yes");
+            }
+            // RFC 3339, or Unix milliseconds
+            "timestamp" => {
+                let text = field
+                    .text()
+                    .await
+                    .map_err(UploadError::multipart)?;
+                let text = text.trim();
+                let parsed = match text.parse::<i64>() {
+                    Ok(millis) => DateTime::from_timestamp_millis(millis),
+                    Err(_) => DateTime::parse_from_rfc3339(text).ok().map(|t| t.to_utc()),
+                };
+                captured_at = Some(parsed.ok_or_else(|| {
+                    UploadError::invalid_field(&name, "timestamp must be RFC 3339 or Unix milliseconds")
+                })?);
+            }
+            // Pixel layout of raw RGBA frames
+            "width" | "height" | "stride" => {
+                let text = field
+                    .text()
+                    .await
+                    .map_err(UploadError::multipart)?;
+                let value: u32 = text
+                    .parse()
+                    .map_err(|_| UploadError::invalid_field(&name, format!("Invalid {}", name)))?;
+                dimensions.insert(name, value.to_string());
+            }
+            // Source monitor details (monitor_id, monitor_name, ...)
+            _ if name.starts_with(MONITOR_FIELD_PREFIX) => {
+                let text = field
+                    .text()
+                    .await
+                    .map_err(UploadError::multipart)?;
+                if text.len() > MAX_MONITOR_FIELD_LEN {
+                    return Err(UploadError::invalid_field(&name, format!("{} too long", name)));
+                }
+                monitor.insert(name, text);
+            }
+            CLIPBOARD_HASH_FIELD => {
+                let text = field
+                    .text()
+                    .await
+                    .map_err(UploadError::multipart)?;
+                let text = text.trim().to_lowercase();
+                if text.len() != 64 || !text.chars().all(|c| c.is_ascii_hexdigit()) {
+                    return Err(UploadError::invalid_field(
+                        &name,
+                        format!("{} must be a hex SHA-256 digest", name),
+                    ));
+                }
+                clipboard_hash = Some(text);
+            }
+            // e.g. "alternate_jpeg"; the bytes must be an image in that format
+            _ if name.starts_with(ALTERNATE_FIELD_PREFIX) => {
+                let format = image::ImageFormat::from_extension(name[ALTERNATE_FIELD_PREFIX.len()..].to_lowercase())
+                    .ok_or_else(|| UploadError::invalid_field(&name, "Unsupported alternate format"))?;
+                let bytes = field
+                    .bytes()
+                    .await
+                    .map_err(UploadError::multipart)?;
+                if image::guess_format(&bytes).ok() != Some(format) {
+                    return Err(UploadError::invalid_field(
+                        &name,
+                        format!("{} is not a {} image", name, format_name(format)),
+                    ));
+                }
+                alternates.insert(format_name(format), bytes.to_vec());
+            }
             _ => {}
         }
     }
 
-    let data = data.ok_or((StatusCode::BAD_REQUEST, "No image file".to_string()))?;
+    state.timings.record(Stage::Decode, decode_started.elapsed());
+
+    // The agent counts from its own origin; store under its namespace
+    let namespace = *state.agent_namespace.read().await;
+    let agent_frame_id = frame_id;
+    if let Some(namespace) = namespace {
+        frame_id = namespaces::global_id(namespace, frame_id).ok_or_else(|| {
+            UploadError::new(StatusCode::BAD_REQUEST, "invalid_frame_id", "Frame id out of range")
+                .with_detail(format!("namespaced frame ids must be in 0..{}", namespaces::NAMESPACE_SPAN))
+        })?;
+    }
+
+    let data = data.ok_or_else(|| {
+        UploadError::new(StatusCode::BAD_REQUEST, "missing_image", "No image file")
+            .with_detail(format!("expected one of: {}", state.upload_fields.image.join(", ")))
+    })?;
+    if data.is_empty() {
+        return Err(UploadError::new(StatusCode::UNPROCESSABLE_ENTITY, "not_an_image", "Image is empty"));
+    }
+    // Encoded frames must at least start like an image; a full decode only
+    // happens with EYE_CANONICAL_FORMAT
+    if format != RAW_FORMAT && image::guess_format(&data).is_err() {
+        return Err(UploadError::new(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "not_an_image",
+            "Image header not recognised",
+        )
+        .with_detail(format!("declared format {}", format)));
+    }
 
     // Store the real format in frame metadata so download endpoints can serve
     // the correct Content-Type and file extension later.
     let mut metadata = HashMap::new();
-    metadata.insert("content-type".to_string(), format!("image/{}", format));
+    if format == RAW_FORMAT {
+        // Dimension fields were parsed as u32 above; here they must be
+        // present and non-zero
+        let dim = |dimensions: &HashMap<String, String>, name: &str| -> Result<u64, UploadError> {
+            match dimensions.get(name).map(|v| v.parse::<u64>()) {
+                Some(Ok(value)) if value > 0 => Ok(value),
+                Some(_) => Err(UploadError::invalid_field(name, format!("{} must be greater than zero", name))),
+                None => Err(UploadError::invalid_field(name, "Raw frames require width and height fields")),
+            }
+        };
+        let (width, height) = (dim(&dimensions, "width")?, dim(&dimensions, "height")?);
+        // Tightly packed rows unless the agent says otherwise
+        dimensions.entry("stride".to_string()).or_insert_with(|| (width * 4).to_string());
+        let stride = dim(&dimensions, "stride")?;
+        if stride < width * 4 {
+            return Err(UploadError::invalid_field("stride", "stride must be at least width * 4"));
+        }
+        if (data.len() as u64) < stride * height {
+            return Err(UploadError::new(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "not_an_image",
+                "Raw frame is shorter than stride * height",
+            )
+            .with_detail(format!("{} bytes, expected {}", data.len(), stride * height)));
+        }
+        metadata.insert("content-type".to_string(), "application/octet-stream".to_string());
+    } else {
+        metadata.insert("content-type".to_string(), format!("image/{}", format));
+    }
     metadata.insert("format".to_string(), format.clone());
+    metadata.extend(dimensions);
+    metadata.extend(monitor);
+    if namespace.is_some() {
+        metadata.insert(AGENT_FRAME_ID_FIELD.to_string(), agent_frame_id.to_string());
+    }
+    if let Some(hash) = clipboard_hash {
+        metadata.insert(CLIPBOARD_HASH_FIELD.to_string(), hash);
+    }
+
+    // Frames without a capture time are stamped on arrival. Should the
+    // clock jump back, the stamp is held just past the previous one so
+    // "latest" and on-disk ordering stay intact, and the real reading is
+    // kept in WALL_TIME_FIELD. Agent-supplied times are left as they are.
+    let timestamp = match captured_at {
+        Some(captured_at) => captured_at,
+        None => {
+            let wall = state.clock.now();
+            let stamp = state.stamps.stamp(wall);
+            if stamp != wall {
+                metadata.insert(WALL_TIME_FIELD.to_string(), wall.to_rfc3339());
+            }
+            stamp
+        }
+    };
+    // A frame from the future would stay "latest" until real time caught
+    // up with it, hiding every frame after it
+    let now = state.clock.now();
+    if let Some(skew) = state.max_frame_skew
+        && timestamp - now > skew
+    {
+        return Err(UploadError::new(
+            StatusCode::BAD_REQUEST,
+            "future_frame",
+            "Frame is timestamped ahead of the server clock by more than the allowed clock skew",
+        )
+        .with_detail(format!(
+            "frame #{} at {} is {}s ahead of {} (tolerance {}s)",
+            frame_id,
+            timestamp.to_rfc3339(),
+            (timestamp - now).num_seconds(),
+            now.to_rfc3339(),
+            skew.num_seconds(),
+        )));
+    }
+    // Small out-of-order arrivals are fine (the store reorders them); a
+    // frame far older than the latest is a bad clock or a stale replay
+    if let Some(skew) = state.max_frame_skew
+        && let Ok(latest) = state.store.get_latest().await
+        && latest.timestamp - timestamp > skew
+    {
+        return Err(UploadError::new(
+            StatusCode::CONFLICT,
+            "stale_frame",
+            "Frame is older than the latest frame by more than the allowed clock skew",
+        )
+        .with_detail(format!(
+            "frame #{} at {} is {}s behind frame #{} at {} (tolerance {}s)",
+            frame_id,
+            timestamp.to_rfc3339(),
+            (latest.timestamp - timestamp).num_seconds(),
+            latest.id,
+            latest.timestamp.to_rfc3339(),
+            skew.num_seconds(),
+        )));
+    }
 
-    let frame = Frame {
+    let mut frame = Frame {
         id: frame_id,
-        data: data.clone(),
-        timestamp: Utc::now(),
+        data,
+        timestamp,
         metadata,
     };
+    let transcode_started = Instant::now();
+    let mut decoded = None;
+    if let Some(canonical) = state.canonical_format {
+        (frame, decoded) = state
+            .transcoder
+            .run(move || canonicalize_frame(&mut frame, canonical).map(|img| (frame, img)))
+            .await
+            .map_err(|e| {
+                UploadError::new(StatusCode::INTERNAL_SERVER_ERROR, "transcode_failed", "Transcoding failed")
+                    .with_detail(format!("{:#}", e))
+            })?
+            .map_err(|e| {
+                UploadError::new(StatusCode::UNPROCESSABLE_ENTITY, "not_an_image", "Image could not be decoded")
+                    .with_detail(format!("{:#}", e))
+            })?;
+    }
+    // Perceptual hash for GET /frames/:id/similar (EYE_PERCEPTUAL_HASH),
+    // from the canonical transcode's image when there was one; data that
+    // doesn't decode is stored without a hash
+    if state.perceptual_hash {
+        frame = state
+            .transcoder
+            .run(move || {
+                if let Some(img) = decoded.map_or_else(|| decode_frame(&frame).ok(), Some) {
+                    let hash = similarity::to_hex(similarity::dhash(&img));
+                    frame.metadata.insert(similarity::PHASH_FIELD.to_string(), hash);
+                }
+                frame
+            })
+            .await
+            .map_err(|e| {
+                UploadError::new(StatusCode::INTERNAL_SERVER_ERROR, "hash_failed", "Perceptual hashing failed")
+                    .with_detail(format!("{:#}", e))
+            })?;
+    }
+    state.timings.record(Stage::Transcode, transcode_started.elapsed());
+    let data_len = frame.data.len();
+    let format = frame.metadata["format"].clone();
 
-    state
+    let store_started = Instant::now();
+    let location = state
         .store
         .store(frame)
         .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        .map_err(|e| {
+            UploadError::new(StatusCode::INTERNAL_SERVER_ERROR, "storage_failed", "Failed to store frame")
+                .with_detail(format!("{:#}", e))
+        })?;
+    state.timings.record(Stage::Store, store_started.elapsed());
+    // Also clears any held for an earlier frame with this id
+    state.alternates.insert(frame_id, alternates);
 
-    info!("Frame #{} stored ({} bytes, {})", frame_id, data.len(), format);
+    info!("Frame #{} stored ({} bytes, {})", frame_id, data_len, format);
+
+    // A late frame doesn't displace a newer one as the latest
+    let latest_id = state.store.get_latest().await.map_or(frame_id, |f| f.id);
+    if latest_id != frame_id {
+        info!("Frame #{} arrived after #{} — reordered", frame_id, latest_id);
+    }
+    state.latest_frame.send_replace(Some(latest_id));
+
+    // A full pin cap doesn't fail the upload — the frame is kept unpinned
+    let pinned = if pin {
+        match state.store.pin(frame_id).await {
+            Ok(()) => true,
+            Err(e) => {
+                info!("Frame #{} not pinned: {}", frame_id, e);
+                false
+            }
+        }
+    } else {
+        false
+    };
 
     let config = state.config.read().await.clone();
+    let usage = state.store.usage().await;
+
+    let mut storage = json!({ "backend": location.backend });
+    if let Some(path) = location.path
+        && state.is_authorized(&headers)
+    {
+        storage["path"] = json!(path.to_string_lossy());
+    }
 
     Ok(Json(json!({
         "status": "ok",
         "frame_id": frame_id,
-        "size_kb": data.len() as f64 / 1024.0,
+        // Highest id stored so far; lets agents see their uploads were sequenced
+        "latest_frame_id": latest_id,
+        "size_kb": data_len as f64 / 1024.0,
+        "pinned": pinned,
         "config": config,
+        "storage": storage,
+        // Lets agents slow down before the server is overwhelmed
+        "storage_pressure": {
+            "frames":     usage.frames,
+            "max_frames": usage.max_frames,
+            "bytes":      usage.bytes,
+            "max_bytes":  usage.max_bytes,
+            "disk_free_bytes": usage.disk_free_bytes,
+            "ratio":      usage.pressure(),
+        },
     })))
 }
 
@@ -305,28 +1737,197 @@ async fn upload_handler(
 
 // Returns the latest frame as raw bytes. Kept for backwards compatibility.
 // Content-Type now reflects the actual format rather than hardcoded image/png.
+// ?convert= re-encodes it like GET /frames/:id.
 async fn snapshot_handler(
     State(state): State<AppState>,
-) -> Result<impl IntoResponse, (StatusCode, String)> {
-    let frame = state
-        .store
-        .get_latest()
-        .await
-        .map_err(|_| (StatusCode::NOT_FOUND, "No frames available".to_string()))?;
+    Query(params): Query<FrameQuery>,
+) -> Result<Response, (StatusCode, String)> {
+    let latest = match (&params.monitor, max_frame_age(&state, params.max_age)) {
+        (None, Some(max_age)) => state.store.get_latest_within(max_age, state.clock.now()).await,
+        (None, None) => state.store.get_latest().await,
+        (Some(monitor), max_age) => {
+            let latest = state.store.latest_with_metadata("monitor_id", monitor).await;
+            match max_age {
+                Some(max_age) => latest.and_then(|frame| storage::ensure_fresh(frame, max_age, state.clock.now())),
+                None => latest,
+            }
+        }
+    };
+    if let Err(e) = &latest
+        && let Some(stale) = e.downcast_ref::<StaleLatest>()
+    {
+        return Ok(stale_frame_response(stale));
+    }
+    let Ok(frame) = latest else {
+        if !params.placeholder.unwrap_or(state.placeholder.enabled) {
+            return Err((StatusCode::NOT_FOUND, "No frames available".to_string()));
+        }
+
+        let png = state
+            .placeholder
+            .render_png()
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("{:#}", e)))?;
+        // Never cached, so the first real frame replaces it on the next poll
+        return Ok((
+            [
+                (header::CONTENT_TYPE, "image/png"),
+                (header::CACHE_CONTROL, "no-store"),
+                (header::HeaderName::from_static("x-placeholder"), "true"),
+            ],
+            png,
+        )
+            .into_response());
+    };
+
+    let (id, timestamp) = (frame.id, frame.timestamp);
+    let (data, content_type) = match params.convert {
+        Some(target) => {
+            let (data, content_type, _) = convert_on_pool(&state, frame, target).await?;
+            (data, content_type)
+        }
+        None => {
+            let content_type = frame
+                .metadata
+                .get("content-type")
+                .cloned()
+                .unwrap_or_else(|| "image/png".to_string());
+            (frame.data, content_type)
+        }
+    };
+
+    axum::response::Response::builder()
+        .status(StatusCode::OK)
+        .extension(FramesServed(vec![id]))
+        .header(header::CONTENT_TYPE, content_type)
+        .header("x-frame-id", id.to_string())
+        .header("x-frame-timestamp", timestamp.to_rfc3339())
+        .body(axum::body::Body::from(data))
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+// GET /snapshot/wait
+
+const DEFAULT_WAIT_MS: u64 = 30_000;
+const MAX_WAIT_MS: u64 = 60_000;
+
+// Long-poll for the next frame: responds like /snapshot.png as soon as the
+// latest frame differs from `since` (so an agent restart that resets ids
+// still wakes waiters), or with 204 No Content once the timeout elapses.
+async fn snapshot_wait_handler(
+    State(state): State<AppState>,
+    Query(params): Query<WaitQuery>,
+) -> Result<Response, (StatusCode, String)> {
+    let timeout = std::time::Duration::from_millis(
+        params.timeout.unwrap_or(DEFAULT_WAIT_MS).min(MAX_WAIT_MS),
+    );
+    let mut latest = state.latest_frame.subscribe();
+    // The watch guard is not Send, so drop it before awaiting anything else
+    let arrived = tokio::time::timeout(
+        timeout,
+        latest.wait_for(|id| id.is_some() && *id != params.since),
+    )
+    .await
+    .is_ok_and(|r| r.is_ok());
+
+    if arrived {
+        snapshot_handler(State(state), Query(FrameQuery::default()))
+            .await
+            .map(IntoResponse::into_response)
+    } else {
+        Ok(StatusCode::NO_CONTENT.into_response())
+    }
+}
+
+// GET /view
+
+const MAX_VIEW_REFRESH_SECS: u64 = 3600;
+
+// Minimal no-JS viewer: the latest frame with its id and timestamp, reloaded
+// by a meta refresh. URLs are relative so the page works wherever the
+// server is mounted, e.g. behind a proxy prefix or under /t/<tenant>/.
+async fn view_handler(State(state): State<AppState>, Query(params): Query<ViewQuery>) -> Response {
+    let interval = state.config.read().await.interval;
+    let refresh = params
+        .refresh
+        .unwrap_or(interval.ceil() as u64)
+        .clamp(1, MAX_VIEW_REFRESH_SECS);
+
+    let body = match state.store.get_latest().await {
+        Ok(frame) => format!(
+            "<p>Frame #{id} &middot; {timestamp}</p>\n<img src=\"frames/{id}\" alt=\"Frame #{id}\">",
+            id = frame.id,
+            timestamp = frame.timestamp.to_rfc3339(),
+        ),
+        Err(_) => "<p>No frames yet</p>".to_string(),
+    };
+    let page = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+         <meta http-equiv=\"refresh\" content=\"{refresh}\">\n<title>Eye</title>\n\
+         <style>body{{margin:0;background:#111;color:#ccc;font:14px sans-serif}}p{{margin:8px}}img{{max-width:100%}}</style>\n\
+         </head>\n<body>\n{body}\n</body>\n</html>\n"
+    );
+
+    ([(header::CACHE_CONTROL, "no-store")], axum::response::Html(page)).into_response()
+}
+
+// GET /frames/latest
+
+// The latest frame's metadata as JSON, for clients that find a JSON wrapper
+// easier than raw bytes. With ?embed=1 the image is inlined as a base64
+// data URL; otherwise "url" points at /snapshot.png. A stale frame gets
+// the same 409 as /snapshot.png.
+async fn frames_latest_handler(
+    State(state): State<AppState>,
+    Query(params): Query<LatestQuery>,
+) -> Result<Response, (StatusCode, String)> {
+    use base64::Engine as _;
+
+    let latest = match max_frame_age(&state, params.max_age) {
+        Some(max_age) => state.store.get_latest_within(max_age, state.clock.now()).await,
+        None => state.store.get_latest().await,
+    };
+    if let Err(e) = &latest
+        && let Some(stale) = e.downcast_ref::<StaleLatest>()
+    {
+        return Ok(stale_frame_response(stale));
+    }
+    let frame = latest.map_err(|_| (StatusCode::NOT_FOUND, "No frames available".to_string()))?;
 
     let content_type = frame
         .metadata
         .get("content-type")
         .cloned()
         .unwrap_or_else(|| "image/png".to_string());
+    let format = frame
+        .metadata
+        .get("format")
+        .cloned()
+        .unwrap_or_else(|| "png".to_string());
 
-    axum::response::Response::builder()
-        .status(StatusCode::OK)
-        .header(header::CONTENT_TYPE, content_type)
-        .header("x-frame-id", frame.id.to_string())
-        .header("x-frame-timestamp", frame.timestamp.to_rfc3339())
-        .body(axum::body::Body::from(frame.data))
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+    let mut body = json!({
+        "id":             frame.id,
+        "timestamp":      frame.timestamp.to_rfc3339(),
+        "timestamp_unix": frame.timestamp.timestamp(),
+        "size_bytes":     frame.data.len(),
+        "format":         format,
+        "content_type":   content_type,
+        "metadata":       frame.metadata,
+    });
+
+    let embed = params
+        .embed
+        .is_some_and(|v| matches!(v.to_lowercase().as_str(), "1" | "true" | "yes"));
+    if embed {
+        body["data_url"] = json!(format!(
+            "data:{};base64,{}",
+            content_type,
+            base64::engine::general_purpose::STANDARD.encode(&frame.data)
+        ));
+    } else {
+        body["url"] = json!("/snapshot.png");
+    }
+
+    Ok(Json(body).into_response())
 }
 
 // GET /frames
@@ -362,51 +1963,200 @@ async fn frames_list_handler(State(state): State<AppState>) -> Json<serde_json::
     }))
 }
 
-// GET /frames/:id
+// GET /frames/:id/meta
 
-// Returns a single frame's raw image bytes.
-// Content-Type reflects the actual format the agent sent.
-// Content-Disposition carries a timestamp-based filename so curl / browsers
-// save it with a meaningful name automatically.
-async fn frame_by_id_handler(
+// Frame metadata without the image bytes. Monitor details recorded at
+// upload are also grouped under "monitor".
+async fn frame_meta_handler(
     State(state): State<AppState>,
     Path(id): Path<i64>,
-) -> Result<impl IntoResponse, (StatusCode, String)> {
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
     let frame = state
         .store
         .get_by_id(id)
         .await
         .map_err(|_| (StatusCode::NOT_FOUND, format!("Frame {} not found", id)))?;
 
-    let content_type = frame
+    let monitor: serde_json::Map<String, serde_json::Value> = frame
         .metadata
-        .get("content-type")
-        .cloned()
-        .unwrap_or_else(|| "image/png".to_string());
-
-    let format = frame
+        .iter()
+        .filter_map(|(k, v)| {
+            k.strip_prefix(MONITOR_FIELD_PREFIX)
+                .map(|key| (key.to_string(), json!(v)))
+        })
+        .collect();
+
+    Ok(Json(json!({
+        "id":             frame.id,
+        "timestamp":      frame.timestamp.to_rfc3339(),
+        "timestamp_unix": frame.timestamp.timestamp(),
+        "size_bytes":     frame.data.len(),
+        "metadata":       frame.metadata,
+        "monitor":        (!monitor.is_empty()).then_some(monitor),
+        "clipboard_sha256": frame.metadata.get(CLIPBOARD_HASH_FIELD),
+    })))
+}
+
+// GET /frames/:id/similar
+
+// Stored frames whose perceptual hash lies within ?threshold= bits of the
+// reference frame's, closest first. Searches every backend by metadata, so
+// frames only on disk or in sqlite are found too. Frames stored without a
+// hash (hashing off, or not decodable) are never matched.
+async fn frame_similar_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    Query(params): Query<SimilarQuery>,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    let threshold = params.threshold.unwrap_or(DEFAULT_SIMILARITY_THRESHOLD);
+    if threshold > similarity::MAX_DISTANCE {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("threshold must be at most {}", similarity::MAX_DISTANCE),
+        ));
+    }
+
+    let reference = state
+        .store
+        .get_by_id(id)
+        .await
+        .map_err(|_| (StatusCode::NOT_FOUND, format!("Frame {} not found", id)))?;
+    let reference_hash = frame_phash(&reference).ok_or((
+        StatusCode::UNPROCESSABLE_ENTITY,
+        format!("Frame {} has no perceptual hash (not a decodable image)", id),
+    ))?;
+
+    // Every frame timestamp falls between the epoch and year 9999
+    let end = DateTime::<Utc>::from_timestamp(253_402_300_799, 0).unwrap_or(DateTime::<Utc>::MAX_UTC);
+    let mut matches: Vec<(u32, FrameMeta)> = state
+        .store
+        .list_range_meta(DateTime::<Utc>::UNIX_EPOCH, end)
+        .await
+        .into_iter()
+        .filter(|f| f.id != id)
+        .filter_map(|f| {
+            let hash = similarity::from_hex(f.metadata.get(similarity::PHASH_FIELD)?)?;
+            Some((similarity::distance(reference_hash, hash), f))
+        })
+        .filter(|(distance, _)| *distance <= threshold)
+        .collect();
+    matches.sort_by_key(|(distance, f)| (*distance, f.id));
+
+    let frames: Vec<serde_json::Value> = matches
+        .iter()
+        .map(|(distance, f)| {
+            json!({
+                "id":        f.id,
+                "timestamp": f.timestamp.to_rfc3339(),
+                "distance":  distance,
+            })
+        })
+        .collect();
+
+    Ok(Json(json!({
+        "reference": id,
+        "threshold": threshold,
+        "count":     frames.len(),
+        "frames":    frames,
+    })))
+}
+
+// Perceptual hash recorded at upload, else computed from the image
+fn frame_phash(frame: &Frame) -> Option<u64> {
+    match frame.metadata.get(similarity::PHASH_FIELD) {
+        Some(hex) => similarity::from_hex(hex),
+        None => decode_frame(frame).ok().map(|img| similarity::dhash(&img)),
+    }
+}
+
+// GET /frames/:id
+
+// Returns a single frame's raw image bytes.
+// Content-Type reflects the actual format the agent sent.
+// Content-Disposition carries a timestamp-based filename so curl / browsers
+// save it with a meaningful name automatically.
+// Raw RGBA frames are served as application/octet-stream with their pixel
+// layout in X-Frame-Width/Height/Stride; ?convert=png re-encodes any frame.
+async fn frame_by_id_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    Query(params): Query<FrameQuery>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let frame = state
+        .store
+        .get_by_id(id)
+        .await
+        .map_err(|_| (StatusCode::NOT_FOUND, format!("Frame {} not found", id)))?;
+
+    let mut content_type = frame
+        .metadata
+        .get("content-type")
+        .cloned()
+        .unwrap_or_else(|| "image/png".to_string());
+
+    let mut format = frame
         .metadata
         .get("format")
         .cloned()
         .unwrap_or_else(|| "png".to_string());
 
+    let mut builder = axum::response::Response::builder()
+        .status(StatusCode::OK)
+        .extension(FramesServed(vec![frame.id]))
+        .header("x-frame-id", frame.id.to_string())
+        .header("x-frame-timestamp", frame.timestamp.to_rfc3339());
+
+    let timestamp = frame.timestamp;
+    let data = match params.convert {
+        Some(target) => {
+            let (data, converted_type, converted_format) = convert_on_pool(&state, frame, target).await?;
+            content_type = converted_type;
+            format = converted_format;
+            data
+        }
+        None => {
+            if format == RAW_FORMAT {
+                for key in ["width", "height", "stride"] {
+                    if let Some(value) = frame.metadata.get(key) {
+                        builder = builder.header(format!("x-frame-{}", key), value);
+                    }
+                }
+            }
+            frame.data
+        }
+    };
+
     // e.g. "frame_2025-03-01T14-32-10.123Z.png"
-    let ts = frame.timestamp.format("%Y-%m-%dT%H-%M-%S%.3fZ");
+    let ts = timestamp.format("%Y-%m-%dT%H-%M-%S%.3fZ");
     let filename = format!("frame_{}.{}", ts, format);
 
-    axum::response::Response::builder()
-        .status(StatusCode::OK)
+    builder
         .header(header::CONTENT_TYPE, content_type)
         .header(
             header::CONTENT_DISPOSITION,
             format!("attachment; filename=\"{}\"", filename),
         )
-        .header("x-frame-id", frame.id.to_string())
-        .header("x-frame-timestamp", frame.timestamp.to_rfc3339())
-        .body(axum::body::Body::from(frame.data))
+        .body(axum::body::Body::from(data))
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
 }
 
+// POST /frames/:id/unpin
+
+// Releases a frame pinned at upload time so it is evicted normally again.
+async fn frame_unpin_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    state
+        .store
+        .unpin(id)
+        .await
+        .map_err(|e| (StatusCode::NOT_FOUND, e.to_string()))?;
+
+    info!("Frame #{} unpinned", id);
+    Ok(Json(json!({ "status": "unpinned", "frame_id": id })))
+}
+
 // GET /frames/closest
 
 // Returns the single frame whose capture timestamp is closest to the requested
@@ -428,10 +2178,7 @@ async fn frames_closest_handler(
     // Find the frame with the smallest absolute time delta from target
     let closest = frames
         .into_iter()
-        .min_by_key(|f| {
-            let delta = (f.timestamp - target).num_milliseconds().abs();
-            delta
-        })
+        .min_by_key(|f| (f.timestamp - target).num_milliseconds().abs())
         .ok_or((StatusCode::NOT_FOUND, "No frames in buffer".to_string()))?;
 
     let content_type = closest
@@ -451,6 +2198,7 @@ async fn frames_closest_handler(
 
     axum::response::Response::builder()
         .status(StatusCode::OK)
+        .extension(FramesServed(vec![closest.id]))
         .header(header::CONTENT_TYPE, content_type)
         .header(
             header::CONTENT_DISPOSITION,
@@ -464,10 +2212,8 @@ async fn frames_closest_handler(
 
 // GET /frames/range
 
-// Returns all frames within [from, to] (Unix seconds) as a zip archive.
-// Each file inside the zip is named with its capture timestamp and format
-// extension. Stored compression is used since image formats are already
-// compressed — applying deflate on top would waste CPU with no size benefit.
+// Returns all frames within [from, to] (Unix seconds) as a zip archive
+// built by `frames_zip`.
 async fn frames_range_handler(
     State(state): State<AppState>,
     Query(params): Query<RangeQuery>,
@@ -497,37 +2243,7 @@ async fn frames_range_handler(
         ));
     }
 
-    // Build the zip in memory
-    let mut zip_buf: Vec<u8> = Vec::new();
-    {
-        let cursor = std::io::Cursor::new(&mut zip_buf);
-        let mut zip = zip::ZipWriter::new(cursor);
-
-        // Stored (no compression) — image data is already compressed
-        let options: zip::write::FileOptions<'_, ()> =
-            zip::write::FileOptions::default()
-                .compression_method(zip::CompressionMethod::Stored);
-
-        for frame in &frames {
-            let format = frame
-                .metadata
-                .get("format")
-                .cloned()
-                .unwrap_or_else(|| "png".to_string());
-
-            let ts = frame.timestamp.format("%Y-%m-%dT%H-%M-%S%.3fZ");
-            let filename = format!("frame_{}.{}", ts, format);
-
-            zip.start_file(&filename, options)
-                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-
-            zip.write_all(&frame.data)
-                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-        }
-
-        zip.finish()
-            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-    }
+    let zip_buf = frames_zip(&frames)?;
 
     let zip_filename = format!(
         "frames_{}_{}.zip",
@@ -544,6 +2260,7 @@ async fn frames_range_handler(
 
     axum::response::Response::builder()
         .status(StatusCode::OK)
+        .extension(FramesServed(frames.iter().map(|f| f.id).collect()))
         .header(header::CONTENT_TYPE, "application/zip")
         .header(
             header::CONTENT_DISPOSITION,
@@ -554,116 +2271,2946 @@ async fn frames_range_handler(
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
 }
 
-// Debug
+// POST /export
 
-async fn debug_handler(State(state): State<AppState>) -> Json<serde_json::Value> {
-    let uptime = state.start_time.elapsed().as_secs_f64();
-    let frames = state.store.list().await;
-    let config = state.config.read().await.clone();
-    let agent_connected = *state.agent_connected.read().await;
+// Build the zip for [from, to] once and hand back a token. The archive is
+// written to disk and is then downloadable — and resumable via Range
+// requests — until it expires. Requires the auth token when one is set.
+async fn export_create_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(params): Json<RangeQuery>,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    state.require_auth(&headers)?;
 
-    Json(json!({
-        "uptime_sec":      uptime,
-        "total_frames":    frames.len(),
-        "current_config":  config,
-        "agent_connected": agent_connected,
-    }))
-}
+    let from = DateTime::<Utc>::from_timestamp(params.from, 0)
+        .ok_or((StatusCode::BAD_REQUEST, "Invalid 'from' timestamp".to_string()))?;
+    let to = DateTime::<Utc>::from_timestamp(params.to, 0)
+        .ok_or((StatusCode::BAD_REQUEST, "Invalid 'to' timestamp".to_string()))?;
 
-// Main
+    if from > to {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "'from' must be before or equal to 'to'".to_string(),
+        ));
+    }
 
-#[tokio::main]
-async fn main() -> Result<()> {
-    let args: Vec<String> = env::args().collect();
-    if args.contains(&"--version".to_string()) {
-        const VERSION: &str = env!("CARGO_PKG_VERSION");
-        println!("eye-server v{}", VERSION);
-        return Ok(());
+    let slot = state
+        .exports
+        .reserve(state.clock.now())
+        .await
+        .map_err(export_error)?;
+
+    let frames = state.store.get_in_range(from, to).await;
+    if frames.is_empty() {
+        return Err((
+            StatusCode::NOT_FOUND,
+            format!(
+                "No frames found between {} and {}",
+                from.to_rfc3339(),
+                to.to_rfc3339()
+            ),
+        ));
     }
 
-    tracing_subscriber::fmt()
-        .with_env_filter(
-            tracing_subscriber::EnvFilter::from_default_env()
-                .add_directive(tracing::Level::INFO.into()),
-        )
-        .with_target(false)
-        .compact()
-        .init();
+    let data = frames_zip(&frames)?;
+    let size_bytes = data.len();
+    let filename = format!(
+        "frames_{}_{}.zip",
+        from.format("%Y-%m-%dT%H-%M-%SZ"),
+        to.format("%Y-%m-%dT%H-%M-%SZ")
+    );
+    let frame_ids = frames.iter().map(|f| f.id).collect();
+    let (token, expires_at) = state
+        .exports
+        .insert(state.clock.now(), slot, data, frame_ids, filename)
+        .await
+        .map_err(export_error)?;
 
-    let port = env::var("EYE_PORT").unwrap_or_else(|_| "8080".to_string());
-    let _auth_token = env::var("EYE_AUTH_TOKEN").ok();
+    info!("Export prepared: {} frames, {} bytes", frames.len(), size_bytes);
 
-    // Ring-buffer capacity — configurable via EYE_MAX_FRAMES, default 100
-    let max_frames: usize = env::var("EYE_MAX_FRAMES")
-        .ok()
-        .and_then(|v| v.parse().ok())
-        .unwrap_or(100);
+    Ok(Json(json!({
+        "token":       token,
+        "url":         format!("/export/{}", token),
+        "frame_count": frames.len(),
+        "size_bytes":  size_bytes,
+        "expires_at":  expires_at.to_rfc3339(),
+    })))
+}
 
-    info!("Ring buffer: {} frames max", max_frames);
+fn export_error(e: ExportError) -> (StatusCode, String) {
+    let status = match e {
+        ExportError::Busy => StatusCode::TOO_MANY_REQUESTS,
+        ExportError::TooLarge => StatusCode::PAYLOAD_TOO_LARGE,
+        ExportError::Io(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    (status, e.to_string())
+}
 
-    let state = AppState::new(max_frames);
+// GET /export/:token
 
-    let app = Router::new()
-        // Status
-        .route("/health",       get(health_handler))
-        .route("/debug",        get(debug_handler))
-        // 1:1 connection lifecycle
-        .route("/connect",      post(connect_handler))
-        .route("/disconnect",   post(disconnect_handler))
-        // Agent upload
-        .route("/upload",       post(upload_handler))
-        // Admin
-        .route("/admin/config", post(admin_config_handler))
-        // Image retrieval
-        // NOTE: /frames/range must be registered BEFORE /frames/:id so that
-        // Axum does not try to parse "range" as an integer frame ID.
-        .route("/snapshot.png",  get(snapshot_handler))
-        .route("/frames",        get(frames_list_handler))
-        .route("/frames/range",   get(frames_range_handler))
-        .route("/frames/closest", get(frames_closest_handler))
-        .route("/frames/:id",     get(frame_by_id_handler))
-        .layer(DefaultBodyLimit::max(50 * 1024 * 1024))
-        .layer(middleware::from_fn(logging_middleware))
-        .with_state(state);
+// Parse a single-range `Range: bytes=...` header against a body of `len`
+// bytes into an inclusive (start, end) pair. Ok(None) means serve the whole
+// body (no header, or a multi-range request); Err means unsatisfiable.
+fn parse_byte_range(headers: &HeaderMap, len: usize) -> Result<Option<(usize, usize)>, ()> {
+    let Some(spec) = headers
+        .get(header::RANGE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().strip_prefix("bytes="))
+    else {
+        return Ok(None);
+    };
+    if spec.contains(',') {
+        return Ok(None);
+    }
 
-    let addr = format!("0.0.0.0:{}", port);
-    info!("Eye Server starting on {} (1:1 agent mode)", addr);
+    let (start, end) = spec.split_once('-').ok_or(())?;
+    let (start, end) = match (start.trim(), end.trim()) {
+        // Suffix range: the last N bytes
+        ("", suffix) => {
+            let n: usize = suffix.parse().map_err(|_| ())?;
+            if n == 0 {
+                return Err(());
+            }
+            (len.saturating_sub(n), len.saturating_sub(1))
+        }
+        (start, "") => (start.parse().map_err(|_| ())?, len.saturating_sub(1)),
+        (start, end) => {
+            let end: usize = end.parse().map_err(|_| ())?;
+            (start.parse().map_err(|_| ())?, end.min(len.saturating_sub(1)))
+        }
+    };
 
-    let listener = tokio::net::TcpListener::bind(&addr)
+    if start >= len || start > end {
+        return Err(());
+    }
+
+    Ok(Some((start, end)))
+}
+
+// Serve a prepared export, honouring Range so interrupted downloads can
+// resume where they stopped. The archive is streamed from disk.
+async fn export_download_handler(
+    State(state): State<AppState>,
+    Path(token): Path<String>,
+    headers: HeaderMap,
+) -> Result<Response, (StatusCode, String)> {
+    state.require_auth(&headers)?;
+
+    let (path, len, frame_ids, filename) = state
+        .exports
+        .get(state.clock.now(), &token)
         .await
-        .context("Failed to bind server")?;
+        .ok_or((StatusCode::NOT_FOUND, "Unknown or expired export token".to_string()))?;
 
-    axum::serve(listener, app)
+    let builder = axum::response::Response::builder()
+        .extension(FramesServed(frame_ids))
+        .header(header::CONTENT_TYPE, "application/zip")
+        .header(header::ACCEPT_RANGES, "bytes")
+        .header(
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"{}\"", filename),
+        );
+
+    let (status, start, end) = match parse_byte_range(&headers, len as usize) {
+        Ok(None) => (StatusCode::OK, 0, len.saturating_sub(1)),
+        Ok(Some((start, end))) => (StatusCode::PARTIAL_CONTENT, start as u64, end as u64),
+        Err(()) => {
+            return builder
+                .status(StatusCode::RANGE_NOT_SATISFIABLE)
+                .header(header::CONTENT_RANGE, format!("bytes */{}", len))
+                .body(axum::body::Body::empty())
+                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()));
+        }
+    };
+
+    let body = export_file_body(&path, start, end - start + 1)
         .await
-        .context("Server error")?;
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to read export: {}", e)))?;
+    let builder = match status {
+        StatusCode::PARTIAL_CONTENT => builder.header(header::CONTENT_RANGE, format!("bytes {}-{}/{}", start, end, len)),
+        _ => builder,
+    };
 
-    Ok(())
+    builder
+        .status(status)
+        .body(body)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
 }
 
-// Tests
+// Stream `len` bytes of `path` from `start` in 64 KiB chunks
+async fn export_file_body(path: &std::path::Path, start: u64, len: u64) -> std::io::Result<axum::body::Body> {
+    use tokio::io::{AsyncReadExt, AsyncSeekExt};
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    let mut file = tokio::fs::File::open(path).await?;
+    file.seek(std::io::SeekFrom::Start(start)).await?;
+    let reader = file.take(len);
+    // The reader is dropped after an error so the stream ends there
+    let chunks = futures::stream::unfold(Some(reader), |reader| async move {
+        let mut reader = reader?;
+        let mut buf = vec![0u8; 64 * 1024];
+        match reader.read(&mut buf).await {
+            Ok(0) => None,
+            Ok(n) => {
+                buf.truncate(n);
+                Some((Ok::<_, std::io::Error>(buf), Some(reader)))
+            }
+            Err(e) => Some((Err(e), None)),
+        }
+    });
+    Ok(axum::body::Body::from_stream(chunks))
+}
 
-    #[test]
-    fn test_app_state_creation() {
-        let state = AppState::new(100);
-        assert!(state.start_time.elapsed().as_secs() < 1);
-    }
+// Pack frames into an in-memory zip archive. Each file is named with its
+// capture timestamp and format extension. Stored compression is used since
+// image formats are already compressed — applying deflate on top would
+// waste CPU with no size benefit.
+fn frames_zip(frames: &[Frame]) -> Result<Vec<u8>, (StatusCode, String)> {
+    let mut zip_buf: Vec<u8> = Vec::new();
+    {
+        let cursor = std::io::Cursor::new(&mut zip_buf);
+        let mut zip = zip::ZipWriter::new(cursor);
 
-    #[test]
-    fn test_default_config() {
-        let config = AgentConfig::default();
-        assert_eq!(config.interval, 1.0);
-        assert_eq!(config.format, "png");
-        assert_eq!(config.quality, 95);
-    }
+        let options: zip::write::FileOptions<'_, ()> =
+            zip::write::FileOptions::default()
+                .compression_method(zip::CompressionMethod::Stored);
 
-    #[tokio::test]
-    async fn test_agent_connected_initial_false() {
-        let state = AppState::new(100);
-        let connected = state.agent_connected.read().await;
-        assert!(!*connected);
+        for frame in frames {
+            let format = frame
+                .metadata
+                .get("format")
+                .cloned()
+                .unwrap_or_else(|| "png".to_string());
+
+            let ts = frame.timestamp.format("%Y-%m-%dT%H-%M-%S%.3fZ");
+            let filename = format!("frame_{}.{}", ts, format);
+
+            zip.start_file(&filename, options)
+                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+            zip.write_all(&frame.data)
+                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        }
+
+        zip.finish()
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    }
+
+    Ok(zip_buf)
+}
+
+// GET /animation.gif
+
+// Assemble frames into a looping animated GIF, each scaled to `width`
+// pixels wide so the result stays shareable. Each frame is shown for its
+// entry in `delays_ms`.
+fn encode_gif(frames: &[Frame], delays_ms: &[u32], width: u32) -> Result<Vec<u8>> {
+    use image::codecs::gif::{GifEncoder, Repeat};
+
+    let mut buffer = Vec::new();
+    {
+        let mut encoder = GifEncoder::new(&mut buffer);
+        encoder.set_repeat(Repeat::Infinite)?;
+
+        for (frame, &delay_ms) in frames.iter().zip(delays_ms) {
+            let delay = image::Delay::from_numer_denom_ms(delay_ms, 1);
+            let img = decode_frame(frame)?;
+            let height = (img.height() as u64 * width as u64 / img.width().max(1) as u64).max(1);
+            let img = img.resize_exact(width, height as u32, image::imageops::FilterType::Triangle);
+            encoder.encode_frame(image::Frame::from_parts(img.into_rgba8(), 0, 0, delay))?;
+        }
+    }
+
+    Ok(buffer)
+}
+
+// Variable-rate timelapse plan for `frames`: decodes each one to measure
+// how much it changed from the last frame kept
+fn adaptive_manifest(frames: &[Frame], base_delay_ms: u32) -> Result<Vec<timelapse::ManifestEntry>> {
+    let fingerprints = frames
+        .iter()
+        .map(|f| Ok((f.id, timelapse::fingerprint(&decode_frame(f)?))))
+        .collect::<Result<Vec<_>>>()?;
+    Ok(timelapse::plan(&fingerprints, base_delay_ms))
+}
+
+// Returns the frames within [from, to] (Unix seconds) as an animated GIF.
+// Query parameters: ?from=&to=&fps=&width=&speed=&manifest=
+async fn animation_gif_handler(
+    State(state): State<AppState>,
+    Query(params): Query<AnimationQuery>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let from = DateTime::<Utc>::from_timestamp(params.from, 0)
+        .ok_or((StatusCode::BAD_REQUEST, "Invalid 'from' timestamp".to_string()))?;
+    let to = DateTime::<Utc>::from_timestamp(params.to, 0)
+        .ok_or((StatusCode::BAD_REQUEST, "Invalid 'to' timestamp".to_string()))?;
+
+    let fps = params.fps.unwrap_or(2.0);
+    if !(fps > 0.0 && fps <= 50.0) {
+        return Err((StatusCode::BAD_REQUEST, "'fps' must be between 0 and 50".to_string()));
+    }
+    let width = params.width.unwrap_or(480).clamp(16, 1920);
+
+    let frames = state.store.get_in_range(from, to).await;
+    if frames.is_empty() {
+        return Err((
+            StatusCode::NOT_FOUND,
+            format!(
+                "No frames found between {} and {}",
+                from.to_rfc3339(),
+                to.to_rfc3339()
+            ),
+        ));
+    }
+
+    let adaptive = match params.speed.as_deref().map(str::to_lowercase).as_deref() {
+        None | Some("uniform") => false,
+        Some("adaptive") => true,
+        Some(other) => {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                format!("Unknown speed '{}': expected uniform or adaptive", other),
+            ));
+        }
+    };
+    let base_delay_ms = (1000.0 / fps).round() as u32;
+    let manifest_only = params.manifest.unwrap_or(false);
+
+    let (manifest, gif) = state
+        .transcoder
+        .run(move || -> Result<_> {
+            let manifest = if adaptive {
+                adaptive_manifest(&frames, base_delay_ms)?
+            } else {
+                frames
+                    .iter()
+                    .map(|f| timelapse::ManifestEntry { id: f.id, delay_ms: base_delay_ms, change: 1.0 })
+                    .collect()
+            };
+            if manifest_only {
+                return Ok((manifest, Vec::new()));
+            }
+
+            // Frames of one range can share an id after an agent restart, so
+            // pick them by position
+            let mut kept = Vec::with_capacity(manifest.len());
+            let mut rest = frames.iter();
+            for entry in &manifest {
+                if let Some(frame) = rest.by_ref().find(|f| f.id == entry.id) {
+                    kept.push(frame.clone());
+                }
+            }
+            let delays: Vec<u32> = manifest.iter().map(|e| e.delay_ms).collect();
+            let gif = encode_gif(&kept, &delays, width)?;
+            Ok((manifest, gif))
+        })
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let frame_count = manifest.len();
+    if manifest_only {
+        let duration_ms: u64 = manifest.iter().map(|e| e.delay_ms as u64).sum();
+        return Ok(Json(json!({
+            "speed":       if adaptive { "adaptive" } else { "uniform" },
+            "frame_count": frame_count,
+            "duration_ms": duration_ms,
+            "frames":      manifest,
+        }))
+        .into_response());
+    }
+
+    info!(
+        "Animation: {} frames ({}) at {} fps, {}px wide",
+        frame_count,
+        if adaptive { "adaptive" } else { "uniform" },
+        fps,
+        width
+    );
+
+    axum::response::Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, "image/gif")
+        .header("x-frame-count", frame_count.to_string())
+        .body(axum::body::Body::from(gif))
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+// Debug
+
+// Returns JSON by default. Clients sending `Accept: text/plain` (ahead of
+// any JSON preference) get the same data as an aligned plain-text table.
+async fn debug_handler(State(state): State<AppState>, headers: HeaderMap) -> Response {
+    let uptime = state.start_time.elapsed().as_secs_f64();
+    let frames = state.store.list().await;
+    let config = state.config.read().await.clone();
+    let agent_connected = *state.agent_connected.read().await;
+    let storage = state.store.stats().await;
+
+    if !prefers_plain_text(&headers) {
+        return Json(json!({
+            "uptime_sec":      uptime,
+            "total_frames":    frames.len(),
+            "current_config":  config,
+            "agent_connected": agent_connected,
+            "transcode":       state.transcoder.stats(),
+            "storage":         storage,
+        }))
+        .into_response();
+    }
+
+    let rows = [
+        ("uptime_sec", format!("{:.2}", uptime)),
+        ("total_frames", frames.len().to_string()),
+        ("agent_connected", agent_connected.to_string()),
+        ("interval", format!("{:.1}s", config.interval)),
+        ("format", config.format.clone()),
+        ("quality", config.quality.to_string()),
+        ("transcode_queue", state.transcoder.stats().queued.to_string()),
+        ("stored_bytes", storage.total_bytes.to_string()),
+    ];
+
+    let mut body = String::from("Eye Server Debug\n");
+    for (key, value) in rows {
+        body.push_str(&format!("{:<17}{}\n", key, value));
+    }
+
+    ([(header::CONTENT_TYPE, "text/plain; charset=utf-8")], body).into_response()
+}
+
+// True when the Accept header lists text/plain before any JSON media type
+fn prefers_plain_text(headers: &HeaderMap) -> bool {
+    let Some(accept) = headers.get(header::ACCEPT).and_then(|v| v.to_str().ok()) else {
+        return false;
+    };
+
+    for media in accept.split(',') {
+        let media = media.split(';').next().unwrap_or("").trim();
+        match media {
+            "text/plain" => return true,
+            "application/json" | "*/*" => return false,
+            _ => {}
+        }
+    }
+
+    false
+}
+
+// Config file and signals
+
+// Write the agent config as pretty-printed JSON
+async fn save_config(path: &std::path::Path, config: &AgentConfig) -> Result<()> {
+    let json = serde_json::to_vec_pretty(config)?;
+    tokio::fs::write(path, json)
+        .await
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+// Re-read the config file into the live config. Agents pick the change up
+// on their next upload. A missing or invalid file leaves the config as is.
+async fn reload_config(state: &AppState) -> Result<AgentConfig> {
+    let path = state
+        .config_path
+        .as_ref()
+        .context("No config file configured (EYE_CONFIG_FILE)")?;
+
+    let bytes = tokio::fs::read(path)
+        .await
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    let loaded: AgentConfig = serde_json::from_slice(&bytes)
+        .with_context(|| format!("Invalid config in {}", path.display()))?;
+    let loaded = loaded
+        .validated()
+        .map_err(|e| anyhow::anyhow!("Invalid config in {}: {}", path.display(), e))?;
+
+    let mut config = state.config.write().await;
+    *config = loaded;
+    Ok(config.clone())
+}
+
+// Resolves when the server should stop: Ctrl-C or SIGTERM. SIGHUP reloads
+// the config file in place and keeps serving.
+async fn shutdown_signal(states: Vec<AppState>) {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{SignalKind, signal};
+
+        let (Ok(mut term), Ok(mut hup)) = (signal(SignalKind::terminate()), signal(SignalKind::hangup()))
+        else {
+            warn!("Failed to install signal handlers — only Ctrl-C will stop the server");
+            let _ = tokio::signal::ctrl_c().await;
+            return;
+        };
+
+        loop {
+            tokio::select! {
+                _ = tokio::signal::ctrl_c() => break,
+                _ = term.recv() => break,
+                _ = hup.recv() => {
+                    for state in &states {
+                        match reload_config(state).await {
+                            Ok(config) => info!("SIGHUP — config reloaded: {:?}", config),
+                            Err(e) => warn!("SIGHUP — config reload failed: {:#}", e),
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = states;
+        let _ = tokio::signal::ctrl_c().await;
+    }
+
+    info!("Shutdown signal received — draining connections");
+}
+
+// Router
+
+fn router(state: AppState) -> Router {
+    let compression = compression_layer(state.compression);
+    let concurrency = middleware::from_fn_with_state(state.clone(), concurrency_middleware);
+    let access_log = middleware::from_fn_with_state(state.clone(), access_log_middleware);
+    let presign = middleware::from_fn_with_state(state.clone(), presign_middleware);
+
+    Router::new()
+        // Status
+        .route("/health",       get(health_handler))
+        .route("/version",      get(version_handler))
+        .route("/debug",        get(debug_handler))
+        // 1:1 connection lifecycle
+        .route("/connect",      post(connect_handler))
+        .route("/disconnect",   post(disconnect_handler))
+        // Agent upload
+        .route("/upload",       post(upload_handler))
+        // Admin
+        .route("/admin/config", post(admin_config_handler))
+        .route("/admin/reset",  post(admin_reset_handler))
+        .route("/admin/logs",   get(admin_logs_handler))
+        .route("/admin/chain",  get(admin_chain_handler))
+        .route("/admin/flush",  post(admin_flush_handler))
+        .route("/admin/access-log", get(admin_access_log_handler))
+        .route("/admin/timings", get(admin_timings_handler))
+        .route("/admin/presign", post(admin_presign_handler))
+        // Image retrieval
+        // NOTE: /frames/latest, /frames/range and /frames/closest must be
+        // registered BEFORE /frames/:id so that Axum does not try to parse
+        // them as integer frame IDs.
+        .route("/snapshot.png",  get(snapshot_handler))
+        .route("/snapshot/wait", get(snapshot_wait_handler))
+        .route("/view",          get(view_handler))
+        .route("/animation.gif", get(animation_gif_handler))
+        .route("/frames",        get(frames_list_handler))
+        .route("/frames/latest",  get(frames_latest_handler))
+        .route("/frames/range",   get(frames_range_handler))
+        .route("/frames/closest", get(frames_closest_handler))
+        .route("/frames/:id",     get(frame_by_id_handler))
+        .route("/frames/:id/meta",  get(frame_meta_handler))
+        .route("/frames/:id/unpin", post(frame_unpin_handler))
+        .route("/frames/:id/similar", get(frame_similar_handler))
+        // Resumable exports
+        .route("/export",        post(export_create_handler))
+        .route("/export/:token", get(export_download_handler))
+        .layer(DefaultBodyLimit::max(state.max_upload_bytes))
+        .layer(presign)
+        .layer(access_log)
+        .layer(compression)
+        .layer(concurrency)
+        .layer(middleware::from_fn(logging_middleware))
+        .with_state(state)
+}
+
+// Main
+
+// Access log kept in memory, and appended to `path` when given
+fn open_access_log(path: Option<&std::path::Path>) -> Result<AccessLog> {
+    match path {
+        Some(path) => {
+            info!("Access log: {}", path.display());
+            AccessLog::default().with_file(path)
+        }
+        None => Ok(AccessLog::default()),
+    }
+}
+
+// Storage settings read from the environment, applied to every store the
+// server opens (one per tenant in multi-tenant mode)
+struct StoreSettings {
+    mode: StorageMode,
+    max_frames: usize,
+    max_memory_bytes: Option<usize>,
+    frame_ttl: Option<std::time::Duration>,
+    max_pinned: usize,
+    reorder_window: usize,
+    hash_chain: bool,
+    dedup: bool,
+    batching: Option<WriteBatching>,
+    memory_fallback: bool,
+    /// Deltas recorded between full keyframes on disk; None writes every
+    /// frame in full
+    delta_keyframes: Option<usize>,
+    /// Check disk frames against their stored checksums on read
+    verify_on_read: bool,
+}
+
+impl StoreSettings {
+    async fn build(&self, disk_path: Option<PathBuf>) -> Result<Manager> {
+        let mut store = Manager::new(self.mode.clone(), self.max_frames, disk_path)
+            .await
+            .context("Failed to initialise storage")?
+            .with_max_pinned(self.max_pinned)
+            .with_reorder_window(self.reorder_window)
+            .with_memory_fallback(self.memory_fallback)
+            .with_disk_verification(self.verify_on_read);
+        if let Some(max_bytes) = self.max_memory_bytes {
+            store = store.with_memory_byte_limit(max_bytes);
+        }
+        if let Some(ttl) = self.frame_ttl {
+            store = store.with_memory_ttl(ttl);
+        }
+        if self.hash_chain {
+            store = store.with_hash_chain();
+        }
+        if self.dedup {
+            store = store.with_dedup();
+        }
+        if let Some(keyframe_every) = self.delta_keyframes {
+            store = store.with_delta_encoding(keyframe_every);
+        }
+        if let Some(batching) = self.batching {
+            store = store.with_write_batching(batching);
+        }
+        Ok(store)
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let args: Vec<String> = env::args().collect();
+    if args.contains(&"--version".to_string()) {
+        const VERSION: &str = env!("CARGO_PKG_VERSION");
+        println!("eye-server v{}", VERSION);
+        return Ok(());
+    }
+
+    // Log lines go to stdout and to any GET /admin/logs subscribers
+    let (logs, _) = tokio::sync::broadcast::channel(LOG_CHANNEL_CAPACITY);
+    {
+        use tracing_subscriber::prelude::*;
+
+        tracing_subscriber::registry()
+            .with(
+                tracing_subscriber::EnvFilter::from_default_env()
+                    .add_directive(tracing::Level::INFO.into()),
+            )
+            .with(tracing_subscriber::fmt::layer().with_target(false).compact())
+            .with(BroadcastLayer::new(logs.clone()))
+            .init();
+    }
+
+    let port = env::var("EYE_PORT").unwrap_or_else(|_| "8080".to_string());
+    let auth_token = env::var("EYE_AUTH_TOKEN").ok();
+
+    // Ring-buffer capacity — configurable via EYE_MAX_FRAMES, default 100
+    let max_frames: usize = env::var("EYE_MAX_FRAMES")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(100);
+
+    // Pinned frames sit outside the ring buffer under their own cap
+    let max_pinned: usize = env::var("EYE_MAX_PINNED")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(max_frames);
+
+    info!("Ring buffer: {} frames max ({} pinned)", max_frames, max_pinned);
+
+    // Byte cap on the frames held in memory, for streams whose frame sizes
+    // vary too much for a frame count to bound memory (EYE_MAX_MEMORY_MB)
+    let max_memory_bytes: Option<usize> = env::var("EYE_MAX_MEMORY_MB")
+        .ok()
+        .and_then(|v| v.parse::<usize>().ok())
+        .map(|mb| mb * 1024 * 1024);
+    if let Some(max_bytes) = max_memory_bytes {
+        info!("Ring buffer: {} bytes max", max_bytes);
+    }
+
+    // Frames in memory expire once older than EYE_FRAME_TTL, e.g. "10m"
+    let frame_ttl = match env::var("EYE_FRAME_TTL") {
+        Ok(spec) => {
+            let ttl = clock::parse_interval(&spec, std::time::Duration::from_secs(1)).context("Invalid EYE_FRAME_TTL")?;
+            info!("Frames expire after {:?}", ttl);
+            Some(ttl)
+        }
+        Err(_) => None,
+    };
+
+    // Late frames within the newest EYE_REORDER_WINDOW are put back in id
+    // order so /snapshot.png always shows the highest id (0 disables)
+    let reorder_window: usize = env::var("EYE_REORDER_WINDOW")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(storage::DEFAULT_REORDER_WINDOW);
+
+    // Storage backend — EYE_STORAGE_MODE=memory|disk|hybrid|sqlite (default
+    // memory). Disk and hybrid modes persist frames under EYE_STORAGE_PATH;
+    // sqlite mode keeps them in the database file EYE_STORAGE_PATH names.
+    let mode: StorageMode = env::var("EYE_STORAGE_MODE")
+        .unwrap_or_else(|_| "memory".to_string())
+        .parse()
+        .context("Invalid EYE_STORAGE_MODE")?;
+    let disk_path = env::var("EYE_STORAGE_PATH").ok().map(PathBuf::from);
+
+    info!("Storage mode: {}", mode.as_str());
+
+    // Optional disk write batching: EYE_DISK_BATCH_FRAMES frames or
+    // EYE_DISK_BATCH_MS milliseconds, whichever fills first
+    let batch_frames: Option<usize> = env::var("EYE_DISK_BATCH_FRAMES")
+        .ok()
+        .and_then(|v| v.parse().ok());
+    let batch_ms: Option<u64> = env::var("EYE_DISK_BATCH_MS")
+        .ok()
+        .and_then(|v| v.parse().ok());
+
+    let mut store_settings = StoreSettings {
+        mode,
+        max_frames,
+        max_memory_bytes,
+        frame_ttl,
+        max_pinned,
+        reorder_window,
+        hash_chain: false,
+        dedup: false,
+        batching: None,
+        memory_fallback: true,
+        delta_keyframes: None,
+        verify_on_read: true,
+    };
+
+    // Tamper-evident hash chain over stored frames (EYE_HASH_CHAIN=1),
+    // checked by GET /admin/chain
+    if env::var("EYE_HASH_CHAIN")
+        .map(|v| matches!(v.to_lowercase().as_str(), "1" | "true" | "yes"))
+        .unwrap_or(false)
+    {
+        info!("Frame hash chain enabled");
+        store_settings.hash_chain = true;
+    }
+
+    // Store an upload identical to the latest frame as a reference to its
+    // bytes rather than a second copy (EYE_DEDUP_UPLOADS=1)
+    if env::var("EYE_DEDUP_UPLOADS")
+        .map(|v| matches!(v.to_lowercase().as_str(), "1" | "true" | "yes"))
+        .unwrap_or(false)
+    {
+        info!("Duplicate upload collapsing enabled");
+        store_settings.dedup = true;
+    }
+
+    // Record disk frames as a full keyframe followed by up to N deltas
+    // against it (EYE_DISK_DELTA_KEYFRAMES=N, 0 = off); reads rebuild full
+    // frames
+    let delta_keyframes: Option<usize> = env::var("EYE_DISK_DELTA_KEYFRAMES")
+        .ok()
+        .map(|v| v.trim().parse())
+        .transpose()
+        .context("Invalid EYE_DISK_DELTA_KEYFRAMES")?;
+    if let Some(keyframe_every) = delta_keyframes.filter(|&n| n > 0) {
+        info!("Disk delta encoding: a keyframe every {} frames", keyframe_every + 1);
+        store_settings.delta_keyframes = Some(keyframe_every);
+    }
+
+    if batch_frames.is_some() || batch_ms.is_some() {
+        let batching = WriteBatching {
+            max_frames: batch_frames.unwrap_or(32),
+            max_delay: std::time::Duration::from_millis(batch_ms.unwrap_or(500)),
+        };
+        info!("Disk write batching: {} frames / {:?}", batching.max_frames, batching.max_delay);
+        store_settings.batching = Some(batching);
+    }
+
+    // Hybrid mode keeps taking frames in memory when the disk fills up —
+    // on by default, EYE_DISK_FULL_FALLBACK=off fails those uploads instead
+    store_settings.memory_fallback = env::var("EYE_DISK_FULL_FALLBACK")
+        .map(|v| !matches!(v.to_lowercase().as_str(), "0" | "false" | "off" | "no"))
+        .unwrap_or(true);
+    // Batched writes fail after the upload has returned, so there is no
+    // upload left to fail
+    if !store_settings.memory_fallback && store_settings.batching.is_some() {
+        anyhow::bail!("EYE_DISK_FULL_FALLBACK=off can't be combined with EYE_DISK_BATCH_FRAMES/EYE_DISK_BATCH_MS");
+    }
+
+    // Disk reads fail on frames whose bytes no longer match their stored
+    // CRC32 — on by default, EYE_DISK_VERIFY=off skips the check
+    store_settings.verify_on_read = env::var("EYE_DISK_VERIFY")
+        .map(|v| !matches!(v.to_lowercase().as_str(), "0" | "false" | "off" | "no"))
+        .unwrap_or(true);
+
+    // Gzip/brotli for JSON/text responses — on by default, EYE_COMPRESSION=off disables
+    let compression = env::var("EYE_COMPRESSION")
+        .map(|v| !matches!(v.to_lowercase().as_str(), "0" | "false" | "off" | "no"))
+        .unwrap_or(true);
+
+    info!("Response compression: {}", if compression { "gzip, br" } else { "off" });
+
+    // Requests in flight at once — EYE_MAX_CONCURRENT, unlimited when unset
+    let max_concurrent: Option<usize> = env::var("EYE_MAX_CONCURRENT")
+        .ok()
+        .and_then(|v| v.parse().ok());
+
+    if let Some(limit) = max_concurrent {
+        info!("Concurrency limit: {} requests", limit);
+    }
+
+    // Agent config persisted to EYE_CONFIG_FILE; reloaded on SIGHUP
+    let config_path = env::var("EYE_CONFIG_FILE").ok().map(PathBuf::from);
+
+    // Transcode every upload to one format, e.g. EYE_CANONICAL_FORMAT=webp
+    let canonical_format = match env::var("EYE_CANONICAL_FORMAT") {
+        Ok(f) if !f.is_empty() => Some(
+            image::ImageFormat::from_extension(f.to_lowercase())
+                .with_context(|| format!("Unsupported EYE_CANONICAL_FORMAT '{}'", f))?,
+        ),
+        _ => None,
+    };
+    if let Some(format) = canonical_format {
+        info!("Canonical frame format: {:?}", format);
+    }
+
+    // EYE_PERCEPTUAL_HASH=1 hashes every upload for GET /frames/:id/similar,
+    // at the cost of decoding it (default: off)
+    let perceptual_hash = env::var("EYE_PERCEPTUAL_HASH")
+        .map(|v| matches!(v.to_lowercase().as_str(), "1" | "true" | "yes"))
+        .unwrap_or(false);
+
+    // Field names accepted for the image and frame id, e.g. for clients
+    // that post "screenshot" instead of "image"
+    let mut upload_fields = UploadFields::default();
+    if let Ok(list) = env::var("EYE_UPLOAD_IMAGE_FIELDS") {
+        upload_fields.image = UploadFields::parse_list(&list);
+    }
+    if let Ok(list) = env::var("EYE_UPLOAD_ID_FIELDS") {
+        upload_fields.frame_id = UploadFields::parse_list(&list);
+    }
+    if upload_fields.image.is_empty() || upload_fields.frame_id.is_empty() {
+        anyhow::bail!("EYE_UPLOAD_IMAGE_FIELDS and EYE_UPLOAD_ID_FIELDS must name at least one field");
+    }
+
+    // Placeholder image for /snapshot.png while no frames exist
+    // (EYE_PLACEHOLDER=1), with optional EYE_PLACEHOLDER_TEXT,
+    // EYE_PLACEHOLDER_COLOR (#rrggbb background) and EYE_PLACEHOLDER_SIZE (WxH)
+    let mut placeholder = Placeholder {
+        enabled: env::var("EYE_PLACEHOLDER")
+            .map(|v| matches!(v.to_lowercase().as_str(), "1" | "true" | "yes"))
+            .unwrap_or(false),
+        ..Placeholder::default()
+    };
+    if let Ok(text) = env::var("EYE_PLACEHOLDER_TEXT") {
+        placeholder.text = text;
+    }
+    if let Ok(color) = env::var("EYE_PLACEHOLDER_COLOR") {
+        placeholder.background =
+            Placeholder::parse_color(&color).context("Invalid EYE_PLACEHOLDER_COLOR")?;
+    }
+    if let Ok(size) = env::var("EYE_PLACEHOLDER_SIZE") {
+        (placeholder.width, placeholder.height) =
+            Placeholder::parse_size(&size).context("Invalid EYE_PLACEHOLDER_SIZE")?;
+    }
+
+    // Request body limit in MiB, e.g. EYE_MAX_UPLOAD_MB=100 (default 50)
+    let max_upload_bytes = match env::var("EYE_MAX_UPLOAD_MB") {
+        Ok(mb) => mb.parse::<usize>().context("Invalid EYE_MAX_UPLOAD_MB")? * 1024 * 1024,
+        Err(_) => DEFAULT_MAX_UPLOAD_BYTES,
+    };
+
+    // Concurrent image transcodes, e.g. EYE_TRANSCODE_WORKERS=2 (default: one
+    // per CPU); further jobs queue
+    let transcode_workers = match env::var("EYE_TRANSCODE_WORKERS") {
+        Ok(n) => n.parse().context("Invalid EYE_TRANSCODE_WORKERS")?,
+        Err(_) => TranscodePool::default_workers(),
+    };
+    info!("Transcode workers: {}", transcode_workers);
+
+    // Reject uploads timestamped more than this many seconds behind the
+    // latest frame or ahead of the server clock, e.g. EYE_MAX_FRAME_SKEW=300
+    // (default: accept any time)
+    let max_frame_skew = match env::var("EYE_MAX_FRAME_SKEW") {
+        Ok(secs) => Some(chrono::Duration::seconds(
+            secs.parse().context("Invalid EYE_MAX_FRAME_SKEW")?,
+        )),
+        Err(_) => None,
+    };
+
+    // Default freshness limit for the latest frame, e.g. EYE_MAX_FRAME_AGE=60
+    // makes /snapshot.png answer 409 once the agent has been quiet for a
+    // minute (default: serve the latest frame however old)
+    let max_frame_age = match env::var("EYE_MAX_FRAME_AGE") {
+        Ok(secs) => Some(chrono::Duration::seconds(
+            secs.parse().context("Invalid EYE_MAX_FRAME_AGE")?,
+        )),
+        Err(_) => None,
+    };
+
+    // EYE_ID_NAMESPACES=1 gives every agent connection its own frame id
+    // namespace, so ids stay unique across agents, tenants and agent
+    // restarts (default: store agent ids as sent)
+    let id_namespaces = env::var("EYE_ID_NAMESPACES")
+        .map(|v| matches!(v.to_lowercase().as_str(), "1" | "true" | "yes"))
+        .unwrap_or(false)
+        .then(|| Arc::new(IdNamespaces::default()));
+
+    // Append-only record of frame retrievals, e.g.
+    // EYE_ACCESS_LOG=/var/log/eye/access.jsonl (default: memory only)
+    let access_log_path = env::var("EYE_ACCESS_LOG").ok().map(PathBuf::from);
+
+    // Prepared exports are written under EYE_EXPORT_DIR (default: the
+    // system temp dir), e.g. EYE_MAX_EXPORTS=4 held at once (building or
+    // downloadable) taking at most EYE_MAX_EXPORT_MB=1024 between them.
+    // Each tenant gets its own subdirectory and limits.
+    let export_dir = env::var("EYE_EXPORT_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| std::env::temp_dir().join("eye-exports"));
+    let max_exports = match env::var("EYE_MAX_EXPORTS") {
+        Ok(n) => n.parse().context("Invalid EYE_MAX_EXPORTS")?,
+        Err(_) => exports::DEFAULT_MAX_EXPORTS,
+    };
+    let max_export_bytes = match env::var("EYE_MAX_EXPORT_MB") {
+        Ok(mb) => mb.parse::<u64>().context("Invalid EYE_MAX_EXPORT_MB")? * 1024 * 1024,
+        Err(_) => exports::DEFAULT_MAX_EXPORT_BYTES,
+    };
+    let open_exports = |dir: PathBuf| -> Result<Exports> {
+        let exports = Exports::new(dir, max_exports, max_export_bytes);
+        exports.clear_stale()?;
+        info!("Exports: {}", exports.dir().display());
+        Ok(exports)
+    };
+
+    let health_check_interval = match env::var("EYE_HEALTH_CHECK_SECS") {
+        Ok(secs) => std::time::Duration::from_secs(secs.parse().context("Invalid EYE_HEALTH_CHECK_SECS")?),
+        Err(_) => HEALTH_CHECK_INTERVAL,
+    };
+
+    // Shared by every store's state; the transcoder and concurrency limit
+    // are server-wide
+    let transcoder = Arc::new(TranscodePool::new(transcode_workers));
+    let configure = |store: Manager, token: Option<String>, config_path: Option<PathBuf>, logs, access_log, exports| {
+        AppState::new(store)
+            .with_access_log(access_log)
+            .with_exports(exports)
+            .with_log_channel(logs)
+            .with_transcoder(transcoder.clone())
+            .with_max_upload_bytes(max_upload_bytes)
+            .with_max_frame_skew(max_frame_skew)
+            .with_max_frame_age(max_frame_age)
+            .with_id_namespaces(id_namespaces.clone())
+            .with_placeholder(placeholder.clone())
+            .with_upload_fields(upload_fields.clone())
+            .with_auth_token(token)
+            .with_canonical_format(canonical_format)
+            .with_perceptual_hash(perceptual_hash)
+            .with_health_check_interval(health_check_interval)
+            .with_compression(compression)
+            .with_max_concurrent(max_concurrent)
+            .with_config_path(config_path)
+    };
+
+    // Multi-tenant mode: EYE_TENANTS="alpha=key-a,beta=key-b" gives each
+    // tenant its own store (under EYE_STORAGE_PATH/<tenant>), agent config
+    // (EYE_CONFIG_FILE with .<tenant>.json), access log (EYE_ACCESS_LOG
+    // with .<tenant>.jsonl) and agent slot. A tenant's key is its auth
+    // token and is required on every request to it.
+    let tenant_keys = match env::var("EYE_TENANTS") {
+        Ok(spec) => Tenants::parse(&spec).context("Invalid EYE_TENANTS")?,
+        Err(_) => Vec::new(),
+    };
+
+    let mut states = Vec::new();
+    let app = if tenant_keys.is_empty() {
+        let store = store_settings.build(disk_path).await?;
+        let access_log = open_access_log(access_log_path.as_deref())?;
+        let exports = open_exports(export_dir.clone())?;
+        let state = configure(store, auth_token, config_path, logs, access_log, exports);
+        states.push(state.clone());
+        router(state)
+    } else {
+        if auth_token.is_some() {
+            warn!("EYE_AUTH_TOKEN is ignored in multi-tenant mode; tenant keys authenticate instead");
+        }
+        let mut tenants = Tenants::default();
+        for (name, key) in tenant_keys {
+            let store = store_settings.build(disk_path.as_ref().map(|p| p.join(&name))).await?;
+            let config_path = config_path.as_ref().map(|p| p.with_extension(format!("{}.json", name)));
+            // Server logs span every tenant, so tenants don't get them
+            let (logs, _) = tokio::sync::broadcast::channel(LOG_CHANNEL_CAPACITY);
+            let access_log_path = access_log_path.as_ref().map(|p| p.with_extension(format!("{}.jsonl", name)));
+            let access_log = open_access_log(access_log_path.as_deref())?;
+            let exports = open_exports(export_dir.join(&name))?;
+            let state = configure(store, Some(key.clone()), config_path, logs, access_log, exports);
+            states.push(state.clone());
+            tenants.add(name, key, router(state));
+        }
+        info!("Multi-tenant mode: {} tenants", states.len());
+        tenants::router(tenants)
+    };
+
+    for state in &states {
+        let Some(path) = &state.config_path else {
+            continue;
+        };
+        if path.exists() {
+            let config = reload_config(state).await?;
+            info!("Config loaded from {}: {:?}", path.display(), config);
+        } else {
+            info!("Config file {} not found — using defaults", path.display());
+        }
+    }
+
+    // Delete expired exports even when nobody asks for them again
+    for state in &states {
+        let state = state.clone();
+        tokio::spawn(async move {
+            let mut sweep = tokio::time::interval(std::time::Duration::from_secs(60));
+            loop {
+                sweep.tick().await;
+                state.exports.expire(state.clock.now()).await;
+            }
+        });
+    }
+
+    let addr = format!("0.0.0.0:{}", port);
+    info!("Eye Server starting on {} (1:1 agent mode)", addr);
+
+    let listener = tokio::net::TcpListener::bind(&addr)
+        .await
+        .context("Failed to bind server")?;
+
+    axum::serve(listener, app)
+        .with_graceful_shutdown(shutdown_signal(states.clone()))
+        .await
+        .context("Server error")?;
+
+    for state in &states {
+        state
+            .store
+            .flush()
+            .await
+            .context("Failed to flush pending disk writes")?;
+    }
+
+    Ok(())
+}
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_app_state_creation() {
+        let state = AppState::new(Manager::in_memory(100));
+        assert!(state.start_time.elapsed().as_secs() < 1);
+    }
+
+    #[test]
+    fn test_default_config() {
+        let config = AgentConfig::default();
+        assert_eq!(config.interval, 1.0);
+        assert_eq!(config.format, "webp");
+        assert_eq!(config.quality, 95);
+    }
+
+    #[tokio::test]
+    async fn test_agent_connected_initial_false() {
+        let state = AppState::new(Manager::in_memory(100));
+        let connected = state.agent_connected.read().await;
+        assert!(!*connected);
+    }
+
+    // Build a multipart upload request for the given frame id
+    // Stand-in frame bytes: a PNG signature, so uploads pass the header
+    // check, without a decodable image behind it
+    const PNG_BYTES: &[u8] = b"\x89PNG\r\n\x1a\nPNGDATA";
+
+    // `head`, then PNG_BYTES, then `tail`
+    fn with_png(head: String, tail: String) -> Vec<u8> {
+        [head.as_bytes(), PNG_BYTES, tail.as_bytes()].concat()
+    }
+
+    fn upload_request(frame_id: i64, token: Option<&str>) -> axum::http::Request<axum::body::Body> {
+        let boundary = "eye-test-boundary";
+        let body = with_png(
+            format!("--{}\r\nContent-Disposition: form-data; name=\"image\"; filename=\"frame.png\"\r\n\r\n", boundary),
+            format!(
+                "\r\n--{b}\r\nContent-Disposition: form-data; name=\"frame_id\"\r\n\r\n{id}\r\n--{b}--\r\n",
+                b = boundary,
+                id = frame_id,
+            ),
+        );
+
+        let mut builder = axum::http::Request::builder()
+            .method("POST")
+            .uri("/upload")
+            .header(
+                header::CONTENT_TYPE,
+                format!("multipart/form-data; boundary={}", boundary),
+            );
+        if let Some(token) = token {
+            builder = builder.header(header::AUTHORIZATION, format!("Bearer {}", token));
+        }
+        builder.body(axum::body::Body::from(body)).unwrap()
+    }
+
+    // Multipart upload carrying real image bytes in `format`
+    fn image_upload_request(
+        frame_id: i64,
+        format: &str,
+        image: &[u8],
+    ) -> axum::http::Request<axum::body::Body> {
+        let boundary = "eye-test-boundary";
+        let mut body = format!(
+            "--{b}\r\nContent-Disposition: form-data; name=\"frame_id\"\r\n\r\n{id}\r\n\
+             --{b}\r\nContent-Disposition: form-data; name=\"format\"\r\n\r\n{format}\r\n\
+             --{b}\r\nContent-Disposition: form-data; name=\"image\"; filename=\"frame\"\r\n\r\n",
+            b = boundary,
+            id = frame_id,
+            format = format,
+        )
+        .into_bytes();
+        body.extend_from_slice(image);
+        body.extend_from_slice(format!("\r\n--{}--\r\n", boundary).as_bytes());
+
+        axum::http::Request::builder()
+            .method("POST")
+            .uri("/upload")
+            .header(
+                header::CONTENT_TYPE,
+                format!("multipart/form-data; boundary={}", boundary),
+            )
+            .body(axum::body::Body::from(body))
+            .unwrap()
+    }
+
+    async fn response_json(response: Response) -> serde_json::Value {
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        serde_json::from_slice(&bytes).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_debug_content_negotiation() {
+        use tower::Service;
+
+        let mut app = router(AppState::new(Manager::in_memory(100)));
+        let request = |accept: &str| {
+            axum::http::Request::builder()
+                .uri("/debug")
+                .header(header::ACCEPT, accept)
+                .body(axum::body::Body::empty())
+                .unwrap()
+        };
+
+        let response = app.call(request("application/json")).await.unwrap();
+        let body = response_json(response).await;
+        assert_eq!(body["total_frames"], 0);
+        assert_eq!(body["agent_connected"], false);
+        assert_eq!(body["storage"]["frame_count"], 0);
+        assert!(body["storage"]["oldest"].is_null());
+
+        let response = app.call(request("text/plain")).await.unwrap();
+        assert!(response.headers()[header::CONTENT_TYPE]
+            .to_str()
+            .unwrap()
+            .starts_with("text/plain"));
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let text = String::from_utf8(bytes.to_vec()).unwrap();
+        assert!(text.contains("uptime_sec"));
+        assert!(text.contains("total_frames     0"));
+        assert!(text.contains("agent_connected  false"));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_transcode_pool_bounds_concurrency() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use tower::Service;
+
+        // The pool never runs more than `workers` jobs at once
+        let pool = Arc::new(TranscodePool::new(2));
+        let running = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+        let jobs: Vec<_> = (0..12)
+            .map(|i| {
+                let (pool, running, peak) = (pool.clone(), running.clone(), peak.clone());
+                tokio::spawn(async move {
+                    pool.run(move || {
+                        let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                        peak.fetch_max(now, Ordering::SeqCst);
+                        std::thread::sleep(std::time::Duration::from_millis(20));
+                        running.fetch_sub(1, Ordering::SeqCst);
+                        i
+                    })
+                    .await
+                })
+            })
+            .collect();
+        for (i, job) in jobs.into_iter().enumerate() {
+            assert_eq!(job.await.unwrap().unwrap(), i);
+        }
+        assert_eq!(peak.load(Ordering::SeqCst), 2);
+        assert_eq!(pool.stats().queued, 0);
+        assert_eq!(pool.stats().active, 0);
+
+        // Concurrent ?convert= requests all complete through a small pool
+        let state = AppState::new(Manager::in_memory(100)).with_transcoder(Arc::new(TranscodePool::new(2)));
+        let mut png = std::io::Cursor::new(Vec::new());
+        image::RgbaImage::new(64, 64).write_to(&mut png, image::ImageFormat::Png).unwrap();
+        state.store.store(Frame {
+            id: 1,
+            data: png.into_inner(),
+            timestamp: Utc::now(),
+            metadata: HashMap::from([("format".to_string(), "png".to_string())]),
+        }).await.unwrap();
+
+        let requests: Vec<_> = (0..16)
+            .map(|_| {
+                let mut app = router(state.clone());
+                tokio::spawn(async move {
+                    let request = axum::http::Request::builder()
+                        .uri("/frames/1?convert=jpeg")
+                        .body(axum::body::Body::empty())
+                        .unwrap();
+                    app.call(request).await.unwrap()
+                })
+            })
+            .collect();
+        for request in requests {
+            let response = request.await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            assert_eq!(response.headers()[header::CONTENT_TYPE], "image/jpeg");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_upload_rejections_carry_error_codes() {
+        use tower::Service;
+
+        let multipart = |parts: &[(&str, &[u8])]| {
+            let boundary = "eye-test-boundary";
+            let mut body = Vec::new();
+            for (name, value) in parts {
+                body.extend_from_slice(
+                    format!("--{}\r\nContent-Disposition: form-data; name=\"{}\"\r\n\r\n", boundary, name)
+                        .as_bytes(),
+                );
+                body.extend_from_slice(value);
+                body.extend_from_slice(b"\r\n");
+            }
+            body.extend_from_slice(format!("--{}--\r\n", boundary).as_bytes());
+            axum::http::Request::builder()
+                .method("POST")
+                .uri("/upload")
+                .header(header::CONTENT_TYPE, format!("multipart/form-data; boundary={}", boundary))
+                .body(axum::body::Body::from(body))
+                .unwrap()
+        };
+
+        let state = AppState::new(Manager::in_memory(100))
+            .with_canonical_format(Some(image::ImageFormat::Png))
+            .with_max_upload_bytes(4096);
+        let mut app = router(state.clone());
+
+        let cases: Vec<(axum::http::Request<axum::body::Body>, StatusCode, &str)> = vec![
+            (multipart(&[("image", PNG_BYTES)]), StatusCode::FORBIDDEN, "not_connected"),
+            (multipart(&[("frame_id", b"1")]), StatusCode::BAD_REQUEST, "missing_image"),
+            (multipart(&[("frame_id", b"one"), ("image", PNG_BYTES)]), StatusCode::BAD_REQUEST, "invalid_frame_id"),
+            (multipart(&[("image", &[0u8; 8192])]), StatusCode::PAYLOAD_TOO_LARGE, "too_large"),
+            (multipart(&[("format", b"jpeg"), ("image", b"definitely not a jpeg")]), StatusCode::UNPROCESSABLE_ENTITY, "not_an_image"),
+            (multipart(&[("format", b"raw"), ("width", b"4"), ("height", b"4"), ("image", &[0u8; 16])]), StatusCode::UNPROCESSABLE_ENTITY, "not_an_image"),
+            (multipart(&[("width", b"wide"), ("image", PNG_BYTES)]), StatusCode::BAD_REQUEST, "invalid_field"),
+            (multipart(&[("format", b"raw"), ("width", b"0"), ("height", b"4"), ("image", &[0u8; 64])]), StatusCode::BAD_REQUEST, "invalid_field"),
+            (multipart(&[("format", b"raw"), ("height", b"4"), ("image", &[0u8; 64])]), StatusCode::BAD_REQUEST, "invalid_field"),
+            (multipart(&[("format", b"raw"), ("width", b"4"), ("height", b"4"), ("stride", b"0"), ("image", &[0u8; 64])]), StatusCode::BAD_REQUEST, "invalid_field"),
+            (multipart(&[("format", b"raw"), ("width", b"4"), ("height", b"4"), ("stride", b"8"), ("image", &[0u8; 64])]), StatusCode::BAD_REQUEST, "invalid_field"),
+            (multipart(&[("image", PNG_BYTES), ("alternate_jpeg", PNG_BYTES)]), StatusCode::BAD_REQUEST, "invalid_field"),
+            (multipart(&[("image", PNG_BYTES), ("alternate_xyz", PNG_BYTES)]), StatusCode::BAD_REQUEST, "invalid_field"),
+        ];
+
+        for (i, (request, status, code)) in cases.into_iter().enumerate() {
+            // Everything after the first case needs a connected agent
+            *state.agent_connected.write().await = i > 0;
+            let response = app.call(request).await.unwrap();
+            assert_eq!(response.status(), status, "case {}", code);
+            let body = response_json(response).await;
+            assert_eq!(body["error"], code);
+            assert!(body["message"].is_string());
+        }
+
+        let request = axum::http::Request::builder()
+            .method("POST")
+            .uri("/upload")
+            .body(axum::body::Body::from("not multipart"))
+            .unwrap();
+        let response = app.call(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(response_json(response).await["error"], "invalid_multipart");
+
+        // Without a canonical format nothing is decoded, but the header is
+        // still checked
+        let state = AppState::new(Manager::in_memory(100));
+        *state.agent_connected.write().await = true;
+        let mut app = router(state.clone());
+        let response = app.call(multipart(&[("image", b"definitely not a png")])).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(response_json(response).await["error"], "not_an_image");
+        assert!(state.store.get_latest().await.is_err());
+        let response = app.call(multipart(&[("image", PNG_BYTES)])).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_uploaded_alternate_serves_conversions() {
+        use tower::Service;
+
+        let mut jpeg = std::io::Cursor::new(Vec::new());
+        image::DynamicImage::ImageRgb8(image::RgbImage::new(8, 8))
+            .write_to(&mut jpeg, image::ImageFormat::Jpeg)
+            .unwrap();
+        let jpeg = jpeg.into_inner();
+
+        let upload = |id: i64, alternate: Option<&[u8]>| {
+            let boundary = "eye-test-boundary";
+            let mut body = with_png(
+                format!(
+                    "--{b}\r\nContent-Disposition: form-data; name=\"frame_id\"\r\n\r\n{id}\r\n\
+                     --{b}\r\nContent-Disposition: form-data; name=\"image\"; filename=\"frame.png\"\r\n\r\n",
+                    b = boundary,
+                    id = id,
+                ),
+                "\r\n".to_string(),
+            );
+            if let Some(alternate) = alternate {
+                body.extend_from_slice(
+                    format!("--{}\r\nContent-Disposition: form-data; name=\"alternate_jpeg\"; filename=\"frame.jpeg\"\r\n\r\n", boundary)
+                        .as_bytes(),
+                );
+                body.extend_from_slice(alternate);
+                body.extend_from_slice(b"\r\n");
+            }
+            body.extend_from_slice(format!("--{}--\r\n", boundary).as_bytes());
+            axum::http::Request::builder()
+                .method("POST")
+                .uri("/upload")
+                .header(header::CONTENT_TYPE, format!("multipart/form-data; boundary={}", boundary))
+                .body(axum::body::Body::from(body))
+                .unwrap()
+        };
+        let get = |uri: &str| axum::http::Request::builder().uri(uri).body(axum::body::Body::empty()).unwrap();
+
+        let state = AppState::new(Manager::in_memory(10));
+        *state.agent_connected.write().await = true;
+        let mut app = router(state);
+
+        let response = app.call(upload(1, Some(&jpeg))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // PNG_BYTES doesn't decode, so only the uploaded JPEG can answer
+        for uri in ["/frames/1?convert=jpeg", "/frames/1?convert=jpg", "/snapshot.png?convert=jpeg"] {
+            let response = app.call(get(uri)).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK, "{}", uri);
+            assert_eq!(response.headers()[header::CONTENT_TYPE], "image/jpeg");
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            assert_eq!(&body[..], &jpeg[..]);
+        }
+        let response = app.call(get("/frames/1?convert=gif")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        // A frame reusing the id doesn't inherit the earlier alternate
+        app.call(upload(1, None)).await.unwrap();
+        let response = app.call(get("/frames/1?convert=jpeg")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
+    #[tokio::test]
+    async fn test_empty_snapshot_placeholder() {
+        use tower::Service;
+
+        let snapshot = |query: &str| {
+            axum::http::Request::builder()
+                .uri(format!("/snapshot.png{}", query))
+                .body(axum::body::Body::empty())
+                .unwrap()
+        };
+
+        let mut app = router(AppState::new(Manager::in_memory(100)));
+        let response = app.call(snapshot("")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let mut app = router(AppState::new(Manager::in_memory(100)).with_placeholder(Placeholder {
+            enabled: true,
+            width: 320,
+            height: 200,
+            ..Placeholder::default()
+        }));
+        let response = app.call(snapshot("")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "image/png");
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let image = image::load_from_memory_with_format(&bytes, image::ImageFormat::Png).unwrap();
+        assert_eq!((image.width(), image.height()), (320, 200));
+
+        // The query parameter overrides the server setting either way
+        let response = app.call(snapshot("?placeholder=false")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let mut app = router(AppState::new(Manager::in_memory(100)));
+        let response = app.call(snapshot("?placeholder=true")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_upload_accepts_field_aliases() {
+        use tower::Service;
+
+        let multipart = |image_field: &str, id_field: &str, id: i64| {
+            let boundary = "eye-test-boundary";
+            let body = with_png(
+                format!(
+                    "--{b}\r\nContent-Disposition: form-data; name=\"{id_field}\"\r\n\r\n{id}\r\n\
+                     --{b}\r\nContent-Disposition: form-data; name=\"{image_field}\"; filename=\"shot.png\"\r\n\r\n",
+                    b = boundary,
+                ),
+                format!("\r\n--{}--\r\n", boundary),
+            );
+            axum::http::Request::builder()
+                .method("POST")
+                .uri("/upload")
+                .header(header::CONTENT_TYPE, format!("multipart/form-data; boundary={}", boundary))
+                .body(axum::body::Body::from(body))
+                .unwrap()
+        };
+
+        let state = AppState::new(Manager::in_memory(100));
+        *state.agent_connected.write().await = true;
+        let mut app = router(state.clone());
+
+        let response = app.call(multipart("file", "id", 42)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(state.store.get_by_id(42).await.unwrap().data, PNG_BYTES);
+
+        // Configured names replace the defaults
+        let state = AppState::new(Manager::in_memory(100)).with_upload_fields(UploadFields {
+            image: vec!["screenshot".to_string()],
+            frame_id: vec!["seq".to_string()],
+        });
+        *state.agent_connected.write().await = true;
+        let mut app = router(state.clone());
+
+        let response = app.call(multipart("screenshot", "seq", 7)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(state.store.get_by_id(7).await.unwrap().data, PNG_BYTES);
+
+        let response = app.call(multipart("image", "frame_id", 8)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_admin_logs_streams_new_events() {
+        use futures::StreamExt;
+        use tower::Service;
+        use tracing_subscriber::prelude::*;
+
+        let state = AppState::new(Manager::in_memory(100))
+            .with_auth_token(Some("secret".to_string()));
+        let mut app = router(state.clone());
+
+        let logs_request = |token: Option<&str>, query: &str| {
+            let mut builder = axum::http::Request::builder().uri(format!("/admin/logs{}", query));
+            if let Some(token) = token {
+                builder = builder.header(header::AUTHORIZATION, format!("Bearer {}", token));
+            }
+            builder.body(axum::body::Body::empty()).unwrap()
+        };
+
+        let response = app.call(logs_request(None, "")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let response = app.call(logs_request(Some("secret"), "?level=warn")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "text/event-stream");
+
+        let subscriber = tracing_subscriber::registry().with(BroadcastLayer::new(state.logs.clone()));
+        tracing::subscriber::with_default(subscriber, || {
+            info!("filtered out by level");
+            warn!(frame_id = 7, "disk almost full");
+        });
+
+        let mut body = response.into_body().into_data_stream();
+        let chunk = tokio::time::timeout(std::time::Duration::from_secs(1), body.next())
+            .await
+            .expect("log line was not delivered")
+            .unwrap()
+            .unwrap();
+        let text = String::from_utf8(chunk.to_vec()).unwrap();
+
+        assert!(text.starts_with("event: WARN\n"), "unexpected event: {}", text);
+        let data = text.lines().find_map(|l| l.strip_prefix("data: ")).unwrap();
+        let line: serde_json::Value = serde_json::from_str(data).unwrap();
+        assert_eq!(line["level"], "WARN");
+        assert_eq!(line["message"], "disk almost full frame_id=7");
+    }
+
+    #[tokio::test]
+    async fn test_admin_reset_restores_defaults() {
+        use tower::Service;
+
+        let path = env::temp_dir().join(format!("eye-config-{}.json", uuid::Uuid::new_v4()));
+        let state = AppState::new(Manager::in_memory(100))
+            .with_auth_token(Some("secret".to_string()))
+            .with_config_path(Some(path.clone()));
+        let mut app = router(state.clone());
+
+        let config_request = |token: Option<&str>| {
+            let mut builder = axum::http::Request::builder()
+                .method("POST")
+                .uri("/admin/config")
+                .header(header::CONTENT_TYPE, "application/json");
+            if let Some(token) = token {
+                builder = builder.header(header::AUTHORIZATION, format!("Bearer {}", token));
+            }
+            builder
+                .body(axum::body::Body::from(r#"{"interval": 5.0, "format": "jpeg", "quality": 50}"#))
+                .unwrap()
+        };
+
+        // Without the token the config is neither changed nor persisted
+        let response = app.call(config_request(None)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(state.config.read().await.format, AgentConfig::default().format);
+        assert!(!path.exists());
+
+        let response = app.call(config_request(Some("secret"))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(state.config.read().await.format, "jpeg");
+
+        let reset_request = |token: Option<&str>| {
+            let mut builder = axum::http::Request::builder().method("POST").uri("/admin/reset");
+            if let Some(token) = token {
+                builder = builder.header(header::AUTHORIZATION, format!("Bearer {}", token));
+            }
+            builder.body(axum::body::Body::empty()).unwrap()
+        };
+
+        let response = app.call(reset_request(None)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(state.config.read().await.format, "jpeg");
+
+        let response = app.call(reset_request(Some("secret"))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response_json(response).await;
+        let defaults = AgentConfig::default();
+        assert_eq!(body["config"]["format"], defaults.format);
+        assert_eq!(body["config"]["interval"], defaults.interval);
+
+        let stored = state.config.read().await.clone();
+        assert_eq!(stored.format, defaults.format);
+        assert_eq!(stored.quality, defaults.quality);
+
+        // The defaults are persisted, so a SIGHUP reload keeps them
+        let reloaded = reload_config(&state).await.unwrap();
+        assert_eq!(reloaded.format, defaults.format);
+        assert_eq!(reloaded.interval, defaults.interval);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_raw_frame_converts_losslessly() {
+        let mut pixels = image::RgbaImage::new(3, 2);
+        for (x, y, p) in pixels.enumerate_pixels_mut() {
+            *p = image::Rgba([x as u8 * 80, y as u8 * 100, 7, 255]);
+        }
+
+        // Pad each row by 4 bytes to exercise stride handling
+        let mut data = Vec::new();
+        for row in pixels.rows() {
+            data.extend(row.flat_map(|p| p.0));
+            data.extend([0u8; 4]);
+        }
+
+        let metadata = HashMap::from([
+            ("format".to_string(), RAW_FORMAT.to_string()),
+            ("width".to_string(), "3".to_string()),
+            ("height".to_string(), "2".to_string()),
+            ("stride".to_string(), "16".to_string()),
+        ]);
+        let frame = Frame { id: 1, data, timestamp: Utc::now(), metadata };
+
+        let png = convert_frame(&frame, image::ImageFormat::Png).unwrap();
+        let decoded = image::load_from_memory(&png).unwrap().into_rgba8();
+        assert_eq!(decoded, pixels);
+    }
+
+    #[test]
+    fn test_encode_gif() {
+        use image::AnimationDecoder;
+
+        let frames: Vec<Frame> = (0..3)
+            .map(|i| {
+                let img = image::DynamicImage::ImageRgba8(image::RgbaImage::from_pixel(
+                    64,
+                    32,
+                    image::Rgba([i * 80, 0, 0, 255]),
+                ));
+                let mut buffer = std::io::Cursor::new(Vec::new());
+                img.write_to(&mut buffer, image::ImageFormat::Png).unwrap();
+                Frame {
+                    id: i as i64,
+                    data: buffer.into_inner(),
+                    timestamp: Utc::now(),
+                    metadata: HashMap::new(),
+                }
+            })
+            .collect();
+
+        let gif = encode_gif(&frames, &[250; 3], 32).unwrap();
+
+        assert!(gif.starts_with(b"GIF89a"));
+        // NETSCAPE2.0 application extension marks the animation as looping
+        assert!(gif.windows(11).any(|w| w == b"NETSCAPE2.0"));
+
+        let decoder = image::codecs::gif::GifDecoder::new(std::io::Cursor::new(gif)).unwrap();
+        let decoded = decoder.into_frames().collect_frames().unwrap();
+        assert_eq!(decoded.len(), 3);
+        assert_eq!(decoded[0].buffer().dimensions(), (32, 16));
+    }
+
+    #[tokio::test]
+    async fn test_disk_upload_reports_relative_path() {
+        use tower::Service;
+
+        let dir = env::temp_dir().join(format!("eye-server-upload-{}", std::process::id()));
+        let store = Manager::new(StorageMode::Disk, 10, Some(dir.clone())).await.unwrap();
+        let state = AppState::new(store).with_auth_token(Some("secret".to_string()));
+        *state.agent_connected.write().await = true;
+        let mut app = router(state);
+
+        let response = app.call(upload_request(42, Some("secret"))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response_json(response).await;
+        assert_eq!(body["storage"]["backend"], "disk");
+        let path = body["storage"]["path"].as_str().unwrap();
+        assert!(path.starts_with("frame_42_") && path.ends_with(".png"));
+        assert!(dir.join(path).exists());
+
+        // Anonymous callers only learn the backend, not the disk layout
+        let response = app.call(upload_request(43, None)).await.unwrap();
+        let body = response_json(response).await;
+        assert_eq!(body["storage"]["backend"], "disk");
+        assert!(body["storage"].get("path").is_none());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_monitor_filter_reads_disk() {
+        use tower::Service;
+
+        // Disk-only stores keep nothing in memory
+        let dir = env::temp_dir().join(format!("eye-server-monitor-{}", std::process::id()));
+        let state = AppState::new(Manager::new(StorageMode::Disk, 10, Some(dir.clone())).await.unwrap());
+        for (id, monitor) in [(1, "0"), (2, "1"), (3, "0")] {
+            state.store.store(Frame {
+                id,
+                data: vec![id as u8; 8],
+                timestamp: Utc::now(),
+                metadata: HashMap::from([
+                    ("format".to_string(), "png".to_string()),
+                    ("monitor_id".to_string(), monitor.to_string()),
+                ]),
+            }).await.unwrap();
+        }
+        let mut app = router(state);
+
+        for (monitor, id) in [("0", 3u8), ("1", 2)] {
+            let request = axum::http::Request::builder()
+                .uri(format!("/snapshot.png?monitor={}", monitor))
+                .body(axum::body::Body::empty())
+                .unwrap();
+            let response = app.call(request).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            assert_eq!(bytes.as_ref(), vec![id; 8]);
+        }
+        let request = axum::http::Request::builder()
+            .uri("/snapshot.png?monitor=9")
+            .body(axum::body::Body::empty())
+            .unwrap();
+        assert_eq!(app.call(request).await.unwrap().status(), StatusCode::NOT_FOUND);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_frames_listing_is_gzip_compressed() {
+        use std::io::Read;
+        use tower::Service;
+
+        let state = AppState::new(Manager::in_memory(100));
+        for id in 1..=50 {
+            let frame = Frame { id, data: vec![0; 64], timestamp: Utc::now(), metadata: HashMap::new() };
+            state.store.store(frame).await.unwrap();
+        }
+
+        let request = |encoding: Option<&str>| {
+            let mut builder = axum::http::Request::builder().uri("/frames");
+            if let Some(encoding) = encoding {
+                builder = builder.header(header::ACCEPT_ENCODING, encoding);
+            }
+            builder.body(axum::body::Body::empty()).unwrap()
+        };
+
+        let mut app = router(state.clone());
+        let response = app.call(request(Some("br;q=1.0, gzip;q=0.8"))).await.unwrap();
+        assert_eq!(response.headers()[header::CONTENT_ENCODING], "br");
+        let response = app.call(request(Some("br;q=0, gzip"))).await.unwrap();
+        assert_eq!(response.headers()[header::CONTENT_ENCODING], "gzip");
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let mut json = String::new();
+        flate2::read::GzDecoder::new(&bytes[..]).read_to_string(&mut json).unwrap();
+        let body: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(body["count"], 50);
+
+        // No Accept-Encoding, or compression switched off: plain JSON
+        let response = app.call(request(None)).await.unwrap();
+        assert!(!response.headers().contains_key(header::CONTENT_ENCODING));
+
+        let mut app = router(state.with_compression(false));
+        let response = app.call(request(Some("gzip"))).await.unwrap();
+        assert!(!response.headers().contains_key(header::CONTENT_ENCODING));
+        assert_eq!(response_json(response).await["count"], 50);
+    }
+
+    #[tokio::test]
+    async fn test_request_id_echoed_or_generated() {
+        use tower::Service;
+
+        let mut app = router(AppState::new(Manager::in_memory(100)));
+        let request = |id: Option<&str>| {
+            let mut builder = axum::http::Request::builder().uri("/health");
+            if let Some(id) = id {
+                builder = builder.header(REQUEST_ID_HEADER, id);
+            }
+            builder.body(axum::body::Body::empty()).unwrap()
+        };
+
+        let response = app.call(request(Some("upload-42"))).await.unwrap();
+        assert_eq!(response.headers()[REQUEST_ID_HEADER], "upload-42");
+
+        let response = app.call(request(None)).await.unwrap();
+        let generated = response.headers()[REQUEST_ID_HEADER].to_str().unwrap().to_string();
+        assert!(uuid::Uuid::parse_str(&generated).is_ok());
+
+        let line = log_line(
+            &axum::http::Method::GET,
+            &"/health".parse().unwrap(),
+            StatusCode::OK,
+            std::time::Duration::from_millis(3),
+            &generated,
+        );
+        assert!(line.ends_with(&format!("rid={}", generated)));
+    }
+
+    #[tokio::test]
+    async fn test_concurrency_limit_rejects_when_saturated() {
+        use tower::Service;
+
+        let state = AppState::new(Manager::in_memory(100)).with_max_concurrent(Some(1));
+        let app: Router = Router::new()
+            .route("/slow", get(|| async {
+                tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+                "done"
+            }))
+            .route("/health", get(|| async { "ok" }))
+            .layer(middleware::from_fn_with_state(state.clone(), concurrency_middleware));
+        let request = |uri: &str| {
+            axum::http::Request::builder()
+                .uri(uri)
+                .body(axum::body::Body::empty())
+                .unwrap()
+        };
+
+        let mut first = app.clone();
+        let slow = tokio::spawn(async move { first.call(request("/slow")).await.unwrap() });
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+
+        let response = app.clone().call(request("/slow")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let response = app.clone().call(request("/health")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        assert_eq!(slow.await.unwrap().status(), StatusCode::OK);
+        let response = app.clone().call(request("/slow")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_upload_timestamps_come_from_clock() {
+        use tower::Service;
+
+        let start = DateTime::parse_from_rfc3339("2026-03-02T09:00:00Z").unwrap().to_utc();
+        let clock = clock::FakeClock::new(start);
+        let state = AppState::new(Manager::in_memory(100)).with_clock(Arc::new(clock.clone()));
+        *state.agent_connected.write().await = true;
+        let mut app = router(state.clone());
+
+        app.call(upload_request(1, None)).await.unwrap();
+        clock.advance(chrono::Duration::hours(1));
+        app.call(upload_request(2, None)).await.unwrap();
+
+        let first = state.store.get_by_id(1).await.unwrap();
+        let second = state.store.get_by_id(2).await.unwrap();
+        assert_eq!(first.timestamp, start);
+        assert_eq!(second.timestamp - first.timestamp, chrono::Duration::hours(1));
+    }
+
+    #[tokio::test]
+    async fn test_export_resumes_with_byte_ranges() {
+        use tower::Service;
+
+        let clock = clock::FakeClock::default();
+        let dir = std::env::temp_dir().join(format!("eye-exports-resume-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let state = AppState::new(Manager::in_memory(100))
+            .with_clock(Arc::new(clock.clone()))
+            .with_exports(Exports::new(dir.clone(), 4, 1024 * 1024));
+        let now = Utc::now();
+        for id in 1..=5 {
+            let timestamp = now + chrono::Duration::seconds(id);
+            let frame = Frame { id, data: vec![id as u8; 500], timestamp, metadata: HashMap::new() };
+            state.store.store(frame).await.unwrap();
+        }
+        let mut app = router(state);
+
+        let body = json!({ "from": now.timestamp() - 60, "to": now.timestamp() + 60 });
+        let request = axum::http::Request::builder()
+            .method("POST")
+            .uri("/export")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(axum::body::Body::from(body.to_string()))
+            .unwrap();
+        let export = response_json(app.call(request).await.unwrap()).await;
+        assert_eq!(export["frame_count"], 5);
+        let url = export["url"].as_str().unwrap().to_string();
+        let size = export["size_bytes"].as_u64().unwrap() as usize;
+
+        let download = |range: Option<&str>| {
+            let mut builder = axum::http::Request::builder().uri(url.as_str());
+            if let Some(range) = range {
+                builder = builder.header(header::RANGE, range);
+            }
+            builder.body(axum::body::Body::empty()).unwrap()
+        };
+
+        let full = app.call(download(None)).await.unwrap();
+        assert_eq!(full.status(), StatusCode::OK);
+        let full = axum::body::to_bytes(full.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(full.len(), size);
+
+        // Connection "drops" after 1000 bytes; resume from there
+        let first = app.call(download(Some("bytes=0-999"))).await.unwrap();
+        assert_eq!(first.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(first.headers()[header::CONTENT_RANGE], format!("bytes 0-999/{}", size));
+        let mut resumed = axum::body::to_bytes(first.into_body(), usize::MAX).await.unwrap().to_vec();
+
+        let rest = app.call(download(Some("bytes=1000-"))).await.unwrap();
+        assert_eq!(rest.status(), StatusCode::PARTIAL_CONTENT);
+        resumed.extend(axum::body::to_bytes(rest.into_body(), usize::MAX).await.unwrap());
+        assert_eq!(resumed, full.to_vec());
+
+        let past_end = app.call(download(Some(&format!("bytes={}-", size)))).await.unwrap();
+        assert_eq!(past_end.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+
+        // The archive lives on disk until it expires
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+        clock.advance(chrono::Duration::minutes(exports::EXPORT_TTL_MINUTES));
+        let expired = app.call(download(None)).await.unwrap();
+        assert_eq!(expired.status(), StatusCode::NOT_FOUND);
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 0);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_exports_require_auth_and_are_capped() {
+        use tower::Service;
+
+        let clock = clock::FakeClock::default();
+        let dir = std::env::temp_dir().join(format!("eye-exports-capped-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let state = AppState::new(Manager::in_memory(100))
+            .with_clock(Arc::new(clock.clone()))
+            .with_auth_token(Some("secret".to_string()))
+            .with_exports(Exports::new(dir.clone(), 2, 3000));
+        let now = Utc::now();
+        for id in 1..=4 {
+            let timestamp = now + chrono::Duration::seconds(id * 10);
+            let frame = Frame { id, data: vec![id as u8; 1000], timestamp, metadata: HashMap::new() };
+            state.store.store(frame).await.unwrap();
+        }
+        let mut app = router(state);
+
+        // Exports of frames [from, to] seconds after `now`
+        let export = |from: i64, to: i64, token: Option<&str>| {
+            let body = json!({ "from": now.timestamp() + from, "to": now.timestamp() + to });
+            let mut builder = axum::http::Request::builder()
+                .method("POST")
+                .uri("/export")
+                .header(header::CONTENT_TYPE, "application/json");
+            if let Some(token) = token {
+                builder = builder.header(header::AUTHORIZATION, format!("Bearer {}", token));
+            }
+            builder.body(axum::body::Body::from(body.to_string())).unwrap()
+        };
+
+        let response = app.call(export(0, 60, None)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        // Three frames don't fit in the byte cap
+        let response = app.call(export(0, 35, Some("secret"))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+        let first = app.call(export(5, 15, Some("secret"))).await.unwrap();
+        assert_eq!(first.status(), StatusCode::OK);
+        let url = response_json(first).await["url"].as_str().unwrap().to_string();
+        let download = axum::http::Request::builder().uri(url.as_str()).body(axum::body::Body::empty()).unwrap();
+        assert_eq!(app.call(download).await.unwrap().status(), StatusCode::UNAUTHORIZED);
+
+        let second = app.call(export(15, 25, Some("secret"))).await.unwrap();
+        assert_eq!(second.status(), StatusCode::OK);
+        let third = app.call(export(25, 35, Some("secret"))).await.unwrap();
+        assert_eq!(third.status(), StatusCode::TOO_MANY_REQUESTS);
+
+        // Expired exports free their slots
+        clock.advance(chrono::Duration::minutes(exports::EXPORT_TTL_MINUTES));
+        let third = app.call(export(25, 35, Some("secret"))).await.unwrap();
+        assert_eq!(third.status(), StatusCode::OK);
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_upload_fields_surface_in_frame_meta() {
+        use tower::Service;
+
+        let state = AppState::new(Manager::in_memory(100));
+        *state.agent_connected.write().await = true;
+        let mut app = router(state);
+
+        let boundary = "eye-test-boundary";
+        let field = |name: &str, value: &str| {
+            format!(
+                "--{b}\r\nContent-Disposition: form-data; name=\"{n}\"\r\n\r\n{v}\r\n",
+                b = boundary, n = name, v = value,
+            )
+        };
+        let body = with_png(
+            format!("--{}\r\nContent-Disposition: form-data; name=\"image\"; filename=\"frame.png\"\r\n\r\n", boundary),
+            format!(
+                "\r\n{}{}{}{}{}--{b}--\r\n",
+                field("frame_id", "1"),
+                field("monitor_id", "7"),
+                field("monitor_name", "DP-1"),
+                field("monitor_width", "2560"),
+                field(CLIPBOARD_HASH_FIELD, &"ab".repeat(32)),
+                b = boundary,
+            ),
+        );
+        let upload = axum::http::Request::builder()
+            .method("POST")
+            .uri("/upload")
+            .header(header::CONTENT_TYPE, format!("multipart/form-data; boundary={}", boundary))
+            .body(axum::body::Body::from(body))
+            .unwrap();
+        assert_eq!(app.call(upload).await.unwrap().status(), StatusCode::OK);
+
+        let request = axum::http::Request::builder()
+            .uri("/frames/1/meta")
+            .body(axum::body::Body::empty())
+            .unwrap();
+        let meta = response_json(app.call(request).await.unwrap()).await;
+        assert_eq!(meta["monitor"]["id"], "7");
+        assert_eq!(meta["monitor"]["name"], "DP-1");
+        assert_eq!(meta["metadata"]["monitor_width"], "2560");
+        assert_eq!(meta["clipboard_sha256"], "ab".repeat(32));
+    }
+
+    #[tokio::test]
+    async fn test_reload_config_from_file() {
+        let path = env::temp_dir().join(format!("eye-config-{}.json", uuid::Uuid::new_v4()));
+        let state = AppState::new(Manager::in_memory(100)).with_config_path(Some(path.clone()));
+
+        // Missing file: reload fails and the live config is untouched
+        assert!(reload_config(&state).await.is_err());
+        assert_eq!(state.config.read().await.format, "webp");
+
+        std::fs::write(&path, r#"{"interval": 2.5, "format": "JPG", "quality": 80}"#).unwrap();
+        reload_config(&state).await.unwrap();
+        {
+            let config = state.config.read().await;
+            assert_eq!(config.interval, 2.5);
+            assert_eq!(config.format, "jpeg");
+            assert_eq!(config.quality, 80);
+            // The old scalar maps onto the active format
+            assert_eq!(config.qualities, BTreeMap::from([("jpeg".to_string(), 80)]));
+        }
+
+        std::fs::write(&path, r#"{"interval": 5.0, "format": "png", "quality": 90}"#).unwrap();
+        reload_config(&state).await.unwrap();
+        assert_eq!(state.config.read().await.interval, 5.0);
+
+        // Per-format map: the active format's entry wins over the scalar
+        std::fs::write(
+            &path,
+            r#"{"interval": 1.0, "format": "jpeg", "quality": 90, "qualities": {"JPG": 50, "webp": 75}}"#,
+        )
+        .unwrap();
+        reload_config(&state).await.unwrap();
+        {
+            let config = state.config.read().await;
+            assert_eq!(config.quality, 50);
+            assert_eq!(
+                config.qualities,
+                BTreeMap::from([("jpeg".to_string(), 50), ("webp".to_string(), 75)])
+            );
+        }
+
+        // Lossless formats and out-of-range values fail the reload, naming
+        // the key, and the live config stays as it was
+        for (qualities, key) in [(r#"{"png": 80}"#, "qualities.png"), (r#"{"webp": 101}"#, "qualities.webp")] {
+            let body = format!(r#"{{"interval": 3.0, "format": "jpeg", "quality": 90, "qualities": {}}}"#, qualities);
+            std::fs::write(&path, body).unwrap();
+            let err = reload_config(&state).await.unwrap_err();
+            assert!(format!("{:#}", err).contains(key));
+            assert_eq!(state.config.read().await.interval, 1.0);
+        }
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_wait_wakes_on_upload() {
+        use tower::Service;
+
+        let state = AppState::new(Manager::in_memory(100));
+        *state.agent_connected.write().await = true;
+        let app = router(state);
+        let wait = |query: &str| {
+            axum::http::Request::builder()
+                .uri(format!("/snapshot/wait?{}", query))
+                .body(axum::body::Body::empty())
+                .unwrap()
+        };
+
+        let mut waiter = app.clone();
+        let start = Instant::now();
+        let pending = tokio::spawn(async move { waiter.call(wait("timeout=5000")).await.unwrap() });
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        app.clone().call(upload_request(1, None)).await.unwrap();
+
+        let response = pending.await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["x-frame-id"], "1");
+        assert!(start.elapsed() < std::time::Duration::from_secs(1));
+
+        // Already holding the latest frame and nothing new arrives
+        let response = app.clone().call(wait("since=1&timeout=100")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    }
+
+    #[tokio::test]
+    async fn test_uploads_transcoded_to_canonical_format() {
+        use tower::Service;
+
+        let state = AppState::new(Manager::in_memory(10))
+            .with_canonical_format(Some(image::ImageFormat::WebP));
+        *state.agent_connected.write().await = true;
+        let mut app = router(state.clone());
+
+        let pixels = image::RgbaImage::from_pixel(8, 8, image::Rgba([10, 20, 30, 255]));
+        let mut png = std::io::Cursor::new(Vec::new());
+        image::DynamicImage::ImageRgba8(pixels)
+            .write_to(&mut png, image::ImageFormat::Png)
+            .unwrap();
+
+        let response = app.call(image_upload_request(1, "png", png.get_ref())).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let stored = state.store.get_by_id(1).await.unwrap();
+        assert_eq!(image::guess_format(&stored.data).unwrap(), image::ImageFormat::WebP);
+        assert_eq!(stored.metadata["format"], "webp");
+        assert_eq!(stored.metadata["content-type"], "image/webp");
+        assert_eq!(stored.metadata[ORIGINAL_FORMAT_KEY], "png");
+
+        // Served as WebP unless the original format is asked for
+        let request = axum::http::Request::builder()
+            .uri("/frames/1?convert=original")
+            .body(axum::body::Body::empty())
+            .unwrap();
+        let response = app.call(request).await.unwrap();
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "image/png");
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(image::guess_format(&bytes).unwrap(), image::ImageFormat::Png);
+    }
+
+    #[tokio::test]
+    async fn test_version_reports_crate_version() {
+        use tower::Service;
+
+        let mut app = router(AppState::new(Manager::in_memory(10)));
+        let request = axum::http::Request::builder()
+            .uri("/version")
+            .body(axum::body::Body::empty())
+            .unwrap();
+        let response = app.call(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = response_json(response).await;
+        assert_eq!(body["name"], "eye-server");
+        assert_eq!(body["version"], env!("CARGO_PKG_VERSION"));
+        assert!(body["git_sha"].as_str().is_some_and(|sha| !sha.is_empty()));
+        assert!(body["build_timestamp"].is_string());
+    }
+
+    #[test]
+    fn test_version_compatibility_policy() {
+        assert_eq!(compatibility(Some("1.4.2"), "1.4.0"), Compatibility::Ok);
+        assert_eq!(compatibility(Some("1.2.0"), "1.4.0"), Compatibility::Warn);
+        assert_eq!(compatibility(Some("2.0.0"), "1.4.0"), Compatibility::Reject);
+        // Minor bumps are breaking before 1.0
+        assert_eq!(compatibility(Some("0.2.0"), "0.1.0"), Compatibility::Reject);
+        assert_eq!(compatibility(Some("v0.1.7-dev"), "0.1.0"), Compatibility::Ok);
+        assert_eq!(compatibility(None, "0.1.0"), Compatibility::Warn);
+        assert_eq!(compatibility(Some("banana"), "0.1.0"), Compatibility::Warn);
+    }
+
+    #[tokio::test]
+    async fn test_connect_negotiates_agent_version() {
+        use tower::Service;
+
+        let state = AppState::new(Manager::in_memory(10));
+        let mut app = router(state.clone());
+        let connect = |version: &str| {
+            axum::http::Request::builder()
+                .method("POST")
+                .uri("/connect")
+                .header(AGENT_VERSION_HEADER, version)
+                .body(axum::body::Body::empty())
+                .unwrap()
+        };
+
+        // A different major version is refused without taking the slot
+        let response = app.call(connect("99.0.0")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UPGRADE_REQUIRED);
+        assert!(!*state.agent_connected.read().await);
+
+        let response = app.call(connect(env!("CARGO_PKG_VERSION"))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[SERVER_VERSION_HEADER], env!("CARGO_PKG_VERSION"));
+        let body = response_json(response).await;
+        assert_eq!(body["compatibility"], "ok");
+        assert!(*state.agent_connected.read().await);
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_serves_highest_id_after_late_upload() {
+        use tower::Service;
+
+        let state = AppState::new(Manager::in_memory(10));
+        *state.agent_connected.write().await = true;
+        let mut app = router(state.clone());
+
+        app.call(upload_request(5, None)).await.unwrap();
+        let response = app.call(upload_request(3, None)).await.unwrap();
+        let body = response_json(response).await;
+        assert_eq!(body["frame_id"], 3);
+        assert_eq!(body["latest_frame_id"], 5);
+
+        assert_eq!(state.store.get_latest().await.unwrap().id, 5);
+        let request = axum::http::Request::builder()
+            .uri("/snapshot.png")
+            .body(axum::body::Body::empty())
+            .unwrap();
+        let response = app.call(request).await.unwrap();
+        assert_eq!(response.headers()["x-frame-id"], "5");
+    }
+
+    #[tokio::test]
+    async fn test_admin_config_accepts_human_interval() {
+        use tower::Service;
+
+        let state = AppState::new(Manager::in_memory(10));
+        let mut app = router(state.clone());
+        let update = |body: &str| {
+            axum::http::Request::builder()
+                .method("POST")
+                .uri("/admin/config")
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(axum::body::Body::from(body.to_string()))
+                .unwrap()
+        };
+
+        let response = app
+            .call(update(r#"{"interval": "1.5s", "format": "png", "quality": 80}"#))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(state.config.read().await.interval, 1.5);
+
+        // Plain seconds still work
+        app.call(update(r#"{"interval": 2, "format": "png", "quality": 80}"#)).await.unwrap();
+        assert_eq!(state.config.read().await.interval, 2.0);
+
+        // Human strings below the minimum or unparseable are rejected
+        for bad in [r#""10ms""#, r#""soon""#] {
+            let body = format!(r#"{{"interval": {}, "format": "png", "quality": 80}}"#, bad);
+            let response = app.call(update(&body)).await.unwrap();
+            assert!(response.status().is_client_error());
+        }
+        assert_eq!(state.config.read().await.interval, 2.0);
+
+        // A quality for a format without one is rejected by name
+        let response = app
+            .call(update(r#"{"interval": 3, "format": "png", "quality": 80, "qualities": {"tiff": 80}}"#))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert!(String::from_utf8_lossy(&body).contains("qualities.tiff"));
+        assert_eq!(state.config.read().await.interval, 2.0);
+    }
+
+    #[tokio::test]
+    async fn test_frames_latest_json_and_embed() {
+        use base64::Engine as _;
+        use tower::Service;
+
+        let state = AppState::new(Manager::in_memory(10));
+        let mut app = router(state.clone());
+        let request = |uri: &str| {
+            axum::http::Request::builder()
+                .uri(uri)
+                .body(axum::body::Body::empty())
+                .unwrap()
+        };
+
+        let response = app.call(request("/frames/latest")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        *state.agent_connected.write().await = true;
+        app.call(upload_request(4, None)).await.unwrap();
+        let stored = state.store.get_latest().await.unwrap().data;
+
+        // Default: metadata plus a link, no image bytes
+        let response = app.call(request("/frames/latest")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response_json(response).await;
+        assert_eq!(body["id"], 4);
+        assert_eq!(body["size_bytes"], stored.len());
+        assert_eq!(body["url"], "/snapshot.png");
+        assert!(body.get("data_url").is_none());
+
+        // ?embed=1 inlines the stored bytes as a data URL
+        let body = response_json(app.call(request("/frames/latest?embed=1")).await.unwrap()).await;
+        assert!(body.get("url").is_none());
+        let data_url = body["data_url"].as_str().unwrap();
+        let prefix = format!("data:{};base64,", body["content_type"].as_str().unwrap());
+        let encoded = data_url.strip_prefix(&prefix).unwrap();
+        let decoded = base64::engine::general_purpose::STANDARD.decode(encoded).unwrap();
+        assert_eq!(decoded, stored);
+    }
+
+    #[tokio::test]
+    async fn test_duplicate_uploads_share_bytes() {
+        use tower::Service;
+
+        let state = AppState::new(Manager::in_memory(10).with_dedup());
+        *state.agent_connected.write().await = true;
+        let mut app = router(state.clone());
+
+        for id in [1, 2] {
+            let response = app.call(upload_request(id, None)).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+        }
+
+        // Two timeline entries, one stored copy of the bytes
+        assert_eq!(state.store.list().await.len(), 2);
+        assert_eq!(state.store.blob_count().await, 1);
+        assert_eq!(state.store.usage().await.bytes, PNG_BYTES.len());
+
+        // The reference resolves to the original bytes
+        let first = state.store.get_by_id(1).await.unwrap();
+        let second = state.store.get_by_id(2).await.unwrap();
+        assert_eq!(second.data, first.data);
+        assert_eq!(second.metadata[storage::DEDUP_OF_KEY], "1");
+        assert!(second.timestamp >= first.timestamp);
+    }
+
+    #[tokio::test]
+    async fn test_admin_flush_writes_batched_frames() {
+        use tower::Service;
+
+        let dir = env::temp_dir().join(format!("eye-flush-{}", uuid::Uuid::new_v4()));
+        let store = Manager::new(StorageMode::Hybrid, 10, Some(dir.clone()))
+            .await
+            .unwrap()
+            .with_write_batching(WriteBatching {
+                max_frames: 100,
+                max_delay: std::time::Duration::from_secs(600),
+            });
+        let state = AppState::new(store).with_auth_token(Some("secret".to_string()));
+        *state.agent_connected.write().await = true;
+        let mut app = router(state.clone());
+
+        for id in 1..=3 {
+            let mut request = upload_request(id, None);
+            request.headers_mut().insert(header::AUTHORIZATION, "Bearer secret".parse().unwrap());
+            assert_eq!(app.call(request).await.unwrap().status(), StatusCode::OK);
+        }
+        // Still queued in the batch writer
+        let on_disk = || std::fs::read_dir(&dir).unwrap().count();
+        assert_eq!(on_disk(), 0);
+
+        let flush = |token: Option<&str>| {
+            let mut builder = axum::http::Request::builder().method("POST").uri("/admin/flush");
+            if let Some(token) = token {
+                builder = builder.header(header::AUTHORIZATION, format!("Bearer {}", token));
+            }
+            builder.body(axum::body::Body::empty()).unwrap()
+        };
+        assert_eq!(app.call(flush(None)).await.unwrap().status(), StatusCode::UNAUTHORIZED);
+
+        let response = app.call(flush(Some("secret"))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response_json(response).await;
+        assert_eq!(body["frames"], 3);
+        assert_eq!(body["bytes"], 3 * PNG_BYTES.len());
+        assert_eq!(body["disk"]["files"], 3);
+        assert_eq!(body["disk"]["bytes"], 3 * PNG_BYTES.len());
+        assert!(body["oldest"].as_str().unwrap() <= body["newest"].as_str().unwrap());
+
+        // Every frame is now readable straight from disk
+        let disk = storage::DiskStore::new(dir.clone()).await.unwrap();
+        for id in 1..=3 {
+            assert_eq!(disk.get_by_id(id).await.unwrap().data, PNG_BYTES);
+        }
+
+        // A disk-only store holds nothing in memory but still reports them
+        let store = Manager::new(StorageMode::Disk, 10, Some(dir.clone())).await.unwrap();
+        let state = AppState::new(store).with_auth_token(Some("secret".to_string()));
+        let response = router(state).call(flush(Some("secret"))).await.unwrap();
+        let body = response_json(response).await;
+        assert_eq!(body["frames"], 3);
+        assert_eq!(body["bytes"], 3 * PNG_BYTES.len());
+        assert!(body["oldest"].is_string());
+        assert!(body["newest"].is_string());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_adaptive_timelapse_drops_near_duplicates() {
+        use tower::Service;
+
+        let png = |img: image::RgbImage| {
+            let mut buffer = std::io::Cursor::new(Vec::new());
+            image::DynamicImage::ImageRgb8(img).write_to(&mut buffer, image::ImageFormat::Png).unwrap();
+            buffer.into_inner()
+        };
+        let black = image::RgbImage::new(64, 64);
+        let mut speck = black.clone();
+        speck.put_pixel(10, 10, image::Rgb([255, 255, 255]));
+        let half = image::RgbImage::from_fn(64, 64, |x, _| {
+            if x < 32 { image::Rgb([255, 255, 255]) } else { image::Rgb([0, 0, 0]) }
+        });
+        let mut corner = half.clone();
+        for y in 48..64 {
+            for x in 48..64 {
+                corner.put_pixel(x, y, image::Rgb([255, 255, 255]));
+            }
+        }
+        let sequence = [&black, &black, &speck, &half, &half, &corner];
+
+        let state = AppState::new(Manager::in_memory(10));
+        let start = Utc::now().timestamp();
+        for (id, img) in sequence.into_iter().enumerate() {
+            let frame = Frame {
+                id: id as i64,
+                data: png(img.clone()),
+                timestamp: DateTime::from_timestamp(start, 0).unwrap(),
+                metadata: HashMap::new(),
+            };
+            state.store.store(frame).await.unwrap();
+        }
+        let mut app = router(state);
+        let request = |query: &str| {
+            axum::http::Request::builder()
+                .uri(format!("/animation.gif?from={}&to={}&fps=2{}", start, start, query))
+                .body(axum::body::Body::empty())
+                .unwrap()
+        };
+
+        let body = response_json(app.call(request("&speed=adaptive&manifest=true")).await.unwrap()).await;
+        let ids: Vec<i64> = body["frames"].as_array().unwrap().iter().map(|f| f["id"].as_i64().unwrap()).collect();
+        let delays: Vec<u64> =
+            body["frames"].as_array().unwrap().iter().map(|f| f["delay_ms"].as_u64().unwrap()).collect();
+        // Exact and near duplicates are dropped; the big change lingers
+        // longer than the small one
+        assert_eq!(ids, vec![0, 3, 5]);
+        assert_eq!(delays[0], 500);
+        assert!(delays[1] > 500);
+        assert!(delays[2] < delays[1]);
+
+        // Uniform playback keeps everything
+        let body = response_json(app.call(request("&manifest=true")).await.unwrap()).await;
+        assert_eq!(body["frame_count"], 6);
+
+        let response = app.call(request("&speed=adaptive")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["x-frame-count"], "3");
+
+        let response = app.call(request("&speed=warp")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_health_reports_unwritable_disk() {
+        use tower::Service;
+
+        let dir = env::temp_dir().join(format!("eye-health-{}", uuid::Uuid::new_v4()));
+        let store = Manager::new(StorageMode::Hybrid, 10, Some(dir.clone())).await.unwrap();
+        let state = AppState::new(store).with_health_check_interval(std::time::Duration::ZERO);
+        *state.agent_connected.write().await = true;
+        let mut app = router(state);
+        let health = || axum::http::Request::builder().uri("/health").body(axum::body::Body::empty()).unwrap();
+
+        let body = response_json(app.call(health()).await.unwrap()).await;
+        assert_eq!(body["status"], "healthy");
+        assert_eq!(body["storage"][0]["backend"], "memory");
+        assert_eq!(body["storage"][1]["backend"], "disk");
+        assert_eq!(body["storage"][1]["healthy"], true);
+
+        // The store directory disappearing makes it unwritable, even as root
+        std::fs::remove_dir_all(&dir).unwrap();
+        let response = app.call(upload_request(1, None)).await.unwrap();
+        assert_ne!(response.status(), StatusCode::OK);
+
+        let body = response_json(app.call(health()).await.unwrap()).await;
+        assert_eq!(body["status"], "degraded");
+        assert_eq!(body["storage"][0]["healthy"], true);
+        let disk = &body["storage"][1];
+        assert_eq!(disk["healthy"], false);
+        assert!(disk["error"].as_str().unwrap().contains("not writable"), "{}", disk);
+        assert!(disk["last_write_error"]["message"].as_str().unwrap().contains("Failed to write"));
+    }
+
+    #[tokio::test]
+    async fn test_health_caches_checks_and_hides_details() {
+        use tower::Service;
+
+        let dir = env::temp_dir().join(format!("eye-health-{}", uuid::Uuid::new_v4()));
+        let store = Manager::new(StorageMode::Hybrid, 10, Some(dir.clone())).await.unwrap();
+        let mut app = router(AppState::new(store).with_auth_token(Some("secret".to_string())));
+        let health = |token: Option<&str>| {
+            let mut builder = axum::http::Request::builder().uri("/health");
+            if let Some(token) = token {
+                builder = builder.header(header::AUTHORIZATION, format!("Bearer {}", token));
+            }
+            builder.body(axum::body::Body::empty()).unwrap()
+        };
+
+        let body = response_json(app.call(health(None)).await.unwrap()).await;
+        assert_eq!(body["storage"][1], json!({ "backend": "disk", "healthy": true, "degraded": false }));
+        let body = response_json(app.call(health(Some("secret"))).await.unwrap()).await;
+        assert!(body["storage"][1]["free_bytes"].is_u64() || body["storage"][1]["free_bytes"].is_null());
+        assert!(body["storage"][1].get("error").is_some());
+
+        // Within the interval the last check is reused rather than the
+        // disk being probed again
+        std::fs::remove_dir_all(&dir).unwrap();
+        let body = response_json(app.call(health(None)).await.unwrap()).await;
+        assert_eq!(body["status"], "healthy");
+    }
+
+    #[tokio::test]
+    async fn test_stale_frame_rejected_beyond_skew() {
+        use tower::Service;
+
+        let state = AppState::new(Manager::in_memory(10))
+            .with_max_frame_skew(Some(chrono::Duration::seconds(60)));
+        *state.agent_connected.write().await = true;
+        let mut app = router(state);
+
+        let upload = |frame_id: i64, timestamp: DateTime<Utc>| {
+            let body = with_png(
+                "--b\r\nContent-Disposition: form-data; name=\"image\"; filename=\"frame.png\"\r\n\r\n".to_string(),
+                format!(
+                    "\r\n--b\r\nContent-Disposition: form-data; name=\"frame_id\"\r\n\r\n\
+                     {}\r\n--b\r\nContent-Disposition: form-data; name=\"timestamp\"\r\n\r\n\
+                     {}\r\n--b--\r\n",
+                    frame_id,
+                    timestamp.to_rfc3339(),
+                ),
+            );
+            axum::http::Request::builder()
+                .method("POST")
+                .uri("/upload")
+                .header(header::CONTENT_TYPE, "multipart/form-data; boundary=b")
+                .body(axum::body::Body::from(body))
+                .unwrap()
+        };
+        let now = Utc::now();
+
+        let response = app.call(upload(2, now)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // An hour behind the latest: a replay or a broken clock
+        let response = app.call(upload(1, now - chrono::Duration::hours(1))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+        let body = response_json(response).await;
+        assert_eq!(body["error"], "stale_frame");
+        assert!(body["detail"].as_str().unwrap().contains("tolerance 60s"));
+
+        // A few seconds out of order is within tolerance
+        let response = app.call(upload(3, now - chrono::Duration::seconds(5))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // So is a clock running a little fast, but not an hour ahead
+        let response = app.call(upload(4, now + chrono::Duration::seconds(30))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = app.call(upload(5, now + chrono::Duration::hours(1))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = response_json(response).await;
+        assert_eq!(body["error"], "future_frame");
+        assert!(body["detail"].as_str().unwrap().contains("tolerance 60s"));
+    }
+
+    #[tokio::test]
+    async fn test_tenants_are_isolated() {
+        use tower::Service;
+
+        let mut tenants = Tenants::default();
+        for (name, key) in [("alpha", "key-a"), ("beta", "key-b")] {
+            let state = AppState::new(Manager::in_memory(10)).with_auth_token(Some(key.to_string()));
+            tenants.add(name, key, router(state));
+        }
+        let mut app = tenants::router(tenants);
+        let get = |uri: &str, key: &str| {
+            axum::http::Request::builder()
+                .uri(uri)
+                .header(header::AUTHORIZATION, format!("Bearer {}", key))
+                .body(axum::body::Body::empty())
+                .unwrap()
+        };
+
+        // Tenant A's agent connects and uploads; its key selects the tenant
+        let connect = axum::http::Request::builder()
+            .method("POST")
+            .uri("/connect")
+            .header(header::AUTHORIZATION, "Bearer key-a")
+            .body(axum::body::Body::empty())
+            .unwrap();
+        assert_eq!(app.call(connect).await.unwrap().status(), StatusCode::OK);
+        let response = app.call(upload_request(1, Some("key-a"))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = response_json(app.call(get("/frames", "key-a")).await.unwrap()).await;
+        assert_eq!(body["count"], 1);
+        let response = app.call(get("/t/alpha/snapshot.png", "key-a")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // Tenant B sees nothing, by header, path prefix or key alone
+        let mut request = get("/frames", "key-b");
+        request.headers_mut().insert(tenants::TENANT_HEADER, "beta".parse().unwrap());
+        let body = response_json(app.call(request).await.unwrap()).await;
+        assert_eq!(body["count"], 0);
+        let response = app.call(get("/t/beta/snapshot.png", "key-b")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        // ...and B's key can't be used to read A
+        let response = app.call(get("/t/alpha/frames", "key-b")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn test_similar_frames_by_perceptual_hash() {
+        use tower::Service;
+
+        let state = AppState::new(Manager::in_memory(10)).with_perceptual_hash(true);
+        *state.agent_connected.write().await = true;
+        let mut app = router(state);
+
+        let png = |pixels: image::RgbaImage| {
+            let mut png = std::io::Cursor::new(Vec::new());
+            image::DynamicImage::ImageRgba8(pixels).write_to(&mut png, image::ImageFormat::Png).unwrap();
+            png.into_inner()
+        };
+        // Brightness falling left to right, its near-duplicate with a small
+        // patch changed, and the mirror image
+        let falling = |x: u32| 255 - (x * 4) as u8;
+        let screen = image::RgbaImage::from_fn(64, 64, |x, _| image::Rgba([falling(x); 4]));
+        let mut edited = screen.clone();
+        for (x, y) in (40..44).flat_map(|x| (10..14).map(move |y| (x, y))) {
+            edited.put_pixel(x, y, image::Rgba([0, 0, 0, 255]));
+        }
+        let mirrored = image::RgbaImage::from_fn(64, 64, |x, _| image::Rgba([falling(63 - x); 4]));
+
+        for (id, pixels) in [(1, screen.clone()), (2, mirrored), (3, edited), (4, screen)] {
+            let response = app.call(image_upload_request(id, "png", &png(pixels))).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+        }
+
+        let similar = |query: &str| {
+            axum::http::Request::builder()
+                .uri(format!("/frames/1/similar{}", query))
+                .body(axum::body::Body::empty())
+                .unwrap()
+        };
+        let body = response_json(app.call(similar("?threshold=4")).await.unwrap()).await;
+        let ids: Vec<i64> = body["frames"].as_array().unwrap().iter().map(|f| f["id"].as_i64().unwrap()).collect();
+        // Both near-duplicates, never the mirror image
+        assert_eq!(ids, vec![3, 4]);
+        assert_eq!(body["frames"][1]["distance"], 0);
+
+        let body = response_json(app.call(similar("?threshold=64")).await.unwrap()).await;
+        assert_eq!(body["count"], 3);
+        assert_eq!(body["frames"][2]["id"], 2);
+
+        let response = app.call(similar("?threshold=65")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_similar_frames_found_on_disk_and_hashing_is_opt_in() {
+        use tower::Service;
+
+        let png = |shade: u8| {
+            let pixels = image::RgbaImage::from_fn(16, 16, |x, _| image::Rgba([shade.saturating_add(x as u8 * 8), 0, 0, 255]));
+            let mut png = std::io::Cursor::new(Vec::new());
+            image::DynamicImage::ImageRgba8(pixels).write_to(&mut png, image::ImageFormat::Png).unwrap();
+            png.into_inner()
+        };
+
+        // Off by default: uploads aren't decoded for a hash
+        let state = AppState::new(Manager::in_memory(10));
+        *state.agent_connected.write().await = true;
+        let mut app = router(state.clone());
+        app.call(image_upload_request(1, "png", &png(0))).await.unwrap();
+        let stored = state.store.get_by_id(1).await.unwrap();
+        assert!(!stored.metadata.contains_key(similarity::PHASH_FIELD));
+
+        // Disk mode keeps nothing in memory; matches come from disk
+        let dir = std::env::temp_dir().join(format!("eye-similar-disk-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let store = Manager::new(StorageMode::Disk, 10, Some(dir.clone())).await.unwrap();
+        let state = AppState::new(store).with_perceptual_hash(true);
+        *state.agent_connected.write().await = true;
+        let mut app = router(state);
+        for id in 1..=2 {
+            app.call(image_upload_request(id, "png", &png(0))).await.unwrap();
+        }
+        let request = axum::http::Request::builder().uri("/frames/1/similar?threshold=0").body(axum::body::Body::empty()).unwrap();
+        let body = response_json(app.call(request).await.unwrap()).await;
+        assert_eq!(body["count"], 1);
+        assert_eq!(body["frames"][0]["id"], 2);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_fetch_is_access_logged() {
+        use tower::Service;
+
+        let state = AppState::new(Manager::in_memory(10)).with_auth_token(Some("secret".to_string()));
+        *state.agent_connected.write().await = true;
+        let mut app = router(state);
+        let get = |uri: &str, token: Option<&str>| {
+            let mut builder = axum::http::Request::builder().uri(uri);
+            if let Some(token) = token {
+                builder = builder.header(header::AUTHORIZATION, format!("Bearer {}", token));
+            }
+            builder.body(axum::body::Body::empty()).unwrap()
+        };
+
+        let response = app.call(upload_request(7, Some("secret"))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        app.call(get("/snapshot.png", Some("secret"))).await.unwrap();
+        app.call(get("/frames/7", None)).await.unwrap();
+        // Unchecked tokens are not logged as identities
+        app.call(get("/frames/7", Some("forged"))).await.unwrap();
+        // Misses serve no frame and leave no entry
+        app.call(get("/frames/8", None)).await.unwrap();
+
+        let response = app.call(get("/admin/access-log", None)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let body = response_json(app.call(get("/admin/access-log", Some("secret"))).await.unwrap()).await;
+        assert_eq!(body["count"], 3);
+        let entry = &body["entries"][0];
+        assert_eq!(entry["frame_id"], 7);
+        assert_eq!(entry["path"], "/snapshot.png");
+        assert_eq!(entry["identity"], access_log::identity(get("/", Some("secret")).headers()));
+        assert!(entry["identity"].as_str().unwrap().starts_with("bearer:"));
+        assert_eq!(body["entries"][1]["identity"], "anonymous");
+        assert_eq!(body["entries"][2]["identity"], "anonymous");
+
+        // The next page starts after the given entry
+        let body = response_json(app.call(get("/admin/access-log?after=1&limit=1", Some("secret"))).await.unwrap()).await;
+        assert_eq!(body["entries"][0]["seq"], 2);
+        assert_eq!(body["next_after"], 2);
+    }
+
+    #[tokio::test]
+    async fn test_access_log_file_is_appended_in_background() {
+        let path = env::temp_dir().join(format!("eye-access-{}.jsonl", uuid::Uuid::new_v4()));
+        let log = AccessLog::default().with_file(&path).unwrap();
+        log.record(Utc::now(), "anonymous", "/frames/1", &[1, 2]);
+
+        let mut lines = Vec::new();
+        for _ in 0..50 {
+            lines = std::fs::read_to_string(&path).unwrap_or_default().lines().map(str::to_string).collect();
+            if lines.len() == 2 {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        assert_eq!(lines.len(), 2);
+        assert!(lines[1].contains(r#""frame_id":2"#));
+
+        // Numbering carries on after a restart
+        drop(log);
+        let log = AccessLog::default().with_file(&path).unwrap();
+        log.record(Utc::now(), "anonymous", "/frames/3", &[3]);
+        assert_eq!(log.page(0, 10)[0].seq, 3);
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_older_than_max_age_is_stale() {
+        use tower::Service;
+
+        let clock = clock::FakeClock::default();
+        let state = AppState::new(Manager::in_memory(10)).with_clock(Arc::new(clock.clone()));
+        *state.agent_connected.write().await = true;
+        let mut app = router(state);
+        let get = |uri: &str| axum::http::Request::builder().uri(uri).body(axum::body::Body::empty()).unwrap();
+
+        let response = app.call(upload_request(1, None)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        clock.advance(chrono::Duration::minutes(2));
+
+        let response = app.call(get("/snapshot.png?max_age=60")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+        assert_eq!(response.headers()["x-frame-stale"], "true");
+        assert_eq!(response.headers()["x-frame-id"], "1");
+        assert_eq!(response.headers()["x-frame-age"], "120");
+
+        let response = app.call(get("/frames/latest?max_age=60")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+        assert_eq!(response.headers()["x-frame-stale"], "true");
+        assert_eq!(response.headers()["x-frame-id"], "1");
+        assert_eq!(response.headers()["x-frame-age"], "120");
+        let response = app.call(get("/frames/latest?max_age=300")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // A wider window, or none at all, still serves the frame
+        let response = app.call(get("/snapshot.png?max_age=300")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = app.call(get("/snapshot.png")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_backward_clock_keeps_timestamps_monotonic() {
+        use tower::Service;
+
+        let clock = clock::FakeClock::default();
+        let state = AppState::new(Manager::in_memory(10)).with_clock(Arc::new(clock.clone()));
+        *state.agent_connected.write().await = true;
+        let mut app = router(state.clone());
+
+        app.call(upload_request(1, None)).await.unwrap();
+        // NTP steps the clock back a minute
+        clock.advance(chrono::Duration::minutes(-1));
+        let wall = clock::Clock::now(&clock);
+        app.call(upload_request(2, None)).await.unwrap();
+
+        let frames = state.store.list().await;
+        assert_eq!(frames.len(), 2);
+        assert!(frames[1].timestamp > frames[0].timestamp);
+        assert_eq!(frames[1].timestamp - frames[0].timestamp, clock::MONOTONIC_EPSILON);
+        assert_eq!(frames[1].metadata[WALL_TIME_FIELD], wall.to_rfc3339());
+        assert!(!frames[0].metadata.contains_key(WALL_TIME_FIELD));
+    }
+
+    #[tokio::test]
+    async fn test_timings_report_store_latency() {
+        use tower::Service;
+
+        let state = AppState::new(Manager::in_memory(10));
+        *state.agent_connected.write().await = true;
+        let mut app = router(state);
+
+        for id in 1..=3 {
+            let response = app.call(upload_request(id, None)).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+        }
+
+        let request = axum::http::Request::builder()
+            .uri("/admin/timings")
+            .body(axum::body::Body::empty())
+            .unwrap();
+        let body = response_json(app.call(request).await.unwrap()).await;
+        let store = &body["stages"]["store"];
+        assert_eq!(store["count"], 3);
+        assert!(store["max_ms"].as_f64().unwrap() >= store["mean_ms"].as_f64().unwrap());
+        let bucketed: u64 = store["buckets"].as_array().unwrap().iter().map(|b| b["count"].as_u64().unwrap()).sum();
+        assert_eq!(bucketed, 3);
+        assert_eq!(body["stages"]["decode"]["count"], 3);
+    }
+
+    #[tokio::test]
+    async fn test_agents_frame_ids_are_namespaced() {
+        use tower::Service;
+
+        let namespaces = Arc::new(IdNamespaces::default());
+        let state = AppState::new(Manager::in_memory(10)).with_id_namespaces(Some(namespaces));
+        let mut app = router(state.clone());
+        let post = |uri: &str| {
+            axum::http::Request::builder()
+                .method("POST")
+                .uri(uri)
+                .body(axum::body::Body::empty())
+                .unwrap()
+        };
+
+        // Two agents in turn, both starting their counters at 0
+        let mut stored = Vec::new();
+        for _ in 0..2 {
+            let body = response_json(app.call(post("/connect")).await.unwrap()).await;
+            let offset = body["id_offset"].as_i64().unwrap();
+            let body = response_json(app.call(upload_request(0, None)).await.unwrap()).await;
+            assert_eq!(body["frame_id"], offset);
+            stored.push(offset);
+            app.call(post("/disconnect")).await.unwrap();
+        }
+        assert_ne!(stored[0], stored[1]);
+        assert!(stored[1] > stored[0]);
+
+        let frames = state.store.list().await;
+        assert_eq!(frames.iter().map(|f| f.id).collect::<Vec<_>>(), stored);
+        assert!(frames.iter().all(|f| f.metadata[AGENT_FRAME_ID_FIELD] == "0"));
+    }
+
+    #[tokio::test]
+    async fn test_timestamp_frame_ids_fit_a_namespace() {
+        use tower::Service;
+
+        let state = AppState::new(Manager::in_memory(10)).with_id_namespaces(Some(Arc::new(IdNamespaces::default())));
+        let mut app = router(state.clone());
+        let connect = axum::http::Request::builder()
+            .method("POST")
+            .uri("/connect")
+            .body(axum::body::Body::empty())
+            .unwrap();
+        let body = response_json(app.call(connect).await.unwrap()).await;
+        let offset = body["id_offset"].as_i64().unwrap();
+
+        // An agent in timestamp id mode sends epoch milliseconds
+        let local = Utc::now().timestamp_millis();
+        let response = app.call(upload_request(local, None)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response_json(response).await;
+        assert_eq!(body["frame_id"], offset + local);
+
+        let frame = state.store.get_latest().await.unwrap();
+        assert_eq!(frame.metadata[AGENT_FRAME_ID_FIELD], local.to_string());
+    }
+
+    #[tokio::test]
+    async fn test_view_page_embeds_snapshot_with_refresh() {
+        use tower::Service;
+
+        let state = AppState::new(Manager::in_memory(10));
+        *state.agent_connected.write().await = true;
+        let mut app = router(state);
+        let get = |uri: &str| axum::http::Request::builder().uri(uri).body(axum::body::Body::empty()).unwrap();
+        let html = |response: Response| async move {
+            assert_eq!(response.headers()[header::CONTENT_TYPE], "text/html; charset=utf-8");
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            String::from_utf8(body.to_vec()).unwrap()
+        };
+
+        let page = html(app.call(get("/view")).await.unwrap()).await;
+        assert!(page.contains("No frames yet"));
+        assert!(page.contains(r#"<meta http-equiv="refresh" content="1">"#));
+
+        app.call(upload_request(5, None)).await.unwrap();
+        let page = html(app.call(get("/view?refresh=10")).await.unwrap()).await;
+        assert!(page.contains(r#"<img src="frames/5""#));
+        assert!(page.contains("Frame #5"));
+        assert!(page.contains(r#"<meta http-equiv="refresh" content="10">"#));
+
+        // The image link serves that frame
+        let response = app.call(get("/frames/5")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(&body[..], PNG_BYTES);
+    }
+
+    #[tokio::test]
+    async fn test_presigned_snapshot_urls() {
+        use tower::Service;
+
+        let clock = clock::FakeClock::default();
+        let state = AppState::new(Manager::in_memory(10))
+            .with_auth_token(Some("key-a".to_string()))
+            .with_clock(Arc::new(clock.clone()));
+        *state.agent_connected.write().await = true;
+        let mut tenants = Tenants::default();
+        tenants.add("alpha", "key-a", router(state));
+        let mut app = tenants::router(tenants);
+        let get = |uri: &str| axum::http::Request::builder().uri(uri).body(axum::body::Body::empty()).unwrap();
+
+        app.call(upload_request(1, Some("key-a"))).await.unwrap();
+        let presign = axum::http::Request::builder()
+            .method("POST")
+            .uri("/t/alpha/admin/presign")
+            .header(header::AUTHORIZATION, "Bearer key-a")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(axum::body::Body::from(r#"{"ttl": 60}"#))
+            .unwrap();
+        let body = response_json(app.call(presign).await.unwrap()).await;
+        let url = format!("/t/alpha{}", body["url"].as_str().unwrap());
+        assert!(url.starts_with("/t/alpha/snapshot.png?expires="));
+
+        // No key needed with a valid signature, but still without one
+        let response = app.call(get(&url)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["x-frame-id"], "1");
+        let response = app.call(get("/t/alpha/snapshot.png")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        // Tampering with the signature, expiry or path is caught
+        let flipped = if url.ends_with('0') { "1" } else { "0" };
+        let tampered = format!("{}{}", &url[..url.len() - 1], flipped);
+        assert_eq!(app.call(get(&tampered)).await.unwrap().status(), StatusCode::FORBIDDEN);
+        let extended = url.replacen("expires=", "expires=9", 1);
+        assert_eq!(app.call(get(&extended)).await.unwrap().status(), StatusCode::FORBIDDEN);
+        let other_frame = url.replacen("/snapshot.png", "/frames/1", 1);
+        assert_eq!(app.call(get(&other_frame)).await.unwrap().status(), StatusCode::FORBIDDEN);
+
+        clock.advance(chrono::Duration::seconds(61));
+        assert_eq!(app.call(get(&url)).await.unwrap().status(), StatusCode::FORBIDDEN);
     }
 }
\ No newline at end of file
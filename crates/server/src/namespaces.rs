@@ -0,0 +1,56 @@
+// crates/server/src/namespaces.rs
+use std::sync::atomic::{AtomicI64, Ordering};
+
+// Low bits of a global frame id hold the agent's own frame id; the bits
+// above them hold the agent's namespace. 2^43 fits the epoch-millisecond
+// ids of the agent's timestamp id mode until the year 2248 and still
+// leaves 2^20 namespaces.
+pub const NAMESPACE_BITS: u32 = 43;
+
+// Agent-local ids must fall in [0, NAMESPACE_SPAN)
+pub const NAMESPACE_SPAN: i64 = 1 << NAMESPACE_BITS;
+
+// Metadata key keeping the id the agent sent for a namespaced frame
+pub const AGENT_FRAME_ID_FIELD: &str = "agent_frame_id";
+
+// Hands each connecting agent a namespace no other agent has had, so
+// frames from different agents (or from one agent across restarts, which
+// resets its counter) never share an id even if their stores are merged.
+// Shared by every tenant's state.
+#[derive(Debug)]
+pub struct IdNamespaces {
+    next: AtomicI64,
+}
+
+impl Default for IdNamespaces {
+    fn default() -> Self {
+        Self { next: AtomicI64::new(1) }
+    }
+}
+
+impl IdNamespaces {
+    // A fresh namespace, never below `floor`. Callers pass one past the
+    // namespace of the newest stored frame so ids keep rising across a
+    // server restart.
+    pub fn allocate(&self, floor: i64) -> i64 {
+        let previous = self
+            .next
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |next| Some(next.max(floor) + 1))
+            .unwrap_or_else(|next| next);
+        previous.max(floor)
+    }
+}
+
+// Namespace a global id belongs to
+pub fn namespace_of(id: i64) -> i64 {
+    id >> NAMESPACE_BITS
+}
+
+// Global id for an agent's local frame id, or None when the local id
+// does not fit in a namespace
+pub fn global_id(namespace: i64, local: i64) -> Option<i64> {
+    if !(0..NAMESPACE_SPAN).contains(&local) {
+        return None;
+    }
+    namespace.checked_mul(NAMESPACE_SPAN)?.checked_add(local)
+}
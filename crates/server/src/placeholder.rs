@@ -0,0 +1,167 @@
+// crates/server/src/placeholder.rs
+use anyhow::{Context, Result};
+use image::{ImageFormat, Rgb, RgbImage};
+use std::io::Cursor;
+
+// Largest placeholder edge accepted from config, in pixels
+const MAX_SIDE: u32 = 4096;
+
+// Glyph cell of the built-in font, in unscaled pixels
+const GLYPH_WIDTH: u32 = 5;
+const GLYPH_HEIGHT: u32 = 7;
+const GLYPH_SPACING: u32 = 1;
+
+// Image served by /snapshot.png while the store is still empty, so
+// dashboards show a message instead of a broken-image icon
+#[derive(Debug, Clone, PartialEq)]
+pub struct Placeholder {
+    /// Serve the placeholder by default; ?placeholder= overrides per request
+    pub enabled: bool,
+    pub text: String,
+    pub background: Rgb<u8>,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Default for Placeholder {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            text: "No frames yet".to_string(),
+            background: Rgb([32, 32, 32]),
+            width: 640,
+            height: 360,
+        }
+    }
+}
+
+impl Placeholder {
+    // Parse "#rrggbb" (the '#' is optional)
+    pub fn parse_color(s: &str) -> Result<Rgb<u8>> {
+        let hex = s.trim().trim_start_matches('#');
+        if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            anyhow::bail!("Invalid color '{}': expected #rrggbb", s);
+        }
+        let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16);
+        Ok(Rgb([channel(0)?, channel(2)?, channel(4)?]))
+    }
+
+    // Parse "WIDTHxHEIGHT", e.g. "640x360"
+    pub fn parse_size(s: &str) -> Result<(u32, u32)> {
+        let (w, h) = s
+            .trim()
+            .split_once(['x', 'X'])
+            .with_context(|| format!("Invalid size '{}': expected WIDTHxHEIGHT", s))?;
+        let w: u32 = w.trim().parse().with_context(|| format!("Invalid width in '{}'", s))?;
+        let h: u32 = h.trim().parse().with_context(|| format!("Invalid height in '{}'", s))?;
+        if w == 0 || h == 0 || w > MAX_SIDE || h > MAX_SIDE {
+            anyhow::bail!("Size '{}' out of range (1-{} per side)", s, MAX_SIDE);
+        }
+        Ok((w, h))
+    }
+
+    // Render the placeholder as PNG: the text centred in black or white,
+    // whichever contrasts with the background, scaled up as far as it fits
+    pub fn render_png(&self) -> Result<Vec<u8>> {
+        let mut image = RgbImage::from_pixel(self.width, self.height, self.background);
+
+        let [r, g, b] = self.background.0;
+        let luma = 0.299 * r as f32 + 0.587 * g as f32 + 0.114 * b as f32;
+        let ink = if luma > 128.0 { Rgb([0, 0, 0]) } else { Rgb([255, 255, 255]) };
+
+        // Only as many characters as fit at the smallest scale
+        let advance = GLYPH_WIDTH + GLYPH_SPACING;
+        let max_chars = ((self.width + GLYPH_SPACING) / advance) as usize;
+        let text: Vec<char> = self.text.chars().take(max_chars).collect();
+
+        if !text.is_empty() && self.height >= GLYPH_HEIGHT {
+            let text_width = text.len() as u32 * advance - GLYPH_SPACING;
+            // Leave a margin of about a fifth of the image around the text
+            let scale = (self.width * 4 / 5 / text_width)
+                .min(self.height * 4 / 5 / GLYPH_HEIGHT)
+                .max(1);
+            let x0 = (self.width.saturating_sub(text_width * scale)) / 2;
+            let y0 = (self.height.saturating_sub(GLYPH_HEIGHT * scale)) / 2;
+
+            for (i, c) in text.iter().enumerate() {
+                let gx = x0 + i as u32 * advance * scale;
+                for (row, bits) in glyph(*c).iter().enumerate() {
+                    for col in 0..GLYPH_WIDTH {
+                        if bits & (1 << (GLYPH_WIDTH - 1 - col)) == 0 {
+                            continue;
+                        }
+                        for dy in 0..scale {
+                            for dx in 0..scale {
+                                let x = gx + col * scale + dx;
+                                let y = y0 + row as u32 * scale + dy;
+                                if x < self.width && y < self.height {
+                                    image.put_pixel(x, y, ink);
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut png = Vec::new();
+        image
+            .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+            .context("Failed to encode placeholder")?;
+        Ok(png)
+    }
+}
+
+// 5x7 bitmap for `c`, one byte per row with the leftmost pixel in bit 4.
+// Lowercase is drawn as uppercase; unsupported characters as '?'.
+fn glyph(c: char) -> [u8; 7] {
+    match c.to_ascii_uppercase() {
+        '0' => [0x0E, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0E],
+        '1' => [0x04, 0x0C, 0x04, 0x04, 0x04, 0x04, 0x0E],
+        '2' => [0x0E, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1F],
+        '3' => [0x1F, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0E],
+        '4' => [0x02, 0x06, 0x0A, 0x12, 0x1F, 0x02, 0x02],
+        '5' => [0x1F, 0x10, 0x1E, 0x01, 0x01, 0x11, 0x0E],
+        '6' => [0x06, 0x08, 0x10, 0x1E, 0x11, 0x11, 0x0E],
+        '7' => [0x1F, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08],
+        '8' => [0x0E, 0x11, 0x11, 0x0E, 0x11, 0x11, 0x0E],
+        '9' => [0x0E, 0x11, 0x11, 0x0F, 0x01, 0x02, 0x0C],
+        'A' => [0x0E, 0x11, 0x11, 0x1F, 0x11, 0x11, 0x11],
+        'B' => [0x1E, 0x11, 0x11, 0x1E, 0x11, 0x11, 0x1E],
+        'C' => [0x0E, 0x11, 0x10, 0x10, 0x10, 0x11, 0x0E],
+        'D' => [0x1C, 0x12, 0x11, 0x11, 0x11, 0x12, 0x1C],
+        'E' => [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x1F],
+        'F' => [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x10],
+        'G' => [0x0E, 0x11, 0x10, 0x17, 0x11, 0x11, 0x0F],
+        'H' => [0x11, 0x11, 0x11, 0x1F, 0x11, 0x11, 0x11],
+        'I' => [0x0E, 0x04, 0x04, 0x04, 0x04, 0x04, 0x0E],
+        'J' => [0x07, 0x02, 0x02, 0x02, 0x02, 0x12, 0x0C],
+        'K' => [0x11, 0x12, 0x14, 0x18, 0x14, 0x12, 0x11],
+        'L' => [0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x1F],
+        'M' => [0x11, 0x1B, 0x15, 0x15, 0x11, 0x11, 0x11],
+        'N' => [0x11, 0x11, 0x19, 0x15, 0x13, 0x11, 0x11],
+        'O' => [0x0E, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E],
+        'P' => [0x1E, 0x11, 0x11, 0x1E, 0x10, 0x10, 0x10],
+        'Q' => [0x0E, 0x11, 0x11, 0x11, 0x15, 0x12, 0x0D],
+        'R' => [0x1E, 0x11, 0x11, 0x1E, 0x14, 0x12, 0x11],
+        'S' => [0x0F, 0x10, 0x10, 0x0E, 0x01, 0x01, 0x1E],
+        'T' => [0x1F, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04],
+        'U' => [0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E],
+        'V' => [0x11, 0x11, 0x11, 0x11, 0x11, 0x0A, 0x04],
+        'W' => [0x11, 0x11, 0x11, 0x15, 0x15, 0x15, 0x0A],
+        'X' => [0x11, 0x11, 0x0A, 0x04, 0x0A, 0x11, 0x11],
+        'Y' => [0x11, 0x11, 0x11, 0x0A, 0x04, 0x04, 0x04],
+        'Z' => [0x1F, 0x01, 0x02, 0x04, 0x08, 0x10, 0x1F],
+        ' ' => [0x00; 7],
+        '.' => [0x00, 0x00, 0x00, 0x00, 0x00, 0x0C, 0x0C],
+        ',' => [0x00, 0x00, 0x00, 0x00, 0x0C, 0x04, 0x08],
+        '-' => [0x00, 0x00, 0x00, 0x1F, 0x00, 0x00, 0x00],
+        ':' => [0x00, 0x0C, 0x0C, 0x00, 0x0C, 0x0C, 0x00],
+        '!' => [0x04, 0x04, 0x04, 0x04, 0x04, 0x00, 0x04],
+        '/' => [0x00, 0x01, 0x02, 0x04, 0x08, 0x10, 0x00],
+        '(' => [0x02, 0x04, 0x08, 0x08, 0x08, 0x04, 0x02],
+        ')' => [0x08, 0x04, 0x02, 0x02, 0x02, 0x04, 0x08],
+        '\'' => [0x04, 0x04, 0x08, 0x00, 0x00, 0x00, 0x00],
+        _ => [0x0E, 0x11, 0x01, 0x02, 0x04, 0x00, 0x04],
+    }
+}
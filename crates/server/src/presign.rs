@@ -0,0 +1,99 @@
+// crates/server/src/presign.rs
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+
+// Query parameters carrying a presigned URL's expiry (Unix seconds) and
+// hex HMAC-SHA256 signature
+pub const EXPIRES_PARAM: &str = "expires";
+pub const SIGNATURE_PARAM: &str = "signature";
+
+// Why a presigned request was turned away
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rejection {
+    Expired,
+    Invalid,
+}
+
+impl std::fmt::Display for Rejection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Rejection::Expired => write!(f, "Presigned URL has expired"),
+            Rejection::Invalid => write!(f, "Invalid presigned URL signature"),
+        }
+    }
+}
+
+// Signs and checks time-limited read-only URLs, so a snapshot can be
+// embedded elsewhere without handing out the bearer token. A signature
+// covers the path and expiry; it is only valid for GETs of that path.
+pub struct Presigner {
+    key: Vec<u8>,
+}
+
+impl Presigner {
+    // Keyed off a server secret, e.g. the auth token: rotating the secret
+    // revokes every URL signed with it
+    pub fn new(secret: &str) -> Self {
+        let key = Sha256::new()
+            .chain_update(b"eye-presign\0")
+            .chain_update(secret.as_bytes())
+            .finalize()
+            .to_vec();
+        Self { key }
+    }
+
+    // Random key for servers without a secret; URLs die with the process
+    pub fn random() -> Self {
+        Self::new(&format!("{}{}", uuid::Uuid::new_v4(), uuid::Uuid::new_v4()))
+    }
+
+    fn mac(&self, path: &str, expires: i64) -> Hmac<Sha256> {
+        let mut mac = Hmac::<Sha256>::new_from_slice(&self.key).expect("HMAC accepts any key length");
+        mac.update(format!("GET\n{}\n{}", path, expires).as_bytes());
+        mac
+    }
+
+    // `path` with the query parameters that grant access until `expires`
+    pub fn url(&self, path: &str, expires: DateTime<Utc>) -> String {
+        let expires = expires.timestamp();
+        let signature = hex::encode(self.mac(path, expires).finalize().into_bytes());
+        format!("{}?{}={}&{}={}", path, EXPIRES_PARAM, expires, SIGNATURE_PARAM, signature)
+    }
+
+    // Check the signature parameters in `query` against `path` at `now`
+    pub fn verify(&self, path: &str, query: &str, now: DateTime<Utc>) -> Result<(), Rejection> {
+        let param = |name: &str| {
+            query
+                .split('&')
+                .filter_map(|pair| pair.split_once('='))
+                .find_map(|(key, value)| (key == name).then_some(value))
+        };
+        let expires: i64 = param(EXPIRES_PARAM).and_then(|v| v.parse().ok()).ok_or(Rejection::Invalid)?;
+        let signature = param(SIGNATURE_PARAM).and_then(|v| hex::decode(v).ok()).ok_or(Rejection::Invalid)?;
+
+        if !is_presignable(path) {
+            return Err(Rejection::Invalid);
+        }
+        // Constant-time comparison
+        self.mac(path, expires).verify_slice(&signature).map_err(|_| Rejection::Invalid)?;
+        if now.timestamp() > expires {
+            return Err(Rejection::Expired);
+        }
+        Ok(())
+    }
+}
+
+// Read-only paths a URL can be presigned for: the latest snapshot or one
+// frame
+pub fn is_presignable(path: &str) -> bool {
+    path == "/snapshot.png"
+        || path
+            .strip_prefix("/frames/")
+            .is_some_and(|id| id.parse::<i64>().is_ok())
+}
+
+// True when a query string carries a presigned URL's signature
+pub fn is_signed(query: Option<&str>) -> bool {
+    query.is_some_and(|q| q.split('&').any(|pair| pair.starts_with(&format!("{}=", SIGNATURE_PARAM))))
+}
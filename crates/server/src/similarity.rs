@@ -0,0 +1,40 @@
+// crates/server/src/similarity.rs
+use image::DynamicImage;
+
+// Metadata key holding a frame's perceptual hash as 16 hex digits
+pub const PHASH_FIELD: &str = "phash";
+
+// Largest meaningful Hamming distance between two 64-bit hashes
+pub const MAX_DISTANCE: u32 = 64;
+
+// Difference hash: shrink to 9x8 greyscale and record, per row, whether
+// each pixel is brighter than its right-hand neighbour. Robust to scaling,
+// re-encoding and small edits, so recurring screens hash within a few bits.
+pub fn dhash(img: &DynamicImage) -> u64 {
+    let small = img
+        .resize_exact(9, 8, image::imageops::FilterType::Triangle)
+        .to_luma8();
+    let mut hash = 0u64;
+    for y in 0..8 {
+        for x in 0..8 {
+            hash <<= 1;
+            if small.get_pixel(x, y).0[0] > small.get_pixel(x + 1, y).0[0] {
+                hash |= 1;
+            }
+        }
+    }
+    hash
+}
+
+pub fn to_hex(hash: u64) -> String {
+    format!("{:016x}", hash)
+}
+
+pub fn from_hex(hex: &str) -> Option<u64> {
+    u64::from_str_radix(hex, 16).ok()
+}
+
+// Number of differing bits, 0 (same) to 64
+pub fn distance(a: u64, b: u64) -> u32 {
+    (a ^ b).count_ones()
+}
@@ -0,0 +1,138 @@
+// crates/server/src/tenants.rs
+use crate::presign;
+use anyhow::Result;
+use axum::Router;
+use axum::extract::{Request, State};
+use axum::http::{HeaderMap, StatusCode, Uri, header};
+use axum::response::{IntoResponse, Response};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tower::Service;
+
+// Header naming the tenant a request is for
+pub const TENANT_HEADER: &str = "x-tenant";
+
+// Alternative to the header: /t/<tenant>/frames, /t/<tenant>/snapshot.png, ...
+const PATH_PREFIX: &str = "/t/";
+
+// Every tenant's own router, each built over a separate AppState (store,
+// config, agent slot), plus the auth key that unlocks each one. A request
+// only ever reaches the router of the tenant its key belongs to.
+#[derive(Default)]
+pub struct Tenants {
+    routers: HashMap<String, Router>,
+    /// Auth key -> tenant name
+    keys: HashMap<String, String>,
+}
+
+impl Tenants {
+    // Parse "alpha=key-a,beta=key-b" into (tenant, key) pairs
+    pub fn parse(spec: &str) -> Result<Vec<(String, String)>> {
+        let mut tenants: Vec<(String, String)> = Vec::new();
+        for entry in spec.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let (name, key) = entry
+                .split_once('=')
+                .ok_or_else(|| anyhow::anyhow!("expected tenant=key, got '{}'", entry))?;
+            let (name, key) = (name.trim(), key.trim());
+            if !is_valid_name(name) {
+                anyhow::bail!("invalid tenant name '{}' (use a-z, 0-9, '-' and '_')", name);
+            }
+            if key.is_empty() {
+                anyhow::bail!("tenant '{}' has an empty key", name);
+            }
+            if tenants.iter().any(|(n, k)| n == name || k == key) {
+                anyhow::bail!("tenant '{}' or its key is listed twice", name);
+            }
+            tenants.push((name.to_string(), key.to_string()));
+        }
+        Ok(tenants)
+    }
+
+    pub fn add(&mut self, name: impl Into<String>, key: impl Into<String>, router: Router) {
+        let name = name.into();
+        self.keys.insert(key.into(), name.clone());
+        self.routers.insert(name, router);
+    }
+}
+
+// Tenant names double as storage directory names
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= 64
+        && name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_')
+}
+
+// Front router: works out the tenant from the path prefix, X-Tenant or the
+// bearer key, checks the key belongs to it and hands the request on
+pub fn router(tenants: Tenants) -> Router {
+    Router::new().fallback(dispatch).with_state(Arc::new(tenants))
+}
+
+fn bearer(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(header::AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")
+        .map(str::trim)
+}
+
+// Split "/t/alpha/frames?x=1" into ("alpha", "/frames?x=1")
+fn strip_prefix(uri: &Uri) -> Option<(String, Uri)> {
+    let rest = uri.path().strip_prefix(PATH_PREFIX)?;
+    let (tenant, path) = match rest.split_once('/') {
+        Some((tenant, path)) => (tenant, format!("/{}", path)),
+        None => (rest, "/".to_string()),
+    };
+    let path_and_query = match uri.query() {
+        Some(query) => format!("{}?{}", path, query),
+        None => path,
+    };
+    Some((tenant.to_string(), path_and_query.parse().ok()?))
+}
+
+async fn dispatch(State(tenants): State<Arc<Tenants>>, mut req: Request) -> Response {
+    let from_path = strip_prefix(req.uri());
+    let from_header = req
+        .headers()
+        .get(TENANT_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.trim().to_string());
+    let key_tenant = bearer(req.headers()).and_then(|key| tenants.keys.get(key)).cloned();
+
+    let named = match (from_path.as_ref().map(|(t, _)| t), from_header.as_ref()) {
+        (Some(path), Some(header)) if path != header => {
+            return (StatusCode::BAD_REQUEST, "Path prefix and X-Tenant name different tenants")
+                .into_response();
+        }
+        (path, header) => path.or(header).cloned(),
+    };
+    let Some(tenant) = named.or_else(|| key_tenant.clone()) else {
+        return (
+            StatusCode::BAD_REQUEST,
+            "No tenant: send X-Tenant, use /t/<tenant>/... or authenticate with a tenant key",
+        )
+            .into_response();
+    };
+    let Some(router) = tenants.routers.get(&tenant) else {
+        return (StatusCode::NOT_FOUND, format!("Unknown tenant '{}'", tenant)).into_response();
+    };
+    // Every request to a tenant, reads included, needs that tenant's key,
+    // except presigned reads, whose signature the tenant's router checks
+    let presigned = req.method() == axum::http::Method::GET
+        && presign::is_signed(req.uri().query())
+        && presign::is_presignable(from_path.as_ref().map_or(req.uri().path(), |(_, uri)| uri.path()));
+    if !presigned && key_tenant.as_deref() != Some(tenant.as_str()) {
+        return (StatusCode::UNAUTHORIZED, format!("Missing or invalid key for tenant '{}'", tenant))
+            .into_response();
+    }
+
+    if let Some((_, uri)) = from_path {
+        *req.uri_mut() = uri;
+    }
+    // Routers are always ready, so no poll_ready first
+    match router.clone().call(req).await {
+        Ok(response) => response,
+        Err(never) => match never {},
+    }
+}
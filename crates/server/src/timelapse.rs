@@ -0,0 +1,71 @@
+// crates/server/src/timelapse.rs
+use image::DynamicImage;
+use serde::Serialize;
+
+// Side of the greyscale thumbnail frames are compared by
+const FINGERPRINT_SIZE: u32 = 16;
+
+// Frames that differ from the last kept frame by less than this (mean
+// absolute luma difference, 0-1) are dropped as near-duplicates
+pub const SKIP_BELOW: f64 = 0.01;
+
+// A change of this size plays for exactly the base delay; smaller changes
+// play shorter and larger ones dwell longer, within the bounds below
+const REFERENCE_CHANGE: f64 = 0.1;
+const MIN_DWELL: f64 = 0.5;
+const MAX_DWELL: f64 = 4.0;
+
+// One frame of a variable-rate timelapse
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ManifestEntry {
+    pub id: i64,
+    /// How long the frame is shown
+    pub delay_ms: u32,
+    /// Change from the previously kept frame, 0-1; 1 for the first frame
+    pub change: f64,
+}
+
+// 16x16 greyscale thumbnail used to measure change between frames
+pub fn fingerprint(img: &DynamicImage) -> Vec<u8> {
+    img.resize_exact(FINGERPRINT_SIZE, FINGERPRINT_SIZE, image::imageops::FilterType::Triangle)
+        .to_luma8()
+        .into_raw()
+}
+
+// Mean absolute difference between two fingerprints, 0 (identical) to 1
+pub fn change_score(a: &[u8], b: &[u8]) -> f64 {
+    if a.len() != b.len() || a.is_empty() {
+        return 1.0;
+    }
+    let total: u64 = a.iter().zip(b).map(|(x, y)| x.abs_diff(*y) as u64).sum();
+    total as f64 / (a.len() as f64 * 255.0)
+}
+
+// Pick the frames of a variable-rate timelapse from `(id, fingerprint)`
+// pairs in playback order. Near-duplicates of the last kept frame are
+// skipped; every kept frame is shown for `base_delay_ms` scaled by how much
+// it changed, so idle stretches fly by and big changes linger.
+pub fn plan(fingerprints: &[(i64, Vec<u8>)], base_delay_ms: u32) -> Vec<ManifestEntry> {
+    let mut manifest = Vec::new();
+    let mut last: Option<&[u8]> = None;
+
+    for (id, fp) in fingerprints {
+        let change = last.map_or(1.0, |prev| change_score(prev, fp));
+        if last.is_some() && change < SKIP_BELOW {
+            continue;
+        }
+
+        let dwell = match last {
+            Some(_) => (change / REFERENCE_CHANGE).clamp(MIN_DWELL, MAX_DWELL),
+            None => 1.0,
+        };
+        manifest.push(ManifestEntry {
+            id: *id,
+            delay_ms: (base_delay_ms as f64 * dwell).round().max(1.0) as u32,
+            change,
+        });
+        last = Some(fp);
+    }
+
+    manifest
+}
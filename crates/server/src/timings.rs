@@ -0,0 +1,110 @@
+// crates/server/src/timings.rs
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+// Upper bounds of the histogram buckets in milliseconds; anything slower
+// lands in a final overflow bucket
+const BUCKET_BOUNDS_MS: [f64; 12] = [0.5, 1.0, 2.5, 5.0, 10.0, 25.0, 50.0, 100.0, 250.0, 500.0, 1000.0, 2500.0];
+
+// Timed steps of handling a frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    /// Reading and validating the multipart upload body
+    Decode,
+    /// Image work on the transcode pool: canonical transcoding, perceptual
+    /// hashing and ?convert= re-encodes
+    Transcode,
+    /// Writing the frame to the store
+    Store,
+}
+
+impl Stage {
+    const ALL: [Stage; 3] = [Stage::Decode, Stage::Transcode, Stage::Store];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Stage::Decode => "decode",
+            Stage::Transcode => "transcode",
+            Stage::Store => "store",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Histogram {
+    buckets: [u64; BUCKET_BOUNDS_MS.len() + 1],
+    count: u64,
+    total: Duration,
+    max: Duration,
+}
+
+// One histogram bucket as reported by GET /admin/timings
+#[derive(Debug, Clone, Serialize)]
+pub struct Bucket {
+    /// Inclusive upper bound; None for the overflow bucket
+    pub le_ms: Option<f64>,
+    pub count: u64,
+}
+
+// Aggregated timings of one stage
+#[derive(Debug, Clone, Serialize)]
+pub struct StageTimings {
+    pub count: u64,
+    pub total_ms: f64,
+    pub mean_ms: f64,
+    pub max_ms: f64,
+    pub buckets: Vec<Bucket>,
+}
+
+// Per-stage latency histograms since startup, to tell whether slow
+// uploads are spent in storage or in image work
+#[derive(Debug, Default)]
+pub struct Timings {
+    stages: Mutex<[Histogram; Stage::ALL.len()]>,
+}
+
+impl Timings {
+    pub fn record(&self, stage: Stage, elapsed: Duration) {
+        let ms = elapsed.as_secs_f64() * 1000.0;
+        let bucket = BUCKET_BOUNDS_MS
+            .iter()
+            .position(|&bound| ms <= bound)
+            .unwrap_or(BUCKET_BOUNDS_MS.len());
+
+        let mut stages = self.stages.lock().unwrap();
+        let histogram = &mut stages[stage as usize];
+        histogram.buckets[bucket] += 1;
+        histogram.count += 1;
+        histogram.total += elapsed;
+        histogram.max = histogram.max.max(elapsed);
+    }
+
+    // Every stage, including ones with no samples yet
+    pub fn snapshot(&self) -> BTreeMap<&'static str, StageTimings> {
+        let stages = *self.stages.lock().unwrap();
+        let ms = |d: Duration| d.as_secs_f64() * 1000.0;
+
+        Stage::ALL
+            .iter()
+            .map(|&stage| {
+                let histogram = &stages[stage as usize];
+                let buckets = histogram
+                    .buckets
+                    .iter()
+                    .enumerate()
+                    .map(|(i, &count)| Bucket { le_ms: BUCKET_BOUNDS_MS.get(i).copied(), count })
+                    .collect();
+                let timings = StageTimings {
+                    count: histogram.count,
+                    total_ms: ms(histogram.total),
+                    mean_ms: if histogram.count == 0 { 0.0 } else { ms(histogram.total) / histogram.count as f64 },
+                    max_ms: ms(histogram.max),
+                    buckets,
+                };
+                (stage.as_str(), timings)
+            })
+            .collect()
+    }
+}
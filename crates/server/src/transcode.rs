@@ -0,0 +1,94 @@
+// crates/server/src/transcode.rs
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tokio::sync::Semaphore;
+
+// Runs CPU-heavy image work (canonical transcoding, ?convert=, GIFs) on the
+// blocking thread pool, at most `workers` jobs at a time. Jobs beyond that
+// wait their turn, so a burst of conversions can't starve uploads or tie
+// up every blocking thread.
+pub struct TranscodePool {
+    permits: Arc<Semaphore>,
+    workers: usize,
+    queued: Arc<AtomicUsize>,
+    active: Arc<AtomicUsize>,
+}
+
+// Point-in-time pool gauges, reported by /debug
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct PoolStats {
+    pub workers: usize,
+    pub active: usize,
+    /// Jobs waiting for a free worker
+    pub queued: usize,
+}
+
+// Decrements a gauge when dropped, so cancelled requests are not counted
+struct Gauge(Arc<AtomicUsize>);
+
+impl Gauge {
+    fn enter(counter: &Arc<AtomicUsize>) -> Self {
+        counter.fetch_add(1, Ordering::Relaxed);
+        Self(counter.clone())
+    }
+}
+
+impl Drop for Gauge {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+impl TranscodePool {
+    pub fn new(workers: usize) -> Self {
+        let workers = workers.max(1);
+        Self {
+            permits: Arc::new(Semaphore::new(workers)),
+            workers,
+            queued: Arc::new(AtomicUsize::new(0)),
+            active: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    // One worker per available CPU
+    pub fn default_workers() -> usize {
+        std::thread::available_parallelism().map_or(4, |n| n.get())
+    }
+
+    // Run `job` once a worker is free and return its result
+    pub async fn run<T, F>(&self, job: F) -> Result<T>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let queued = Gauge::enter(&self.queued);
+        let permit = self
+            .permits
+            .clone()
+            .acquire_owned()
+            .await
+            .context("Transcode pool closed")?;
+        drop(queued);
+
+        let active = Gauge::enter(&self.active);
+        // The permit moves into the task, so an abandoned request still
+        // holds its worker until the job actually finishes
+        tokio::task::spawn_blocking(move || {
+            let _permit = permit;
+            let _active = active;
+            job()
+        })
+        .await
+        .context("Transcode job panicked")
+    }
+
+    pub fn stats(&self) -> PoolStats {
+        PoolStats {
+            workers: self.workers,
+            active: self.active.load(Ordering::Relaxed),
+            queued: self.queued.load(Ordering::Relaxed),
+        }
+    }
+}
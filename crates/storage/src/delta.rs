@@ -0,0 +1,101 @@
+// crates/storage/src/delta.rs
+use anyhow::{Context, Result};
+
+// Leads every delta-encoded file, so reads can tell a delta from a full
+// frame without any other bookkeeping
+const MAGIC: &[u8] = b"\0EYE-DELTA\0v1\0";
+
+// Magic, keyframe id, keyframe timestamp, reconstructed length
+pub const HEADER_LEN: usize = MAGIC.len() + 8 + 8 + 8;
+
+// Unchanged stretches shorter than this are cheaper to carry as literal
+// bytes than as a new (copy, literal) op of 16 bytes
+const MIN_RUN: usize = 16;
+
+// The keyframe a delta was taken against, as (id, timestamp) — enough to
+// name its file. None for anything that isn't a delta.
+pub fn keyframe_of(data: &[u8]) -> Option<(i64, i64)> {
+    let header = data.strip_prefix(MAGIC)?;
+    let id = i64::from_le_bytes(header.get(0..8)?.try_into().ok()?);
+    let ts = i64::from_le_bytes(header.get(8..16)?.try_into().ok()?);
+    Some((id, ts))
+}
+
+// Bytes of `frame` that match `keyframe` from `at` on
+fn matching_run(keyframe: &[u8], frame: &[u8], at: usize) -> usize {
+    keyframe
+        .get(at..)
+        .unwrap_or_default()
+        .iter()
+        .zip(&frame[at..])
+        .take_while(|(a, b)| a == b)
+        .count()
+}
+
+// Encode `frame` as the bytes that differ from `keyframe`: a header, then
+// ops of (bytes to copy from the keyframe, literal bytes that follow),
+// each length a little-endian u64. Works on the stored bytes, so for raw
+// frames a changed pixel costs exactly its own bytes.
+pub fn encode(keyframe_id: i64, keyframe_ts: i64, keyframe: &[u8], frame: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(HEADER_LEN + frame.len() / 8);
+    out.extend_from_slice(MAGIC);
+    out.extend_from_slice(&keyframe_id.to_le_bytes());
+    out.extend_from_slice(&keyframe_ts.to_le_bytes());
+    out.extend_from_slice(&(frame.len() as u64).to_le_bytes());
+
+    let mut pos = 0;
+    while pos < frame.len() {
+        let copy = matching_run(keyframe, frame, pos);
+        let literal_start = pos + copy;
+        let mut end = literal_start;
+        while end < frame.len() {
+            let run = matching_run(keyframe, frame, end);
+            if run >= MIN_RUN {
+                break;
+            }
+            end += run.max(1);
+        }
+
+        out.extend_from_slice(&(copy as u64).to_le_bytes());
+        out.extend_from_slice(&((end - literal_start) as u64).to_le_bytes());
+        out.extend_from_slice(&frame[literal_start..end]);
+        pos = end;
+    }
+    out
+}
+
+// Rebuild the frame `delta` was encoded from, given its keyframe's bytes
+pub fn decode(keyframe: &[u8], delta: &[u8]) -> Result<Vec<u8>> {
+    let header = delta.strip_prefix(MAGIC).context("Not a delta-encoded frame")?;
+    let len = read_len(header, 16)?;
+    let mut ops = &header[24..];
+
+    let mut frame = Vec::with_capacity(len);
+    while !ops.is_empty() {
+        let copy = read_len(ops, 0)?;
+        let literal = read_len(ops, 8)?;
+        ops = &ops[16..];
+
+        let start = frame.len();
+        let copied = start
+            .checked_add(copy)
+            .and_then(|end| keyframe.get(start..end))
+            .context("Delta reaches past the end of its keyframe")?;
+        frame.extend_from_slice(copied);
+
+        let bytes = ops.get(..literal).context("Truncated delta")?;
+        frame.extend_from_slice(bytes);
+        ops = &ops[literal..];
+    }
+
+    if frame.len() != len {
+        anyhow::bail!("Delta rebuilt {} bytes, expected {}", frame.len(), len);
+    }
+    Ok(frame)
+}
+
+fn read_len(data: &[u8], at: usize) -> Result<usize> {
+    let bytes = data.get(at..at + 8).context("Truncated delta")?;
+    let len = u64::from_le_bytes(bytes.try_into().unwrap());
+    usize::try_from(len).context("Delta length out of range")
+}
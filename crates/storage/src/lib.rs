@@ -2,11 +2,21 @@
 use anyhow::{Context, Result};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::path::PathBuf;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::Duration;
 use tokio::fs;
-use tokio::sync::RwLock;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::sync::{Mutex, RwLock, mpsc, oneshot};
+
+mod delta;
+mod sqlite;
+
+pub use sqlite::SqliteStore;
 
 // Data structure representing a stored frame
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -17,73 +27,714 @@ pub struct Frame {
     pub metadata: HashMap<String, String>,
 }
 
+// A stored frame without its bytes, for listing a range cheaply
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FrameMeta {
+    pub id: i64,
+    pub timestamp: DateTime<Utc>,
+    /// Length of the frame's bytes
+    pub size_bytes: usize,
+    pub metadata: HashMap<String, String>,
+    /// CRC32 of the frame's bytes, where the backend records one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub checksum: Option<u32>,
+}
+
 // Storage modes
 #[derive(Debug, Clone)]
 pub enum StorageMode {
     Memory,
     Disk,
     Hybrid,
+    Sqlite,
+}
+
+impl StorageMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StorageMode::Memory => "memory",
+            StorageMode::Disk => "disk",
+            StorageMode::Hybrid => "hybrid",
+            StorageMode::Sqlite => "sqlite",
+        }
+    }
+}
+
+impl FromStr for StorageMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "memory" => Ok(StorageMode::Memory),
+            "disk" => Ok(StorageMode::Disk),
+            "hybrid" => Ok(StorageMode::Hybrid),
+            "sqlite" => Ok(StorageMode::Sqlite),
+            other => anyhow::bail!("unknown storage mode '{}'", other),
+        }
+    }
+}
+
+// Where a frame ended up after Manager::store
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StorageLocation {
+    /// Backend the frame was written to ("memory", "disk" or "hybrid")
+    pub backend: String,
+    /// File path relative to the disk store root, when persisted to disk
+    pub path: Option<PathBuf>,
+}
+
+// Fill level of the memory store, reported to agents for flow control
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Usage {
+    pub frames: usize,
+    pub max_frames: usize,
+    pub bytes: usize,
+    /// Byte budget, if the store enforces one
+    pub max_bytes: Option<usize>,
+    /// Free space where frames are persisted, for disk and sqlite stores
+    pub disk_free_bytes: Option<u64>,
+}
+
+// Free disk space below which storage starts reporting pressure, rising
+// to 1.0 as the disk fills
+const DISK_PRESSURE_BYTES: u64 = 1024 * 1024 * 1024;
+
+impl Usage {
+    // How close the store is to refusing or losing frames, 0.0..=1.0: the
+    // higher of the byte budget's fill ratio and how far free disk space
+    // has dropped below DISK_PRESSURE_BYTES. A full frame count is not
+    // pressure — the memory store is a ring buffer and is normally full.
+    pub fn pressure(&self) -> f64 {
+        let bytes = match self.max_bytes {
+            Some(0) => 1.0,
+            Some(max_bytes) => (self.bytes as f64 / max_bytes as f64).min(1.0),
+            None => 0.0,
+        };
+        let disk = match self.disk_free_bytes {
+            Some(free) => 1.0 - (free as f64 / DISK_PRESSURE_BYTES as f64).min(1.0),
+            None => 0.0,
+        };
+        bytes.max(disk)
+    }
+}
+
+// Size and time span of what a store holds, for the debug panel
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct StorageStats {
+    /// Frames in memory, pinned ones included
+    pub frame_count: usize,
+    /// Image bytes in memory, counting bytes shared by duplicates once
+    pub total_bytes: usize,
+    /// Time span of every stored frame, on disk as well as in memory
+    pub oldest: Option<DateTime<Utc>>,
+    pub newest: Option<DateTime<Utc>>,
+    /// Frame files on disk, when the store persists to disk
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub disk_frame_count: Option<usize>,
+}
+
+// Range covering every stored frame. Stops at the end of year 9999 rather
+// than DateTime::MAX_UTC, which the sqlite store can't compare as text.
+fn all_time() -> (DateTime<Utc>, DateTime<Utc>) {
+    let end = DateTime::<Utc>::from_timestamp(253_402_300_799, 0).unwrap_or(DateTime::<Utc>::MAX_UTC);
+    (DateTime::<Utc>::UNIX_EPOCH, end)
+}
+
+// Hash chain
+
+// Metadata keys holding a frame's link in the integrity chain
+pub const PREV_HASH_KEY: &str = "prev_hash";
+pub const THIS_HASH_KEY: &str = "this_hash";
+
+// prev_hash of the first frame in a chain
+pub const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+// Hex SHA-256 over the previous hash, id, timestamp and image bytes.
+// Other metadata is not covered.
+pub fn chain_hash(prev_hash: &str, frame: &Frame) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(prev_hash.as_bytes());
+    hasher.update(frame.id.to_le_bytes());
+    hasher.update(frame.timestamp.to_rfc3339().as_bytes());
+    hasher.update(&frame.data);
+    hex::encode(hasher.finalize())
 }
 
 // Memory Store
 pub struct MemoryStore {
-    frames: Arc<RwLock<Vec<Frame>>>,
+    frames: Arc<RwLock<VecDeque<Entry>>>,
     max_frames: usize,
-    current: Arc<RwLock<usize>>,
+    /// Cap on the image bytes held, on top of `max_frames`
+    max_bytes: Option<usize>,
+    max_pinned: usize,
+    /// How many of the newest frames a late arrival may be slotted between
+    reorder_window: usize,
+    /// Hashes of chained frames dropped by eviction. Their successors may
+    /// legitimately point at a missing frame; once a successor is evicted
+    /// too the entry is no longer needed.
+    evicted_hashes: std::sync::Mutex<HashSet<String>>,
+    /// Share the bytes of a frame identical to the latest instead of
+    /// holding a second copy
+    dedup: bool,
+    /// Metadata (key, value) -> ids of the held frames carrying it
+    metadata_index: std::sync::Mutex<MetadataIndex>,
+    /// Unpinned frames timestamped longer ago than this are dropped
+    ttl: Option<Duration>,
+    /// Time source for TTL expiry
+    clock: clock::SharedClock,
+    /// Called with each frame evicted or expired
+    on_evict: Option<Arc<EvictCallback>>,
+}
+
+// Callback receiving frames as they leave a MemoryStore
+pub type EvictCallback = dyn Fn(Frame) + Send + Sync;
+
+// Frames that arrive out of order (e.g. from concurrent uploads) are put
+// back in id order as long as they land within this many of the newest
+// frames. Anything older is treated as a new id sequence, e.g. after an
+// agent restart, and appended.
+pub const DEFAULT_REORDER_WINDOW: usize = 8;
+
+// Metadata key set on a frame stored as a reference to the identical
+// bytes of an earlier frame; the value is that frame's id
+pub const DEDUP_OF_KEY: &str = "dedup_of";
+
+// A stored frame plus its retention state
+struct Entry {
+    /// The frame with its `data` left empty; the bytes live in `data`
+    frame: Frame,
+    /// Image bytes, shared between consecutive duplicate frames. Freed
+    /// once the last frame using them is evicted.
+    data: Arc<Vec<u8>>,
+    /// Pinned frames are exempt from eviction and count toward `max_pinned`
+    /// instead of `max_frames`
+    pinned: bool,
+}
+
+impl Entry {
+    // The stored frame with its bytes filled back in
+    fn resolved(&self) -> Frame {
+        Frame {
+            data: self.data.as_ref().clone(),
+            ..self.frame.clone()
+        }
+    }
+}
+
+// How a metadata query compares a frame's value with the one asked for.
+// Comparisons are case-sensitive.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MetadataMatch {
+    Exact,
+    Prefix,
+    Contains,
+}
+
+impl MetadataMatch {
+    pub fn matches(&self, candidate: &str, value: &str) -> bool {
+        match self {
+            MetadataMatch::Exact => candidate == value,
+            MetadataMatch::Prefix => candidate.starts_with(value),
+            MetadataMatch::Contains => candidate.contains(value),
+        }
+    }
+}
+
+// Inverted index over frame metadata, kept in step with the frames a
+// MemoryStore holds so lookups by value don't scan every frame. Values are
+// sorted per key, so prefix queries read one run of them and contains
+// queries check each distinct value once. Ids can repeat after an agent
+// restart, so each posting counts its frames.
+#[derive(Default)]
+struct MetadataIndex {
+    postings: HashMap<String, BTreeMap<String, BTreeMap<i64, usize>>>,
+}
+
+impl MetadataIndex {
+    fn insert(&mut self, frame: &Frame) {
+        for (key, value) in &frame.metadata {
+            *self
+                .postings
+                .entry(key.clone())
+                .or_default()
+                .entry(value.clone())
+                .or_default()
+                .entry(frame.id)
+                .or_default() += 1;
+        }
+    }
+
+    fn remove(&mut self, frame: &Frame) {
+        for (key, value) in &frame.metadata {
+            let Some(values) = self.postings.get_mut(key) else {
+                continue;
+            };
+            let Some(ids) = values.get_mut(value) else {
+                continue;
+            };
+            if let Some(count) = ids.get_mut(&frame.id) {
+                *count -= 1;
+                if *count == 0 {
+                    ids.remove(&frame.id);
+                }
+            }
+            if ids.is_empty() {
+                values.remove(value);
+            }
+            if values.is_empty() {
+                self.postings.remove(key);
+            }
+        }
+    }
+
+    // Ids of frames whose `key` matches `value`, ascending
+    fn query(&self, key: &str, value: &str, how: MetadataMatch) -> Vec<i64> {
+        let Some(values) = self.postings.get(key) else {
+            return Vec::new();
+        };
+        let ids: BTreeSet<i64> = match how {
+            MetadataMatch::Exact => values.get(value).into_iter().flat_map(|ids| ids.keys()).copied().collect(),
+            MetadataMatch::Prefix => values
+                .range::<str, _>((std::ops::Bound::Included(value), std::ops::Bound::Unbounded))
+                .take_while(|(candidate, _)| candidate.starts_with(value))
+                .flat_map(|(_, ids)| ids.keys())
+                .copied()
+                .collect(),
+            MetadataMatch::Contains => values
+                .iter()
+                .filter(|(candidate, _)| candidate.contains(value))
+                .flat_map(|(_, ids)| ids.keys())
+                .copied()
+                .collect(),
+        };
+        ids.into_iter().collect()
+    }
+}
+
+// Total length of the distinct byte buffers behind `frames`
+fn held_bytes<'a>(frames: impl IntoIterator<Item = &'a Entry>) -> usize {
+    let mut seen = HashSet::new();
+    frames
+        .into_iter()
+        .filter(|e| seen.insert(Arc::as_ptr(&e.data)))
+        .map(|e| e.data.len())
+        .sum()
 }
 
 // Implementation of MemoryStore
 impl MemoryStore {
     pub fn new(max_frames: usize) -> Self {
         Self {
-            frames: Arc::new(RwLock::new(Vec::with_capacity(max_frames))),
+            frames: Arc::new(RwLock::new(VecDeque::with_capacity(max_frames))),
             max_frames,
-            current: Arc::new(RwLock::new(0)),
+            max_bytes: None,
+            max_pinned: max_frames,
+            reorder_window: DEFAULT_REORDER_WINDOW,
+            evicted_hashes: std::sync::Mutex::new(HashSet::new()),
+            dedup: false,
+            metadata_index: std::sync::Mutex::new(MetadataIndex::default()),
+            ttl: None,
+            clock: clock::system(),
+            on_evict: None,
+        }
+    }
+
+    // Store a frame whose bytes equal the latest frame's as a reference to
+    // those bytes. It still gets its own id, timestamp and metadata.
+    pub fn with_dedup(mut self) -> Self {
+        self.dedup = true;
+        self
+    }
+
+    // Also evict the oldest unpinned frames while the image bytes held
+    // exceed `max_bytes`. The newest frame is always kept, so a frame
+    // larger than the limit is stored and evicts every other unpinned one.
+    pub fn with_byte_limit(mut self, max_bytes: usize) -> Self {
+        self.max_bytes = Some(max_bytes);
+        self
+    }
+
+    // Drop unpinned frames once their timestamp is more than `ttl` old.
+    // Expiry is lazy: expired frames are removed when the store is next
+    // written or read, with no background task.
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    pub fn with_clock(mut self, clock: clock::SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    // Call `callback` with every frame that leaves the store through
+    // eviction or TTL expiry — e.g. to spill it to disk or count it. Each
+    // removed frame is passed exactly once, oldest first within one store
+    // call, after the store's lock is released, so the callback may read
+    // from or store into the store. Callbacks from concurrent store calls
+    // may interleave.
+    pub fn on_evict(mut self, callback: impl Fn(Frame) + Send + Sync + 'static) -> Self {
+        self.on_evict = Some(Arc::new(callback));
+        self
+    }
+
+    // Set the reordering window; 0 stores frames strictly in arrival order
+    pub fn with_reorder_window(mut self, window: usize) -> Self {
+        self.reorder_window = window;
+        self
+    }
+
+    // Set how many frames may be pinned at once (defaults to max_frames)
+    pub fn with_max_pinned(mut self, max_pinned: usize) -> Self {
+        self.max_pinned = max_pinned;
+        self
+    }
+
+    // Store a frame in memory, evicting the oldest unpinned frames once
+    // more than max_frames, or more than the byte limit, are held. A frame
+    // whose id is lower than the newest is slotted into id order within
+    // the reordering window, so get_latest always returns the highest id
+    // of the current sequence.
+    pub async fn store(&self, mut frame: Frame) -> Result<()> {
+        let mut frames = self.frames.write().await;
+
+        let data = match frames.back() {
+            Some(latest) if self.dedup && *latest.data == frame.data => {
+                // Point at the frame that first held the bytes
+                let original = latest
+                    .frame
+                    .metadata
+                    .get(DEDUP_OF_KEY)
+                    .cloned()
+                    .unwrap_or_else(|| latest.frame.id.to_string());
+                frame.metadata.insert(DEDUP_OF_KEY.to_string(), original);
+                frame.data = Vec::new();
+                latest.data.clone()
+            }
+            _ => Arc::new(std::mem::take(&mut frame.data)),
+        };
+
+        self.metadata_index.lock().unwrap().insert(&frame);
+        let position = self.insert_position(&frames, frame.id);
+        frames.insert(position, Entry { frame, data, pinned: false });
+        let mut evicted = self.expire(&mut frames);
+        evicted.extend(self.evict(&mut frames));
+        drop(frames);
+        self.notify_evicted(evicted);
+
+        Ok(())
+    }
+
+    // Index at which a frame with `id` keeps the newest frames in id order
+    fn insert_position(&self, frames: &VecDeque<Entry>, id: i64) -> usize {
+        let newer = frames
+            .iter()
+            .rev()
+            .take(self.reorder_window)
+            .take_while(|e| e.frame.id > id)
+            .count();
+
+        // Older than the whole window while more frames lie beyond it: a
+        // fresh sequence rather than a late frame
+        if newer == self.reorder_window && frames.len() > newer {
+            return frames.len();
         }
+        frames.len() - newer
     }
 
-    // Store a frame in memory
-    pub async fn store(&self, frame: Frame) -> Result<()> {
+    // Pin the most recent frame with this ID so it survives eviction.
+    // Fails when the pinned cap is already reached.
+    pub async fn pin(&self, id: i64) -> Result<()> {
         let mut frames = self.frames.write().await;
-        let mut current = self.current.write().await;
+        let pinned = frames.iter().filter(|e| e.pinned).count();
+
+        let entry = frames
+            .iter_mut()
+            .rev()
+            .find(|e| e.frame.id == id)
+            .ok_or_else(|| anyhow::anyhow!("frame {} not found", id))?;
 
-        if frames.len() < self.max_frames {
-            frames.push(frame);
-        } else {
-            frames[*current] = frame;
-            *current = (*current + 1) % self.max_frames;
+        if entry.pinned {
+            return Ok(());
+        }
+        if pinned >= self.max_pinned {
+            anyhow::bail!("pinned frame limit ({}) reached", self.max_pinned);
         }
 
+        entry.pinned = true;
         Ok(())
     }
 
-    // Retrieve the latest frame
-    pub async fn get_latest(&self) -> Result<Frame> {
+    // Release a pinned frame back into normal eviction. If the store is
+    // over capacity the oldest unpinned frames are evicted immediately.
+    pub async fn unpin(&self, id: i64) -> Result<()> {
+        let mut frames = self.frames.write().await;
+
+        let entry = frames
+            .iter_mut()
+            .rev()
+            .find(|e| e.frame.id == id && e.pinned)
+            .ok_or_else(|| anyhow::anyhow!("frame {} is not pinned", id))?;
+        entry.pinned = false;
+
+        let evicted = self.evict(&mut frames);
+        drop(frames);
+        self.notify_evicted(evicted);
+        Ok(())
+    }
+
+    // Remove every frame with this id, pinned or not, returning whether
+    // there was one. Deleted frames aren't passed to on_evict, and a
+    // deleted chained frame is reported by verify_chain as a break, as it
+    // is on disk.
+    pub async fn delete(&self, id: i64) -> Result<bool> {
+        let mut frames = self.frames.write().await;
+        Ok(!self.remove_where(&mut frames, |e| e.frame.id == id).is_empty())
+    }
+
+    // Remove every frame timestamped before `ts`, pinned or not, returning
+    // how many were dropped. Like eviction, pruning the oldest frames is
+    // not a chain break.
+    pub async fn prune_before(&self, ts: DateTime<Utc>) -> usize {
+        let mut frames = self.frames.write().await;
+        let pruned = self.remove_where(&mut frames, |e| e.frame.timestamp < ts);
+        for entry in &pruned {
+            self.record_eviction(&entry.frame);
+        }
+        pruned.len()
+    }
+
+    fn remove_where(&self, frames: &mut VecDeque<Entry>, doomed: impl Fn(&Entry) -> bool) -> Vec<Entry> {
+        let mut removed = Vec::new();
+        let mut idx = 0;
+        while idx < frames.len() {
+            if doomed(&frames[idx]) {
+                removed.extend(self.remove_entry(frames, idx));
+            } else {
+                idx += 1;
+            }
+        }
+        removed
+    }
+
+    // Whether the most recent frame with this ID is pinned
+    pub async fn is_pinned(&self, id: i64) -> bool {
+        let frames = self.frames.read().await;
+        frames
+            .iter()
+            .rev()
+            .find(|e| e.frame.id == id)
+            .is_some_and(|e| e.pinned)
+    }
+
+    // Current number of unpinned frames and total bytes held. Bytes shared
+    // by duplicate frames are counted once.
+    pub async fn usage(&self) -> Usage {
+        let frames = self.frames.read().await;
+
+        Usage {
+            frames: frames.iter().filter(|e| !e.pinned).count(),
+            max_frames: self.max_frames,
+            bytes: held_bytes(frames.iter()),
+            max_bytes: self.max_bytes,
+            disk_free_bytes: None,
+        }
+    }
+
+    // Frame count, bytes and time span, in one pass under the read lock
+    pub async fn stats(&self) -> StorageStats {
+        let frames = self.frames.read().await;
+
+        let mut stats = StorageStats::default();
+        let mut seen = HashSet::new();
+        for e in frames.iter() {
+            stats.frame_count += 1;
+            if seen.insert(Arc::as_ptr(&e.data)) {
+                stats.total_bytes += e.data.len();
+            }
+            let ts = e.frame.timestamp;
+            stats.oldest = Some(stats.oldest.map_or(ts, |oldest| oldest.min(ts)));
+            stats.newest = Some(stats.newest.map_or(ts, |newest| newest.max(ts)));
+        }
+        stats
+    }
+
+    // Image bytes held, counting bytes shared by duplicates once
+    pub async fn current_bytes(&self) -> usize {
+        held_bytes(self.frames.read().await.iter())
+    }
+
+    // Distinct byte buffers held; lower than the frame count when
+    // duplicates share their bytes
+    pub async fn blob_count(&self) -> usize {
         let frames = self.frames.read().await;
-        let current = self.current.read().await;
+        frames.iter().map(|e| Arc::as_ptr(&e.data)).collect::<HashSet<_>>().len()
+    }
+
+    // The memory store can't fail, so this only reports its fill level
+    pub async fn storage_health(&self) -> StorageHealth {
+        let usage = self.usage().await;
+        StorageHealth {
+            backend: "memory".to_string(),
+            healthy: true,
+            error: None,
+            frames: usage.frames,
+            bytes: usage.bytes as u64,
+            free_bytes: None,
+            last_write_error: None,
+            degraded: false,
+        }
+    }
+
+    // Remove unpinned frames older than the TTL, returning them oldest
+    // first
+    fn expire(&self, frames: &mut VecDeque<Entry>) -> Vec<Entry> {
+        let mut expired = Vec::new();
+        let Some(ttl) = self.ttl else {
+            return expired;
+        };
+        let cutoff = self.clock.now() - chrono::Duration::from_std(ttl).unwrap_or(chrono::Duration::MAX);
+
+        let mut idx = 0;
+        while idx < frames.len() {
+            if !frames[idx].pinned && frames[idx].frame.timestamp < cutoff {
+                if let Some(entry) = self.remove_entry(frames, idx) {
+                    self.record_eviction(&entry.frame);
+                    expired.push(entry);
+                }
+            } else {
+                idx += 1;
+            }
+        }
+        expired
+    }
+
+    // Apply the TTL before a read. Only takes the write lock when a TTL is
+    // set.
+    async fn expire_now(&self) {
+        if self.ttl.is_some() {
+            let expired = self.expire(&mut *self.frames.write().await);
+            self.notify_evicted(expired);
+        }
+    }
+
+    // Drop the oldest unpinned frames until at most max_frames (and the
+    // byte limit) remain, returning them oldest first
+    fn evict(&self, frames: &mut VecDeque<Entry>) -> Vec<Entry> {
+        let mut evicted = Vec::new();
+        let mut unpinned = frames.iter().filter(|e| !e.pinned).count();
+        let mut bytes = self.max_bytes.map(|_| held_bytes(frames.iter()));
+        let over_bytes = |bytes: Option<usize>| bytes.zip(self.max_bytes).is_some_and(|(held, max)| held > max);
+
+        while unpinned > self.max_frames || (unpinned > 1 && over_bytes(bytes)) {
+            match frames.iter().position(|e| !e.pinned) {
+                Some(idx) => {
+                    if let Some(entry) = self.remove_entry(frames, idx) {
+                        self.record_eviction(&entry.frame);
+                        // Shared bytes are only freed with their last frame
+                        if let Some(held) = bytes.as_mut()
+                            && Arc::strong_count(&entry.data) == 1
+                        {
+                            *held -= entry.data.len();
+                        }
+                        evicted.push(entry);
+                    }
+                    unpinned -= 1;
+                }
+                None => break,
+            }
+        }
+        evicted
+    }
+
+    // Take a frame out of the store and every index that refers to it.
+    // Callers retiring the frame (eviction, expiry, pruning) record it
+    // with record_eviction.
+    fn remove_entry(&self, frames: &mut VecDeque<Entry>, idx: usize) -> Option<Entry> {
+        let entry = frames.remove(idx)?;
+        self.metadata_index.lock().unwrap().remove(&entry.frame);
+        Some(entry)
+    }
 
-        if frames.is_empty() {
-            anyhow::bail!("no frames available");
+    // Hand evicted frames to the on_evict callback. Called once the frames
+    // lock is released, so the callback may use the store.
+    fn notify_evicted(&self, evicted: Vec<Entry>) {
+        if let Some(on_evict) = &self.on_evict {
+            for entry in evicted {
+                on_evict(entry.resolved());
+            }
         }
+    }
 
-        let frame = if frames.len() < self.max_frames {
-            frames.last().unwrap()
-        } else {
-            let idx = (*current + self.max_frames - 1) % self.max_frames;
-            &frames[idx]
+    // Remember an evicted chained frame so its successor isn't reported as
+    // a break. Each hash has exactly one successor, so the frame's own
+    // predecessor entry can go.
+    fn record_eviction(&self, frame: &Frame) {
+        let Some(hash) = frame.metadata.get(THIS_HASH_KEY) else {
+            return;
         };
 
-        Ok(frame.clone())
+        let mut evicted = self.evicted_hashes.lock().unwrap();
+        if let Some(prev) = frame.metadata.get(PREV_HASH_KEY) {
+            evicted.remove(prev);
+        }
+        evicted.insert(hash.clone());
+    }
+
+    // Ids of chained frames where the integrity chain is broken: the
+    // frame's bytes no longer match its hash, or the frame it links to is
+    // missing without having been evicted
+    pub async fn verify_chain(&self) -> Vec<i64> {
+        let frames = self.frames.read().await;
+        let evicted = self.evicted_hashes.lock().unwrap();
+
+        let held: HashSet<&str> = frames
+            .iter()
+            .filter_map(|e| e.frame.metadata.get(THIS_HASH_KEY))
+            .map(String::as_str)
+            .collect();
+
+        frames
+            .iter()
+            .map(Entry::resolved)
+            .filter(|f| {
+                let (Some(prev), Some(hash)) =
+                    (f.metadata.get(PREV_HASH_KEY), f.metadata.get(THIS_HASH_KEY))
+                else {
+                    return false;
+                };
+
+                let linked = prev == GENESIS_HASH
+                    || held.contains(prev.as_str())
+                    || evicted.contains(prev);
+                chain_hash(prev, f) != *hash || !linked
+            })
+            .map(|f| f.id)
+            .collect()
     }
 
-    // Retrieve a single frame by its ID
+    // Retrieve the latest frame (highest id of the current sequence)
+    pub async fn get_latest(&self) -> Result<Frame> {
+        self.expire_now().await;
+        let frames = self.frames.read().await;
+
+        frames
+            .back()
+            .map(Entry::resolved)
+            .ok_or_else(|| anyhow::anyhow!("no frames available"))
+    }
+
+    // Retrieve a single frame by its ID. Ids repeat once an agent restarts
+    // its counter; the most recently stored frame with the id wins.
     pub async fn get_by_id(&self, id: i64) -> Result<Frame> {
+        self.expire_now().await;
         let frames = self.frames.read().await;
 
         frames
             .iter()
-            .find(|f| f.id == id)
-            .cloned()
+            .rev()
+            .find(|e| e.frame.id == id)
+            .map(Entry::resolved)
             .ok_or_else(|| anyhow::anyhow!("frame {} not found", id))
     }
 
@@ -93,125 +744,1801 @@ impl MemoryStore {
         from: DateTime<Utc>,
         to: DateTime<Utc>,
     ) -> Vec<Frame> {
+        self.expire_now().await;
         let frames = self.frames.read().await;
 
         frames
             .iter()
-            .filter(|f| f.timestamp >= from && f.timestamp <= to)
-            .cloned()
+            .filter(|e| e.frame.timestamp >= from && e.frame.timestamp <= to)
+            .map(Entry::resolved)
             .collect()
     }
 
-    // List all stored frames
+    // Frames timestamped within [start, end], oldest first rather than in
+    // id order; empty when start > end
+    pub async fn get_range(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> Vec<Frame> {
+        let mut frames = self.get_in_range(start, end).await;
+        frames.sort_by_key(|f| f.timestamp);
+        frames
+    }
+
+    // get_range without copying any frame's bytes
+    pub async fn list_range_meta(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> Vec<FrameMeta> {
+        self.expire_now().await;
+        let frames = self.frames.read().await;
+
+        let mut found: Vec<FrameMeta> = frames
+            .iter()
+            .filter(|e| e.frame.timestamp >= start && e.frame.timestamp <= end)
+            .map(|e| FrameMeta {
+                id: e.frame.id,
+                timestamp: e.frame.timestamp,
+                size_bytes: e.data.len(),
+                metadata: e.frame.metadata.clone(),
+                checksum: None,
+            })
+            .collect();
+        found.sort_by_key(|m| m.timestamp);
+        found
+    }
+
+    // Every frame held, oldest first. Eviction pops the front of the
+    // deque, so this stays chronological once the store is full.
     pub async fn list(&self) -> Vec<Frame> {
+        self.expire_now().await;
         let frames = self.frames.read().await;
-        frames.clone()
+        frames.iter().map(Entry::resolved).collect()
+    }
+
+    // Ids of held frames whose metadata value for `key` matches `value`,
+    // ascending, answered from the metadata index
+    pub fn query_metadata(&self, key: &str, value: &str, how: MetadataMatch) -> Vec<i64> {
+        self.metadata_index.lock().unwrap().query(key, value, how)
     }
 }
 
-// Disk Store
-pub struct DiskStore {
-    base_path: PathBuf,
+// Disk write batching: frames are queued to a background writer and
+// written together once `max_frames` are pending or `max_delay` has passed
+// since the first of them, whichever comes first. Frames still queued are
+// lost if the process dies before a flush.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WriteBatching {
+    pub max_frames: usize,
+    pub max_delay: Duration,
 }
 
-// Implementation of DiskStore
-impl DiskStore {
-    pub async fn new(base_path: PathBuf) -> Result<Self> {
-        fs::create_dir_all(&base_path)
-            .await
-            .context("Failed to create storage directory")?;
+enum WriterMsg {
+    /// Id of the frame the file belongs to, its path and its bytes
+    Write(i64, PathBuf, Vec<u8>),
+    Flush(oneshot::Sender<Result<()>>),
+}
 
-        Ok(Self { base_path })
-    }
+// Suffix of the temporary file a frame is written to before being renamed
+// into place. Compaction removes any left behind by a crash.
+const TEMP_SUFFIX: &str = ".tmp";
 
-    // Store a frame on disk
-    pub async fn store(&self, frame: &Frame) -> Result<()> {
-        let filename = format!("frame_{}_{}.png", frame.id, frame.timestamp.timestamp());
-        let filepath = self.base_path.join(filename);
+fn temp_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(TEMP_SUFFIX);
+    PathBuf::from(name)
+}
 
-        fs::write(&filepath, &frame.data)
-            .await
-            .context("Failed to write frame to disk")?;
+// Up to the first `len` bytes of a file
+async fn read_prefix(path: &Path, len: usize) -> std::io::Result<Vec<u8>> {
+    let mut prefix = Vec::with_capacity(len);
+    fs::File::open(path).await?.take(len as u64).read_to_end(&mut prefix).await?;
+    Ok(prefix)
+}
 
-        Ok(())
+// Write and fsync `data` to a temporary file beside `path`, then rename it
+// into place, so a crash, power loss or failed write never leaves a torn
+// file under the real name. The temporary file is removed on failure.
+async fn write_atomic(path: &Path, data: &[u8]) -> Result<()> {
+    let tmp = temp_path(path);
+    let written = async {
+        let mut file = fs::File::create(&tmp).await?;
+        file.write_all(data).await?;
+        file.flush().await?;
+        file.sync_all().await
     }
-
-    // Retrieve the latest frame from disk (not implemented)
-    pub async fn get_latest(&self) -> Result<Frame> {
-        anyhow::bail!("not implemented")
+    .await;
+    if let Err(e) = written {
+        let _ = fs::remove_file(&tmp).await;
+        return Err(anyhow::Error::new(e).context(format!("Failed to write {}", tmp.display())));
     }
+    if let Err(e) = fs::rename(&tmp, path).await {
+        let _ = fs::remove_file(&tmp).await;
+        return Err(anyhow::Error::new(e).context(format!("Failed to move {} into place", tmp.display())));
+    }
+    Ok(())
 }
 
-// Storage Manager
-pub struct Manager {
-    memory: MemoryStore,
-    disk: Option<DiskStore>,
-    mode: StorageMode,
-}
-
-// Implementation of Storage Manager
-impl Manager {
-    pub async fn new(
-        mode: StorageMode,
-        memory_size: usize,
-        disk_path: Option<PathBuf>,
-    ) -> Result<Self> {
-        let memory = MemoryStore::new(memory_size);
+// Write and fsync a batch of files, each to a temporary file renamed into
+// place once synced. Every file is attempted; the first error is returned.
+// The ids of frames with a file that failed go in `failed`, and their
+// other files are removed so no frame is left half written.
+async fn write_batch(pending: &mut Vec<(i64, PathBuf, Vec<u8>)>, failed: &mut Vec<i64>) -> Result<()> {
+    let mut result = Ok(());
+    let mut written = Vec::with_capacity(pending.len());
 
-        let disk = match &mode {
-            StorageMode::Disk | StorageMode::Hybrid => {
-                let path = disk_path.context("Disk path required for disk/hybrid mode")?;
-                Some(DiskStore::new(path).await?)
+    for (id, path, data) in pending.drain(..) {
+        let tmp = temp_path(&path);
+        let file = async {
+            let mut file = fs::File::create(&tmp).await?;
+            file.write_all(&data).await?;
+            // tokio buffers the write; flush so a full disk fails it here
+            file.flush().await?;
+            Ok::<_, std::io::Error>(file)
+        }
+        .await;
+        match file {
+            Ok(file) => written.push((id, file, tmp, path)),
+            Err(e) => {
+                let _ = fs::remove_file(&tmp).await;
+                failed.push(id);
+                if result.is_ok() {
+                    result = Err(anyhow::Error::new(e)
+                        .context(format!("Failed to write {}", path.display())));
+                }
             }
-            _ => None,
-        };
-
-        Ok(Self { memory, disk, mode })
+        }
     }
 
-    // Store a frame based on the storage mode
-    pub async fn store(&self, frame: Frame) -> Result<()> {
-        match self.mode {
-            StorageMode::Memory | StorageMode::Hybrid => {
-                self.memory.store(frame.clone()).await?;
+    // Sync once the whole batch is written so the disk sees one burst
+    let mut moved_into_place = Vec::with_capacity(written.len());
+    for (id, file, tmp, path) in written {
+        let synced = file.sync_all().await.context("Failed to sync frame to disk");
+        drop(file);
+        let moved = match synced {
+            Ok(()) => fs::rename(&tmp, &path)
+                .await
+                .with_context(|| format!("Failed to move {} into place", tmp.display())),
+            Err(e) => Err(e),
+        };
+        match moved {
+            Ok(()) => moved_into_place.push((id, path)),
+            Err(e) => {
+                let _ = fs::remove_file(&tmp).await;
+                failed.push(id);
+                if result.is_ok() {
+                    result = Err(e);
+                }
             }
-            _ => {}
         }
+    }
 
-        match (&self.mode, &self.disk) {
-            (StorageMode::Disk, Some(disk)) | (StorageMode::Hybrid, Some(disk)) => {
-                disk.store(&frame).await?;
-            }
-            _ => {}
+    for (id, path) in moved_into_place {
+        if failed.contains(&id) {
+            let _ = fs::remove_file(&path).await;
         }
-
-        Ok(())
     }
+    result
+}
 
-    // Retrieve the latest frame from memory
-    pub async fn get_latest(&self) -> Result<Frame> {
-        self.memory.get_latest().await
-    }
+// What the background writer shares with its DiskStore
+struct WriterShared {
+    last_error: Arc<std::sync::Mutex<Option<WriteError>>>,
+    index: Arc<std::sync::Mutex<FrameIndex>>,
+    delta: Option<Arc<std::sync::Mutex<DeltaState>>>,
+    out_of_space: Arc<AtomicBool>,
+}
 
-    pub async fn list(&self) -> Vec<Frame> {
-        self.memory.list().await
+impl WriterShared {
+    // Write a batch and settle its outcome: frames that didn't land are
+    // dropped from the index, and running out of space sets the flag a
+    // hybrid Manager reports as degraded until a later batch succeeds
+    async fn write(&self, pending: &mut Vec<(i64, PathBuf, Vec<u8>)>) -> Result<()> {
+        let mut failed = Vec::new();
+        let result = write_batch(pending, &mut failed).await;
+        match &result {
+            Ok(()) => {
+                if self.out_of_space.swap(false, Ordering::Relaxed) {
+                    tracing::info!("Batched disk writes succeeding again");
+                }
+            }
+            Err(e) => {
+                record_write_error(&self.last_error, e);
+                if is_out_of_space(e) && !self.out_of_space.swap(true, Ordering::Relaxed) {
+                    tracing::warn!("Disk full ({:#}) — batched frames are not being written", e);
+                }
+                let mut index = self.index.lock().unwrap();
+                for id in &failed {
+                    index.remove(*id);
+                }
+                drop(index);
+                // Don't leave later deltas against a keyframe that never
+                // landed
+                if let Some(delta) = &self.delta {
+                    let mut state = delta.lock().unwrap();
+                    if state.keyframe.as_ref().is_some_and(|(id, ..)| failed.contains(id)) {
+                        state.keyframe = None;
+                    }
+                }
+            }
+        }
+        result
     }
 }
 
-// Unit tests
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[tokio::test]
-    async fn test_memory_store() {
-        let store = MemoryStore::new(10);
+// Background task behind a batching DiskStore. A failed batch write is
+// reported by the next flush and recorded in the store's last write error.
+async fn run_writer(mut rx: mpsc::Receiver<WriterMsg>, batching: WriteBatching, shared: WriterShared) {
+    let mut pending = Vec::new();
+    let mut deadline: Option<tokio::time::Instant> = None;
+    let mut failure: Option<anyhow::Error> = None;
 
-        let frame = Frame {
-            id: 1,
-            data: vec![1, 2, 3],
-            timestamp: Utc::now(),
-            metadata: HashMap::new(),
+    loop {
+        let msg = match deadline {
+            Some(at) => tokio::select! {
+                msg = rx.recv() => msg,
+                _ = tokio::time::sleep_until(at) => {
+                    if let Err(e) = shared.write(&mut pending).await {
+                        failure.get_or_insert(e);
+                    }
+                    deadline = None;
+                    continue;
+                }
+            },
+            None => rx.recv().await,
+        };
+
+        match msg {
+            Some(WriterMsg::Write(id, path, data)) => {
+                pending.push((id, path, data));
+                deadline.get_or_insert_with(|| tokio::time::Instant::now() + batching.max_delay);
+                if pending.len() >= batching.max_frames {
+                    if let Err(e) = shared.write(&mut pending).await {
+                        failure.get_or_insert(e);
+                    }
+                    deadline = None;
+                }
+            }
+            Some(WriterMsg::Flush(ack)) => {
+                let result = shared.write(&mut pending).await;
+                deadline = None;
+                let _ = ack.send(match failure.take() {
+                    Some(e) => Err(e),
+                    None => result,
+                });
+            }
+            None => {
+                let _ = shared.write(&mut pending).await;
+                break;
+            }
+        }
+    }
+}
+
+// Outcome of a DiskStore::compact pass
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct CompactionStats {
+    /// Frame files left in place or moved up into the store root
+    pub files_kept: usize,
+    /// Stale files deleted: empty frame files, leftover temp files and
+    /// copies shadowed by a frame of the same name in the root
+    pub files_removed: usize,
+    /// Empty directories deleted
+    pub dirs_removed: usize,
+    pub bytes_reclaimed: u64,
+}
+
+// Frame files in a disk store and their combined size
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct DiskUsage {
+    pub files: usize,
+    pub bytes: u64,
+}
+
+// A disk write that failed, kept for health reporting
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct WriteError {
+    pub at: DateTime<Utc>,
+    pub message: String,
+}
+
+fn record_write_error(slot: &std::sync::Mutex<Option<WriteError>>, err: &anyhow::Error) {
+    *slot.lock().unwrap() = Some(WriteError {
+        at: Utc::now(),
+        message: format!("{:#}", err),
+    });
+}
+
+// Error for a newest frame older than the caller's freshness limit: a sign
+// the agent has stopped uploading. Callers can downcast to this.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StaleLatest {
+    pub frame_id: i64,
+    pub timestamp: DateTime<Utc>,
+    pub age: chrono::Duration,
+    pub max_age: chrono::Duration,
+}
+
+impl std::fmt::Display for StaleLatest {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "latest frame #{} is {}s old, over the {}s limit",
+            self.frame_id,
+            self.age.num_seconds(),
+            self.max_age.num_seconds()
+        )
+    }
+}
+
+impl std::error::Error for StaleLatest {}
+
+// Pass `frame` through if it is at most `max_age` old at `now`
+pub fn ensure_fresh(frame: Frame, max_age: chrono::Duration, now: DateTime<Utc>) -> Result<Frame> {
+    let age = now - frame.timestamp;
+    if age > max_age {
+        return Err(StaleLatest { frame_id: frame.id, timestamp: frame.timestamp, age, max_age }.into());
+    }
+    Ok(frame)
+}
+
+// Health of one storage backend, as reported by /health
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StorageHealth {
+    /// "memory", "disk" or "sqlite"
+    pub backend: String,
+    pub healthy: bool,
+    /// Why the backend is unhealthy
+    pub error: Option<String>,
+    /// Frames held in memory, or frame files on disk
+    pub frames: usize,
+    pub bytes: u64,
+    /// Space left on the disk store's filesystem, where the platform reports it
+    pub free_bytes: Option<u64>,
+    /// Most recent failed disk write, even if later writes succeeded
+    pub last_write_error: Option<WriteError>,
+    /// The disk ran out of space and new frames are only kept in memory
+    pub degraded: bool,
+}
+
+// Whether a failed write ran out of disk space or quota
+fn is_out_of_space(err: &anyhow::Error) -> bool {
+    err.chain()
+        .filter_map(|cause| cause.downcast_ref::<std::io::Error>())
+        .any(|e| matches!(e.kind(), std::io::ErrorKind::StorageFull | std::io::ErrorKind::QuotaExceeded))
+}
+
+// A disk store with less free space than this reports itself unhealthy,
+// so monitoring fires before writes start failing
+const MIN_FREE_BYTES: u64 = 64 * 1024 * 1024;
+
+// Prefix of the scratch file written to check the store is writable
+const HEALTH_PROBE_PREFIX: &str = ".health-probe";
+
+// Keeps concurrent probes from deleting each other's scratch file
+static HEALTH_PROBES: AtomicUsize = AtomicUsize::new(0);
+
+// Space available to unprivileged writers on the filesystem holding `path`
+#[cfg(unix)]
+fn free_bytes(path: &Path) -> Option<u64> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let path = std::ffi::CString::new(path.as_os_str().as_bytes()).ok()?;
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    // SAFETY: `path` is NUL-terminated and `stat` is a valid out-pointer
+    if unsafe { libc::statvfs(path.as_ptr(), &mut stat) } != 0 {
+        return None;
+    }
+    Some(stat.f_bavail as u64 * stat.f_frsize as u64)
+}
+
+#[cfg(not(unix))]
+fn free_bytes(_path: &Path) -> Option<u64> {
+    None
+}
+
+// Stale files younger than this are left alone by compaction; they may
+// still be being written
+const COMPACTION_GRACE: Duration = Duration::from_secs(60);
+
+// Frame file extensions as (extension, format, content type). A frame is
+// written under the first extension listed for its "format" metadata and
+// read back with that format and content type.
+const FRAME_EXTENSIONS: [(&str, &str, &str); 7] = [
+    ("png", "png", "image/png"),
+    ("jpg", "jpeg", "image/jpeg"),
+    ("jpeg", "jpeg", "image/jpeg"),
+    ("webp", "webp", "image/webp"),
+    ("gif", "gif", "image/gif"),
+    ("bmp", "bmp", "image/bmp"),
+    ("raw", "raw", "application/octet-stream"),
+];
+
+// Extension for a frame's file, from its "format" metadata ("jpg" and
+// "jpeg" alike). Frames of no or an unknown format keep the historical
+// ".png".
+fn frame_extension(frame: &Frame) -> &'static str {
+    let format = frame.metadata.get("format").map(|f| f.to_lowercase()).unwrap_or_default();
+    FRAME_EXTENSIONS
+        .iter()
+        .find(|(ext, name, _)| format == *name || format == *ext)
+        .map_or("png", |(ext, _, _)| ext)
+}
+
+fn frame_filename(id: i64, ts: i64, extension: &str) -> PathBuf {
+    PathBuf::from(format!("frame_{}_{}.{}", id, ts, extension))
+}
+
+// Parse "frame_{id}_{ts}.{ext}" into (id, ts), for any known extension
+fn parse_frame_filename(name: &str) -> Option<(i64, i64)> {
+    let (stem, ext) = name.strip_prefix("frame_")?.rsplit_once('.')?;
+    frame_format(ext)?;
+    let (id, ts) = stem.split_once('_')?;
+    Some((id.parse().ok()?, ts.parse().ok()?))
+}
+
+// Extension of the JSON sidecar holding a disk frame's id, exact
+// timestamp and metadata, next to the frame file
+const SIDECAR_EXTENSION: &str = "json";
+
+// What a frame's sidecar records; the image bytes stay in the frame file
+#[derive(Debug, Serialize, Deserialize)]
+struct Sidecar {
+    id: i64,
+    timestamp: DateTime<Utc>,
+    metadata: HashMap<String, String>,
+    /// Length of the frame's bytes, which differs from the file's for
+    /// delta-encoded frames; absent from older sidecars
+    #[serde(default)]
+    size_bytes: Option<usize>,
+    /// CRC32 of the frame's bytes (the full frame for deltas); absent from
+    /// older sidecars
+    #[serde(default)]
+    checksum: Option<u32>,
+}
+
+// True for "frame_{id}_{ts}.json"
+fn is_sidecar_filename(name: &str) -> bool {
+    name.strip_suffix(SIDECAR_EXTENSION)
+        .and_then(|stem| stem.strip_suffix('.'))
+        .is_some_and(|stem| parse_frame_filename(&format!("{}.png", stem)).is_some())
+}
+
+// Whether the frame a sidecar belongs to still has its file beside it
+async fn has_frame_file(sidecar: &Path) -> Result<bool> {
+    for (extension, _, _) in FRAME_EXTENSIONS {
+        if fs::try_exists(sidecar.with_extension(extension)).await? {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+// Format and content type of a frame file extension
+fn frame_format(extension: &str) -> Option<(&'static str, &'static str)> {
+    FRAME_EXTENSIONS
+        .iter()
+        .find(|(ext, _, _)| *ext == extension)
+        .map(|(_, format, content_type)| (*format, *content_type))
+}
+
+// Frame ids tracked by a DiskStore's lookup index unless configured otherwise
+pub const DEFAULT_INDEX_CAPACITY: usize = 10_000;
+
+// Least-recently-used map from frame id to its file, relative to the store
+// root. Ids pushed out by the cap are found again by a directory scan.
+struct FrameIndex {
+    entries: HashMap<i64, (PathBuf, u64)>,
+    /// Last use tick -> id, oldest first
+    recency: BTreeMap<u64, i64>,
+    tick: u64,
+    capacity: usize,
+}
+
+impl FrameIndex {
+    fn new(capacity: usize) -> Self {
+        Self {
+            entries: HashMap::new(),
+            recency: BTreeMap::new(),
+            tick: 0,
+            capacity,
+        }
+    }
+
+    fn touch(&mut self, id: i64) -> u64 {
+        self.tick += 1;
+        self.recency.insert(self.tick, id);
+        self.tick
+    }
+
+    fn get(&mut self, id: i64) -> Option<PathBuf> {
+        let (_, last_used) = self.entries.get(&id)?;
+        self.recency.remove(last_used);
+        let tick = self.touch(id);
+        let entry = self.entries.get_mut(&id)?;
+        entry.1 = tick;
+        Some(entry.0.clone())
+    }
+
+    fn insert(&mut self, id: i64, path: PathBuf) {
+        if self.capacity == 0 {
+            return;
+        }
+        self.remove(id);
+        let tick = self.touch(id);
+        self.entries.insert(id, (path, tick));
+        self.trim();
+    }
+
+    fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        self.trim();
+    }
+
+    // Drop least recently used ids until the cap is respected
+    fn trim(&mut self) {
+        while self.entries.len() > self.capacity {
+            let Some((_, oldest)) = self.recency.pop_first() else {
+                break;
+            };
+            self.entries.remove(&oldest);
+        }
+    }
+
+    fn remove(&mut self, id: i64) {
+        if let Some((_, last_used)) = self.entries.remove(&id) {
+            self.recency.remove(&last_used);
+        }
+    }
+
+    fn clear(&mut self) {
+        self.entries.clear();
+        self.recency.clear();
+    }
+}
+
+// Disk Store
+pub struct DiskStore {
+    base_path: PathBuf,
+    /// Queue to the background writer when batching is enabled
+    writer: Option<mpsc::Sender<WriterMsg>>,
+    /// Id -> file lookups, so get_by_id doesn't scan the directory
+    index: Arc<std::sync::Mutex<FrameIndex>>,
+    /// Directory scans performed, including the one at startup
+    scans: AtomicUsize,
+    /// Last failed write, shared with the background writer
+    last_write_error: Arc<std::sync::Mutex<Option<WriteError>>>,
+    /// Set by the background writer while batches fail for lack of space
+    out_of_space: Arc<AtomicBool>,
+    /// Keyframe/delta recording; None writes every frame in full
+    delta: Option<Arc<std::sync::Mutex<DeltaState>>>,
+    /// Check frames read back against the checksum in their sidecar
+    verify_on_read: bool,
+}
+
+// Delta recording state of a DiskStore
+struct DeltaState {
+    /// Deltas written between keyframes
+    keyframe_every: usize,
+    /// Id, timestamp, file extension and bytes of the frame deltas are
+    /// taken against; only frames of the same format are encoded against it
+    keyframe: Option<(i64, i64, &'static str, Arc<Vec<u8>>)>,
+    since_keyframe: usize,
+}
+
+// Implementation of DiskStore
+impl DiskStore {
+    pub async fn new(base_path: PathBuf) -> Result<Self> {
+        fs::create_dir_all(&base_path)
+            .await
+            .context("Failed to create storage directory")?;
+
+        let store = Self {
+            base_path,
+            writer: None,
+            index: Arc::new(std::sync::Mutex::new(FrameIndex::new(DEFAULT_INDEX_CAPACITY))),
+            scans: AtomicUsize::new(0),
+            last_write_error: Arc::new(std::sync::Mutex::new(None)),
+            out_of_space: Arc::new(AtomicBool::new(false)),
+            delta: None,
+            verify_on_read: true,
+        };
+        store.rebuild_index().await?;
+        Ok(store)
+    }
+
+    // Cap the id lookup index; 0 disables it and every lookup scans
+    pub fn with_index_capacity(self, capacity: usize) -> Self {
+        self.index.lock().unwrap().set_capacity(capacity);
+        self
+    }
+
+    // Whether reads recompute each frame's CRC32 and fail on a mismatch
+    // with its sidecar (the default). Turning it off saves a pass over the
+    // bytes on hot read paths.
+    pub fn with_verify_on_read(mut self, verify: bool) -> Self {
+        self.verify_on_read = verify;
+        self
+    }
+
+    // Record a full keyframe and then, for the next `keyframe_every`
+    // frames, only the bytes that differ from it. Reads rebuild the full
+    // frame. A frame is written in full, becoming the next keyframe, when
+    // one is due or its delta would be no smaller than the frame itself.
+    pub fn with_delta_encoding(mut self, keyframe_every: usize) -> Self {
+        self.delta = Some(Arc::new(std::sync::Mutex::new(DeltaState {
+            keyframe_every,
+            keyframe: None,
+            since_keyframe: 0,
+        })));
+        self
+    }
+
+    // Frame files in the store root as (id, timestamp, file name). Counts
+    // as one directory scan.
+    async fn scan(&self) -> Result<Vec<(i64, i64, PathBuf)>> {
+        self.scans.fetch_add(1, Ordering::Relaxed);
+
+        let mut frames = Vec::new();
+        let mut entries = fs::read_dir(&self.base_path)
+            .await
+            .with_context(|| format!("Failed to read {}", self.base_path.display()))?;
+        while let Some(entry) = entries.next_entry().await? {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if let Some((id, ts)) = parse_frame_filename(&name) {
+                frames.push((id, ts, PathBuf::from(name)));
+            }
+        }
+
+        // Oldest first; the newest file wins when an id was stored twice
+        frames.sort_by_key(|&(id, ts, _)| (ts, id));
+        Ok(frames)
+    }
+
+    // Refill the index from one directory scan, most recent frames last so
+    // they are the ones kept when the store holds more than the cap
+    async fn rebuild_index(&self) -> Result<()> {
+        let frames = self.scan().await?;
+
+        let mut index = self.index.lock().unwrap();
+        index.clear();
+        for (id, _, path) in frames {
+            index.insert(id, path);
+        }
+        Ok(())
+    }
+
+    // Retrieve a frame by id, via the index or, on a miss, a directory
+    // scan. Its timestamp and metadata come from the JSON sidecar; frames
+    // stored without one (by older versions) get the second-resolution
+    // timestamp from the file name and just "format" and "content-type"
+    // metadata from the extension. Delta-encoded frames are rebuilt from
+    // their keyframe. Fails if the bytes don't match the sidecar's
+    // checksum, unless verification is off.
+    pub async fn get_by_id(&self, id: i64) -> Result<Frame> {
+        self.flush().await?;
+
+        let (path, data) = self.read(id).await?;
+        let meta = self.describe(id, &path).await?;
+        let data = self.rebuild(id, &path, data).await?;
+        self.verify(&meta, &data)?;
+        Ok(Frame { id, data, timestamp: meta.timestamp, metadata: meta.metadata })
+    }
+
+    // Compare a frame's rebuilt bytes with the checksum it was stored with
+    fn verify(&self, meta: &FrameMeta, data: &[u8]) -> Result<()> {
+        if !self.verify_on_read {
+            return Ok(());
+        }
+        if let Some(expected) = meta.checksum {
+            let actual = crc32fast::hash(data);
+            if actual != expected {
+                anyhow::bail!(
+                    "Frame {} is corrupt: checksum {:08x}, expected {:08x}",
+                    meta.id,
+                    actual,
+                    expected
+                );
+            }
+        }
+        Ok(())
+    }
+
+    // The full bytes of the frame stored at `path`: delta-encoded frames
+    // are rebuilt from their keyframe
+    async fn rebuild(&self, id: i64, path: &Path, data: Vec<u8>) -> Result<Vec<u8>> {
+        let Some((keyframe_id, keyframe_ts)) = delta::keyframe_of(&data) else {
+            return Ok(data);
+        };
+        let extension = path.extension().and_then(|ext| ext.to_str()).unwrap_or("png");
+        let keyframe_path = self.base_path.join(frame_filename(keyframe_id, keyframe_ts, extension));
+        let keyframe = fs::read(&keyframe_path)
+            .await
+            .with_context(|| format!("Failed to read keyframe {} of frame {}", keyframe_path.display(), id))?;
+        delta::decode(&keyframe, &data).with_context(|| format!("Failed to rebuild frame {}", id))
+    }
+
+    // Everything but the bytes of the frame stored at `path`, from its
+    // sidecar or, for frames stored without one, its file name and size
+    async fn describe(&self, id: i64, path: &Path) -> Result<FrameMeta> {
+        let extension = path.extension().and_then(|ext| ext.to_str()).unwrap_or("png");
+        let sidecar = match fs::read(self.base_path.join(path.with_extension(SIDECAR_EXTENSION))).await {
+            Ok(json) => match serde_json::from_slice::<Sidecar>(&json) {
+                Ok(sidecar) => Some(sidecar),
+                Err(e) => {
+                    tracing::warn!("Ignoring unreadable sidecar of frame {}: {}", id, e);
+                    None
+                }
+            },
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => return Err(anyhow::Error::new(e).context(format!("Failed to read sidecar of frame {}", id))),
+        };
+
+        let (timestamp, mut metadata, size_bytes, checksum) = match sidecar {
+            Some(sidecar) => (sidecar.timestamp, sidecar.metadata, sidecar.size_bytes, sidecar.checksum),
+            None => {
+                let timestamp = path
+                    .to_str()
+                    .and_then(parse_frame_filename)
+                    .and_then(|(_, ts)| DateTime::from_timestamp(ts, 0))
+                    .unwrap_or_default();
+                (timestamp, HashMap::new(), None, None)
+            }
+        };
+        if let Some((format, content_type)) = frame_format(extension) {
+            metadata.entry("format".to_string()).or_insert_with(|| format.to_string());
+            metadata.entry("content-type".to_string()).or_insert_with(|| content_type.to_string());
+        }
+        let size_bytes = match size_bytes {
+            Some(size) => size,
+            None => fs::metadata(self.base_path.join(path))
+                .await
+                .with_context(|| format!("Failed to read {}", path.display()))?
+                .len() as usize,
+        };
+
+        Ok(FrameMeta { id, timestamp, size_bytes, metadata, checksum })
+    }
+
+    // Frames timestamped within [start, end] with their files, oldest
+    // first. File names narrow the scan to whole seconds; sidecars give
+    // the exact times. Frames deleted mid-listing are skipped.
+    async fn range_files(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> Result<Vec<(PathBuf, FrameMeta)>> {
+        if start > end {
+            return Ok(Vec::new());
+        }
+        self.flush().await?;
+
+        let mut found = Vec::new();
+        for (id, ts, path) in self.scan().await? {
+            if ts < start.timestamp() || ts > end.timestamp() {
+                continue;
+            }
+            let meta = match self.describe(id, &path).await {
+                Ok(meta) => meta,
+                Err(_) if !fs::try_exists(self.base_path.join(&path)).await? => continue,
+                Err(e) => return Err(e),
+            };
+            if meta.timestamp >= start && meta.timestamp <= end {
+                found.push((path, meta));
+            }
+        }
+        found.sort_by_key(|(_, meta)| meta.timestamp);
+        Ok(found)
+    }
+
+    // Frames timestamped within [start, end], oldest first
+    pub async fn get_range(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> Result<Vec<Frame>> {
+        let mut frames = Vec::new();
+        for (path, meta) in self.range_files(start, end).await? {
+            let data = match fs::read(self.base_path.join(&path)).await {
+                Ok(data) => data,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(anyhow::Error::new(e).context(format!("Failed to read {}", path.display()))),
+            };
+            let data = self.rebuild(meta.id, &path, data).await?;
+            self.verify(&meta, &data)?;
+            frames.push(Frame { id: meta.id, data, timestamp: meta.timestamp, metadata: meta.metadata });
+        }
+        Ok(frames)
+    }
+
+    // get_range without reading any frame file, only the sidecars
+    pub async fn list_range_meta(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> Result<Vec<FrameMeta>> {
+        Ok(self.range_files(start, end).await?.into_iter().map(|(_, meta)| meta).collect())
+    }
+
+    // A frame's file, relative to the store root, and its bytes as stored
+    async fn read(&self, id: i64) -> Result<(PathBuf, Vec<u8>)> {
+        let indexed = self.index.lock().unwrap().get(id);
+        let path = match indexed {
+            Some(path) => path,
+            None => self.find(id).await?,
+        };
+
+        match fs::read(self.base_path.join(&path)).await {
+            Ok(data) => Ok((path, data)),
+            // Deleted behind our back: forget it and look again
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                self.index.lock().unwrap().remove(id);
+                let path = self.find(id).await?;
+                let data = fs::read(self.base_path.join(&path))
+                    .await
+                    .with_context(|| format!("Failed to read {}", path.display()))?;
+                Ok((path, data))
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    // Scan the directory for a frame the index doesn't know and index it
+    async fn find(&self, id: i64) -> Result<PathBuf> {
+        let path = self
+            .scan()
+            .await?
+            .into_iter()
+            .rev()
+            .find(|(frame_id, _, _)| *frame_id == id)
+            .map(|(_, _, path)| path)
+            .ok_or_else(|| anyhow::anyhow!("frame {} not found", id))?;
+
+        self.index.lock().unwrap().insert(id, path.clone());
+        Ok(path)
+    }
+
+    // Delete every file stored for this id, with its sidecar, returning
+    // whether there was one
+    pub async fn delete(&self, id: i64) -> Result<bool> {
+        self.flush().await?;
+
+        let doomed: Vec<_> = self.scan().await?.into_iter().filter(|(frame_id, _, _)| *frame_id == id).collect();
+        if doomed.is_empty() {
+            return Ok(false);
+        }
+        self.remove_files(doomed).await?;
+        Ok(true)
+    }
+
+    // Delete every frame timestamped before `ts`, returning how many were
+    // removed. Frames from the same second as `ts` are judged by the exact
+    // time in their sidecar.
+    pub async fn prune_before(&self, ts: DateTime<Utc>) -> Result<usize> {
+        self.flush().await?;
+
+        let mut doomed = Vec::new();
+        for (id, secs, path) in self.scan().await? {
+            let before = match secs.cmp(&ts.timestamp()) {
+                std::cmp::Ordering::Less => true,
+                std::cmp::Ordering::Equal => self.describe(id, &path).await?.timestamp < ts,
+                std::cmp::Ordering::Greater => false,
+            };
+            if before {
+                doomed.push((id, secs, path));
+            }
+        }
+
+        let removed = doomed.len();
+        self.remove_files(doomed).await?;
+        Ok(removed)
+    }
+
+    // Unlink frame files and their sidecars. Deltas taken against a doomed
+    // keyframe would become unreadable, so they are first rewritten in
+    // full; only files at least as new as the oldest doomed frame can be
+    // such deltas, and only their headers are read to find them.
+    async fn remove_files(&self, doomed: Vec<(i64, i64, PathBuf)>) -> Result<()> {
+        let Some(oldest) = doomed.iter().map(|&(_, ts, _)| ts).min() else {
+            return Ok(());
+        };
+        let doomed_paths: HashSet<&PathBuf> = doomed.iter().map(|(_, _, path)| path).collect();
+
+        for (id, ts, path) in self.scan().await? {
+            if ts < oldest || doomed_paths.contains(&path) {
+                continue;
+            }
+            let header = match read_prefix(&self.base_path.join(&path), delta::HEADER_LEN).await {
+                Ok(header) => header,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(anyhow::Error::new(e).context(format!("Failed to read {}", path.display()))),
+            };
+            let extension = path.extension().and_then(|ext| ext.to_str()).unwrap_or("png");
+            let Some((keyframe_id, keyframe_ts)) = delta::keyframe_of(&header) else {
+                continue;
+            };
+            if !doomed_paths.contains(&frame_filename(keyframe_id, keyframe_ts, extension)) {
+                continue;
+            }
+
+            let filepath = self.base_path.join(&path);
+            let data = fs::read(&filepath)
+                .await
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+            let full = self.rebuild(id, &path, data).await?;
+            write_atomic(&filepath, &full).await?;
+        }
+
+        if let Some(delta) = &self.delta {
+            let mut state = delta.lock().unwrap();
+            let current = state
+                .keyframe
+                .as_ref()
+                .map(|(id, ts, extension, _)| frame_filename(*id, *ts, extension));
+            if current.is_some_and(|path| doomed_paths.contains(&path)) {
+                state.keyframe = None;
+            }
+        }
+
+        for (id, _, path) in &doomed {
+            let filepath = self.base_path.join(path);
+            for file in [filepath.with_extension(SIDECAR_EXTENSION), filepath] {
+                match fs::remove_file(&file).await {
+                    Ok(()) => {}
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                    Err(e) => {
+                        return Err(anyhow::Error::new(e).context(format!("Failed to remove {}", file.display())));
+                    }
+                }
+            }
+            self.index.lock().unwrap().remove(*id);
+        }
+        Ok(())
+    }
+
+    // Hand writes to a background task that batches them. Must be called
+    // from within a Tokio runtime, after with_delta_encoding.
+    pub fn with_batching(mut self, batching: WriteBatching) -> Self {
+        let batching = WriteBatching {
+            max_frames: batching.max_frames.max(1),
+            ..batching
+        };
+        let (tx, rx) = mpsc::channel(batching.max_frames * 2);
+        let shared = WriterShared {
+            last_error: self.last_write_error.clone(),
+            index: self.index.clone(),
+            delta: self.delta.clone(),
+            out_of_space: self.out_of_space.clone(),
+        };
+        tokio::spawn(run_writer(rx, batching, shared));
+        self.writer = Some(tx);
+        self
+    }
+
+    // Store a frame on disk, returning its path relative to the store root.
+    // The file extension follows the frame's format; the frame's exact
+    // timestamp and metadata go in a JSON sidecar beside it, written first
+    // so a frame file never appears without one. With batching the write
+    // is only queued; call `flush` to wait for it.
+    pub async fn store(&self, frame: &Frame) -> Result<PathBuf> {
+        let extension = frame_extension(frame);
+        let filename = frame_filename(frame.id, frame.timestamp.timestamp(), extension);
+        let filepath = self.base_path.join(&filename);
+        let sidecar_path = filepath.with_extension(SIDECAR_EXTENSION);
+        let sidecar = serde_json::to_vec(&Sidecar {
+            id: frame.id,
+            timestamp: frame.timestamp,
+            metadata: frame.metadata.clone(),
+            size_bytes: Some(frame.data.len()),
+            checksum: Some(crc32fast::hash(&frame.data)),
+        })
+        .context("Failed to serialise frame metadata")?;
+        let data = self.encode(frame, extension);
+
+        match &self.writer {
+            Some(writer) => {
+                // Indexed before queueing, so a failed batch can take it out
+                self.index.lock().unwrap().insert(frame.id, filename.clone());
+                for (path, bytes) in [(sidecar_path, sidecar), (filepath, data)] {
+                    writer
+                        .send(WriterMsg::Write(frame.id, path, bytes))
+                        .await
+                        .map_err(|_| anyhow::anyhow!("Disk writer has stopped"))?;
+                }
+            }
+            None => {
+                let mut written = write_atomic(&sidecar_path, &sidecar).await;
+                if written.is_ok() {
+                    written = write_atomic(&filepath, &data).await;
+                    if written.is_err() {
+                        let _ = fs::remove_file(&sidecar_path).await;
+                    }
+                }
+                if let Err(e) = written {
+                    // Don't leave later deltas against a keyframe that never
+                    // landed
+                    if let Some(delta) = &self.delta {
+                        delta.lock().unwrap().keyframe = None;
+                    }
+                    let e = e.context("Failed to write frame to disk");
+                    record_write_error(&self.last_write_error, &e);
+                    return Err(e);
+                }
+                self.index.lock().unwrap().insert(frame.id, filename.clone());
+            }
+        }
+
+        Ok(filename)
+    }
+
+    // The bytes to write for `frame`: a delta against the current keyframe
+    // when delta encoding is on and that saves space, otherwise the frame
+    // itself
+    fn encode(&self, frame: &Frame, extension: &'static str) -> Vec<u8> {
+        let Some(delta) = &self.delta else {
+            return frame.data.clone();
+        };
+
+        let mut state = delta.lock().unwrap();
+        if state.since_keyframe < state.keyframe_every
+            && let Some((keyframe_id, keyframe_ts, keyframe_extension, keyframe)) = &state.keyframe
+            && *keyframe_extension == extension
+        {
+            let encoded = delta::encode(*keyframe_id, *keyframe_ts, keyframe, &frame.data);
+            if encoded.len() < frame.data.len() {
+                state.since_keyframe += 1;
+                return encoded;
+            }
+        }
+
+        state.keyframe = Some((frame.id, frame.timestamp.timestamp(), extension, Arc::new(frame.data.clone())));
+        state.since_keyframe = 0;
+        frame.data.clone()
+    }
+
+    // Whether the last batch of queued writes failed for lack of space.
+    // Always false without batching, where the failure goes to the caller.
+    pub fn out_of_space(&self) -> bool {
+        self.out_of_space.load(Ordering::Relaxed)
+    }
+
+    // Wait until every queued write is on disk. A no-op without batching.
+    pub async fn flush(&self) -> Result<()> {
+        let Some(writer) = &self.writer else {
+            return Ok(());
+        };
+
+        let (ack, done) = oneshot::channel();
+        writer
+            .send(WriterMsg::Flush(ack))
+            .await
+            .map_err(|_| anyhow::anyhow!("Disk writer has stopped"))?;
+        done.await.context("Disk writer has stopped")?
+    }
+
+    // Consolidate the store into its flat layout: frame files found in
+    // subdirectories are moved up into the root, stale files are deleted
+    // and empty directories removed. Unrecognised files are never touched.
+    // Safe alongside readers — frames are moved with an atomic rename, so
+    // each stays readable at its old or new path throughout. Pending
+    // batched writes are flushed first.
+    pub async fn compact(&self) -> Result<CompactionStats> {
+        self.flush().await?;
+
+        let mut stats = CompactionStats::default();
+        let mut dirs = Vec::new();
+        let mut pending = vec![self.base_path.clone()];
+
+        while let Some(dir) = pending.pop() {
+            let mut entries = fs::read_dir(&dir)
+                .await
+                .with_context(|| format!("Failed to read {}", dir.display()))?;
+
+            while let Some(entry) = entries.next_entry().await? {
+                let path = entry.path();
+                let meta = match entry.metadata().await {
+                    Ok(meta) => meta,
+                    // Removed by someone else mid-scan
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                    Err(e) => return Err(e.into()),
+                };
+                if meta.is_dir() {
+                    dirs.push(path.clone());
+                    pending.push(path);
+                    continue;
+                }
+
+                let name = entry.file_name().to_string_lossy().into_owned();
+                let settled = meta
+                    .modified()
+                    .ok()
+                    .and_then(|m| m.elapsed().ok())
+                    .is_some_and(|age| age >= COMPACTION_GRACE);
+                let is_frame = parse_frame_filename(&name).is_some();
+                // Sidecars move with their frames but aren't counted
+                let is_sidecar = is_sidecar_filename(&name);
+                let orphaned = is_sidecar && settled && !has_frame_file(&path).await?;
+                let stale = orphaned || (settled && (name.ends_with(TEMP_SUFFIX) || (is_frame && meta.len() == 0)));
+                let target = self.base_path.join(&name);
+                let shadowed =
+                    (is_frame || is_sidecar) && dir != self.base_path && fs::try_exists(&target).await?;
+
+                if stale || shadowed {
+                    fs::remove_file(&path)
+                        .await
+                        .with_context(|| format!("Failed to remove {}", path.display()))?;
+                    stats.files_removed += 1;
+                    stats.bytes_reclaimed += meta.len();
+                } else if is_frame {
+                    if dir != self.base_path {
+                        fs::rename(&path, &target)
+                            .await
+                            .with_context(|| format!("Failed to move {}", path.display()))?;
+                    }
+                    stats.files_kept += 1;
+                } else if is_sidecar && dir != self.base_path {
+                    fs::rename(&path, &target)
+                        .await
+                        .with_context(|| format!("Failed to move {}", path.display()))?;
+                }
+            }
+        }
+
+        // Deepest first, so parents empty out before they are checked
+        dirs.sort_by_key(|d| std::cmp::Reverse(d.components().count()));
+        for dir in dirs {
+            // Fails harmlessly on directories that still hold files
+            if fs::remove_dir(&dir).await.is_ok() {
+                stats.dirs_removed += 1;
+            }
+        }
+
+        // Files were moved and removed; start the index over
+        self.rebuild_index().await?;
+
+        Ok(stats)
+    }
+
+    // Frame files in the store root and their total size. Batched writes
+    // still queued are not counted; flush first for an exact figure.
+    pub async fn disk_usage(&self) -> Result<DiskUsage> {
+        let mut usage = DiskUsage::default();
+        for (_, _, path) in self.scan().await? {
+            match fs::metadata(self.base_path.join(&path)).await {
+                Ok(meta) => {
+                    usage.files += 1;
+                    usage.bytes += meta.len();
+                }
+                // Removed since the scan
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Ok(usage)
+    }
+
+    // Frame files in the store, from one directory scan
+    pub async fn frame_count(&self) -> Result<usize> {
+        Ok(self.scan().await?.len())
+    }
+
+    // Check the store directory still takes writes (by writing and removing
+    // a scratch file) and report its size, free space and last failed write
+    pub async fn storage_health(&self) -> StorageHealth {
+        let probe = self.base_path.join(format!(
+            "{}-{}",
+            HEALTH_PROBE_PREFIX,
+            HEALTH_PROBES.fetch_add(1, Ordering::Relaxed)
+        ));
+        let writable = async {
+            fs::write(&probe, b"ok").await?;
+            fs::remove_file(&probe).await
+        }
+        .await;
+        let usage = self.disk_usage().await;
+        let free_bytes = free_bytes(&self.base_path);
+
+        let error = match (&writable, &usage) {
+            (Err(e), _) => Some(format!("Storage directory is not writable: {}", e)),
+            (_, Err(e)) => Some(format!("Failed to read storage directory: {:#}", e)),
+            _ if free_bytes.is_some_and(|free| free < MIN_FREE_BYTES) => {
+                Some("Disk is nearly full".to_string())
+            }
+            _ => None,
+        };
+        let usage = usage.unwrap_or_default();
+
+        StorageHealth {
+            backend: "disk".to_string(),
+            healthy: error.is_none(),
+            error,
+            frames: usage.files,
+            bytes: usage.bytes,
+            free_bytes,
+            last_write_error: self.last_write_error.lock().unwrap().clone(),
+            degraded: false,
+        }
+    }
+
+    // Retrieve the newest frame on disk
+    pub async fn get_latest(&self) -> Result<Frame> {
+        self.flush().await?;
+
+        let (id, _, _) = self
+            .scan()
+            .await?
+            .pop()
+            .ok_or_else(|| anyhow::anyhow!("no frames available"))?;
+        self.get_by_id(id).await
+    }
+}
+
+// Frames from a persistent backend plus those from memory it lacks, by
+// (id, timestamp), oldest first
+fn merge_by_time<T>(mut persisted: Vec<T>, memory: Vec<T>, key: fn(&T) -> (i64, DateTime<Utc>)) -> Vec<T> {
+    let held: HashSet<_> = persisted.iter().map(key).collect();
+    persisted.extend(memory.into_iter().filter(|item| !held.contains(&key(item))));
+    persisted.sort_by_key(|item| key(item).1);
+    persisted
+}
+
+// Storage Manager
+pub struct Manager {
+    memory: MemoryStore,
+    disk: Option<DiskStore>,
+    /// Database of a sqlite-mode store
+    sqlite: Option<SqliteStore>,
+    mode: StorageMode,
+    /// Hash of the last chained frame, read back from storage on the first
+    /// store; None when chaining is disabled
+    chain: Option<Mutex<Option<String>>>,
+    /// Hybrid mode keeps accepting frames in memory while the disk is full
+    memory_fallback: bool,
+    /// Set while disk writes are failing for lack of space
+    disk_full: AtomicBool,
+}
+
+// Implementation of Storage Manager
+impl Manager {
+    pub async fn new(
+        mode: StorageMode,
+        memory_size: usize,
+        disk_path: Option<PathBuf>,
+    ) -> Result<Self> {
+        let memory = MemoryStore::new(memory_size);
+
+        let disk = match &mode {
+            StorageMode::Disk | StorageMode::Hybrid => {
+                let path = disk_path.clone().context("Disk path required for disk/hybrid mode")?;
+                Some(DiskStore::new(path).await?)
+            }
+            _ => None,
+        };
+        // In sqlite mode the path names the database file
+        let sqlite = match &mode {
+            StorageMode::Sqlite => {
+                let path = disk_path.context("Database path required for sqlite mode")?;
+                Some(SqliteStore::open(path).await?)
+            }
+            _ => None,
+        };
+
+        Ok(Self {
+            memory,
+            disk,
+            sqlite,
+            mode,
+            chain: None,
+            memory_fallback: true,
+            disk_full: AtomicBool::new(false),
+        })
+    }
+
+    // Manager keeping frames in the SQLite database at `path`
+    pub async fn sqlite(path: PathBuf, memory_size: usize) -> Result<Self> {
+        Self::new(StorageMode::Sqlite, memory_size, Some(path)).await
+    }
+
+    // Memory-only manager; needs no disk setup so it can be built synchronously
+    pub fn in_memory(memory_size: usize) -> Self {
+        Self {
+            memory: MemoryStore::new(memory_size),
+            disk: None,
+            sqlite: None,
+            mode: StorageMode::Memory,
+            chain: None,
+            memory_fallback: true,
+            disk_full: AtomicBool::new(false),
+        }
+    }
+
+    // Whether a hybrid store falls back to memory only when the disk is
+    // full (the default), rather than failing the upload
+    pub fn with_memory_fallback(mut self, enabled: bool) -> Self {
+        self.memory_fallback = enabled;
+        self
+    }
+
+    // Link every stored frame to its predecessor by hash, so modified or
+    // removed frames show up in verify_chain
+    pub fn with_hash_chain(mut self) -> Self {
+        self.chain = Some(Mutex::new(None));
+        self
+    }
+
+    // Keep one copy of the bytes when a frame is identical to the latest
+    // one in memory; the duplicate is stored as a reference (see
+    // DEDUP_OF_KEY) and reads resolve it transparently. Disk copies are
+    // written in full.
+    pub fn with_dedup(mut self) -> Self {
+        self.memory = self.memory.with_dedup();
+        self
+    }
+
+    // Set how many frames may be pinned in memory at once
+    pub fn with_max_pinned(mut self, max_pinned: usize) -> Self {
+        self.memory = self.memory.with_max_pinned(max_pinned);
+        self
+    }
+
+    // Cap the image bytes held in memory, evicting oldest first
+    pub fn with_memory_byte_limit(mut self, max_bytes: usize) -> Self {
+        self.memory = self.memory.with_byte_limit(max_bytes);
+        self
+    }
+
+    // Expire frames in memory once they are older than `ttl`
+    pub fn with_memory_ttl(mut self, ttl: Duration) -> Self {
+        self.memory = self.memory.with_ttl(ttl);
+        self
+    }
+
+    // Set how far back out-of-order frames are reordered in memory
+    pub fn with_reorder_window(mut self, window: usize) -> Self {
+        self.memory = self.memory.with_reorder_window(window);
+        self
+    }
+
+    // Batch disk writes in disk/hybrid mode; ignored for memory-only stores
+    pub fn with_write_batching(mut self, batching: WriteBatching) -> Self {
+        self.disk = self.disk.map(|disk| disk.with_batching(batching));
+        self
+    }
+
+    // Record disk frames as periodic keyframes plus deltas against them;
+    // ignored for memory-only stores
+    pub fn with_delta_encoding(mut self, keyframe_every: usize) -> Self {
+        self.disk = self.disk.map(|disk| disk.with_delta_encoding(keyframe_every));
+        self
+    }
+
+    // Whether disk reads check frames against their stored checksums;
+    // ignored for stores without a disk
+    pub fn with_disk_verification(mut self, verify: bool) -> Self {
+        self.disk = self.disk.map(|disk| disk.with_verify_on_read(verify));
+        self
+    }
+
+    pub fn mode(&self) -> &StorageMode {
+        &self.mode
+    }
+
+    // Wait for batched disk writes to land, e.g. before shutdown
+    pub async fn flush(&self) -> Result<()> {
+        match &self.disk {
+            Some(disk) => disk.flush().await,
+            None => Ok(()),
+        }
+    }
+
+    // Compact the disk store; None for memory-only stores
+    pub async fn compact(&self) -> Result<Option<CompactionStats>> {
+        match &self.disk {
+            Some(disk) => disk.compact().await.map(Some),
+            None => Ok(None),
+        }
+    }
+
+    // Size of the disk store; None for memory-only stores
+    pub async fn disk_usage(&self) -> Result<Option<DiskUsage>> {
+        match &self.disk {
+            Some(disk) => disk.disk_usage().await.map(Some),
+            None => Ok(None),
+        }
+    }
+
+    // Health of each backend in use: memory for memory/hybrid stores, disk
+    // for disk/hybrid stores, the database for sqlite stores
+    pub async fn storage_health(&self) -> Vec<StorageHealth> {
+        let mut health = Vec::new();
+        if matches!(self.mode, StorageMode::Memory | StorageMode::Hybrid) {
+            health.push(self.memory.storage_health().await);
+        }
+        if let Some(disk) = &self.disk {
+            let mut disk_health = disk.storage_health().await;
+            // Batched writes fail after the upload returns, so the writer
+            // flags it on the disk store instead
+            let batch_full = matches!(self.mode, StorageMode::Hybrid) && disk.out_of_space();
+            if self.disk_full.load(Ordering::Relaxed) || batch_full {
+                disk_health.healthy = false;
+                disk_health.degraded = true;
+                disk_health.error.get_or_insert_with(|| "Disk is full; new frames are kept in memory only".to_string());
+            }
+            health.push(disk_health);
+        }
+        if let Some(sqlite) = &self.sqlite {
+            health.push(sqlite.storage_health().await);
+        }
+        health
+    }
+
+    // Store a frame based on the storage mode and report where it landed.
+    // With hash chaining enabled, the frame's prev_hash/this_hash metadata
+    // is filled in first.
+    pub async fn store(&self, mut frame: Frame) -> Result<StorageLocation> {
+        let Some(chain) = &self.chain else {
+            return self.store_unchained(frame).await;
+        };
+
+        // Held until the frame is stored so chain order matches store
+        // order, and only advanced once it is, so a failed write leaves no
+        // gap in the chain
+        let mut head = chain.lock().await;
+        let prev = match head.as_ref() {
+            Some(hash) => hash.clone(),
+            None => self.persisted_chain_head().await,
+        };
+        let hash = chain_hash(&prev, &frame);
+        frame.metadata.insert(PREV_HASH_KEY.to_string(), prev);
+        frame.metadata.insert(THIS_HASH_KEY.to_string(), hash.clone());
+        let location = self.store_unchained(frame).await?;
+        *head = Some(hash);
+        Ok(location)
+    }
+
+    // Hash of the newest stored frame, so a restarted server continues the
+    // chain it left on disk; GENESIS_HASH for an empty or unchained store
+    async fn persisted_chain_head(&self) -> String {
+        self.get_latest()
+            .await
+            .ok()
+            .and_then(|frame| frame.metadata.get(THIS_HASH_KEY).cloned())
+            .unwrap_or_else(|| GENESIS_HASH.to_string())
+    }
+
+    async fn store_unchained(&self, frame: Frame) -> Result<StorageLocation> {
+        match self.mode {
+            StorageMode::Memory | StorageMode::Hybrid => {
+                self.memory.store(frame.clone()).await?;
+            }
+            _ => {}
+        }
+
+        let path = match (&self.mode, &self.disk) {
+            (StorageMode::Disk, Some(disk)) => Some(disk.store(&frame).await?),
+            (StorageMode::Hybrid, Some(disk)) => match disk.store(&frame).await {
+                Ok(path) => {
+                    // Every write retries the disk, so freeing space recovers
+                    if self.disk_full.swap(false, Ordering::Relaxed) {
+                        tracing::info!("Disk writes succeeding again — hybrid storage restored");
+                    }
+                    Some(path)
+                }
+                Err(e) if self.memory_fallback && is_out_of_space(&e) => {
+                    if !self.disk_full.swap(true, Ordering::Relaxed) {
+                        tracing::warn!("Disk full ({:#}) — keeping new frames in memory only", e);
+                    }
+                    return Ok(StorageLocation {
+                        backend: StorageMode::Memory.as_str().to_string(),
+                        path: None,
+                    });
+                }
+                Err(e) => return Err(e),
+            },
+            (StorageMode::Sqlite, _) => {
+                if let Some(sqlite) = &self.sqlite {
+                    sqlite.store(&frame).await?;
+                }
+                None
+            }
+            _ => None,
+        };
+
+        Ok(StorageLocation {
+            backend: self.mode.as_str().to_string(),
+            path,
+        })
+    }
+
+    // Ids of frames whose metadata value for `key` matches `value`: those
+    // in memory, or in the database for sqlite stores
+    pub async fn query_metadata(&self, key: &str, value: &str, how: MetadataMatch) -> Vec<i64> {
+        match &self.sqlite {
+            Some(sqlite) => sqlite.query_metadata(key, value, how).await.unwrap_or_else(|e| {
+                tracing::warn!("Failed to query frame metadata: {:#}", e);
+                Vec::new()
+            }),
+            None => self.memory.query_metadata(key, value, how),
+        }
+    }
+
+    // Newest frame (highest id) with metadata `key` equal to `value`. The
+    // frames in memory are the newest, so the disk store's sidecars are
+    // only read when none of them match, e.g. in disk-only mode.
+    pub async fn latest_with_metadata(&self, key: &str, value: &str) -> Result<Frame> {
+        let mut id = self.query_metadata(key, value, MetadataMatch::Exact).await.into_iter().max();
+        if id.is_none()
+            && let Some(disk) = &self.disk
+        {
+            let (start, end) = all_time();
+            id = disk
+                .list_range_meta(start, end)
+                .await?
+                .into_iter()
+                .filter(|meta| meta.metadata.get(key).map(String::as_str) == Some(value))
+                .map(|meta| meta.id)
+                .max();
+        }
+        let id = id.with_context(|| format!("No frame with {} {}", key, value))?;
+        self.get_by_id(id).await
+    }
+
+    // Retrieve the latest frame from memory, falling back to disk when
+    // memory holds none: in disk-only mode, or in hybrid mode after a
+    // restart before any new frame arrives. Sqlite stores read from the
+    // database.
+    pub async fn get_latest(&self) -> Result<Frame> {
+        match (self.memory.get_latest().await, &self.disk, &self.sqlite) {
+            (Ok(frame), _, _) => Ok(frame),
+            (Err(_), Some(disk), _) => disk.get_latest().await,
+            (Err(_), None, Some(sqlite)) => sqlite.get_latest().await,
+            (Err(e), None, None) => Err(e),
+        }
+    }
+
+    // The latest frame, provided it was captured no more than `max_age`
+    // before `now`; otherwise fails with `StaleLatest`
+    pub async fn get_latest_within(&self, max_age: chrono::Duration, now: DateTime<Utc>) -> Result<Frame> {
+        ensure_fresh(self.get_latest().await?, max_age, now)
+    }
+
+    // Retrieve a single frame by its ID from memory, falling back to disk
+    // (or the database) for frames that have been evicted or were never
+    // held in memory
+    pub async fn get_by_id(&self, id: i64) -> Result<Frame> {
+        match (self.memory.get_by_id(id).await, &self.disk, &self.sqlite) {
+            (Ok(frame), _, _) => Ok(frame),
+            (Err(_), Some(disk), _) => disk.get_by_id(id).await,
+            (Err(_), None, Some(sqlite)) => sqlite.get_by_id(id).await,
+            (Err(e), None, None) => Err(e),
+        }
+    }
+
+    // Frames timestamped at or after `since`, oldest first: an indexed
+    // query on a sqlite store, otherwise the frames held in memory
+    pub async fn list_since(&self, since: DateTime<Utc>) -> Result<Vec<Frame>> {
+        match &self.sqlite {
+            Some(sqlite) => sqlite.list_since(since).await,
+            None => Ok(self.memory.get_in_range(since, DateTime::<Utc>::MAX_UTC).await),
+        }
+    }
+
+    // Retrieve frames from memory within [from, to] (inclusive)
+    pub async fn get_in_range(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> Vec<Frame> {
+        self.memory.get_in_range(from, to).await
+    }
+
+    // Frames timestamped within [start, end] (inclusive) from every backend
+    // in use, oldest first; empty when start > end. Disk and sqlite stores
+    // are read along with any frames only memory holds, e.g. ones kept in
+    // memory while the disk was full. If the disk or database can't be
+    // read, only the frames in memory are returned.
+    pub async fn get_range(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> Vec<Frame> {
+        let memory = self.memory.get_range(start, end).await;
+        let persisted = match (&self.disk, &self.sqlite) {
+            (Some(disk), _) => disk.get_range(start, end).await,
+            (None, Some(sqlite)) => sqlite.get_range(start, end).await,
+            (None, None) => return memory,
+        };
+        match persisted {
+            Ok(frames) => merge_by_time(frames, memory, |f| (f.id, f.timestamp)),
+            Err(e) => {
+                tracing::warn!("Failed to read frame range from {} storage: {:#}", self.mode.as_str(), e);
+                memory
+            }
+        }
+    }
+
+    // get_range without the frames' bytes; disk stores read only sidecars
+    // and sqlite stores skip the data column
+    pub async fn list_range_meta(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> Vec<FrameMeta> {
+        let memory = self.memory.list_range_meta(start, end).await;
+        let persisted = match (&self.disk, &self.sqlite) {
+            (Some(disk), _) => disk.list_range_meta(start, end).await,
+            (None, Some(sqlite)) => sqlite.list_range_meta(start, end).await,
+            (None, None) => return memory,
+        };
+        match persisted {
+            Ok(metas) => merge_by_time(metas, memory, |m| (m.id, m.timestamp)),
+            Err(e) => {
+                tracing::warn!("Failed to list frame range from {} storage: {:#}", self.mode.as_str(), e);
+                memory
+            }
+        }
+    }
+
+    // Frames held in memory, oldest first. Sqlite stores list the same
+    // window from the database: the newest `max_frames` frames.
+    pub async fn list(&self) -> Vec<Frame> {
+        match &self.sqlite {
+            Some(sqlite) => sqlite.list_latest(self.memory.max_frames).await.unwrap_or_else(|e| {
+                tracing::warn!("Failed to list frames from sqlite storage: {:#}", e);
+                Vec::new()
+            }),
+            None => self.memory.list().await,
+        }
+    }
+
+    // Exempt a frame from memory eviction. Sqlite stores record the pin in
+    // the database, under the same limit.
+    pub async fn pin(&self, id: i64) -> Result<()> {
+        match &self.sqlite {
+            Some(sqlite) => sqlite.pin(id, self.memory.max_pinned).await,
+            None => self.memory.pin(id).await,
+        }
+    }
+
+    // Return a pinned frame to normal eviction
+    pub async fn unpin(&self, id: i64) -> Result<()> {
+        match &self.sqlite {
+            Some(sqlite) => sqlite.unpin(id).await,
+            None => self.memory.unpin(id).await,
+        }
+    }
+
+    pub async fn is_pinned(&self, id: i64) -> bool {
+        match &self.sqlite {
+            Some(sqlite) => sqlite.is_pinned(id).await.unwrap_or(false),
+            None => self.memory.is_pinned(id).await,
+        }
+    }
+
+    // Delete a frame from memory and from disk or the database, returning
+    // whether any backend held it
+    pub async fn delete(&self, id: i64) -> Result<bool> {
+        let mut deleted = self.memory.delete(id).await?;
+        if let Some(disk) = &self.disk {
+            deleted |= disk.delete(id).await?;
+        }
+        if let Some(sqlite) = &self.sqlite {
+            deleted |= sqlite.delete(id).await?;
+        }
+        Ok(deleted)
+    }
+
+    // Delete every frame timestamped before `ts` from all backends in use.
+    // Returns how many were removed from disk or the database, or from
+    // memory for memory-only stores.
+    pub async fn prune_before(&self, ts: DateTime<Utc>) -> Result<usize> {
+        let in_memory = self.memory.prune_before(ts).await;
+        match (&self.disk, &self.sqlite) {
+            (Some(disk), _) => disk.prune_before(ts).await,
+            (None, Some(sqlite)) => sqlite.prune_before(ts).await,
+            (None, None) => Ok(in_memory),
+        }
+    }
+
+    // Memory fill level, plus free space where frames are persisted. For
+    // sqlite stores, the frames and bytes held in the database.
+    pub async fn usage(&self) -> Usage {
+        let mut usage = self.memory.usage().await;
+        if let Some(sqlite) = &self.sqlite {
+            match sqlite.stats().await {
+                Ok(stats) => (usage.frames, usage.bytes) = (stats.frame_count, stats.total_bytes),
+                Err(e) => tracing::warn!("Failed to read sqlite storage usage: {:#}", e),
+            }
+        }
+        usage.disk_free_bytes = match (&self.disk, &self.sqlite) {
+            (Some(disk), _) => free_bytes(&disk.base_path),
+            (None, Some(sqlite)) => sqlite.free_bytes(),
+            (None, None) => None,
+        };
+        usage
+    }
+
+    // Memory stats, plus a count of frame files when persisting to disk,
+    // with the time span widened to the frames on disk. A failed directory
+    // scan leaves the disk out. Sqlite stores report the whole database.
+    pub async fn stats(&self) -> StorageStats {
+        if let Some(sqlite) = &self.sqlite {
+            return sqlite.stats().await.unwrap_or_else(|e| {
+                tracing::warn!("Failed to read sqlite storage stats: {:#}", e);
+                StorageStats::default()
+            });
+        }
+        let mut stats = self.memory.stats().await;
+        if let Some(disk) = &self.disk {
+            match disk.scan().await {
+                Ok(files) => {
+                    stats.disk_frame_count = Some(files.len());
+                    // File names carry whole seconds
+                    for ts in files.iter().filter_map(|(_, ts, _)| DateTime::from_timestamp(*ts, 0)) {
+                        stats.oldest = Some(stats.oldest.map_or(ts, |oldest| oldest.min(ts)));
+                        stats.newest = Some(stats.newest.map_or(ts, |newest| newest.max(ts)));
+                    }
+                }
+                Err(e) => tracing::warn!("Failed to count frames on disk: {:#}", e),
+            }
+        }
+        stats
+    }
+
+    // Distinct image buffers held in memory
+    pub async fn blob_count(&self) -> usize {
+        self.memory.blob_count().await
+    }
+
+    // Ids of frames where the hash chain is broken; empty when the chain
+    // is intact. Fails if chaining is not enabled. Disk and sqlite stores
+    // are checked as persisted: every frame is read back and re-hashed.
+    pub async fn verify_chain(&self) -> Result<Vec<i64>> {
+        if self.chain.is_none() {
+            anyhow::bail!("hash chaining is not enabled");
+        }
+        if self.disk.is_none() && self.sqlite.is_none() {
+            return Ok(self.memory.verify_chain().await);
+        }
+
+        let (start, end) = all_time();
+        let metas = match (&self.disk, &self.sqlite) {
+            (Some(disk), _) => disk.list_range_meta(start, end).await?,
+            (None, Some(sqlite)) => sqlite.list_range_meta(start, end).await?,
+            (None, None) => unreachable!(),
+        };
+        let persisted: HashSet<i64> = metas.iter().map(|meta| meta.id).collect();
+
+        // (id, timestamp, prev_hash, this_hash, whether the bytes still
+        // match) for every chained frame, oldest first. Frames kept only in
+        // memory while the disk was full are part of the chain too.
+        let mut links = Vec::new();
+        for meta in metas {
+            let (Some(prev), Some(hash)) =
+                (meta.metadata.get(PREV_HASH_KEY).cloned(), meta.metadata.get(THIS_HASH_KEY).cloned())
+            else {
+                continue;
+            };
+            let stored = match (&self.disk, &self.sqlite) {
+                (Some(disk), _) => disk.get_by_id(meta.id).await,
+                (None, Some(sqlite)) => sqlite.get_by_id(meta.id).await,
+                (None, None) => unreachable!(),
+            };
+            // A frame that can't be read back (e.g. a checksum mismatch)
+            // counts as altered
+            let intact = stored.is_ok_and(|frame| chain_hash(&prev, &frame) == hash);
+            links.push((meta.id, meta.timestamp, prev, hash, intact));
+        }
+        for frame in self.memory.list().await {
+            if persisted.contains(&frame.id) {
+                continue;
+            }
+            if let (Some(prev), Some(hash)) = (frame.metadata.get(PREV_HASH_KEY), frame.metadata.get(THIS_HASH_KEY)) {
+                let intact = chain_hash(prev, &frame) == *hash;
+                links.push((frame.id, frame.timestamp, prev.clone(), hash.clone(), intact));
+            }
+        }
+        links.sort_by_key(|(id, timestamp, ..)| (*timestamp, *id));
+
+        // The oldest frame may link to frames already pruned; every later
+        // one must link to a frame that is still stored
+        let held: HashSet<&str> = links.iter().map(|(_, _, _, hash, _)| hash.as_str()).collect();
+        Ok(links
+            .iter()
+            .enumerate()
+            .filter(|(i, (_, _, prev, _, intact))| {
+                let linked = *i == 0 || prev == GENESIS_HASH || held.contains(prev.as_str());
+                !intact || !linked
+            })
+            .map(|(_, (id, ..))| *id)
+            .collect())
+    }
+}
+
+// Unit tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_memory_store() {
+        let store = MemoryStore::new(10);
+
+        let frame = Frame {
+            id: 1,
+            data: vec![1, 2, 3],
+            timestamp: Utc::now(),
+            metadata: HashMap::new(),
         };
 
         store.store(frame.clone()).await.unwrap();
@@ -235,7 +2562,30 @@ mod tests {
         let frame = store.get_by_id(2).await.unwrap();
         assert_eq!(frame.id, 2);
 
-        assert!(store.get_by_id(99).await.is_err());
+        let error = store.get_by_id(99).await.unwrap_err();
+        assert!(error.to_string().contains("99"));
+
+        // A reused id resolves to the newer frame
+        store.store(Frame { id: 2, data: vec![20], timestamp: Utc::now(), metadata: HashMap::new() }).await.unwrap();
+        assert_eq!(store.get_by_id(2).await.unwrap().data, vec![20]);
+    }
+
+    #[tokio::test]
+    async fn test_latest_within_rejects_stale_frames() {
+        let store = Manager::in_memory(10);
+        let max_age = chrono::Duration::seconds(60);
+        // An empty store is not stale, just empty
+        let error = store.get_latest_within(max_age, Utc::now()).await.unwrap_err();
+        assert!(error.downcast_ref::<StaleLatest>().is_none());
+
+        let taken = Utc::now();
+        store.store(Frame { id: 1, data: vec![1], timestamp: taken, metadata: HashMap::new() }).await.unwrap();
+        assert_eq!(store.get_latest_within(max_age, taken + max_age).await.unwrap().id, 1);
+
+        let error = store.get_latest_within(max_age, taken + chrono::Duration::seconds(90)).await.unwrap_err();
+        let stale = error.downcast_ref::<StaleLatest>().unwrap();
+        assert_eq!(stale.frame_id, 1);
+        assert_eq!(stale.age.num_seconds(), 90);
     }
 
     #[tokio::test]
@@ -262,4 +2612,892 @@ mod tests {
         assert_eq!(results.len(), 3);
         assert!(results.iter().all(|f| f.id >= 1 && f.id <= 3));
     }
+
+    #[tokio::test]
+    async fn test_pinned_frame_survives_eviction() {
+        let store = MemoryStore::new(3).with_max_pinned(1);
+        let frame = |id: i64| Frame {
+            id,
+            data: vec![id as u8],
+            timestamp: Utc::now(),
+            metadata: HashMap::new(),
+        };
+
+        store.store(frame(1)).await.unwrap();
+        store.pin(1).await.unwrap();
+        store.store(frame(2)).await.unwrap();
+
+        // Separate cap: a second pin is refused
+        assert!(store.pin(2).await.is_err());
+
+        for id in 3..=6 {
+            store.store(frame(id)).await.unwrap();
+        }
+
+        // Frame 1 is pinned and kept; unpinned neighbours 2 and 3 were evicted
+        let ids: Vec<i64> = store.list().await.iter().map(|f| f.id).collect();
+        assert_eq!(ids, vec![1, 4, 5, 6]);
+
+        // Once unpinned it is the oldest frame and goes first
+        store.unpin(1).await.unwrap();
+        let ids: Vec<i64> = store.list().await.iter().map(|f| f.id).collect();
+        assert_eq!(ids, vec![4, 5, 6]);
+    }
+
+    #[tokio::test]
+    async fn test_manager_reports_disk_location() {
+        let dir = std::env::temp_dir().join(format!("eye-storage-test-{}", std::process::id()));
+        let manager = Manager::new(StorageMode::Hybrid, 10, Some(dir.clone()))
+            .await
+            .unwrap();
+
+        let timestamp = Utc::now();
+        let location = manager.store(Frame {
+            id: 7,
+            data: vec![1, 2, 3],
+            timestamp,
+            metadata: HashMap::new(),
+        }).await.unwrap();
+
+        let expected = PathBuf::from(format!("frame_7_{}.png", timestamp.timestamp()));
+        assert_eq!(location.backend, "hybrid");
+        assert_eq!(location.path.as_ref(), Some(&expected));
+        assert!(dir.join(&expected).exists());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_batched_disk_writes_land_on_flush() {
+        let dir = std::env::temp_dir().join(format!("eye-storage-batch-{}", std::process::id()));
+        let disk = DiskStore::new(dir.clone()).await.unwrap().with_batching(WriteBatching {
+            max_frames: 100,
+            max_delay: Duration::from_secs(3600),
+        });
+
+        let timestamp = Utc::now();
+        let mut paths = Vec::new();
+        for id in 0..10 {
+            let frame = Frame { id, data: vec![id as u8; 16], timestamp, metadata: HashMap::new() };
+            paths.push(disk.store(&frame).await.unwrap());
+        }
+
+        // Neither the size nor the time limit has been hit yet
+        assert!(paths.iter().all(|p| !dir.join(p).exists()));
+
+        disk.flush().await.unwrap();
+        for (id, path) in paths.iter().enumerate() {
+            assert_eq!(std::fs::read(dir.join(path)).unwrap(), vec![id as u8; 16]);
+        }
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_compaction_keeps_only_live_frames() {
+        let dir = std::env::temp_dir().join(format!("eye-storage-compact-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let disk = DiskStore::new(dir.clone()).await.unwrap();
+
+        let timestamp = Utc::now();
+        for id in 0..4 {
+            let frame = Frame { id, data: vec![id as u8; 16], timestamp, metadata: HashMap::new() };
+            disk.store(&frame).await.unwrap();
+        }
+        let live = |id: i64| format!("frame_{}_{}.png", id, timestamp.timestamp());
+
+        // An older nested layout: one live frame, one partition whose frames
+        // were all deleted, one copy shadowed by the same frame in the root
+        std::fs::create_dir_all(dir.join("2026/03/01")).unwrap();
+        std::fs::create_dir_all(dir.join("2026/03/02")).unwrap();
+        std::fs::write(dir.join("2026/03/01").join(live(7)), [7u8; 16]).unwrap();
+        std::fs::write(dir.join("2026/03/01").join(live(1)), [1u8; 16]).unwrap();
+
+        // Leftovers of interrupted writes, old enough to be settled
+        let old = std::time::SystemTime::now() - COMPACTION_GRACE * 2;
+        for name in [live(9), format!("{}.tmp", live(8))] {
+            let file = std::fs::File::create(dir.join(&name)).unwrap();
+            file.set_modified(old).unwrap();
+        }
+        // Deleted by an operator, leaving its sidecar orphaned
+        std::fs::remove_file(dir.join(live(2))).unwrap();
+        let orphan = dir.join(format!("frame_2_{}.json", timestamp.timestamp()));
+        let orphan_len = std::fs::metadata(&orphan).unwrap().len();
+        std::fs::File::options().write(true).open(&orphan).unwrap().set_modified(old).unwrap();
+
+        let stats = disk.compact().await.unwrap();
+        assert_eq!(stats.files_kept, 4);
+        assert_eq!(stats.files_removed, 4);
+        assert_eq!(stats.dirs_removed, 4);
+        assert_eq!(stats.bytes_reclaimed, 16 + orphan_len);
+
+        let mut names: Vec<String> = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+            .filter(|name| !name.ends_with(".json"))
+            .collect();
+        names.sort();
+        assert_eq!(names, vec![live(0), live(1), live(3), live(7)]);
+        assert!(!orphan.exists());
+        assert_eq!(std::fs::read(dir.join(live(7))).unwrap(), vec![7u8; 16]);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_disk_lookups_use_index() {
+        let dir = std::env::temp_dir().join(format!("eye-storage-index-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let timestamp = Utc::now();
+        for id in 0..3 {
+            std::fs::write(dir.join(format!("frame_{}_{}.png", id, timestamp.timestamp())), [id as u8]).unwrap();
+        }
+
+        // One scan at startup indexes the existing frames
+        let disk = DiskStore::new(dir.clone()).await.unwrap().with_index_capacity(3);
+        let scans = disk.scans.load(Ordering::Relaxed);
+        assert_eq!(disk.get_by_id(1).await.unwrap().data, vec![1]);
+
+        // Frames stored after startup are indexed as they are written
+        let frame = Frame { id: 3, data: vec![3], timestamp, metadata: HashMap::new() };
+        disk.store(&frame).await.unwrap();
+        assert_eq!(disk.get_by_id(3).await.unwrap().data, vec![3]);
+        assert_eq!(disk.scans.load(Ordering::Relaxed), scans);
+
+        // Frame 0 was the least recently used and fell out of the capped
+        // index; it is still found by scanning
+        assert_eq!(disk.get_by_id(0).await.unwrap().data, vec![0]);
+        assert_eq!(disk.scans.load(Ordering::Relaxed), scans + 1);
+        assert!(disk.get_by_id(99).await.is_err());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_hash_chain_detects_tampering() {
+        let manager = Manager::in_memory(4).with_hash_chain();
+        let timestamp = Utc::now();
+        for id in 0..6 {
+            let frame = Frame { id, data: vec![id as u8; 8], timestamp, metadata: HashMap::new() };
+            manager.store(frame).await.unwrap();
+        }
+
+        // Frames 0 and 1 were evicted, which is not a break
+        assert_eq!(manager.verify_chain().await.unwrap(), Vec::<i64>::new());
+        let first = manager.get_by_id(2).await.unwrap();
+        let second = manager.get_by_id(3).await.unwrap();
+        assert_eq!(second.metadata[PREV_HASH_KEY], first.metadata[THIS_HASH_KEY]);
+
+        // Altering a middle frame's bytes breaks the chain at that frame
+        Arc::make_mut(&mut manager.memory.frames.write().await[1].data)[0] ^= 0xff;
+        assert_eq!(manager.verify_chain().await.unwrap(), vec![3]);
+
+        // Removing a frame breaks the link of the one after it
+        manager.memory.frames.write().await.remove(1);
+        assert_eq!(manager.verify_chain().await.unwrap(), vec![4]);
+
+        assert!(Manager::in_memory(4).verify_chain().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_hash_chain_persists_on_disk() {
+        let dir = std::env::temp_dir().join(format!("eye-storage-chain-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let timestamp = Utc::now();
+        let frame = |id: i64| Frame {
+            id,
+            data: vec![id as u8; 8],
+            timestamp: timestamp + chrono::Duration::seconds(id),
+            metadata: HashMap::new(),
+        };
+        let open = || async { Manager::new(StorageMode::Disk, 4, Some(dir.clone())).await.unwrap().with_hash_chain() };
+
+        let manager = open().await;
+        for id in 0..4 {
+            manager.store(frame(id)).await.unwrap();
+        }
+        let head = manager.get_by_id(3).await.unwrap().metadata[THIS_HASH_KEY].clone();
+
+        // A restarted server carries the chain on from disk
+        let manager = open().await;
+        manager.store(frame(4)).await.unwrap();
+        assert_eq!(manager.get_by_id(4).await.unwrap().metadata[PREV_HASH_KEY], head);
+        assert_eq!(manager.verify_chain().await.unwrap(), Vec::<i64>::new());
+
+        // Tampering with a file on disk breaks the chain at that frame
+        let file = |id: i64| dir.join(frame_filename(id, (timestamp + chrono::Duration::seconds(id)).timestamp(), "png"));
+        std::fs::write(file(1), [0xffu8; 8]).unwrap();
+        assert_eq!(manager.verify_chain().await.unwrap(), vec![1]);
+
+        // Deleting one breaks the link of the frame after it
+        std::fs::remove_file(file(2)).unwrap();
+        std::fs::remove_file(file(2).with_extension(SIDECAR_EXTENSION)).unwrap();
+        assert_eq!(manager.verify_chain().await.unwrap(), vec![1, 3]);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_deleted_chained_frame_breaks_chain_in_every_mode() {
+        let dir = std::env::temp_dir().join(format!("eye-storage-chain-delete-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let timestamp = Utc::now();
+        let frame = |id: i64| Frame {
+            id,
+            data: vec![id as u8; 8],
+            timestamp: timestamp + chrono::Duration::seconds(id),
+            metadata: HashMap::new(),
+        };
+
+        for mode in [StorageMode::Memory, StorageMode::Disk] {
+            let manager = Manager::new(mode.clone(), 10, Some(dir.clone())).await.unwrap().with_hash_chain();
+            for id in 0..4 {
+                manager.store(frame(id)).await.unwrap();
+            }
+
+            // Deleting a middle frame breaks the link of the one after it
+            assert!(manager.delete(1).await.unwrap());
+            assert_eq!(manager.verify_chain().await.unwrap(), vec![2], "{:?}", mode);
+
+            // Pruning the oldest frames, the gap included, is not a break
+            manager.prune_before(timestamp + chrono::Duration::seconds(3)).await.unwrap();
+            assert_eq!(manager.verify_chain().await.unwrap(), Vec::<i64>::new(), "{:?}", mode);
+
+            let _ = std::fs::remove_dir_all(&dir);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_out_of_order_frames_are_reordered() {
+        let store = MemoryStore::new(10).with_reorder_window(4);
+        let frame = |id| Frame {
+            id,
+            data: vec![],
+            timestamp: Utc::now(),
+            metadata: HashMap::new(),
+        };
+
+        store.store(frame(5)).await.unwrap();
+        store.store(frame(3)).await.unwrap();
+        assert_eq!(store.get_latest().await.unwrap().id, 5);
+
+        store.store(frame(6)).await.unwrap();
+        store.store(frame(4)).await.unwrap();
+        let ids: Vec<i64> = store.list().await.iter().map(|f| f.id).collect();
+        assert_eq!(ids, vec![3, 4, 5, 6]);
+
+        // Far below the window: a restarted sequence, which becomes latest
+        for id in 7..10 {
+            store.store(frame(id)).await.unwrap();
+        }
+        store.store(frame(0)).await.unwrap();
+        assert_eq!(store.get_latest().await.unwrap().id, 0);
+    }
+
+    // A frame file symlinked to /dev/full fails its write with ENOSPC
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_hybrid_falls_back_to_memory_when_disk_full() {
+        let dir = std::env::temp_dir().join(format!("eye-storage-full-{}", std::process::id()));
+        let store = Manager::new(StorageMode::Hybrid, 10, Some(dir.clone())).await.unwrap();
+        let timestamp = Utc::now();
+        let frame = |id: i64| Frame { id, data: vec![id as u8; 16], timestamp, metadata: HashMap::new() };
+
+        std::os::unix::fs::symlink("/dev/full", dir.join(format!("frame_1_{}.png.tmp", timestamp.timestamp()))).unwrap();
+        let location = store.store(frame(1)).await.unwrap();
+        assert_eq!(location.backend, "memory");
+        assert_eq!(location.path, None);
+        assert_eq!(store.get_latest().await.unwrap().id, 1);
+
+        let disk = store.storage_health().await.pop().unwrap();
+        assert!(disk.degraded);
+        assert!(!disk.healthy);
+        assert!(disk.last_write_error.is_some());
+
+        // The next successful write clears the degradation
+        let location = store.store(frame(2)).await.unwrap();
+        assert_eq!(location.backend, "hybrid");
+        assert!(location.path.is_some());
+        let disk = store.storage_health().await.pop().unwrap();
+        assert!(!disk.degraded);
+        assert!(disk.healthy);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    // The batched version: the upload has already returned when the write
+    // fails, so the writer unindexes the frame and flags the degradation
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_batched_hybrid_degrades_when_disk_full() {
+        let dir = std::env::temp_dir().join(format!("eye-storage-batch-full-{}", std::process::id()));
+        let store = Manager::new(StorageMode::Hybrid, 10, Some(dir.clone()))
+            .await
+            .unwrap()
+            .with_write_batching(WriteBatching { max_frames: 8, max_delay: Duration::from_secs(3600) });
+        let timestamp = Utc::now();
+        let frame = |id: i64| Frame { id, data: vec![id as u8; 16], timestamp, metadata: HashMap::new() };
+        let frame_file = |id: i64| dir.join(frame_filename(id, timestamp.timestamp(), "png"));
+
+        std::os::unix::fs::symlink("/dev/full", temp_path(&frame_file(1))).unwrap();
+        store.store(frame(1)).await.unwrap();
+        assert!(store.flush().await.is_err());
+
+        // Still served from memory, but no longer claimed by the disk store
+        assert_eq!(store.get_by_id(1).await.unwrap().id, 1);
+        let disk = store.disk.as_ref().unwrap();
+        assert_eq!(disk.index.lock().unwrap().get(1), None);
+        assert!(!frame_file(1).with_extension(SIDECAR_EXTENSION).exists());
+        let health = store.storage_health().await.pop().unwrap();
+        assert!(health.degraded);
+        assert!(!health.healthy);
+
+        // A batch that lands clears the degradation
+        store.store(frame(2)).await.unwrap();
+        store.flush().await.unwrap();
+        assert!(frame_file(2).exists());
+        let health = store.storage_health().await.pop().unwrap();
+        assert!(!health.degraded);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_metadata_index_tracks_stored_frames() {
+        let manager = Manager::in_memory(3);
+        let titles = ["Editor", "Browser", "Editor", "Terminal"];
+        for (id, title) in titles.iter().enumerate() {
+            let mut metadata = HashMap::from([("window_title".to_string(), title.to_string())]);
+            if id % 2 == 0 {
+                metadata.insert("monitor_id".to_string(), "0".to_string());
+            }
+            manager
+                .store(Frame { id: id as i64, data: vec![id as u8], timestamp: Utc::now(), metadata })
+                .await
+                .unwrap();
+        }
+
+        // Frame 0 was evicted, and dropped from the index with it
+        assert_eq!(manager.query_metadata("window_title", "Editor", MetadataMatch::Exact).await, vec![2]);
+        assert_eq!(manager.query_metadata("window_title", "Browser", MetadataMatch::Exact).await, vec![1]);
+        assert_eq!(manager.query_metadata("monitor_id", "0", MetadataMatch::Exact).await, vec![2]);
+        assert!(manager.query_metadata("window_title", "Mail", MetadataMatch::Exact).await.is_empty());
+        assert!(manager.query_metadata("title", "Editor", MetadataMatch::Exact).await.is_empty());
+
+        // Partial matches
+        assert!(manager.query_metadata("window_title", "Edit", MetadataMatch::Exact).await.is_empty());
+        assert_eq!(manager.query_metadata("window_title", "Edit", MetadataMatch::Prefix).await, vec![2]);
+        assert_eq!(manager.query_metadata("window_title", "r", MetadataMatch::Contains).await, vec![1, 2, 3]);
+        assert!(manager.query_metadata("window_title", "er", MetadataMatch::Prefix).await.is_empty());
+        assert_eq!(manager.query_metadata("window_title", "", MetadataMatch::Prefix).await, vec![1, 2, 3]);
+
+        // The index alone answers queries: its postings cover exactly the
+        // held frames' metadata
+        let index = manager.memory.metadata_index.lock().unwrap();
+        let postings: usize = index.postings.values().map(BTreeMap::len).sum();
+        assert_eq!(postings, 4);
+    }
+
+    #[tokio::test]
+    async fn test_delta_frames_rebuild_exactly_and_save_space() {
+        let dir = std::env::temp_dir().join(format!("eye-storage-delta-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let disk = DiskStore::new(dir.clone()).await.unwrap().with_delta_encoding(10);
+
+        // A 64x64 RGBA screen where each frame repaints a small patch
+        let mut screen: Vec<u8> = (0..64 * 64 * 4).map(|i| (i * 7 % 251) as u8).collect();
+        let timestamp = Utc::now();
+        let mut originals = Vec::new();
+        for id in 0..6 {
+            if id > 0 {
+                let start = id as usize * 1000;
+                screen[start..start + 40].iter_mut().for_each(|b| *b = b.wrapping_add(id as u8));
+            }
+            let frame = Frame { id, data: screen.clone(), timestamp, metadata: HashMap::new() };
+            disk.store(&frame).await.unwrap();
+            originals.push(frame.data);
+        }
+
+        for (id, original) in originals.iter().enumerate() {
+            assert_eq!(&disk.get_by_id(id as i64).await.unwrap().data, original);
+        }
+        assert_eq!(&disk.get_latest().await.unwrap().data, originals.last().unwrap());
+
+        // One full keyframe plus five small deltas
+        let usage = disk.disk_usage().await.unwrap();
+        let full: u64 = originals.iter().map(|data| data.len() as u64).sum();
+        assert_eq!(usage.files, 6);
+        assert!(usage.bytes < full / 4, "{} bytes on disk", usage.bytes);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_disk_files_are_named_by_format() {
+        let dir = std::env::temp_dir().join(format!("eye-storage-format-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let manager = Manager::new(StorageMode::Disk, 10, Some(dir.clone())).await.unwrap();
+
+        let timestamp = Utc::now();
+        let jpeg = vec![0xFF, 0xD8, 0xFF, 0xE0, 1, 2, 3];
+        let metadata = HashMap::from([
+            ("format".to_string(), "jpeg".to_string()),
+            ("content-type".to_string(), "image/jpeg".to_string()),
+        ]);
+        let location = manager
+            .store(Frame { id: 3, data: jpeg.clone(), timestamp, metadata })
+            .await
+            .unwrap();
+
+        let expected = PathBuf::from(format!("frame_3_{}.jpg", timestamp.timestamp()));
+        assert_eq!(location.path.as_ref(), Some(&expected));
+        assert!(dir.join(&expected).exists());
+
+        for frame in [manager.get_by_id(3).await.unwrap(), manager.get_latest().await.unwrap()] {
+            assert_eq!(frame.id, 3);
+            assert_eq!(frame.data, jpeg);
+            assert_eq!(frame.metadata["format"], "jpeg");
+            assert_eq!(frame.metadata["content-type"], "image/jpeg");
+        }
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_list_is_oldest_first_after_eviction() {
+        let store = MemoryStore::new(10);
+        for id in 0..15 {
+            store
+                .store(Frame { id, data: vec![id as u8], timestamp: Utc::now(), metadata: HashMap::new() })
+                .await
+                .unwrap();
+        }
+
+        let ids: Vec<i64> = store.list().await.iter().map(|f| f.id).collect();
+        assert_eq!(ids, (5..15).collect::<Vec<_>>());
+    }
+
+    #[tokio::test]
+    async fn test_byte_limit_evicts_oldest_frames() {
+        let store = MemoryStore::new(100).with_byte_limit(1000);
+        let frame = |id: i64, len: usize| Frame { id, data: vec![0; len], timestamp: Utc::now(), metadata: HashMap::new() };
+
+        for id in 0..5 {
+            store.store(frame(id, 300)).await.unwrap();
+        }
+        let ids: Vec<i64> = store.list().await.iter().map(|f| f.id).collect();
+        assert_eq!(ids, [2, 3, 4]);
+        assert_eq!(store.current_bytes().await, 900);
+        assert_eq!(store.usage().await.max_bytes, Some(1000));
+
+        // An oversized frame is kept, alone
+        store.store(frame(5, 5000)).await.unwrap();
+        let ids: Vec<i64> = store.list().await.iter().map(|f| f.id).collect();
+        assert_eq!(ids, [5]);
+        assert_eq!(store.current_bytes().await, 5000);
+
+        // and goes as soon as anything else arrives
+        store.store(frame(6, 10)).await.unwrap();
+        assert_eq!(store.current_bytes().await, 10);
+    }
+
+    #[tokio::test]
+    async fn test_frames_expire_after_ttl() {
+        let clock = clock::FakeClock::default();
+        let store = MemoryStore::new(10)
+            .with_ttl(Duration::from_secs(30))
+            .with_clock(Arc::new(clock.clone()));
+        let frame = |id: i64| Frame {
+            id,
+            data: vec![id as u8],
+            timestamp: clock::Clock::now(&clock),
+            metadata: HashMap::new(),
+        };
+
+        store.store(frame(1)).await.unwrap();
+        clock.advance(chrono::Duration::seconds(20));
+        store.store(frame(2)).await.unwrap();
+        assert_eq!(store.list().await.len(), 2);
+
+        // Frame 1 is now 40s old, frame 2 only 20s
+        clock.advance(chrono::Duration::seconds(20));
+        let ids: Vec<i64> = store.list().await.iter().map(|f| f.id).collect();
+        assert_eq!(ids, [2]);
+        assert!(store.get_by_id(1).await.is_err());
+
+        clock.advance(chrono::Duration::seconds(11));
+        let error = store.get_latest().await.unwrap_err();
+        assert_eq!(error.to_string(), "no frames available");
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_failed_write_leaves_no_frame_file() {
+        let dir = std::env::temp_dir().join(format!("eye-storage-atomic-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let disk = DiskStore::new(dir.clone()).await.unwrap();
+        let timestamp = Utc::now();
+        let name = format!("frame_1_{}.png", timestamp.timestamp());
+
+        // Writes to the temporary file fail as if the disk were full
+        std::os::unix::fs::symlink("/dev/full", dir.join(format!("{}.tmp", name))).unwrap();
+        let frame = Frame { id: 1, data: vec![1; 4096], timestamp, metadata: HashMap::new() };
+        assert!(disk.store(&frame).await.is_err());
+
+        let left: Vec<_> = std::fs::read_dir(&dir).unwrap().map(|e| e.unwrap().file_name()).collect();
+        assert!(left.is_empty(), "{:?}", left);
+        assert!(disk.get_by_id(1).await.is_err());
+
+        // Retrying once the disk recovers lands the whole frame
+        disk.store(&frame).await.unwrap();
+        assert_eq!(std::fs::read(dir.join(&name)).unwrap(), frame.data);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_sidecar_restores_frame_metadata() {
+        let dir = std::env::temp_dir().join(format!("eye-storage-sidecar-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let disk = DiskStore::new(dir.clone()).await.unwrap();
+
+        let timestamp = Utc::now();
+        let metadata = HashMap::from([
+            ("format".to_string(), "webp".to_string()),
+            ("content-type".to_string(), "image/webp".to_string()),
+            ("window_title".to_string(), "Editor".to_string()),
+        ]);
+        let frame = Frame { id: 4, data: vec![4; 32], timestamp, metadata: metadata.clone() };
+        let path = disk.store(&frame).await.unwrap();
+        let sidecar = dir.join(path.with_extension("json"));
+        assert!(sidecar.exists());
+
+        // Exact timestamp and every tag come back
+        for read in [disk.get_by_id(4).await.unwrap(), disk.get_latest().await.unwrap()] {
+            assert_eq!(read.timestamp, timestamp);
+            assert_eq!(read.metadata, metadata);
+            assert_eq!(read.data, frame.data);
+        }
+
+        // Directories written before sidecars still load
+        std::fs::remove_file(&sidecar).unwrap();
+        let read = disk.get_by_id(4).await.unwrap();
+        assert_eq!(read.timestamp.timestamp(), timestamp.timestamp());
+        assert_eq!(read.metadata.get("window_title"), None);
+        assert_eq!(read.metadata["format"], "webp");
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_on_evict_sees_each_evicted_frame_once() {
+        let evicted = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let seen = evicted.clone();
+        let store = MemoryStore::new(3).on_evict(move |frame| seen.lock().unwrap().push((frame.id, frame.data)));
+        let frame = |id: i64| Frame { id, data: vec![id as u8], timestamp: Utc::now(), metadata: HashMap::new() };
+
+        for id in 0..3 {
+            store.store(frame(id)).await.unwrap();
+        }
+        assert!(evicted.lock().unwrap().is_empty());
+
+        store.pin(0).await.unwrap();
+        for id in 3..6 {
+            store.store(frame(id)).await.unwrap();
+        }
+        // Frame 0 is pinned; the rest go oldest first, bytes and all
+        assert_eq!(*evicted.lock().unwrap(), [(1, vec![1]), (2, vec![2])]);
+
+        // Unpinning pushes the store over capacity again
+        store.unpin(0).await.unwrap();
+        let ids: Vec<i64> = evicted.lock().unwrap().iter().map(|(id, _)| *id).collect();
+        assert_eq!(ids, [1, 2, 0]);
+    }
+
+    #[tokio::test]
+    async fn test_hybrid_reads_fall_back_to_disk_after_restart() {
+        let dir = std::env::temp_dir().join(format!("eye-storage-restart-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let timestamp = Utc::now();
+        let before = Manager::new(StorageMode::Hybrid, 10, Some(dir.clone())).await.unwrap();
+        for id in 1..=2 {
+            before
+                .store(Frame { id, data: vec![id as u8; 8], timestamp, metadata: HashMap::new() })
+                .await
+                .unwrap();
+        }
+        drop(before);
+
+        // A fresh manager starts with empty memory; the frames are only on disk
+        let manager = Manager::new(StorageMode::Hybrid, 10, Some(dir.clone())).await.unwrap();
+        assert!(manager.list().await.is_empty());
+        let latest = manager.get_latest().await.unwrap();
+        assert_eq!((latest.id, latest.data), (2, vec![2; 8]));
+        assert_eq!(manager.get_by_id(1).await.unwrap().data, vec![1; 8]);
+
+        // Memory wins once it holds a frame again
+        manager
+            .store(Frame { id: 3, data: vec![3; 8], timestamp, metadata: HashMap::new() })
+            .await
+            .unwrap();
+        assert_eq!(manager.get_latest().await.unwrap().id, 3);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_store_queries_by_id_and_time() {
+        let dir = std::env::temp_dir().join(format!("eye-storage-sqlite-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let db = dir.join("frames.db");
+        let start = Utc::now();
+        let manager = Manager::new(StorageMode::Sqlite, 10, Some(db.clone())).await.unwrap();
+        for id in 1..=3 {
+            let metadata = HashMap::from([("format".to_string(), "jpeg".to_string())]);
+            let timestamp = start + chrono::Duration::seconds(id * 10);
+            let location = manager
+                .store(Frame { id, data: vec![id as u8; 8], timestamp, metadata })
+                .await
+                .unwrap();
+            assert_eq!((location.backend.as_str(), location.path), ("sqlite", None));
+        }
+
+        let latest = manager.get_latest().await.unwrap();
+        assert_eq!((latest.id, latest.timestamp), (3, start + chrono::Duration::seconds(30)));
+        assert_eq!(latest.metadata["content-type"], "image/jpeg");
+        assert_eq!(manager.get_by_id(2).await.unwrap().data, vec![2; 8]);
+        assert!(manager.get_by_id(9).await.is_err());
+
+        let since: Vec<i64> = manager
+            .list_since(start + chrono::Duration::seconds(20))
+            .await
+            .unwrap()
+            .iter()
+            .map(|f| f.id)
+            .collect();
+        assert_eq!(since, [2, 3]);
+
+        assert_eq!(manager.query_metadata("format", "jp", MetadataMatch::Prefix).await, [1, 2, 3]);
+        assert_eq!(manager.query_metadata("format", "pe", MetadataMatch::Contains).await, [1, 2, 3]);
+        assert!(manager.query_metadata("format", "pe", MetadataMatch::Prefix).await.is_empty());
+        assert!(manager.query_metadata("format", "%", MetadataMatch::Contains).await.is_empty());
+
+        // Persisted across a reopen, with the timestamp index in place
+        drop(manager);
+        let sqlite = SqliteStore::open(db.clone()).await.unwrap();
+        assert_eq!(sqlite.get_latest().await.unwrap().id, 3);
+        let conn = rusqlite::Connection::open(&db).unwrap();
+        let plan: String = conn
+            .query_row("EXPLAIN QUERY PLAN SELECT id FROM frames WHERE timestamp >= '2000'", [], |row| row.get(3))
+            .unwrap();
+        assert!(plan.contains("frames_timestamp"), "{}", plan);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_range_queries_are_inclusive_and_oldest_first() {
+        let dir = std::env::temp_dir().join(format!("eye-storage-range-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let start = DateTime::from_timestamp(1_700_000_000, 250_000_000).unwrap();
+        let at = |ms: i64| start + chrono::Duration::milliseconds(ms);
+
+        for (mode, path) in [
+            (StorageMode::Memory, None),
+            (StorageMode::Disk, Some(dir.join("disk"))),
+            (StorageMode::Sqlite, Some(dir.join("frames.db"))),
+        ] {
+            let name = mode.as_str();
+            let manager = Manager::new(mode, 10, path).await.unwrap();
+            // Stored out of time order, two of them within the same second
+            for (id, ms) in [(1, 2000), (2, 0), (3, 500), (4, 4000), (5, 1000)] {
+                manager
+                    .store(Frame { id, data: vec![id as u8; 8], timestamp: at(ms), metadata: HashMap::new() })
+                    .await
+                    .unwrap();
+            }
+
+            let frames = manager.get_range(at(500), at(2000)).await;
+            let ids: Vec<i64> = frames.iter().map(|f| f.id).collect();
+            assert_eq!(ids, [3, 5, 1], "{}", name);
+            assert_eq!(frames[0].data, vec![3; 8]);
+
+            let metas = manager.list_range_meta(at(500), at(2000)).await;
+            let ids: Vec<i64> = metas.iter().map(|m| m.id).collect();
+            assert_eq!(ids, [3, 5, 1], "{}", name);
+            assert!(metas.iter().all(|m| m.size_bytes == 8));
+            assert_eq!(metas[1].timestamp, at(1000));
+
+            assert!(manager.get_range(at(2000), at(500)).await.is_empty());
+            assert!(manager.list_range_meta(at(2000), at(500)).await.is_empty());
+            assert!(manager.get_range(at(4001), at(9000)).await.is_empty());
+        }
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_stats_cover_memory_and_disk() {
+        let store = MemoryStore::new(10);
+        assert_eq!(store.stats().await, StorageStats::default());
+
+        let start = Utc::now();
+        for (id, secs) in [(1, 20), (2, 0), (3, 10)] {
+            let timestamp = start + chrono::Duration::seconds(secs);
+            store.store(Frame { id, data: vec![0; 4], timestamp, metadata: HashMap::new() }).await.unwrap();
+        }
+        store.pin(2).await.unwrap();
+        let stats = store.stats().await;
+        assert_eq!((stats.frame_count, stats.total_bytes), (3, 12));
+        assert_eq!(stats.oldest, Some(start));
+        assert_eq!(stats.newest, Some(start + chrono::Duration::seconds(20)));
+        assert_eq!(stats.disk_frame_count, None);
+
+        let dir = std::env::temp_dir().join(format!("eye-storage-stats-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let manager = Manager::new(StorageMode::Hybrid, 2, Some(dir.clone())).await.unwrap();
+        for id in 1..=3 {
+            manager
+                .store(Frame { id, data: vec![0; 4], timestamp: start, metadata: HashMap::new() })
+                .await
+                .unwrap();
+        }
+        let stats = manager.stats().await;
+        assert_eq!((stats.frame_count, stats.disk_frame_count), (2, Some(3)));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_delete_and_prune_remove_frames_everywhere() {
+        let start = Utc::now();
+        let at = |secs: i64| start + chrono::Duration::seconds(secs);
+        let store = MemoryStore::new(10);
+        for id in 1..=4 {
+            store
+                .store(Frame { id, data: vec![id as u8; 4], timestamp: at(id), metadata: HashMap::new() })
+                .await
+                .unwrap();
+        }
+        store.pin(1).await.unwrap();
+        assert!(store.delete(3).await.unwrap());
+        assert!(!store.delete(3).await.unwrap());
+        // Pinned frames are pruned too
+        assert_eq!(store.prune_before(at(2)).await, 1);
+        let ids: Vec<i64> = store.list().await.iter().map(|f| f.id).collect();
+        assert_eq!(ids, [2, 4]);
+
+        // Deleting a keyframe first rewrites the deltas taken against it
+        let dir = std::env::temp_dir().join(format!("eye-storage-delete-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let disk = DiskStore::new(dir.clone()).await.unwrap().with_delta_encoding(10);
+        let mut screen = vec![7u8; 4096];
+        let mut originals = Vec::new();
+        for id in 0..4 {
+            screen[id as usize * 100] = id as u8;
+            let frame = Frame { id, data: screen.clone(), timestamp: at(id), metadata: HashMap::new() };
+            disk.store(&frame).await.unwrap();
+            originals.push(frame.data);
+        }
+        assert!(disk.delete(0).await.unwrap());
+        assert!(!disk.delete(0).await.unwrap());
+        assert!(disk.get_by_id(0).await.is_err());
+        for id in 1..4 {
+            assert_eq!(disk.get_by_id(id).await.unwrap().data, originals[id as usize]);
+        }
+        let frame = Frame { id: 4, data: screen.clone(), timestamp: at(4), metadata: HashMap::new() };
+        disk.store(&frame).await.unwrap();
+        assert_eq!(disk.get_by_id(4).await.unwrap().data, screen);
+
+        assert_eq!(disk.prune_before(at(3)).await.unwrap(), 2);
+        let ids: Vec<i64> = disk.scan().await.unwrap().iter().map(|&(id, _, _)| id).collect();
+        assert_eq!(ids, [3, 4]);
+        let names: Vec<String> = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        assert_eq!(names.len(), 4, "{:?}", names);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_corrupt_disk_frame_fails_checksum() {
+        let dir = std::env::temp_dir().join(format!("eye-storage-checksum-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let disk = DiskStore::new(dir.clone()).await.unwrap();
+        let frame = Frame { id: 1, data: b"PNGDATA".to_vec(), timestamp: Utc::now(), metadata: HashMap::new() };
+        let path = dir.join(disk.store(&frame).await.unwrap());
+        assert_eq!(disk.get_latest().await.unwrap().data, frame.data);
+
+        // Flip one byte of the stored file
+        let mut bytes = std::fs::read(&path).unwrap();
+        bytes[3] ^= 0x01;
+        std::fs::write(&path, bytes).unwrap();
+        let err = disk.get_latest().await.unwrap_err();
+        assert!(format!("{:#}", err).contains("checksum"), "{:#}", err);
+
+        let disk = disk.with_verify_on_read(false);
+        assert_ne!(disk.get_latest().await.unwrap().data, frame.data);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_full_ring_buffer_is_not_pressure() {
+        let usage = Usage { frames: 100, max_frames: 100, bytes: 512, max_bytes: None, disk_free_bytes: None };
+        assert_eq!(usage.pressure(), 0.0);
+
+        let budget = Usage { max_bytes: Some(1024), ..usage };
+        assert_eq!(budget.pressure(), 0.5);
+
+        let plenty = Usage { disk_free_bytes: Some(4 * DISK_PRESSURE_BYTES), ..usage };
+        assert_eq!(plenty.pressure(), 0.0);
+        let low = Usage { disk_free_bytes: Some(DISK_PRESSURE_BYTES / 10), ..usage };
+        assert!((low.pressure() - 0.9).abs() < 1e-9);
+    }
+
+    // Checks shared by every manager backend: frames listed, pinned,
+    // counted and found by metadata the same way
+    async fn check_manager(manager: Manager) {
+        let start = Utc::now();
+        for (id, title) in [(1, "Editor"), (2, "Browser")] {
+            let metadata = HashMap::from([("window_title".to_string(), title.to_string())]);
+            let timestamp = start + chrono::Duration::seconds(id);
+            manager.store(Frame { id, data: vec![id as u8; 4], timestamp, metadata }).await.unwrap();
+        }
+
+        let ids: Vec<i64> = manager.list().await.iter().map(|f| f.id).collect();
+        assert_eq!(ids, [1, 2]);
+        assert_eq!(manager.query_metadata("window_title", "Editor", MetadataMatch::Exact).await, vec![1]);
+        assert!(manager.query_metadata("window_title", "Mail", MetadataMatch::Exact).await.is_empty());
+
+        let usage = manager.usage().await;
+        assert_eq!((usage.frames, usage.bytes), (2, 8));
+        let stats = manager.stats().await;
+        assert_eq!((stats.frame_count, stats.total_bytes), (2, 8));
+        assert_eq!((stats.oldest, stats.newest), (Some(start + chrono::Duration::seconds(1)), Some(start + chrono::Duration::seconds(2))));
+
+        manager.pin(1).await.unwrap();
+        assert!(manager.is_pinned(1).await);
+        assert!(!manager.is_pinned(2).await);
+        assert!(manager.pin(9).await.is_err());
+        manager.unpin(1).await.unwrap();
+        assert!(!manager.is_pinned(1).await);
+        assert!(manager.unpin(1).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_memory_manager_lists_pins_and_counts() {
+        check_manager(Manager::in_memory(10)).await;
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_manager_lists_pins_and_counts() {
+        let dir = std::env::temp_dir().join(format!("eye-storage-sqlite-manager-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        check_manager(Manager::sqlite(dir.join("frames.db"), 10).await.unwrap()).await;
+        let _ = std::fs::remove_dir_all(&dir);
+    }
 }
\ No newline at end of file
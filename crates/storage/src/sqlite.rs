@@ -0,0 +1,345 @@
+// crates/storage/src/sqlite.rs
+use crate::{Frame, FrameMeta, MetadataMatch, StorageHealth, StorageStats, frame_format, free_bytes};
+use anyhow::{Context, Result};
+use chrono::{DateTime, SecondsFormat, Utc};
+use rusqlite::{Connection, OptionalExtension, Row, params};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+// Schema of a SQLite store. Timestamps are fixed-width RFC 3339 in UTC,
+// so text order is time order and the timestamp index serves range
+// queries. Metadata beyond the format is kept as a JSON object.
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS frames (
+        id INTEGER NOT NULL,
+        timestamp TEXT NOT NULL,
+        format TEXT NOT NULL,
+        size_bytes INTEGER NOT NULL,
+        data BLOB NOT NULL,
+        metadata TEXT NOT NULL DEFAULT '{}'
+    );
+    CREATE INDEX IF NOT EXISTS frames_timestamp ON frames (timestamp);
+    CREATE INDEX IF NOT EXISTS frames_id ON frames (id);
+    CREATE TABLE IF NOT EXISTS pins (id INTEGER PRIMARY KEY);
+";
+
+const COLUMNS: &str = "id, timestamp, format, data, metadata";
+
+fn timestamp_text(timestamp: DateTime<Utc>) -> String {
+    timestamp.to_rfc3339_opts(SecondsFormat::Nanos, true)
+}
+
+// Frames in a single SQLite database file: lookups by id or time go
+// through an index instead of a directory scan, and history can be
+// queried in one call. Queries run on the blocking thread pool.
+pub struct SqliteStore {
+    path: PathBuf,
+    conn: Arc<Mutex<Connection>>,
+}
+
+impl SqliteStore {
+    // Open (or create) the database at `path`, creating its directory
+    pub async fn open(path: PathBuf) -> Result<Self> {
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            tokio::fs::create_dir_all(dir)
+                .await
+                .context("Failed to create storage directory")?;
+        }
+
+        let db = path.clone();
+        let conn = tokio::task::spawn_blocking(move || {
+            let conn = Connection::open(&db)?;
+            // Readers don't wait behind a write
+            conn.pragma_update(None, "journal_mode", "WAL")?;
+            conn.execute_batch(SCHEMA)?;
+            Ok::<_, rusqlite::Error>(conn)
+        })
+        .await?
+        .with_context(|| format!("Failed to open SQLite store {}", path.display()))?;
+
+        Ok(Self { path, conn: Arc::new(Mutex::new(conn)) })
+    }
+
+    async fn with_conn<T: Send + 'static>(
+        &self,
+        query: impl FnOnce(&Connection) -> rusqlite::Result<T> + Send + 'static,
+    ) -> Result<T> {
+        let conn = self.conn.clone();
+        let result = tokio::task::spawn_blocking(move || query(&conn.lock().unwrap())).await?;
+        result.with_context(|| format!("SQLite query on {} failed", self.path.display()))
+    }
+
+    pub async fn store(&self, frame: &Frame) -> Result<()> {
+        let format = frame.metadata.get("format").cloned().unwrap_or_else(|| "png".to_string());
+        let metadata = serde_json::to_string(&frame.metadata).context("Failed to serialise frame metadata")?;
+        let (id, timestamp, data) = (frame.id, timestamp_text(frame.timestamp), frame.data.clone());
+
+        self.with_conn(move |conn| {
+            conn.execute(
+                "INSERT INTO frames (id, timestamp, format, size_bytes, data, metadata) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![id, timestamp, format, data.len() as i64, data, metadata],
+            )
+        })
+        .await?;
+        Ok(())
+    }
+
+    // The newest frame by timestamp; among equal timestamps, the last stored
+    pub async fn get_latest(&self) -> Result<Frame> {
+        let sql = format!("SELECT {} FROM frames ORDER BY timestamp DESC, rowid DESC LIMIT 1", COLUMNS);
+        let row = self.with_conn(move |conn| conn.query_row(&sql, [], read_row).optional()).await?;
+        row.ok_or_else(|| anyhow::anyhow!("no frames available"))?
+    }
+
+    // The most recently stored frame with this id
+    pub async fn get_by_id(&self, id: i64) -> Result<Frame> {
+        let sql = format!("SELECT {} FROM frames WHERE id = ?1 ORDER BY rowid DESC LIMIT 1", COLUMNS);
+        let row = self.with_conn(move |conn| conn.query_row(&sql, [id], read_row).optional()).await?;
+        row.ok_or_else(|| anyhow::anyhow!("frame {} not found", id))?
+    }
+
+    // Frames timestamped at or after `since`, oldest first
+    pub async fn list_since(&self, since: DateTime<Utc>) -> Result<Vec<Frame>> {
+        let sql = format!("SELECT {} FROM frames WHERE timestamp >= ?1 ORDER BY timestamp, rowid", COLUMNS);
+        let since = timestamp_text(since);
+        let rows = self
+            .with_conn(move |conn| {
+                conn.prepare(&sql)?
+                    .query_map([since], read_row)?
+                    .collect::<rusqlite::Result<Vec<_>>>()
+            })
+            .await?;
+        rows.into_iter().collect()
+    }
+
+    // The `limit` newest frames, oldest first
+    pub async fn list_latest(&self, limit: usize) -> Result<Vec<Frame>> {
+        let sql = format!(
+            "SELECT {} FROM (SELECT *, rowid AS seq FROM frames ORDER BY timestamp DESC, rowid DESC LIMIT ?1) \
+             ORDER BY timestamp, seq",
+            COLUMNS
+        );
+        let limit = i64::try_from(limit).unwrap_or(i64::MAX);
+        let rows = self
+            .with_conn(move |conn| {
+                conn.prepare(&sql)?
+                    .query_map([limit], read_row)?
+                    .collect::<rusqlite::Result<Vec<_>>>()
+            })
+            .await?;
+        rows.into_iter().collect()
+    }
+
+    // Ids of frames whose metadata value for `key` matches `value`,
+    // ascending
+    pub async fn query_metadata(&self, key: &str, value: &str, how: MetadataMatch) -> Result<Vec<i64>> {
+        let (key, value) = (key.to_string(), value.to_string());
+        // instr() and substr() compare bytes, unlike LIKE, which ignores
+        // ASCII case and treats % and _ as wildcards
+        let condition = match how {
+            MetadataMatch::Exact => "meta.value = ?2",
+            MetadataMatch::Prefix => "substr(meta.value, 1, length(?2)) = ?2",
+            MetadataMatch::Contains => "instr(meta.value, ?2) > 0",
+        };
+        let sql = format!(
+            "SELECT DISTINCT frames.id FROM frames, json_each(frames.metadata) AS meta \
+             WHERE meta.key = ?1 AND {} ORDER BY frames.id",
+            condition
+        );
+        self.with_conn(move |conn| {
+            conn.prepare(&sql)?
+                .query_map([key, value], |row| row.get(0))?
+                .collect()
+        })
+        .await
+    }
+
+    // Mark a stored frame pinned, up to `max_pinned` pins. Nothing is
+    // evicted from the database, but pins are kept so they can be listed
+    // and survive restarts.
+    pub async fn pin(&self, id: i64, max_pinned: usize) -> Result<()> {
+        self.with_conn(move |conn| {
+            let exists: bool = conn.query_row("SELECT EXISTS(SELECT 1 FROM frames WHERE id = ?1)", [id], |row| row.get(0))?;
+            if !exists {
+                return Ok(Err(anyhow::anyhow!("frame {} not found", id)));
+            }
+            let pinned: bool = conn.query_row("SELECT EXISTS(SELECT 1 FROM pins WHERE id = ?1)", [id], |row| row.get(0))?;
+            let count: i64 = conn.query_row("SELECT COUNT(*) FROM pins", [], |row| row.get(0))?;
+            if !pinned && count as usize >= max_pinned {
+                return Ok(Err(anyhow::anyhow!("pinned frame limit ({}) reached", max_pinned)));
+            }
+            conn.execute("INSERT OR IGNORE INTO pins (id) VALUES (?1)", [id])?;
+            Ok(Ok(()))
+        })
+        .await?
+    }
+
+    pub async fn unpin(&self, id: i64) -> Result<()> {
+        let removed = self.with_conn(move |conn| conn.execute("DELETE FROM pins WHERE id = ?1", [id])).await?;
+        if removed == 0 {
+            anyhow::bail!("frame {} is not pinned", id);
+        }
+        Ok(())
+    }
+
+    pub async fn is_pinned(&self, id: i64) -> Result<bool> {
+        self.with_conn(move |conn| conn.query_row("SELECT EXISTS(SELECT 1 FROM pins WHERE id = ?1)", [id], |row| row.get(0)))
+            .await
+    }
+
+    // Frame count, bytes and time span of the whole database
+    pub async fn stats(&self) -> Result<StorageStats> {
+        let (frames, bytes, oldest, newest) = self
+            .with_conn(|conn| {
+                conn.query_row(
+                    "SELECT COUNT(*), COALESCE(SUM(size_bytes), 0), MIN(timestamp), MAX(timestamp) FROM frames",
+                    [],
+                    |row| {
+                        Ok((
+                            row.get::<_, i64>(0)?,
+                            row.get::<_, i64>(1)?,
+                            row.get::<_, Option<String>>(2)?,
+                            row.get::<_, Option<String>>(3)?,
+                        ))
+                    },
+                )
+            })
+            .await?;
+        let parse = |ts: Option<String>| -> Result<Option<DateTime<Utc>>> {
+            ts.map(|ts| {
+                DateTime::parse_from_rfc3339(&ts)
+                    .map(|ts| ts.with_timezone(&Utc))
+                    .with_context(|| format!("Invalid timestamp '{}'", ts))
+            })
+            .transpose()
+        };
+
+        Ok(StorageStats {
+            frame_count: frames as usize,
+            total_bytes: bytes as usize,
+            oldest: parse(oldest)?,
+            newest: parse(newest)?,
+            disk_frame_count: None,
+        })
+    }
+
+    // Frames timestamped within [start, end], oldest first
+    pub async fn get_range(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> Result<Vec<Frame>> {
+        if start > end {
+            return Ok(Vec::new());
+        }
+        let sql = format!(
+            "SELECT {} FROM frames WHERE timestamp BETWEEN ?1 AND ?2 ORDER BY timestamp, rowid",
+            COLUMNS
+        );
+        let bounds = [timestamp_text(start), timestamp_text(end)];
+        let rows = self
+            .with_conn(move |conn| {
+                conn.prepare(&sql)?
+                    .query_map(bounds, read_row)?
+                    .collect::<rusqlite::Result<Vec<_>>>()
+            })
+            .await?;
+        rows.into_iter().collect()
+    }
+
+    // get_range without reading the data column
+    pub async fn list_range_meta(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> Result<Vec<FrameMeta>> {
+        if start > end {
+            return Ok(Vec::new());
+        }
+        let bounds = [timestamp_text(start), timestamp_text(end)];
+        let rows = self
+            .with_conn(move |conn| {
+                conn.prepare(
+                    "SELECT id, timestamp, format, size_bytes, metadata FROM frames \
+                     WHERE timestamp BETWEEN ?1 AND ?2 ORDER BY timestamp, rowid",
+                )?
+                .query_map(bounds, |row| {
+                    let size_bytes: i64 = row.get(3)?;
+                    Ok(decode_row(row.get(0)?, row.get(1)?, row.get(2)?, Vec::new(), row.get(4)?).map(|frame| {
+                        FrameMeta {
+                            id: frame.id,
+                            timestamp: frame.timestamp,
+                            size_bytes: size_bytes as usize,
+                            metadata: frame.metadata,
+                            checksum: None,
+                        }
+                    }))
+                })?
+                .collect::<rusqlite::Result<Vec<_>>>()
+            })
+            .await?;
+        rows.into_iter().collect()
+    }
+
+    // Delete every row with this id, returning whether there was one
+    pub async fn delete(&self, id: i64) -> Result<bool> {
+        let deleted = self
+            .with_conn(move |conn| {
+                conn.execute("DELETE FROM pins WHERE id = ?1", [id])?;
+                conn.execute("DELETE FROM frames WHERE id = ?1", [id])
+            })
+            .await?;
+        Ok(deleted > 0)
+    }
+
+    // Delete every frame timestamped before `ts`, returning how many
+    pub async fn prune_before(&self, ts: DateTime<Utc>) -> Result<usize> {
+        let ts = timestamp_text(ts);
+        self.with_conn(move |conn| {
+            let deleted = conn.execute("DELETE FROM frames WHERE timestamp < ?1", [ts])?;
+            conn.execute("DELETE FROM pins WHERE id NOT IN (SELECT id FROM frames)", [])?;
+            Ok(deleted)
+        })
+        .await
+    }
+
+    // Space left on the filesystem holding the database
+    pub(crate) fn free_bytes(&self) -> Option<u64> {
+        self.path.parent().and_then(free_bytes)
+    }
+
+    // Check the database answers queries and report its size
+    pub async fn storage_health(&self) -> StorageHealth {
+        let counts = self
+            .with_conn(|conn| {
+                conn.query_row("SELECT COUNT(*), COALESCE(SUM(size_bytes), 0) FROM frames", [], |row| {
+                    Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?))
+                })
+            })
+            .await;
+        let (frames, bytes) = counts.as_ref().copied().unwrap_or_default();
+
+        StorageHealth {
+            backend: "sqlite".to_string(),
+            healthy: counts.is_ok(),
+            error: counts.err().map(|e| format!("{:#}", e)),
+            frames: frames as usize,
+            bytes: bytes as u64,
+            free_bytes: self.free_bytes(),
+            last_write_error: None,
+            degraded: false,
+        }
+    }
+}
+
+// A frame from a row of COLUMNS. Rows that don't decode come back as
+// errors of their own rather than failing the whole query.
+fn read_row(row: &Row) -> rusqlite::Result<Result<Frame>> {
+    Ok(decode_row(row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?))
+}
+
+fn decode_row(id: i64, timestamp: String, format: String, data: Vec<u8>, metadata: String) -> Result<Frame> {
+    let timestamp = DateTime::parse_from_rfc3339(&timestamp)
+        .with_context(|| format!("Frame {} has an invalid timestamp '{}'", id, timestamp))?
+        .with_timezone(&Utc);
+    let mut metadata: HashMap<String, String> =
+        serde_json::from_str(&metadata).with_context(|| format!("Frame {} has invalid metadata", id))?;
+    if let Some((_, content_type)) = frame_format(&format) {
+        metadata.entry("content-type".to_string()).or_insert_with(|| content_type.to_string());
+    }
+    metadata.entry("format".to_string()).or_insert(format);
+    Ok(Frame { id, data, timestamp, metadata })
+}
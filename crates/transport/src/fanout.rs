@@ -0,0 +1,290 @@
+// crates/transport/src/fanout.rs
+use crate::{Alternate, Client, Compatibility, ServerVersion};
+use anyhow::Result;
+use futures::future::join_all;
+use std::future::Future;
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+// The server operations the agent relies on. Implemented by `Client`;
+// lets `FanOut` drive several servers, or test doubles, the same way.
+pub trait Transport: Send + Sync {
+    // Base URL of the server, for logs and stats
+    fn url(&self) -> &str;
+
+    fn connect(&self) -> impl Future<Output = Result<Compatibility>> + Send;
+
+    fn disconnect(&self) -> impl Future<Output = Result<()>> + Send;
+
+    fn health_check(&self) -> impl Future<Output = Result<()>> + Send;
+
+    fn server_version(&self) -> impl Future<Output = Result<ServerVersion>> + Send;
+
+    fn upload_frame_with_alternates(
+        &self,
+        frame_id: i64,
+        data: Vec<u8>,
+        fields: &[(&str, String)],
+        alternates: &[Alternate],
+    ) -> impl Future<Output = Result<serde_json::Value>> + Send;
+}
+
+impl Transport for Client {
+    fn url(&self) -> &str {
+        &self.server_url
+    }
+
+    async fn connect(&self) -> Result<Compatibility> {
+        Client::connect(self).await
+    }
+
+    async fn disconnect(&self) -> Result<()> {
+        Client::disconnect(self).await
+    }
+
+    async fn health_check(&self) -> Result<()> {
+        Client::health_check(self).await
+    }
+
+    async fn server_version(&self) -> Result<ServerVersion> {
+        Client::server_version(self).await
+    }
+
+    async fn upload_frame_with_alternates(
+        &self,
+        frame_id: i64,
+        data: Vec<u8>,
+        fields: &[(&str, String)],
+        alternates: &[Alternate],
+    ) -> Result<serde_json::Value> {
+        Client::upload_frame_with_alternates(self, frame_id, data, fields, alternates).await
+    }
+}
+
+// When a fanned-out operation counts as successful
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FanOutPolicy {
+    /// Every server must accept it
+    All,
+    /// One server accepting it is enough; the rest are best effort
+    #[default]
+    Any,
+}
+
+impl FromStr for FanOutPolicy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "all" => Ok(Self::All),
+            "any" | "at-least-one" => Ok(Self::Any),
+            other => anyhow::bail!("Unknown fan-out policy '{}': expected all or any", other),
+        }
+    }
+}
+
+// Upload outcomes for one server of a fan-out
+#[derive(Debug, Clone, PartialEq)]
+pub struct EndpointStats {
+    pub url: String,
+    pub succeeded: u64,
+    pub failed: u64,
+    /// Most recent upload error, even if later uploads succeeded
+    pub last_error: Option<String>,
+}
+
+// Servers a fanned-out upload did not reach, attached to its error so a
+// retry can go to just those instead of sending duplicates to the rest
+#[derive(Debug, Clone, PartialEq)]
+pub struct FailedEndpoints(pub Vec<String>);
+
+impl std::fmt::Display for FailedEndpoints {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Not delivered to {}", self.0.join(", "))
+    }
+}
+
+struct Endpoint<T> {
+    transport: T,
+    succeeded: AtomicU64,
+    failed: AtomicU64,
+    last_error: Mutex<Option<String>>,
+}
+
+// Sends every operation to several servers at once for redundancy. The
+// first server is the primary: its response is the one returned whenever
+// it succeeds, so e.g. config updates come from one place.
+pub struct FanOut<T = Client> {
+    endpoints: Vec<Endpoint<T>>,
+    policy: FanOutPolicy,
+}
+
+impl<T: Transport> FanOut<T> {
+    // `transports` must not be empty; the first is the primary
+    pub fn new(transports: Vec<T>, policy: FanOutPolicy) -> Self {
+        assert!(!transports.is_empty(), "fan-out needs at least one server");
+        let endpoints = transports
+            .into_iter()
+            .map(|transport| Endpoint {
+                transport,
+                succeeded: AtomicU64::new(0),
+                failed: AtomicU64::new(0),
+                last_error: Mutex::new(None),
+            })
+            .collect();
+        Self { endpoints, policy }
+    }
+
+    pub fn policy(&self) -> FanOutPolicy {
+        self.policy
+    }
+
+    pub fn urls(&self) -> Vec<&str> {
+        self.endpoints.iter().map(|e| e.transport.url()).collect()
+    }
+
+    // Upload counts per server, primary first
+    pub fn stats(&self) -> Vec<EndpointStats> {
+        self.endpoints
+            .iter()
+            .map(|e| EndpointStats {
+                url: e.transport.url().to_string(),
+                succeeded: e.succeeded.load(Ordering::Relaxed),
+                failed: e.failed.load(Ordering::Relaxed),
+                last_error: e.last_error.lock().unwrap().clone(),
+            })
+            .collect()
+    }
+
+    // Pair per-server results, in endpoint order, with their servers
+    fn labelled<V>(&self, results: Vec<Result<V>>) -> Vec<(&str, Result<V>)> {
+        self.endpoints.iter().map(|e| e.transport.url()).zip(results).collect()
+    }
+
+    // Apply the policy to per-server results, in endpoint order. Returns
+    // the primary's value if it succeeded, else the first success; on
+    // failure, the first error, so callers can still downcast it.
+    fn settle<V>(&self, what: &str, results: Vec<(&str, Result<V>)>) -> Result<V> {
+        let total = results.len();
+        let mut value = None;
+        let mut errors = Vec::new();
+        for (url, result) in results {
+            match result {
+                Ok(v) => {
+                    value.get_or_insert(v);
+                }
+                Err(e) => errors.push((url, e)),
+            }
+        }
+
+        let failed = errors.len();
+        let first_error = errors.into_iter().next();
+        match (value, first_error, self.policy) {
+            (Some(value), None, _) | (Some(value), Some(_), FanOutPolicy::Any) => Ok(value),
+            (_, Some((url, e)), _) => {
+                Err(e.context(format!("{} failed on {} of {} servers (first: {})", what, failed, total, url)))
+            }
+            (None, None, _) => unreachable!("a fan-out always has at least one server"),
+        }
+    }
+
+    // Register with every server. The most cautious compatibility verdict
+    // among the servers that accepted wins.
+    pub async fn connect(&self) -> Result<Compatibility> {
+        let results = join_all(self.endpoints.iter().map(|e| e.transport.connect())).await;
+        let worst = results.iter().filter_map(|r| r.as_ref().ok()).fold(Compatibility::Ok, |worst, c| {
+            match (worst, c) {
+                (Compatibility::Reject, _) | (_, Compatibility::Reject) => Compatibility::Reject,
+                (Compatibility::Warn, _) | (_, Compatibility::Warn) => Compatibility::Warn,
+                _ => Compatibility::Ok,
+            }
+        });
+        self.settle("Connect", self.labelled(results)).map(|_| worst)
+    }
+
+    // Unregister from every server; fails if any of them failed
+    pub async fn disconnect(&self) -> Result<()> {
+        let results = join_all(self.endpoints.iter().map(|e| e.transport.disconnect())).await;
+        match results.into_iter().find_map(Result::err) {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+
+    pub async fn health_check(&self) -> Result<()> {
+        let results = join_all(self.endpoints.iter().map(|e| e.transport.health_check())).await;
+        self.settle("Health check", self.labelled(results))
+    }
+
+    // Version of the first server that reports one
+    pub async fn server_version(&self) -> Result<ServerVersion> {
+        let results = join_all(self.endpoints.iter().map(|e| e.transport.server_version())).await;
+        self.settle("Version request", self.labelled(results))
+    }
+
+    // Upload one frame to every server concurrently, counting each
+    // server's outcome
+    pub async fn upload_frame_with_fields(
+        &self,
+        frame_id: i64,
+        data: Vec<u8>,
+        fields: &[(&str, String)],
+    ) -> Result<serde_json::Value> {
+        self.upload_frame_to(frame_id, data, fields, &[], &[]).await
+    }
+
+    // upload_frame_with_fields, plus other encodings of the frame
+    pub async fn upload_frame_with_alternates(
+        &self,
+        frame_id: i64,
+        data: Vec<u8>,
+        fields: &[(&str, String)],
+        alternates: &[Alternate],
+    ) -> Result<serde_json::Value> {
+        self.upload_frame_to(frame_id, data, fields, alternates, &[]).await
+    }
+
+    // Upload one frame to the servers in `urls`, or to every server if it
+    // is empty, e.g. to retry only where an earlier upload failed. The
+    // policy applies to those servers alone. On failure the error carries
+    // the servers that didn't get the frame as a FailedEndpoints context.
+    pub async fn upload_frame_to(
+        &self,
+        frame_id: i64,
+        data: Vec<u8>,
+        fields: &[(&str, String)],
+        alternates: &[Alternate],
+        urls: &[String],
+    ) -> Result<serde_json::Value> {
+        let targets: Vec<&Endpoint<T>> = self
+            .endpoints
+            .iter()
+            .filter(|e| urls.is_empty() || urls.iter().any(|url| url == e.transport.url()))
+            .collect();
+        if targets.is_empty() {
+            anyhow::bail!("None of the servers {} are configured", urls.join(", "));
+        }
+
+        let results = join_all(
+            targets
+                .iter()
+                .map(|e| e.transport.upload_frame_with_alternates(frame_id, data.clone(), fields, alternates)),
+        )
+        .await;
+
+        let mut failed = Vec::new();
+        for (endpoint, result) in targets.iter().zip(&results) {
+            match result {
+                Ok(_) => endpoint.succeeded.fetch_add(1, Ordering::Relaxed),
+                Err(e) => {
+                    failed.push(endpoint.transport.url().to_string());
+                    *endpoint.last_error.lock().unwrap() = Some(format!("{:#}", e));
+                    endpoint.failed.fetch_add(1, Ordering::Relaxed)
+                }
+            };
+        }
+        let results = targets.iter().map(|e| e.transport.url()).zip(results).collect();
+        self.settle("Upload", results).map_err(|e| e.context(FailedEndpoints(failed)))
+    }
+}
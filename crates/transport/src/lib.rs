@@ -5,6 +5,106 @@ use reqwest::{Client as HttpClient, multipart};
 use serde::{Deserialize, Serialize};
 use std::time::Duration;
 
+mod fanout;
+
+pub use fanout::{EndpointStats, FailedEndpoints, FanOut, FanOutPolicy, Transport};
+
+// Correlation header the server echoes (or generates) for every request
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+// Header carrying the agent's version so the server can check compatibility
+pub const AGENT_VERSION_HEADER: &str = "x-agent-version";
+
+// Multipart field prefix for alternate encodings, e.g. "alternate_jpeg"
+pub const ALTERNATE_FIELD_PREFIX: &str = "alternate_";
+
+// Another encoding of an uploaded frame, from the same capture
+#[derive(Debug, Clone, PartialEq)]
+pub struct Alternate {
+    /// Lowercase format name, e.g. "jpeg"
+    pub format: String,
+    pub data: Vec<u8>,
+}
+
+// Longest Retry-After the client will sleep through before retrying
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(60);
+
+// Wait used when a 429 carries no usable Retry-After header
+const DEFAULT_RETRY_AFTER: Duration = Duration::from_secs(1);
+
+// Upload error returned when the server is still rate limiting after the
+// client waited out one Retry-After. Callers can downcast to this to slow
+// down instead of retrying immediately.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimited {
+    pub retry_after: Duration,
+}
+
+impl std::fmt::Display for RateLimited {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Rate limited by server (retry after {:?})", self.retry_after)
+    }
+}
+
+impl std::error::Error for RateLimited {}
+
+// Upload error for a frame the server refused because its timestamp is
+// further behind the latest frame than the server's clock-skew tolerance.
+// Resending it won't help.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StaleFrame {
+    pub detail: String,
+}
+
+impl std::fmt::Display for StaleFrame {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Server rejected stale frame: {}", self.detail)
+    }
+}
+
+impl std::error::Error for StaleFrame {}
+
+// Upload error for a frame the server refused with a 4xx other than the
+// stale-frame conflict or rate limiting. Resending the same request won't
+// help.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rejected {
+    pub status: u16,
+    pub body: String,
+}
+
+impl std::fmt::Display for Rejected {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Upload failed: {} - {}", self.status, self.body)
+    }
+}
+
+impl std::error::Error for Rejected {}
+
+// True when `err` means the server could not be reached at all (connection
+// refused, DNS failure, timeout) rather than that it rejected the request
+pub fn is_unreachable(err: &anyhow::Error) -> bool {
+    err.chain()
+        .filter_map(|cause| cause.downcast_ref::<reqwest::Error>())
+        .any(|e| e.is_connect() || e.is_timeout())
+}
+
+// Parse Retry-After as delay-seconds or an HTTP-date, capped at
+// MAX_RETRY_AFTER
+fn parse_retry_after(headers: &reqwest::header::HeaderMap, now: DateTime<Utc>) -> Option<Duration> {
+    let value = headers.get(reqwest::header::RETRY_AFTER)?.to_str().ok()?.trim();
+
+    let delay = match value.parse::<u64>() {
+        Ok(secs) => Duration::from_secs(secs),
+        Err(_) => {
+            let at = DateTime::parse_from_rfc2822(value).ok()?.with_timezone(&Utc);
+            (at - now).to_std().unwrap_or(Duration::ZERO)
+        }
+    };
+
+    Some(delay.min(MAX_RETRY_AFTER))
+}
+
 // Data structures for transport communication
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FrameMetadata {
@@ -27,6 +127,19 @@ pub struct UploadResponse {
     pub success: bool,
     pub message: String,
     pub frame_id: i64,
+    /// Where the server persisted the frame, if it reported it
+    #[serde(default)]
+    pub storage: Option<StorageInfo>,
+}
+
+// Storage location reported by the server in an upload acknowledgment.
+// `path` is relative to the server's disk root and only present for
+// authenticated uploads to a disk or hybrid store.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StorageInfo {
+    pub backend: String,
+    #[serde(default)]
+    pub path: Option<String>,
 }
 
 // Configuration structure for the agent
@@ -37,11 +150,34 @@ pub struct AgentConfig {
     pub quality: i32,
 }
 
+// Server's verdict on the agent version sent at connect time
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Compatibility {
+    /// Same release line
+    #[default]
+    Ok,
+    /// Works, but the versions have drifted
+    Warn,
+    /// Breaking difference; the server refused the connection
+    Reject,
+}
+
+// Build details reported by GET /version
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServerVersion {
+    pub version: String,
+    pub git_sha: String,
+    pub build_timestamp: Option<String>,
+}
+
 // Client for communicating with the server (1:1 model — one client per server)
 pub struct Client {
     server_url: String,
     token: String,
     client: HttpClient,
+    /// Sent as X-Agent-Version when connecting
+    agent_version: Option<String>,
 }
 
 // Implementation of Client
@@ -63,9 +199,15 @@ impl Client {
             server_url,
             token,
             client,
+            agent_version: None,
         }
     }
 
+    pub fn with_agent_version(mut self, version: impl Into<String>) -> Self {
+        self.agent_version = Some(version.into());
+        self
+    }
+
     // Build a request with the optional auth header applied
     fn with_auth(&self, request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
         if !self.token.is_empty() {
@@ -76,12 +218,18 @@ impl Client {
     }
 
     // Register this agent with the server, claiming the single connection slot.
-    // Returns an error if another agent is already connected (HTTP 409).
-    pub async fn connect(&self) -> Result<()> {
+    // Returns an error if another agent is already connected (HTTP 409) or
+    // the server rejects this agent's version (HTTP 426). Otherwise returns
+    // the server's compatibility verdict; servers that predate version
+    // negotiation report none and are treated as compatible.
+    pub async fn connect(&self) -> Result<Compatibility> {
         let url = format!("{}/connect", self.server_url);
 
-        let response = self
-            .with_auth(self.client.post(&url))
+        let mut request = self.with_auth(self.client.post(&url));
+        if let Some(version) = &self.agent_version {
+            request = request.header(AGENT_VERSION_HEADER, version);
+        }
+        let response = request
             .send()
             .await
             .context("Failed to send connect request")?;
@@ -93,13 +241,22 @@ impl Client {
             );
         }
 
+        if response.status() == reqwest::StatusCode::UPGRADE_REQUIRED {
+            let body = response.text().await.unwrap_or_default();
+            anyhow::bail!("Server rejected this agent version: {}", body);
+        }
+
         if !response.status().is_success() {
             let status = response.status();
             let body = response.text().await.unwrap_or_default();
             anyhow::bail!("Connect failed: {} - {}", status, body);
         }
 
-        Ok(())
+        let body: serde_json::Value = response.json().await.unwrap_or_default();
+        Ok(body
+            .get("compatibility")
+            .and_then(|c| serde_json::from_value(c.clone()).ok())
+            .unwrap_or_default())
     }
 
     // Unregister this agent from the server, freeing the connection slot.
@@ -124,32 +281,128 @@ impl Client {
 
     // Upload a frame to the server
     pub async fn upload_frame(&self, frame_id: i64, data: Vec<u8>) -> Result<serde_json::Value> {
-        let url = format!("{}/upload", self.server_url);
+        self.upload_frame_with_fields(frame_id, data, &[]).await
+    }
 
-        let form = multipart::Form::new()
-            .part("image", multipart::Part::bytes(data).file_name("frame.png"))
-            .text("frame_id", frame_id.to_string());
+    // Upload a frame with additional multipart text fields, e.g. "format"
+    // or the dimensions of a raw frame
+    pub async fn upload_frame_with_fields(
+        &self,
+        frame_id: i64,
+        data: Vec<u8>,
+        fields: &[(&str, String)],
+    ) -> Result<serde_json::Value> {
+        self.upload_frame_with_alternates(frame_id, data, fields, &[]).await
+    }
 
-        let response = self
-            .with_auth(self.client.post(&url).multipart(form))
-            .send()
-            .await
-            .context("Failed to send upload request")?;
+    // Upload a frame together with other encodings of it, each sent as an
+    // "alternate_<format>" file part
+    pub async fn upload_frame_with_alternates(
+        &self,
+        frame_id: i64,
+        data: Vec<u8>,
+        fields: &[(&str, String)],
+        alternates: &[Alternate],
+    ) -> Result<serde_json::Value> {
+        let url = format!("{}/upload", self.server_url);
+        // Cheap to clone, so a rate-limited upload can be resent as-is
+        let data = bytes::Bytes::from(data);
+        let alternates: Vec<(String, bytes::Bytes)> = alternates
+            .iter()
+            .map(|a| (a.format.clone(), bytes::Bytes::from(a.data.clone())))
+            .collect();
+
+        // On 429, honour Retry-After once before giving up
+        let mut waited = false;
+        let response = loop {
+            let mut form = multipart::Form::new()
+                .part(
+                    "image",
+                    multipart::Part::stream(reqwest::Body::from(data.clone())).file_name("frame.png"),
+                )
+                .text("frame_id", frame_id.to_string());
+            for (name, value) in fields {
+                form = form.text(name.to_string(), value.clone());
+            }
+            for (format, data) in &alternates {
+                form = form.part(
+                    format!("{}{}", ALTERNATE_FIELD_PREFIX, format),
+                    multipart::Part::stream(reqwest::Body::from(data.clone())).file_name(format!("frame.{}", format)),
+                );
+            }
+
+            let response = self
+                .with_auth(self.client.post(&url).multipart(form))
+                .send()
+                .await
+                .context("Failed to send upload request")?;
+
+            if response.status() != reqwest::StatusCode::TOO_MANY_REQUESTS {
+                break response;
+            }
+
+            let retry_after = parse_retry_after(response.headers(), Utc::now())
+                .unwrap_or(DEFAULT_RETRY_AFTER);
+            if waited {
+                return Err(RateLimited { retry_after }.into());
+            }
+            tokio::time::sleep(retry_after).await;
+            waited = true;
+        };
 
         if !response.status().is_success() {
             let status = response.status();
             let body = response.text().await.unwrap_or_default();
+            if status == reqwest::StatusCode::CONFLICT
+                && let Ok(error) = serde_json::from_str::<serde_json::Value>(&body)
+                && error["error"] == "stale_frame"
+            {
+                let detail = error["detail"].as_str().unwrap_or_default().to_string();
+                return Err(StaleFrame { detail }.into());
+            }
+            if status.is_client_error() {
+                return Err(Rejected { status: status.as_u16(), body }.into());
+            }
             anyhow::bail!("Upload failed: {} - {}", status, body);
         }
 
-        let json = response
+        let request_id = response
+            .headers()
+            .get(REQUEST_ID_HEADER)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string);
+
+        let mut json = response
             .json::<serde_json::Value>()
             .await
             .context("Failed to parse response")?;
 
+        // Surface the server's correlation ID so callers can log it
+        if let (Some(id), Some(obj)) = (request_id, json.as_object_mut()) {
+            obj.entry("request_id").or_insert(serde_json::Value::String(id));
+        }
+
         Ok(json)
     }
 
+    // Fetch the server's version and build info
+    pub async fn server_version(&self) -> Result<ServerVersion> {
+        let url = format!("{}/version", self.server_url);
+
+        let response = self
+            .client
+            .get(&url)
+            .send()
+            .await
+            .context("Failed to send version request")?;
+
+        if !response.status().is_success() {
+            anyhow::bail!("Version request failed: {}", response.status());
+        }
+
+        response.json().await.context("Failed to parse version response")
+    }
+
     // Health check to verify server availability
     pub async fn health_check(&self) -> Result<()> {
         let url = format!("{}/health", self.server_url);
@@ -190,6 +443,7 @@ impl Default for WebSocketServer {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::sync::{Arc, Mutex};
 
     #[test]
     fn test_client_creation() {
@@ -199,4 +453,175 @@ mod tests {
         );
         assert!(!client.server_url.is_empty());
     }
-}
\ No newline at end of file
+
+    // Minimal HTTP server: answers each connection with the next canned
+    // response and records when each request arrived
+    async fn mock_server(
+        responses: Vec<String>,
+    ) -> (String, tokio::task::JoinHandle<Vec<std::time::Instant>>) {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+
+        let handle = tokio::spawn(async move {
+            let mut arrivals = Vec::new();
+            for response in responses {
+                let (mut socket, _) = listener.accept().await.unwrap();
+
+                // Read headers, then the body according to Content-Length
+                let mut request = Vec::new();
+                let mut buf = [0u8; 4096];
+                let body_len = loop {
+                    let n = socket.read(&mut buf).await.unwrap();
+                    request.extend_from_slice(&buf[..n]);
+                    if let Some(end) = request.windows(4).position(|w| w == b"\r\n\r\n") {
+                        let head = String::from_utf8_lossy(&request[..end]).to_lowercase();
+                        let len: usize = head
+                            .lines()
+                            .find_map(|l| l.strip_prefix("content-length:"))
+                            .map(|v| v.trim().parse().unwrap())
+                            .unwrap_or(0);
+                        break end + 4 + len;
+                    }
+                };
+                while request.len() < body_len {
+                    let n = socket.read(&mut buf).await.unwrap();
+                    request.extend_from_slice(&buf[..n]);
+                }
+
+                arrivals.push(std::time::Instant::now());
+                socket.write_all(response.as_bytes()).await.unwrap();
+                socket.shutdown().await.unwrap();
+            }
+            arrivals
+        });
+
+        (url, handle)
+    }
+
+    #[tokio::test]
+    async fn test_upload_honours_retry_after() {
+        let body = r#"{"success":true,"message":"ok","frame_id":1}"#;
+        let (url, server) = mock_server(vec![
+            "HTTP/1.1 429 Too Many Requests\r\nRetry-After: 2\r\n\
+             Content-Length: 0\r\nConnection: close\r\n\r\n"
+                .to_string(),
+            format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\n\
+                 Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            ),
+        ])
+        .await;
+
+        let client = Client::new(url, String::new());
+        let response = client.upload_frame(1, vec![1, 2, 3]).await.unwrap();
+        assert_eq!(response["frame_id"], 1);
+
+        let arrivals = server.await.unwrap();
+        let waited = arrivals[1] - arrivals[0];
+        assert!(waited >= Duration::from_secs(2), "retried after {:?}", waited);
+        assert!(waited < Duration::from_secs(3), "retried after {:?}", waited);
+    }
+
+    #[test]
+    fn test_parse_retry_after_http_date() {
+        let now = DateTime::parse_from_rfc3339("2026-03-02T09:00:00Z").unwrap().to_utc();
+        let mut headers = reqwest::header::HeaderMap::new();
+        headers.insert(
+            reqwest::header::RETRY_AFTER,
+            "Mon, 02 Mar 2026 09:00:05 GMT".parse().unwrap(),
+        );
+        assert_eq!(parse_retry_after(&headers, now), Some(Duration::from_secs(5)));
+
+        headers.insert(reqwest::header::RETRY_AFTER, "3600".parse().unwrap());
+        assert_eq!(parse_retry_after(&headers, now), Some(MAX_RETRY_AFTER));
+    }
+
+    // Records the frames it receives; fails every upload when `down`
+    struct MockTransport {
+        url: String,
+        down: bool,
+        received: Arc<Mutex<Vec<i64>>>,
+    }
+
+    impl Transport for MockTransport {
+        fn url(&self) -> &str {
+            &self.url
+        }
+
+        async fn connect(&self) -> Result<Compatibility> {
+            Ok(Compatibility::Ok)
+        }
+
+        async fn disconnect(&self) -> Result<()> {
+            Ok(())
+        }
+
+        async fn health_check(&self) -> Result<()> {
+            Ok(())
+        }
+
+        async fn server_version(&self) -> Result<ServerVersion> {
+            anyhow::bail!("not implemented")
+        }
+
+        async fn upload_frame_with_alternates(
+            &self,
+            frame_id: i64,
+            _data: Vec<u8>,
+            _fields: &[(&str, String)],
+            _alternates: &[Alternate],
+        ) -> Result<serde_json::Value> {
+            if self.down {
+                return Err(StaleFrame { detail: format!("{} is down", self.url) }.into());
+            }
+            self.received.lock().unwrap().push(frame_id);
+            Ok(serde_json::json!({ "server": self.url }))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fan_out_uploads_to_every_server() {
+        let (a, b) = (Arc::new(Mutex::new(Vec::new())), Arc::new(Mutex::new(Vec::new())));
+        let mock = |url: &str, down, received: &Arc<Mutex<Vec<i64>>>| MockTransport {
+            url: url.to_string(),
+            down,
+            received: received.clone(),
+        };
+
+        // Both servers get every frame; the primary's response is returned
+        let fan_out = FanOut::new(vec![mock("a", false, &a), mock("b", false, &b)], FanOutPolicy::All);
+        let response = fan_out.upload_frame_with_fields(1, vec![1, 2, 3], &[]).await.unwrap();
+        assert_eq!(response["server"], "a");
+        assert_eq!(*a.lock().unwrap(), vec![1]);
+        assert_eq!(*b.lock().unwrap(), vec![1]);
+
+        // With the primary down, "any" still succeeds via the other server
+        let any = FanOut::new(vec![mock("a", true, &a), mock("b", false, &b)], FanOutPolicy::Any);
+        let response = any.upload_frame_with_fields(2, vec![], &[]).await.unwrap();
+        assert_eq!(response["server"], "b");
+        let stats = any.stats();
+        assert_eq!((stats[0].failed, stats[1].succeeded), (1, 1));
+        assert!(stats[0].last_error.as_ref().unwrap().contains("a is down"));
+
+        // ...while "all" fails, keeping the underlying error downcastable
+        let all = FanOut::new(vec![mock("a", true, &a), mock("b", false, &b)], FanOutPolicy::All);
+        let err = all.upload_frame_with_fields(3, vec![], &[]).await.unwrap_err();
+        assert!(err.downcast_ref::<StaleFrame>().is_some());
+        assert!(format!("{:#}", err).contains("1 of 2 servers"));
+        assert_eq!(*b.lock().unwrap(), vec![1, 2, 3]);
+
+        // The error names the server that missed the frame, and a retry
+        // goes to that one only
+        let failed = err.downcast_ref::<FailedEndpoints>().unwrap();
+        assert_eq!(failed.0, vec!["a".to_string()]);
+        let (c, d) = (Arc::new(Mutex::new(Vec::new())), Arc::new(Mutex::new(Vec::new())));
+        let retry = FanOut::new(vec![mock("a", false, &c), mock("b", false, &d)], FanOutPolicy::All);
+        retry.upload_frame_to(3, vec![], &[], &[], &failed.0).await.unwrap();
+        assert_eq!((c.lock().unwrap().clone(), d.lock().unwrap().clone()), (vec![3], vec![]));
+        assert!(retry.upload_frame_to(4, vec![], &[], &[], &["z".to_string()]).await.is_err());
+    }
+}